@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use renderer::atlas::TextureAtlas;
+use renderer::gl::Gl;
+use renderer::random::Rng;
+use renderer::sprites::{Sprite, SpriteBatch, TextureId};
+use renderer::stats::{self, FrameStats};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const PAGE_SIZE: u32 = 256;
+const ICONS: usize = 240;
+/// Icons added every `ADD_INTERVAL` frames until there are `ICONS`.
+const ADD_BATCH: usize = 6;
+const ADD_INTERVAL: u32 = 15;
+
+/// A `size` square icon: a disc, ring or diamond in a random colour,
+/// opaque right up to its edges so bleeding would show.
+fn icon(rng: &mut Rng, size: u32) -> Vec<u8> {
+  let shape = rng.next_u32() % 3;
+  let fill = [rng.range(64.0, 255.0) as u8, rng.range(64.0, 255.0) as u8, rng.range(64.0, 255.0) as u8];
+  let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+  let half = size as f32 * 0.5;
+  for y in 0..size {
+    for x in 0..size {
+      let (dx, dy) = ((x as f32 + 0.5 - half) / half, (y as f32 + 0.5 - half) / half);
+      let distance = (dx * dx + dy * dy).sqrt();
+      let inside = match shape {
+        0 => distance < 0.9,
+        1 => distance < 0.9 && distance > 0.5,
+        _ => dx.abs() + dy.abs() < 1.0,
+      };
+      let edge = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+      let texel = if inside {
+        [fill[0], fill[1], fill[2], 255]
+      } else if edge {
+        [255, 255, 255, 255]
+      } else {
+        [fill[0] / 4, fill[1] / 4, fill[2] / 4, 255]
+      };
+      pixels.extend_from_slice(&texel);
+    }
+  }
+  pixels
+}
+
+/// Icons of random sizes arriving a few at a time into a runtime atlas
+/// of 256 pixel pages, shown on the left, and drawn from it as a
+/// magnified grid of sprites on the right. Each arrival that doesn't
+/// fit repacks every page.
+pub fn draw (
+  context: &Gl,
+  _width: f32,
+  _height: f32,
+) -> Result<(), JsValue> {
+  let mut batch = SpriteBatch::new(context)?;
+  let mut atlas = TextureAtlas::new(PAGE_SIZE, 1, 2);
+  // Sprite batch ids of the atlas pages; pages that survive a repack
+  // keep their texture, so ids only need adding for new ones.
+  let mut pages: Vec<TextureId> = Vec::new();
+  let mut rng = Rng::new(7);
+  let mut keys: Vec<String> = Vec::new();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut frame_number: u32 = 0;
+  let mut repacks = 0;
+  let mut time: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    if frame_number % ADD_INTERVAL == 0 && keys.len() < ICONS {
+      for _ in 0..ADD_BATCH {
+        let size = 12 + rng.next_u32() % 37;
+        let key = format!("icon{}", keys.len());
+        if atlas.add_rgba(&ctx, &key, size, size, &icon(&mut rng, size)).unwrap() {
+          repacks += 1;
+        }
+        keys.push(key);
+      }
+      let size = PAGE_SIZE as i32;
+      pages.truncate(atlas.page_count());
+      for page in pages.len()..atlas.page_count() {
+        pages.push(batch.register(atlas.page(page).clone(), size, size));
+      }
+    }
+    frame_number += 1;
+
+    let (width, height) = (ctx.drawing_buffer_width(), ctx.drawing_buffer_height());
+    ctx.viewport(0, 0, width, height);
+    ctx.clear_color(0.1, 0.1, 0.12, 1.0);
+    ctx.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    let (width, height) = (width as f32, height as f32);
+
+    // The pages, one under the other, shrunk to fit the left third.
+    let count = atlas.page_count();
+    let page_size = (width / 3.0 - 20.0).min((height - 10.0) / count.max(1) as f32 - 10.0);
+    for page in 0..count {
+      let position = [10.0, 10.0 + page as f32 * (page_size + 10.0)];
+      let mut backdrop = Sprite::quad(position, [page_size, page_size], [0.25, 0.25, 0.3, 1.0]);
+      backdrop.layer = -1;
+      batch.push(backdrop);
+      batch.push(Sprite::new(pages[page], position, [page_size, page_size]));
+    }
+
+    // Every icon, magnified and turning, from whichever page it is on.
+    let (left, columns) = (width / 3.0 + 20.0, 16);
+    let cell = (width - left - 10.0) / columns as f32;
+    for (i, key) in keys.iter().enumerate() {
+      let placement = atlas.get(key).unwrap();
+      let position = [
+        left + (i % columns) as f32 * cell + cell * 0.5,
+        10.0 + (i / columns) as f32 * cell + cell * 0.5,
+      ];
+      let size = placement.rect.width as f32 / 48.0 * cell * 0.9;
+      let mut sprite = Sprite::new(pages[placement.page], position, [size, size]);
+      sprite.uv = atlas.uv(key).unwrap();
+      sprite.pivot = [0.5, 0.5];
+      sprite.rotation = (time + i as f32 * 0.1).sin() * 0.5;
+      batch.push(sprite);
+    }
+    // One bar per repack so far.
+    for i in 0..repacks {
+      let mut bar = Sprite::quad([left + i as f32 * 14.0, height - 20.0], [10.0, 10.0], [1.0, 0.8, 0.2, 1.0]);
+      bar.layer = 1;
+      batch.push(bar);
+    }
+
+    let mut frame = FrameStats::default();
+    frame.objects = batch.len() as u32;
+    frame.draw_calls = batch.flush(&ctx).unwrap();
+    frame.triangles = frame.objects * 2;
+    stats::publish(frame);
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
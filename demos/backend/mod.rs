@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  console,
+  WebGlRenderingContext,
+};
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A grid of lit cubes through the backend independent API: vertex
+/// array objects and uniform blocks on WebGL2, plain attributes and
+/// uniforms on WebGL1.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  console::log_1(&format!("renderer backend: {}", context.backend().as_str()).into());
+
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let cube = Mesh::new(context, &geometry::cube())?;
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    let eye = glm::vec3((rotation * 0.3).sin() * 14.0, 6.0, (rotation * 0.3).cos() * 14.0);
+    let camera = Camera {
+      view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+      projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+      position: eye,
+    };
+    uniforms.update(&ctx, &camera, &light).unwrap();
+
+    program.begin(&ctx, &uniforms);
+    for x in 0..5 {
+      for z in 0..5 {
+        let position = glm::vec3(x as f32 * 3.0 - 6.0, 0.0, z as f32 * 3.0 - 6.0);
+        let mut model = glm::translate(&Mat4::identity(), &position);
+        model = glm::rotate(&model, rotation + (x * 5 + z) as f32 * 0.3, &glm::vec3(0.0, 1.0, 0.0));
+        program.draw(&ctx, &cube, &model, [x as f32 / 4.0, 0.5, z as f32 / 4.0, 1.0]);
+      }
+    }
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,188 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{console, WebGlRenderingContext};
+
+use renderer::compressed::CompressedTexture;
+use renderer::compressed::container::{full_mip_count, gl_formats, mip_size};
+use renderer::gl::Gl;
+use renderer::sprites::{Sprite, SpriteBatch};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A different colour per mip level, so the level sampled shows.
+const TINTS: [[u8; 3]; 9] = [
+  [240, 240, 240], [240, 80, 80], [80, 240, 80], [80, 80, 240], [240, 240, 80],
+  [240, 80, 240], [80, 240, 240], [160, 160, 160], [80, 80, 80],
+];
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+  bytes.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+}
+
+/// Whether texel `x, y` of a checker of 16 texel cells at level 0,
+/// shrinking with the level but never below one 4x4 block, is dark.
+fn dark(x: u32, y: u32, level: u32) -> bool {
+  let cell = (16 >> level).max(4);
+  (x / cell + y / cell) % 2 == 1
+}
+
+/// A DDS file of DXT1 blocks with a full mip chain. Every block is one
+/// flat colour, which DXT1 stores exactly.
+fn dds_checker(size: u32) -> Vec<u8> {
+  let levels = full_mip_count(size, size);
+  let mut bytes = b"DDS ".to_vec();
+  // Size, flags (caps, height, width, pixel format, mip count, linear
+  // size), height, width, linear size, depth, mip count.
+  for &value in &[124, 0xA1007, size, size, (size / 4) * (size / 4) * 8, 0, levels] {
+    push_u32(&mut bytes, value);
+  }
+  bytes.extend_from_slice(&[0; 11 * 4]);
+  // Pixel format: size, four character code flag, "DXT1", no masks.
+  push_u32(&mut bytes, 32);
+  push_u32(&mut bytes, 0x4);
+  bytes.extend_from_slice(b"DXT1");
+  bytes.extend_from_slice(&[0; 5 * 4]);
+  // Caps: texture, mipmap, complex.
+  push_u32(&mut bytes, 0x401008);
+  bytes.extend_from_slice(&[0; 4 * 4]);
+
+  for level in 0..levels {
+    let (width, height) = mip_size(size, size, level);
+    for by in 0..(height + 3) / 4 {
+      for bx in 0..(width + 3) / 4 {
+        let tint = TINTS[level as usize % TINTS.len()];
+        let shade = if dark(bx * 4, by * 4, level) { 2 } else { 1 };
+        let (r, g, b) = (tint[0] as u16 / shade, tint[1] as u16 / shade, tint[2] as u16 / shade);
+        let color = (r >> 3) << 11 | (g >> 2) << 5 | b >> 3;
+        bytes.extend_from_slice(&[color as u8, (color >> 8) as u8, color as u8, (color >> 8) as u8, 0, 0, 0, 0]);
+      }
+    }
+  }
+  bytes
+}
+
+/// A KTX 1.1 file header for a 2D texture.
+fn ktx_header(gl_type: u32, format: u32, internal_format: u32, size: u32, levels: u32) -> Vec<u8> {
+  let mut bytes = vec![0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+  // Endianness, type, type size, format, internal format, base
+  // internal format, width, height, depth, array elements, faces,
+  // levels and key/value bytes.
+  for &value in &[0x04030201, gl_type, 1, format, internal_format, format, size, size, 0, 0, 1, levels, 0] {
+    push_u32(&mut bytes, value);
+  }
+  bytes
+}
+
+/// A KTX file of uncompressed RGBA with only three mip levels, so
+/// WebGL2 clamps to them and WebGL1 ignores them.
+fn ktx_gradient(size: u32) -> Vec<u8> {
+  let levels = 3;
+  let mut bytes = ktx_header(gl_formats::UNSIGNED_BYTE, gl_formats::RGBA, gl_formats::RGBA, size, levels);
+  for level in 0..levels {
+    let (width, height) = mip_size(size, size, level);
+    push_u32(&mut bytes, width * height * 4);
+    let tint = TINTS[level as usize];
+    for y in 0..height {
+      for x in 0..width {
+        let fade = (x + y) as f32 / (width + height) as f32;
+        let shade = if dark(x, y, level) { 0.5 } else { 1.0 };
+        for &channel in &tint {
+          bytes.push((channel as f32 * fade * shade) as u8);
+        }
+        bytes.push(255);
+      }
+    }
+  }
+  bytes
+}
+
+/// A single level KTX file of ETC1 blocks, each one flat colour in the
+/// individual mode with the smallest modifier.
+fn ktx_etc1(size: u32) -> Vec<u8> {
+  let mut bytes = ktx_header(0, 0, gl_formats::RGB_ETC1, size, 1);
+  push_u32(&mut bytes, (size / 4) * (size / 4) * 8);
+  for by in 0..size / 4 {
+    for bx in 0..size / 4 {
+      let (r, g, b) = if dark(bx * 4, by * 4, 0) { (2, 6, 12) } else { (12, 10, 3) };
+      bytes.extend_from_slice(&[r << 4 | r, g << 4 | g, b << 4 | b, 0, 0, 0, 0, 0]);
+    }
+  }
+  bytes
+}
+
+/// A DXT1 DDS, an RGBA KTX and an ETC1 KTX built in memory, uploaded
+/// compressed where the context supports the format and decoded
+/// otherwise, see the console. Each row shrinks and grows one of them
+/// next to fixed sizes; the tint shows which mip level is sampled.
+pub fn draw (
+  context: &Gl,
+  _width: f32,
+  _height: f32,
+) -> Result<(), JsValue> {
+  let mut batch = SpriteBatch::new(context)?;
+  let mut rows = Vec::new();
+  for &(name, ref bytes) in &[
+    ("DDS", dds_checker(256)),
+    ("KTX", ktx_gradient(256)),
+    ("KTX", ktx_etc1(256)),
+  ] {
+    let texture = CompressedTexture::from_bytes(context, bytes)?;
+    console::log_1(&format!(
+      "{} {:?}: {}x{}, {} levels, {}",
+      name, texture.format, texture.width, texture.height, texture.levels,
+      if texture.decoded { "decoded in software" } else { "uploaded as is" },
+    ).into());
+    let size = texture.width as i32;
+    rows.push(batch.register(texture.texture, size, size));
+  }
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let (width, height) = (ctx.drawing_buffer_width(), ctx.drawing_buffer_height());
+    ctx.viewport(0, 0, width, height);
+    ctx.clear_color(0.1, 0.1, 0.12, 1.0);
+    ctx.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    let height = height as f32;
+
+    let row_height = (height - 40.0) / rows.len() as f32;
+    for (i, &texture) in rows.iter().enumerate() {
+      let top = 10.0 + i as f32 * (row_height + 10.0);
+      let mut x = 10.0;
+      for &size in &[128.0, 64.0, 32.0, 16.0, 8.0] {
+        let size = size * row_height / 128.0;
+        batch.push(Sprite::new(texture, [x, top], [size, size]));
+        x += size + 10.0;
+      }
+      let size = row_height * (0.55 + 0.45 * (time + i as f32).sin());
+      let mut sprite = Sprite::new(texture, [x + row_height * 0.5 + 10.0, top + row_height * 0.5], [size, size]);
+      sprite.pivot = [0.5, 0.5];
+      batch.push(sprite);
+    }
+    batch.flush(&ctx).unwrap();
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::scene::Scene;
+use renderer::stats::{self, FrameStats};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const ARMS: usize = 12;
+const CUBES_PER_ARM: usize = 20;
+
+/// Arms of cubes spinning around a camera at the center, so most of
+/// them are behind or beside it at any time and get culled.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let meshes = vec![Mesh::new(context, &geometry::cube())?];
+
+  let mut scene = Scene::new();
+  let root = scene.add("root", None, Mat4::identity());
+  for arm in 0..ARMS {
+    let angle = arm as f32 / ARMS as f32 * 2.0 * std::f32::consts::PI;
+    let tilt = glm::rotate(&Mat4::identity(), angle, &glm::vec3(0.0, 1.0, 0.0));
+    let parent = scene.add(&format!("arm{}", arm), Some(root), tilt);
+    for i in 0..CUBES_PER_ARM {
+      let offset = glm::vec3(0.0, (i % 3) as f32 - 1.0, -4.0 - i as f32 * 2.5);
+      let local = glm::scale(
+        &glm::translate(&Mat4::identity(), &offset),
+        &glm::vec3(0.5, 0.5, 0.5),
+      );
+      scene.add_mesh(&format!("arm{}.cube{}", arm, i), Some(parent), local, 0, meshes[0].bounds);
+    }
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let camera = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: glm::vec3(0.0, 0.0, 0.0),
+  };
+  let frustum = camera.frustum();
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut frame = FrameStats::default();
+
+    scene.set_local(root, glm::rotate(&Mat4::identity(), rotation, &glm::vec3(0.0, 1.0, 0.0)));
+    scene.update_world();
+    // Culling happens before any GL call for the skipped objects.
+    let visible = scene.visible(&frustum, &mut frame);
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+    for id in visible {
+      let node = scene.node(id);
+      let mesh = &meshes[node.mesh.unwrap()];
+      let shade = (id % CUBES_PER_ARM) as f32 / CUBES_PER_ARM as f32;
+      program.draw(&ctx, mesh, &node.world, [1.0 - shade, 0.4, shade, 1.0]);
+      frame.record_draw(mesh.triangle_count());
+    }
+    stats::publish(frame);
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
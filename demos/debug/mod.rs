@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::debug::DebugDraw;
+use renderer::debug::lines::{CYAN, GREY, YELLOW};
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::scene::Scene;
+use renderer::stats::{self, FrameStats};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const RING: usize = 16;
+
+/// A ring of cubes and spheres seen from above while a second camera
+/// turns at its center. Only what that camera sees is drawn; its
+/// frustum is drawn on top of the scene, and every object's bounds in
+/// green when it's inside and red when it's culled, with vertex normals,
+/// axes and a ground grid tested against the depth buffer.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let mut debug = DebugDraw::new(context)?;
+  let geometries = vec![geometry::cube(), geometry::sphere(16, 12)];
+  let meshes = vec![Mesh::new(context, &geometries[0])?, Mesh::new(context, &geometries[1])?];
+
+  let mut scene = Scene::new();
+  let root = scene.add("root", None, Mat4::identity());
+  for i in 0..RING {
+    let angle = i as f32 / RING as f32 * 2.0 * std::f32::consts::PI;
+    let position = glm::vec3(angle.cos() * 6.0, 0.5, angle.sin() * 6.0);
+    let local = glm::scale(
+      &glm::rotate(&glm::translate(&Mat4::identity(), &position), angle, &glm::vec3(0.0, 1.0, 0.0)),
+      &glm::vec3(0.5, 0.5, 0.5),
+    );
+    let mesh = i % 2;
+    scene.add_mesh(&format!("object{}", i), Some(root), local, mesh, meshes[mesh].bounds);
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let mut overview = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: glm::vec3(0.0, 0.0, 0.0),
+  };
+  let mut culling = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, 4.0 / 3.0, 0.5, 9.0),
+    position: glm::vec3(0.0, 1.0, 0.0),
+  };
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut frame = FrameStats::default();
+
+    let eye = glm::vec3((time * 0.2).cos() * 16.0, 11.0, (time * 0.2).sin() * 16.0);
+    overview.view = glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0));
+    overview.position = eye;
+    let target = culling.position + glm::vec3(time.cos(), -0.1, time.sin());
+    culling.view = glm::look_at(&culling.position, &target, &glm::vec3(0.0, 1.0, 0.0));
+
+    scene.update_world();
+    let frustum = culling.frustum();
+    let visible = scene.visible(&frustum, &mut frame);
+
+    ctx.clear_color(0.05, 0.05, 0.08, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &overview, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+    for id in visible {
+      let node = scene.node(id);
+      let mesh = &meshes[node.mesh.unwrap()];
+      program.draw(&ctx, mesh, &node.world, [0.8, 0.8, 0.85, 1.0]);
+      frame.record_draw(mesh.triangle_count());
+    }
+
+    debug.depth_tested.grid(10.0, 1.0, GREY);
+    debug.depth_tested.scene_bounds(&scene, Some(&frustum));
+    debug.depth_tested.scene_normals(&scene, &geometries, 0.3, CYAN);
+    for node in scene.nodes().iter().filter(|node| node.mesh.is_some()) {
+      debug.depth_tested.axes(&node.world, 1.5);
+    }
+    debug.on_top.frustum(&culling.view_projection(), YELLOW);
+    debug.on_top.axes(&glm::inverse(&culling.view), 1.0);
+    frame.draw_calls += debug.flush(&ctx, &overview.view_projection()).unwrap();
+    stats::publish(frame);
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,183 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  console,
+  HtmlCanvasElement,
+  MouseEvent,
+  WebGlRenderingContext,
+};
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::idpick::IdPass;
+use renderer::instancing::{InstancedMesh, InstancedProgram, Instancing};
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const GRID: usize = 30;
+/// Ids of the plain meshes start after the instances.
+const PILLAR_IDS: u32 = (GRID * GRID) as u32;
+
+const INSTANCE_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 1.0];
+const SELECTED_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+
+/// Where the mouse was pressed, `None` when no button is down.
+type Drag = Rc<Cell<Option<(i32, i32)>>>;
+/// A selection rectangle waiting for the next frame's id pass.
+type Request = Rc<Cell<Option<(i32, i32, i32, i32)>>>;
+
+/// A field of instanced cubes and a few plain pillars. Click to select
+/// one object, drag to select everything inside the rectangle; the id
+/// pass only runs on those frames.
+pub fn draw (
+  canvas: &HtmlCanvasElement,
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let instancing = Instancing::new(context)?;
+  let instanced_program = InstancedProgram::new(context)?;
+  let mut uniforms = SceneUniforms::new(context)?;
+  let lit = LitProgram::new(context, &uniforms)?;
+  let id_pass = IdPass::new(context, width as i32, height as i32)?;
+
+  let cube = geometry::cube();
+  let pillar = Mesh::new(context, &cube)?;
+  let mut cubes = InstancedMesh::new(context, &cube, GRID * GRID)?;
+  for x in 0..GRID {
+    for z in 0..GRID {
+      let position = glm::vec3(x as f32 * 2.5 - 36.0, 0.0, z as f32 * 2.5 - 36.0);
+      cubes.push(&glm::translate(&Mat4::identity(), &position), INSTANCE_COLOR);
+    }
+  }
+  let pillars: Vec<Mat4> = (0..5).map(|i| {
+    let position = glm::vec3(i as f32 * 12.0 - 24.0, 4.0, 0.0);
+    glm::scale(&glm::translate(&Mat4::identity(), &position), &glm::vec3(1.0, 4.0, 1.0))
+  }).collect();
+  let mut pillar_selected = vec![false; pillars.len()];
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let eye = glm::vec3(0.0, 45.0, 60.0);
+  let camera = Camera {
+    view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 200.0),
+    position: eye,
+  };
+  let light = Light::default();
+
+  let drag: Drag = Rc::new(Cell::new(None));
+  let request: Request = Rc::new(Cell::new(None));
+  {
+    // Mouse positions in drawing buffer pixels, the canvas may be
+    // scaled by CSS.
+    let element = canvas.clone();
+    let to_pixels = move |event: &MouseEvent| (
+      (event.offset_x() as f32 * width / element.client_width() as f32) as i32,
+      (event.offset_y() as f32 * height / element.client_height() as f32) as i32,
+    );
+
+    let down = drag.clone();
+    let down_pixels = to_pixels.clone();
+    let on_mouse_down = Closure::wrap(Box::new(move |event: MouseEvent| {
+      down.set(Some(down_pixels(&event)));
+    }) as Box<FnMut(MouseEvent)>);
+    canvas.add_event_listener_with_callback("mousedown", on_mouse_down.as_ref().unchecked_ref())?;
+    on_mouse_down.forget();
+
+    let up = drag.clone();
+    let pending = request.clone();
+    let on_mouse_up = Closure::wrap(Box::new(move |event: MouseEvent| {
+      if let Some((x0, y0)) = up.take() {
+        let (x1, y1) = to_pixels(&event);
+        // A short drag is still a click.
+        if (x1 - x0).abs() < 3 && (y1 - y0).abs() < 3 {
+          pending.set(Some((x1, y1, 1, 1)));
+        } else {
+          pending.set(Some((x0, y0, x1 - x0, y1 - y0)));
+        }
+      }
+    }) as Box<FnMut(MouseEvent)>);
+    canvas.add_event_listener_with_callback("mouseup", on_mouse_up.as_ref().unchecked_ref())?;
+    on_mouse_up.forget();
+  }
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    if let Some((x, y, w, h)) = request.take() {
+      id_pass.begin(&ctx, &camera);
+      id_pass.draw_instanced(&ctx, &instancing, &mut cubes, 0).unwrap();
+      for (i, model) in pillars.iter().enumerate() {
+        id_pass.draw(&ctx, &pillar, model, PILLAR_IDS + i as u32);
+      }
+      id_pass.end(&ctx, width as i32, height as i32);
+      let ids = id_pass.read_rect(&ctx, x, y, w, h).unwrap();
+      console::log_1(&format!("selected ids: {:?}", ids).into());
+
+      for i in 0..cubes.len() {
+        cubes.set_color(i, INSTANCE_COLOR);
+      }
+      for selected in pillar_selected.iter_mut() {
+        *selected = false;
+      }
+      for id in ids {
+        if id < PILLAR_IDS {
+          cubes.set_color(id as usize, SELECTED_COLOR);
+        } else if let Some(selected) = pillar_selected.get_mut((id - PILLAR_IDS) as usize) {
+          *selected = true;
+        }
+      }
+    }
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    ctx.use_program(Some(&instanced_program.program));
+    let data: JsValue = JsValue::from_serde(&camera.projection).unwrap().into();
+    ctx.uniform_matrix4fv_with_f32_sequence(
+        instanced_program.projection_matrix.as_ref(), false, &data
+    );
+    let data: JsValue = JsValue::from_serde(&camera.view).unwrap().into();
+    ctx.uniform_matrix4fv_with_f32_sequence(
+        instanced_program.view_matrix.as_ref(), false, &data
+    );
+    cubes.draw(&ctx, &instancing, &instanced_program).unwrap();
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    lit.begin(&ctx, &uniforms);
+    for (model, &selected) in pillars.iter().zip(pillar_selected.iter()) {
+      let color = if selected { SELECTED_COLOR } else { [0.8, 0.8, 0.8, 1.0] };
+      lit.draw(&ctx, &pillar, model, color);
+    }
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
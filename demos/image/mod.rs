@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  console,
+  CanvasRenderingContext2d,
+  HtmlCanvasElement,
+  WebGlRenderingContext,
+};
+
+use renderer::gl::Gl;
+use renderer::image::ImageTexture;
+use renderer::image::pipeline::DecodeOptions;
+use renderer::sprites::{Sprite, SpriteBatch};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A `width` x `height` 2D canvas with stripes, a ring and a label, on
+/// an opaque background or a transparent one.
+fn paint(width: u32, height: u32, opaque: bool) -> Result<HtmlCanvasElement, JsValue> {
+  let canvas = window()
+      .document()
+      .unwrap()
+      .create_element("canvas")?
+      .dyn_into::<HtmlCanvasElement>()?;
+  canvas.set_width(width);
+  canvas.set_height(height);
+  let context = canvas
+      .get_context("2d")?
+      .unwrap()
+      .dyn_into::<CanvasRenderingContext2d>()?;
+  let (width, height) = (width as f64, height as f64);
+  if opaque {
+    let gradient = context.create_linear_gradient(0.0, 0.0, width, height);
+    gradient.add_color_stop(0.0, "#1d3b6e")?;
+    gradient.add_color_stop(1.0, "#d8703a")?;
+    context.set_fill_style(&gradient);
+    context.fill_rect(0.0, 0.0, width, height);
+    context.set_fill_style(&JsValue::from_str("rgba(255, 255, 255, 0.8)"));
+    let mut x = 0.0;
+    while x < width {
+      context.fill_rect(x, 0.0, 3.0, height);
+      x += 12.0;
+    }
+  }
+  context.set_stroke_style(&JsValue::from_str("#ffd23f"));
+  context.set_line_width(height * 0.08);
+  context.begin_path();
+  context.arc(width * 0.5, height * 0.5, height * 0.35, 0.0, 2.0 * std::f64::consts::PI)?;
+  context.stroke();
+  context.set_fill_style(&JsValue::from_str(if opaque { "#ffffff" } else { "#e03050" }));
+  context.set_font(&format!("bold {}px sans-serif", (height * 0.22) as u32));
+  context.set_text_align("center");
+  context.set_text_baseline("middle");
+  context.fill_text(&format!("{}x{}", width, height), width * 0.5, height * 0.5)?;
+  Ok(canvas)
+}
+
+/// The bytes of `canvas` encoded by the browser as `mime`.
+fn encode(canvas: &HtmlCanvasElement, mime: &str) -> Result<Vec<u8>, JsValue> {
+  let url = canvas.to_data_url_with_type(mime)?;
+  let base64 = url.splitn(2, ',').nth(1).ok_or("bad data URL")?;
+  // `atob` returns one character per byte.
+  Ok(window().atob(base64)?.chars().map(|c| c as u8).collect())
+}
+
+/// The repo's PNG and two canvases the browser encodes, a JPEG and a
+/// PNG with transparency, all decoded in Rust with mipmaps built in
+/// software, the canvases at sizes that aren't powers of two. Each row
+/// shrinks and grows one of them next to fixed sizes over a checker, so
+/// the alpha edges and the minified levels show; see the console for
+/// sizes and decode times.
+pub fn draw (
+  context: &Gl,
+  _width: f32,
+  _height: f32,
+) -> Result<(), JsValue> {
+  let sources = vec![
+    ("cubetexture.png", include_bytes!("../../tutorial/sample6/cubetexture.png").to_vec()),
+    ("canvas JPEG", encode(&paint(300, 180, true)?, "image/jpeg")?),
+    ("canvas PNG", encode(&paint(240, 150, false)?, "image/png")?),
+  ];
+
+  let mut batch = SpriteBatch::new(context)?;
+  let mut rows = Vec::new();
+  for (name, bytes) in sources {
+    let start = js_sys::Date::now();
+    let texture = ImageTexture::from_bytes(context, &bytes, &DecodeOptions::default())?;
+    console::log_1(&format!(
+      "{}: {} bytes to {}x{} with {} levels in {:.1} ms",
+      name, bytes.len(), texture.width, texture.height, texture.levels, js_sys::Date::now() - start,
+    ).into());
+    let aspect = texture.width as f32 / texture.height as f32;
+    let (width, height) = (texture.width as i32, texture.height as i32);
+    rows.push((batch.register(texture.texture, width, height), aspect));
+  }
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.01;
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let (width, height) = (ctx.drawing_buffer_width(), ctx.drawing_buffer_height());
+    ctx.viewport(0, 0, width, height);
+    ctx.clear_color(0.1, 0.1, 0.12, 1.0);
+    ctx.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+
+    let (width, height) = (width as f32, height as f32);
+    let cell = 16.0;
+    for row in 0..(height / cell) as i32 + 1 {
+      for column in 0..(width / cell) as i32 + 1 {
+        if (row + column) % 2 == 0 {
+          let position = [column as f32 * cell, row as f32 * cell];
+          batch.push(Sprite::quad(position, [cell, cell], [0.22, 0.22, 0.26, 1.0]));
+        }
+      }
+    }
+
+    let row_height = (height - 40.0) / rows.len() as f32;
+    for (i, &(texture, aspect)) in rows.iter().enumerate() {
+      let top = 10.0 + i as f32 * (row_height + 10.0);
+      let mut x = 10.0;
+      for &scale in &[1.0, 0.5, 0.25, 0.125] {
+        let size = [row_height * scale * aspect, row_height * scale];
+        batch.push(Sprite::new(texture, [x, top], size));
+        x += size[0] + 10.0;
+      }
+      let scale = 0.55 + 0.45 * (time + i as f32).sin();
+      let size = [row_height * scale * aspect, row_height * scale];
+      let mut sprite = Sprite::new(texture, [x + row_height * aspect * 0.5, top + row_height * 0.5], size);
+      sprite.pivot = [0.5, 0.5];
+      batch.push(sprite);
+    }
+    batch.flush(&ctx).unwrap();
+
+    time += delta_time;
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+  Ok(())
+}
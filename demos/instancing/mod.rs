@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::instancing::{InstancedMesh, InstancedProgram, Instancing};
+
+/// Cubes per axis of the grid, 25 x 20 x 20 = 10k cubes.
+const GRID: (usize, usize, usize) = (25, 20, 20);
+const SPACING: f32 = 3.0;
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A stress test: ten thousand spinning sample5 style cubes drawn with a
+/// single instanced draw call per frame.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let instancing = Instancing::new(context)?;
+  let program = InstancedProgram::new(context)?;
+
+  let (nx, ny, nz) = GRID;
+  let count = nx * ny * nz;
+  let mut cubes = InstancedMesh::new(context, &geometry::cube(), count)?;
+
+  // Where every cube sits and which axis it spins around.
+  let mut placements = Vec::with_capacity(count);
+  for x in 0..nx {
+    for y in 0..ny {
+      for z in 0..nz {
+        let position = glm::vec3(
+          (x as f32 - (nx - 1) as f32 * 0.5) * SPACING,
+          (y as f32 - (ny - 1) as f32 * 0.5) * SPACING,
+          (z as f32 - (nz - 1) as f32 * 0.5) * SPACING,
+        );
+        let axis = glm::normalize(&glm::vec3(
+          1.0 + x as f32 / nx as f32,
+          0.5 + y as f32 / ny as f32,
+          z as f32 / nz as f32,
+        ));
+        let color = [
+          x as f32 / (nx - 1) as f32,
+          y as f32 / (ny - 1) as f32,
+          z as f32 / (nz - 1) as f32,
+          1.0,
+        ];
+        cubes.push(&glm::translate(&Mat4::identity(), &position), color);
+        placements.push((position, axis));
+      }
+    }
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let projection_matrix = glm::perspective(field_of_view, width / height, 0.1, 400.0);
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    // Every cube spins, so every instance is rewritten each frame.
+    for (i, &(position, axis)) in placements.iter().enumerate() {
+      let mut model = glm::translate(&Mat4::identity(), &position);
+      model = glm::rotate(&model, rotation * (1.0 + (i % 7) as f32 * 0.2), &axis);
+      cubes.set_transform(i, &model);
+    }
+
+    // Slowly orbit the camera around the grid.
+    let eye = glm::vec3(
+      (rotation * 0.1).sin() * 110.0,
+      40.0,
+      (rotation * 0.1).cos() * 110.0,
+    );
+    let view_matrix = glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0));
+
+    ctx.use_program(Some(&program.program));
+    let data: JsValue = JsValue::from_serde(&projection_matrix).unwrap().into();
+    ctx.uniform_matrix4fv_with_f32_sequence(
+        program.projection_matrix.as_ref(), false, &data
+    );
+    let data: JsValue = JsValue::from_serde(&view_matrix).unwrap().into();
+    ctx.uniform_matrix4fv_with_f32_sequence(
+        program.view_matrix.as_ref(), false, &data
+    );
+
+    cubes.draw(&ctx, &instancing, &program).unwrap();
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
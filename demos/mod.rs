@@ -0,0 +1,21 @@
+pub mod postfx;
+pub mod instancing;
+pub mod backend;
+pub mod culling;
+pub mod picking;
+pub mod idpick;
+pub mod text;
+pub mod sprites;
+pub mod particles;
+pub mod skinning;
+pub mod timeline;
+pub mod skybox;
+pub mod pbr;
+pub mod atlas;
+pub mod compressed;
+pub mod image;
+pub mod debug;
+pub mod viewmode;
+pub mod terrain;
+pub mod transparency;
+pub mod resources;
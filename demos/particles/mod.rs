@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::particles::{Blend, ParticleRenderer, RenderMode};
+use renderer::particles::curve::Curve;
+use renderer::particles::emitter::{Distribution, Emitter, EmitterConfig, Shape};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A spinning cube shedding sparks from its faces, with a fountain
+/// beside it and smoke rising from a sphere on the other side.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let lit = LitProgram::new(context, &uniforms)?;
+  let cube_geometry = geometry::cube();
+  let cube = Mesh::new(context, &cube_geometry)?;
+  let mut renderer = ParticleRenderer::new(context)?;
+
+  let mut sparks = Emitter::new(EmitterConfig {
+    shape: Shape::mesh_surface(&cube_geometry),
+    rate: 300.0,
+    lifetime: Distribution::Uniform { min: 0.4, max: 1.0 },
+    speed: Distribution::Normal { mean: 2.0, deviation: 0.5 },
+    gravity: glm::vec3(0.0, -4.0, 0.0),
+    drag: 1.0,
+    size: Curve::linear(0.15, 0.0),
+    color: Curve::new(vec![
+      (0.0, [1.0, 1.0, 0.8, 1.0]),
+      (0.3, [1.0, 0.6, 0.1, 1.0]),
+      (1.0, [0.6, 0.1, 0.0, 0.0]),
+    ]),
+    max_particles: 2000,
+    seed: 7,
+    ..EmitterConfig::default()
+  });
+
+  let mut fountain = Emitter::new(EmitterConfig {
+    shape: Shape::Cone { angle: 0.25, radius: 0.1 },
+    rate: 400.0,
+    lifetime: Distribution::Constant(2.0),
+    speed: Distribution::Uniform { min: 5.0, max: 6.0 },
+    gravity: glm::vec3(0.0, -9.8, 0.0),
+    size: Curve::constant(0.12),
+    color: Curve::linear([0.3, 0.6, 1.0, 1.0], [0.1, 0.2, 1.0, 0.0]),
+    max_particles: 1000,
+    seed: 11,
+    ..EmitterConfig::default()
+  });
+  fountain.transform = glm::translate(&Mat4::identity(), &glm::vec3(-4.0, -2.0, 0.0));
+
+  let mut smoke = Emitter::new(EmitterConfig {
+    shape: Shape::Sphere { radius: 0.5 },
+    rate: 40.0,
+    lifetime: Distribution::Uniform { min: 2.5, max: 3.5 },
+    speed: Distribution::Constant(0.2),
+    velocity: glm::vec3(0.0, 1.0, 0.0),
+    drag: 0.3,
+    size: Curve::linear(0.6, 2.0),
+    color: Curve::new(vec![
+      (0.0, [0.5, 0.5, 0.5, 0.0]),
+      (0.2, [0.5, 0.5, 0.5, 0.5]),
+      (1.0, [0.3, 0.3, 0.3, 0.0]),
+    ]),
+    max_particles: 200,
+    seed: 3,
+    ..EmitterConfig::default()
+  });
+  smoke.transform = glm::translate(&Mat4::identity(), &glm::vec3(4.0, -2.0, 0.0));
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let eye = glm::vec3(0.0, 2.0, 12.0);
+  let camera = Camera {
+    view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: eye,
+  };
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  // A fixed step keeps the simulation deterministic.
+  let delta_time = 1.0 / 60.0;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut model = glm::rotate(&Mat4::identity(), rotation, &glm::vec3(0.5, 1.0, 0.0));
+    model = glm::scale(&model, &glm::vec3(0.8, 0.8, 0.8));
+    sparks.transform = model;
+    sparks.update(delta_time);
+    fountain.update(delta_time);
+    smoke.update(delta_time);
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    lit.begin(&ctx, &uniforms);
+    lit.draw(&ctx, &cube, &model, [0.3, 0.3, 0.35, 1.0]);
+
+    renderer.draw(&ctx, &smoke, &camera, RenderMode::Quads, Blend::Alpha).unwrap();
+    renderer.draw(&ctx, &fountain, &camera, RenderMode::Points, Blend::Additive).unwrap();
+    renderer.draw(&ctx, &sparks, &camera, RenderMode::Quads, Blend::Additive).unwrap();
+
+    rotation += 0.01;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlRenderingContext, WebGlTexture};
+
+use glm::Mat4;
+
+use demos::skybox::{canvas_texture, paint_panorama};
+use renderer::cubemap::{Skybox, TextureCube};
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::pbr::{Environment, PbrMaterial, PbrProgram};
+use renderer::texture::create_rgba_texture;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const GRID: usize = 5;
+const MAP_SIZE: i32 = 128;
+const TILES: i32 = 4;
+
+/// Normal, occlusion and emissive maps of raised tiles with glowing
+/// grout between them.
+fn tile_maps(context: &Gl) -> Result<(WebGlTexture, WebGlTexture, WebGlTexture), JsValue> {
+  let tile = MAP_SIZE / TILES;
+  // 0 in the grout, rising to 1 over a bevel at each tile's edge.
+  let height = |x: i32, y: i32| {
+    let (x, y) = ((x + MAP_SIZE) % tile, (y + MAP_SIZE) % tile);
+    let edge = x.min(y).min(tile - 1 - x).min(tile - 1 - y) as f32;
+    ((edge - 1.0) / 4.0).max(0.0).min(1.0)
+  };
+  let (mut normals, mut occlusion, mut emissive) = (Vec::new(), Vec::new(), Vec::new());
+  for y in 0..MAP_SIZE {
+    for x in 0..MAP_SIZE {
+      let dx = height(x + 1, y) - height(x - 1, y);
+      let dy = height(x, y + 1) - height(x, y - 1);
+      let normal = glm::normalize(&glm::vec3(-dx * 2.0, -dy * 2.0, 1.0));
+      let byte = |value: f32| ((value * 0.5 + 0.5) * 255.0) as u8;
+      normals.extend_from_slice(&[byte(normal.x), byte(normal.y), byte(normal.z), 255]);
+      let h = height(x, y);
+      let shade = (96.0 + 159.0 * h) as u8;
+      occlusion.extend_from_slice(&[shade, shade, shade, 255]);
+      let glow = if h == 0.0 { 255 } else { 0 };
+      emissive.extend_from_slice(&[glow, glow / 2, 0, 255]);
+    }
+  }
+  let linear = WebGlRenderingContext::LINEAR;
+  Ok((
+    create_rgba_texture(context, MAP_SIZE, MAP_SIZE, &normals, linear)?,
+    create_rgba_texture(context, MAP_SIZE, MAP_SIZE, &occlusion, linear)?,
+    create_rgba_texture(context, MAP_SIZE, MAP_SIZE, &emissive, linear)?,
+  ))
+}
+
+/// Spheres from dielectric to metal bottom to top and from smooth to
+/// rough left to right, lit by the skybox panorama, and a tiled cube
+/// and sphere with generated tangents showing normal, occlusion and
+/// emissive maps.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let panorama = canvas_texture(context, &paint_panorama()?)?;
+  let cube_map = TextureCube::from_equirectangular(context, &panorama, 512)?;
+  context.delete_texture(Some(&panorama));
+  let environment = Environment::new(context, &cube_map)?;
+
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = PbrProgram::new(context, &uniforms)?;
+  let skybox = Skybox::new(context)?;
+  let sphere = Mesh::new(context, &geometry::sphere(48, 24))?;
+  let mut tiled_cube = geometry::cube();
+  tiled_cube.generate_tangents()?;
+  let cube = Mesh::new(context, &tiled_cube)?;
+  let mut tiled_sphere = geometry::sphere(48, 24);
+  tiled_sphere.generate_tangents()?;
+  let tiled_sphere = Mesh::new(context, &tiled_sphere)?;
+
+  let mut spheres = Vec::new();
+  for row in 0..GRID {
+    for column in 0..GRID {
+      let material = PbrMaterial {
+        base_color: [0.9, 0.6, 0.2, 1.0],
+        metallic: row as f32 / (GRID - 1) as f32,
+        roughness: column as f32 / (GRID - 1) as f32,
+        ..PbrMaterial::default()
+      };
+      let offset = glm::vec3(column as f32 * 2.4 - 4.8, row as f32 * 2.4 - 4.8, 0.0);
+      spheres.push((glm::translate(&Mat4::identity(), &offset), material));
+    }
+  }
+  let (normal, occlusion, emissive) = tile_maps(context)?;
+  let tiles = PbrMaterial {
+    base_color: [0.8, 0.8, 0.85, 1.0],
+    metallic: 0.0,
+    roughness: 0.35,
+    emissive: [1.0, 1.0, 1.0],
+    normal_texture: Some(normal),
+    occlusion_texture: Some(occlusion),
+    emissive_texture: Some(emissive),
+    ..PbrMaterial::default()
+  };
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let projection = glm::perspective(field_of_view, width / height, 0.1, 100.0);
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.005;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let position = glm::vec3(rotation.sin() * 16.0, 2.0, rotation.cos() * 16.0);
+    let camera = Camera {
+      view: glm::look_at(&position, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+      projection,
+      position,
+    };
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms, &environment);
+    for &(ref model, ref material) in &spheres {
+      program.draw(&ctx, &sphere, model, material);
+    }
+    let model = glm::rotate(
+      &glm::translate(&Mat4::identity(), &glm::vec3(0.0, 0.0, 3.0)),
+      rotation * 4.0,
+      &glm::vec3(0.3, 1.0, 0.0),
+    );
+    program.draw(&ctx, &cube, &model, &tiles);
+    let model = glm::rotate(
+      &glm::translate(&Mat4::identity(), &glm::vec3(0.0, 0.0, -3.0)),
+      rotation * 2.0,
+      &glm::vec3(0.0, 1.0, 0.0),
+    );
+    program.draw(&ctx, &tiled_sphere, &model, &tiles);
+    skybox.draw(&ctx, &cube_map, &camera);
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
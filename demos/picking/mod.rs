@@ -0,0 +1,134 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  console,
+  HtmlCanvasElement,
+  MouseEvent,
+  WebGlRenderingContext,
+};
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::picking::{self, Ray};
+use renderer::scene::{NodeId, Scene};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// A rotating ring of cubes; clicking one ray casts into the scene and
+/// highlights the hit.
+pub fn draw (
+  canvas: &HtmlCanvasElement,
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  // Picking needs the triangles on the CPU, keep them next to the meshes.
+  let geometries = vec![geometry::cube()];
+  let meshes = vec![Mesh::new(context, &geometries[0])?];
+
+  let scene = Rc::new(RefCell::new(Scene::new()));
+  let root = {
+    let mut scene = scene.borrow_mut();
+    let root = scene.add("ring", None, Mat4::identity());
+    for i in 0..8 {
+      let angle = i as f32 / 8.0 * 2.0 * std::f32::consts::PI;
+      let mut local = glm::translate(&Mat4::identity(), &glm::vec3(angle.cos() * 4.0, 0.0, angle.sin() * 4.0));
+      local = glm::rotate(&local, angle, &glm::vec3(1.0, 1.0, 0.0));
+      scene.add_mesh(&format!("cube{}", i), Some(root), local, 0, meshes[0].bounds);
+    }
+    root
+  };
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let eye = glm::vec3(0.0, 6.0, 12.0);
+  let camera = Camera {
+    view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: eye,
+  };
+  let light = Light::default();
+
+  let selected: Rc<Cell<Option<NodeId>>> = Rc::new(Cell::new(None));
+
+  {
+    let scene = scene.clone();
+    let selected = selected.clone();
+    let camera = camera.clone();
+    let element = canvas.clone();
+    let on_mouse_down = Closure::wrap(Box::new(move |event: MouseEvent| {
+      // The canvas may be scaled by CSS, pick in drawing buffer pixels.
+      let x = event.offset_x() as f32 * width / element.client_width() as f32;
+      let y = event.offset_y() as f32 * height / element.client_height() as f32;
+      let ray = Ray::from_camera(x, y, width, height, &camera);
+      let hit = picking::pick(&scene.borrow(), &geometries, &ray);
+      selected.set(hit.as_ref().map(|hit| hit.node));
+      if let Some(ref hit) = hit {
+        console::log_1(&format!("picked {} triangle {}", hit.name, hit.triangle).into());
+      }
+      picking::notify(hit.as_ref()).unwrap();
+    }) as Box<FnMut(MouseEvent)>);
+    canvas.add_event_listener_with_callback("mousedown", on_mouse_down.as_ref().unchecked_ref())?;
+    on_mouse_down.forget();
+  }
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.005;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut scene = scene.borrow_mut();
+    scene.set_local(root, glm::rotate(&Mat4::identity(), rotation, &glm::vec3(0.0, 1.0, 0.0)));
+    scene.update_world();
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+    for (id, node) in scene.nodes().iter().enumerate() {
+      if let Some(mesh) = node.mesh {
+        let color = if selected.get() == Some(id) {
+          [1.0, 0.85, 0.2, 1.0]
+        } else {
+          [0.3, 0.5, 0.9, 1.0]
+        };
+        program.draw(&ctx, &meshes[mesh], &node.world, color);
+      }
+    }
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
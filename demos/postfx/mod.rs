@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::postfx::{self, PostChain};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// The sample5 cube, lit brighter than 1.0 so bloom and tone mapping
+/// have something to work with, run through the post chain.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let cube = Mesh::new(context, &geometry::cube())?;
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let camera = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: glm::vec3(0.0, 0.0, 0.0),
+  };
+  let light = Light {
+    color: glm::vec3(2.5, 2.5, 2.5),
+    ..Light::default()
+  };
+
+  // The chain is shared with the `postfx_*` exports so passes can be
+  // toggled from JS while the loop runs.
+  let chain = Rc::new(RefCell::new(PostChain::new(context, width as i32, height as i32)?));
+  postfx::set_active_chain(Some(chain.clone()));
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut square_rotation = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let chain = chain.borrow();
+
+    // Render the scene offscreen, then run it through the passes.
+    chain.begin_scene(&ctx);
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    let mut model = glm::translate(&Mat4::identity(), &glm::vec3(-0.0, 0.0, -6.0));
+    model = glm::rotate(&model, square_rotation, &glm::vec3(0.5, 0.0, 1.0));
+    model = glm::rotate(&model, square_rotation * 0.7, &glm::vec3(0.0, 1.0, 0.0));
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+    program.draw(&ctx, &cube, &model, [1.0, 0.6, 0.25, 1.0]);
+
+    chain.present(&ctx);
+
+    square_rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,217 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  console,
+  HtmlCanvasElement,
+  MouseEvent,
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::contextloss::ContextLoss;
+use renderer::geometry::{self, Geometry};
+use renderer::gl::Gl;
+use renderer::lit;
+use renderer::resources::{self, Handle, Resources};
+use renderer::stats::{self, FrameStats};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// Buffers of one geometry; cloning shares them.
+#[derive(Clone)]
+struct Shape {
+  positions: Handle<WebGlBuffer>,
+  normals: Handle<WebGlBuffer>,
+  indices: Handle<WebGlBuffer>,
+  index_count: i32,
+}
+
+impl Shape {
+  fn new(resources: &Resources, geometry: &Geometry) -> Result<Shape, JsValue> {
+    Ok(Shape {
+      positions: resources.array_buffer(&geometry.positions)?,
+      normals: resources.array_buffer(&geometry.normals)?,
+      indices: resources.element_buffer(&geometry.indices)?,
+      index_count: geometry.indices.len() as i32,
+    })
+  }
+
+  fn draw(&self, context: &Gl) {
+    for &(location, buffer) in &[(0, &self.positions), (1, &self.normals)] {
+      context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&*buffer.get()));
+      context.vertex_attrib_pointer_with_i32(location, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+      context.enable_vertex_attrib_array(location);
+    }
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&*self.indices.get()));
+    context.draw_elements_with_i32(
+        WebGlRenderingContext::TRIANGLES,
+        self.index_count,
+        WebGlRenderingContext::UNSIGNED_SHORT,
+        0,
+    );
+    context.disable_vertex_attrib_array(1);
+  }
+}
+
+/// What isn't managed and so must be made again for a restored
+/// context: the uniform buffers and the program's locations.
+struct Uniforms {
+  scene: SceneUniforms,
+  model_matrix: Option<WebGlUniformLocation>,
+  color: Option<WebGlUniformLocation>,
+}
+
+impl Uniforms {
+  fn new(context: &Gl, program: &WebGlProgram) -> Result<Uniforms, JsValue> {
+    let scene = SceneUniforms::new(context)?;
+    scene.attach(context, program);
+    Ok(Uniforms {
+      scene,
+      model_matrix: context.get_uniform_location(program, "uModelMatrix"),
+      color: context.get_uniform_location(program, "uColor"),
+    })
+  }
+}
+
+fn log_resources(label: &str) {
+  let live = resources::live_resources();
+  console::log_1(&format!(
+    "{}: {} buffers, {} textures, {} programs, {} framebuffers, {:.1} KiB",
+    label, live.buffers, live.textures, live.programs, live.framebuffers, live.bytes as f32 / 1024.0,
+  ).into());
+}
+
+/// A ring of spheres whose buffers are created through a resource
+/// manager. Every second one sphere is rebuilt at a new detail; the
+/// others keep sharing the old buffers until they are rebuilt too, and
+/// the last handle to go deletes them. Clicking the canvas stops the
+/// loop and tears the sample down, which the console log shows.
+///
+/// `renderer_lose_context()` and then `renderer_restore_context()` from
+/// JS pause the loop and bring every sphere and the program back.
+pub fn draw (
+  canvas: &HtmlCanvasElement,
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let resources = Resources::new(context);
+  let loss = ContextLoss::watch(canvas, context)?;
+  let program = resources.program(&lit::SHADERS)?;
+  let mut uniforms = Uniforms::new(context, &program.get())?;
+
+  let shared = Shape::new(&resources, &geometry::sphere(8, 6))?;
+  let mut shapes = vec![shared; 8];
+  log_resources("start");
+
+  let running = Rc::new(RefCell::new(true));
+  {
+    let running = running.clone();
+    let on_mouse_down = Closure::wrap(Box::new(move |_event: MouseEvent| {
+      *running.borrow_mut() = false;
+    }) as Box<FnMut(MouseEvent)>);
+    canvas.add_event_listener_with_callback("mousedown", on_mouse_down.as_ref().unchecked_ref())?;
+    on_mouse_down.forget();
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let eye = glm::vec3(0.0, 6.0, 12.0);
+  let camera = Camera {
+    view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: eye,
+  };
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut frame_count: u32 = 0;
+  let mut rebuilt = 0;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    if !*running.borrow() {
+      shapes.clear();
+      log_resources("released shapes");
+      let deleted = resources.teardown();
+      console::log_1(&format!("teardown deleted {} objects", deleted).into());
+      log_resources("torn down");
+      // Not scheduling another frame ends the loop.
+      return;
+    }
+
+    if loss.is_lost() {
+      request_animation_frame(f.borrow().as_ref().unwrap());
+      return;
+    }
+    if loss.take_restored() {
+      match Uniforms::new(&ctx, &program.get()) {
+        Ok(restored) => uniforms = restored,
+        Err(error) => console::error_1(&error),
+      }
+      log_resources("restored");
+    }
+
+    let mut frame = FrameStats::default();
+    if frame_count % 60 == 59 {
+      let (index, detail) = (rebuilt % 8, 8 + (rebuilt % 5) * 4);
+      match Shape::new(&resources, &geometry::sphere(detail, detail * 3 / 4)) {
+        Ok(shape) => shapes[index] = shape,
+        Err(error) => console::error_1(&error),
+      }
+      rebuilt += 1;
+      log_resources(&format!("rebuilt sphere {}", index));
+    }
+
+    ctx.clear_color(0.08, 0.08, 0.1, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    let program = program.get();
+    uniforms.scene.update(&ctx, &camera, &light).unwrap();
+    ctx.use_program(Some(&*program));
+    uniforms.scene.apply(&ctx, &program);
+    for (i, shape) in shapes.iter().enumerate() {
+      let angle = i as f32 / 8.0 * 2.0 * std::f32::consts::PI + frame_count as f32 * 0.005;
+      let model = glm::translate(&Mat4::identity(), &glm::vec3(angle.cos() * 4.0, 0.0, angle.sin() * 4.0));
+      let data: JsValue = JsValue::from_serde(&model).unwrap().into();
+      ctx.uniform_matrix4fv_with_f32_sequence(uniforms.model_matrix.as_ref(), false, &data);
+      // Spheres sharing buffers share a colour.
+      let shade = 0.4 + 0.15 * (shape.positions.id() % 5) as f32;
+      ctx.uniform4f(uniforms.color.as_ref(), shade, 0.5, 1.0 - shade, 1.0);
+      shape.draw(&ctx);
+      frame.record_draw(shape.index_count as u32 / 3);
+    }
+    stats::publish(frame);
+
+    frame_count += 1;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
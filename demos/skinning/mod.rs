@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::capabilities::capabilities;
+use renderer::geometry::Geometry;
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::skinning::{SkinnedProgram, SkinningMode};
+use renderer::skinning::clip::{Animator, Channel, Clip, Interpolation, Keys};
+use renderer::skinning::skeleton::{quat_from_axis_angle, Joint, Pose, Skeleton, Transform};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const JOINTS: usize = 6;
+const SEGMENT_LENGTH: f32 = 0.6;
+const RINGS_PER_SEGMENT: usize = 4;
+const SIDES: usize = 12;
+
+/// A chain of joints straight up `+Y`, one segment apart.
+fn tentacle_skeleton() -> Result<Skeleton, String> {
+  let joints = (0..JOINTS)
+      .map(|i| Joint { name: format!("joint{}", i), parent: if i == 0 { None } else { Some(i - 1) } })
+      .collect();
+  let rest = Pose {
+    joints: (0..JOINTS)
+        .map(|i| Transform {
+          translation: [0.0, if i == 0 { 0.0 } else { SEGMENT_LENGTH }, 0.0],
+          ..Transform::default()
+        })
+        .collect(),
+  };
+  Skeleton::new(joints, rest)
+}
+
+/// A tapering tube along the skeleton, each ring weighted between the
+/// two joints it sits between. Returns the geometry with its joint and
+/// weight attributes.
+fn tentacle_geometry() -> (Geometry, Vec<f32>, Vec<f32>) {
+  let mut geometry = Geometry::default();
+  let mut joints = Vec::new();
+  let mut weights = Vec::new();
+  let rings = (JOINTS - 1) * RINGS_PER_SEGMENT + 1;
+  for ring in 0..rings {
+    let along = ring as f32 / RINGS_PER_SEGMENT as f32;
+    let joint = (along.floor() as usize).min(JOINTS - 2);
+    let t = along - joint as f32;
+    let radius = 0.25 * (1.0 - 0.8 * ring as f32 / (rings - 1) as f32);
+    for side in 0..SIDES {
+      let angle = side as f32 / SIDES as f32 * 2.0 * std::f32::consts::PI;
+      let (sin, cos) = angle.sin_cos();
+      geometry.positions.extend_from_slice(&[cos * radius, along * SEGMENT_LENGTH, sin * radius]);
+      geometry.normals.extend_from_slice(&[cos, 0.0, sin]);
+      joints.extend_from_slice(&[joint as f32, (joint + 1) as f32, 0.0, 0.0]);
+      weights.extend_from_slice(&[1.0 - t, t, 0.0, 0.0]);
+    }
+  }
+  for ring in 0..rings - 1 {
+    for side in 0..SIDES {
+      let a = (ring * SIDES + side) as u16;
+      let b = (ring * SIDES + (side + 1) % SIDES) as u16;
+      let (c, d) = (a + SIDES as u16, b + SIDES as u16);
+      geometry.indices.extend_from_slice(&[a, c, b, b, c, d]);
+    }
+  }
+  (geometry, joints, weights)
+}
+
+/// Every joint but the root turning around `axis` through `angles`,
+/// one key per second.
+fn bend_clip(name: &str, axis: [f32; 3], angles: &[f32], interpolation: Interpolation) -> Result<Clip, String> {
+  let times: Vec<f32> = (0..angles.len()).map(|i| i as f32).collect();
+  let channels = (1..JOINTS)
+      .map(|joint| Channel {
+        joint,
+        interpolation,
+        times: times.clone(),
+        keys: Keys::Rotation(angles.iter().map(|&angle| quat_from_axis_angle(axis, angle)).collect()),
+      })
+      .collect();
+  Clip::new(name, channels)
+}
+
+/// Two tentacles cross-fading between a smooth sway and a stepped
+/// twitch every few seconds. The left one gets its joints as uniforms,
+/// the right one from a float texture when the context can do that.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let skeleton = tentacle_skeleton()?;
+  let clips = vec![
+    bend_clip("sway", [0.0, 0.0, 1.0], &[0.0, 0.35, 0.0, -0.35, 0.0], Interpolation::Linear)?,
+    bend_clip("twitch", [1.0, 0.0, 0.0], &[0.0, 0.3, -0.1, 0.4, 0.0], Interpolation::Step)?,
+  ];
+
+  let (geometry, joints, weights) = tentacle_geometry();
+  let mesh = Mesh::with_attributes(context, &geometry, &[(3, 4, &joints), (4, 4, &weights)])?;
+
+  let mut programs = vec![SkinnedProgram::with_mode(context, &uniforms, JOINTS, SkinningMode::Uniforms)?];
+  let caps = capabilities(context);
+  if caps.features.float_textures && caps.limits.max_vertex_texture_image_units > 0 {
+    programs.push(SkinnedProgram::with_mode(context, &uniforms, JOINTS, SkinningMode::Texture)?);
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let position = glm::vec3(0.0, 1.5, 6.0);
+  let camera = Camera {
+    view: glm::look_at(&position, &glm::vec3(0.0, 1.5, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position,
+  };
+  let light = Light::default();
+
+  let mut animator = Animator::new(0);
+  let mut matrices = Vec::new();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut next_switch: f32 = 3.0;
+  let delta_time = 1.0 / 60.0;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    next_switch -= delta_time;
+    if next_switch <= 0.0 {
+      let next = (animator.current.clip + 1) % clips.len();
+      animator.cross_fade(next, 0.75);
+      next_switch = 3.0;
+    }
+    animator.update(delta_time, &clips);
+    skeleton.joint_matrices(&animator.pose(&skeleton, &clips), &mut matrices);
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    for (i, program) in programs.iter().enumerate() {
+      let x = if programs.len() == 1 { 0.0 } else { i as f32 * 2.0 - 1.0 };
+      let model = glm::translate(&Mat4::identity(), &glm::vec3(x, 0.0, 0.0));
+      let color = if i == 0 { [0.9, 0.5, 0.3, 1.0] } else { [0.3, 0.6, 0.9, 1.0] };
+      program.begin(&ctx, &uniforms);
+      program.draw(&ctx, &mesh, &model, color, &matrices).unwrap();
+    }
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, WebGlRenderingContext, WebGlTexture};
+
+use glm::Mat4;
+
+use renderer::cubemap::{ReflectiveProgram, Skybox, TextureCube};
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::texture::set_sampling;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const PANORAMA_WIDTH: u32 = 1024;
+const PANORAMA_HEIGHT: u32 = 512;
+
+/// Paint an equirectangular panorama: sky, sun, ground and a labelled
+/// post every 45 degrees so the orientation is easy to follow.
+pub fn paint_panorama() -> Result<HtmlCanvasElement, JsValue> {
+  let document = window().document().unwrap();
+  let canvas = document
+      .create_element("canvas")?
+      .dyn_into::<HtmlCanvasElement>()?;
+  canvas.set_width(PANORAMA_WIDTH);
+  canvas.set_height(PANORAMA_HEIGHT);
+  let context = canvas
+      .get_context("2d")?
+      .unwrap()
+      .dyn_into::<CanvasRenderingContext2d>()?;
+  let (width, height) = (PANORAMA_WIDTH as f64, PANORAMA_HEIGHT as f64);
+  let horizon = height / 2.0;
+
+  let sky = context.create_linear_gradient(0.0, 0.0, 0.0, horizon);
+  sky.add_color_stop(0.0, "#0b1d4a")?;
+  sky.add_color_stop(1.0, "#9cc8f0")?;
+  context.set_fill_style(&sky);
+  context.fill_rect(0.0, 0.0, width, horizon);
+  let ground = context.create_linear_gradient(0.0, horizon, 0.0, height);
+  ground.add_color_stop(0.0, "#6b5a3a")?;
+  ground.add_color_stop(1.0, "#2a2116")?;
+  context.set_fill_style(&ground);
+  context.fill_rect(0.0, horizon, width, height - horizon);
+
+  context.set_fill_style(&JsValue::from_str("#fff3c0"));
+  context.begin_path();
+  context.arc(width * 0.3, horizon * 0.45, 24.0, 0.0, 2.0 * std::f64::consts::PI)?;
+  context.fill();
+
+  context.set_font("28px sans-serif");
+  context.set_text_align("center");
+  for (i, label) in ["E", "NE", "N", "NW", "W", "SW", "S", "SE"].iter().enumerate() {
+    let x = (i as f64 + 0.5) * width / 8.0;
+    context.set_fill_style(&JsValue::from_str(if i % 2 == 0 { "#c03030" } else { "#3060c0" }));
+    context.fill_rect(x - 6.0, horizon - 80.0, 12.0, 100.0);
+    context.set_fill_style(&JsValue::from_str("white"));
+    context.fill_text(label, x, horizon - 90.0)?;
+  }
+  Ok(canvas)
+}
+
+/// A linearly filtered, clamped texture of `canvas`.
+pub fn canvas_texture(context: &Gl, canvas: &HtmlCanvasElement) -> Result<WebGlTexture, JsValue> {
+  let texture = context.create_texture().ok_or("failed to create texture")?;
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  context.tex_image_2d_with_canvas(
+      WebGlRenderingContext::TEXTURE_2D,
+      0,
+      WebGlRenderingContext::RGBA as i32,
+      WebGlRenderingContext::RGBA,
+      WebGlRenderingContext::UNSIGNED_BYTE,
+      canvas,
+  )?;
+  set_sampling(context, WebGlRenderingContext::LINEAR);
+  Ok(texture)
+}
+
+/// A painted panorama turned into a cube map on the GPU, drawn as a
+/// skybox around an orbiting camera and reflected by a sphere and a
+/// cube.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let panorama = canvas_texture(context, &paint_panorama()?)?;
+  let environment = TextureCube::from_equirectangular(context, &panorama, 512)?;
+  context.delete_texture(Some(&panorama));
+
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = ReflectiveProgram::new(context, &uniforms)?;
+  let skybox = Skybox::new(context)?;
+  let sphere = Mesh::new(context, &geometry::sphere(48, 24))?;
+  let cube = Mesh::new(context, &geometry::cube())?;
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let projection = glm::perspective(field_of_view, width / height, 0.1, 100.0);
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.005;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let position = glm::vec3(rotation.sin() * 6.0, 1.0, rotation.cos() * 6.0);
+    let camera = Camera {
+      view: glm::look_at(&position, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+      projection,
+      position,
+    };
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms, &environment);
+    let left = glm::translate(&Mat4::identity(), &glm::vec3(-1.5, 0.0, 0.0));
+    program.draw(&ctx, &sphere, &left, [1.0, 1.0, 1.0, 1.0], 0.9);
+    let right = glm::rotate(
+      &glm::translate(&Mat4::identity(), &glm::vec3(1.5, 0.0, 0.0)),
+      rotation * 3.0,
+      &glm::vec3(1.0, 1.0, 0.0),
+    );
+    program.draw(&ctx, &cube, &glm::scale(&right, &glm::vec3(0.7, 0.7, 0.7)), [0.8, 0.6, 0.2, 1.0], 0.5);
+    skybox.draw(&ctx, &environment, &camera);
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
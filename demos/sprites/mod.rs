@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use renderer::gl::Gl;
+use renderer::sprites::{Sprite, SpriteBatch};
+use renderer::stats::{self, FrameStats};
+use renderer::texture::create_rgba_texture;
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const FRAME: usize = 32;
+
+/// Two 32x32 frames side by side: a soft disc and a diamond.
+fn sprite_sheet() -> Vec<u8> {
+  let mut pixels = Vec::with_capacity(FRAME * 2 * FRAME * 4);
+  for y in 0..FRAME {
+    for x in 0..FRAME * 2 {
+      let (fx, fy) = ((x % FRAME) as f32 - 15.5, y as f32 - 15.5);
+      let alpha = if x < FRAME {
+        1.0 - ((fx * fx + fy * fy).sqrt() / 16.0).min(1.0)
+      } else if fx.abs() + fy.abs() < 15.0 {
+        1.0
+      } else {
+        0.0
+      };
+      pixels.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+    }
+  }
+  pixels
+}
+
+fn checker() -> Vec<u8> {
+  let mut pixels = Vec::with_capacity(8 * 8 * 4);
+  for y in 0..8 {
+    for x in 0..8 {
+      let shade = if (x + y) % 2 == 0 { 40 } else { 60 };
+      pixels.extend_from_slice(&[shade, shade, shade + 10, 255]);
+    }
+  }
+  pixels
+}
+
+/// A few hundred rotating sprites from one sheet over a tiled
+/// background, with a plain quad overlay on top. Sprites are pushed in
+/// mixed order; sorting brings it down to one draw per layer and
+/// texture, see `renderer_frame_stats()`.
+pub fn draw (
+  context: &Gl,
+  _width: f32,
+  _height: f32,
+) -> Result<(), JsValue> {
+  let mut batch = SpriteBatch::new(context)?;
+  let sheet = batch.register(
+    create_rgba_texture(context, (FRAME * 2) as i32, FRAME as i32, &sprite_sheet(), WebGlRenderingContext::LINEAR)?,
+    (FRAME * 2) as i32,
+    FRAME as i32,
+  );
+  let background = batch.register(
+    create_rgba_texture(context, 8, 8, &checker(), WebGlRenderingContext::NEAREST)?,
+    8,
+    8,
+  );
+  let frames = [
+    batch.region(sheet, 0.0, 0.0, FRAME as f32, FRAME as f32),
+    batch.region(sheet, FRAME as f32, 0.0, FRAME as f32, FRAME as f32),
+  ];
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    // The canvas may have been resized, the batch camera follows it.
+    let (width, height) = (ctx.drawing_buffer_width(), ctx.drawing_buffer_height());
+    ctx.viewport(0, 0, width, height);
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    let (width, height) = (width as f32, height as f32);
+
+    for i in 0..400 {
+      let t = time + i as f32 * 0.05;
+      let radius = 40.0 + (i % 40) as f32 * 5.0;
+      let mut sprite = Sprite::new(
+        sheet,
+        [width * 0.5 + t.cos() * radius, height * 0.5 + (t * 1.3).sin() * radius * 0.7],
+        [24.0, 24.0],
+      );
+      sprite.uv = frames[i % 2];
+      sprite.pivot = [0.5, 0.5];
+      sprite.rotation = t * 2.0;
+      sprite.color = [0.5 + 0.5 * t.sin(), 0.6, 0.5 + 0.5 * t.cos(), 0.9];
+      sprite.layer = (i % 2) as i32;
+      batch.push(sprite);
+
+      // Interleave the background and overlay with the sprites to give
+      // the sort something to do.
+      if i < 12 {
+        let mut tile = Sprite::new(background, [(i % 4) as f32 * width / 4.0, (i / 4) as f32 * height / 3.0], [width / 4.0, height / 3.0]);
+        tile.layer = -1;
+        batch.push(tile);
+      }
+      if i % 100 == 0 {
+        let mut bar = Sprite::quad([10.0, 10.0 + (i / 100) as f32 * 14.0], [100.0 + (time * 3.0 + i as f32).sin() * 60.0, 10.0], [1.0, 0.8, 0.2, 0.8]);
+        bar.layer = 10;
+        batch.push(bar);
+      }
+    }
+
+    let mut frame = FrameStats::default();
+    frame.objects = batch.len() as u32;
+    frame.draw_calls = batch.flush(&ctx).unwrap();
+    frame.triangles = frame.objects * 2;
+    stats::publish(frame);
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
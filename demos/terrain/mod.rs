@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{console, WebGlRenderingContext};
+
+use glm::Mat4;
+
+use renderer::debug::DebugDraw;
+use renderer::debug::lines::{BLUE, GREEN, RED, YELLOW};
+use renderer::gl::Gl;
+use renderer::image::pipeline::Image;
+use renderer::random::Rng;
+use renderer::stats::{self, FrameStats};
+use renderer::terrain::{SplatMaterial, Terrain, TerrainProgram};
+use renderer::terrain::chunk::TerrainOptions;
+use renderer::terrain::heightfield::Heightfield;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const SAMPLES: usize = 257;
+const HEIGHT: f32 = 40.0;
+
+/// `size` x `size` values from 0 to 1: random values on a lattice of
+/// `cells` squares, smoothly interpolated, summed over octaves.
+fn fractal_noise(size: usize, cells: usize, octaves: usize, rng: &mut Rng) -> Vec<f32> {
+  let mut values = vec![0.0; size * size];
+  let mut amplitude = 0.5;
+  let mut cells = cells;
+  for _ in 0..octaves {
+    let lattice: Vec<f32> = (0..(cells + 1) * (cells + 1)).map(|_| rng.next_f32()).collect();
+    for z in 0..size {
+      for x in 0..size {
+        let (fx, fz) = (x as f32 * cells as f32 / size as f32, z as f32 * cells as f32 / size as f32);
+        let (ix, iz) = (fx.floor() as usize, fz.floor() as usize);
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, tz) = (smooth(fx - ix as f32), smooth(fz - iz as f32));
+        let at = |x: usize, z: usize| lattice[z * (cells + 1) + x];
+        let near = at(ix, iz) * (1.0 - tx) + at(ix + 1, iz) * tx;
+        let far = at(ix, iz + 1) * (1.0 - tx) + at(ix + 1, iz + 1) * tx;
+        values[z * size + x] += (near * (1.0 - tz) + far * tz) * amplitude;
+      }
+    }
+    amplitude *= 0.5;
+    cells *= 2;
+  }
+  values
+}
+
+/// Sand by the lowest ground, grass on flat ground, rock on slopes and
+/// snow on the tops, blended near the boundaries.
+fn splat_weights(heightfield: &Heightfield, spacing: f32) -> Image {
+  let (low, high) = heightfield.range();
+  let mut pixels = Vec::with_capacity(heightfield.width * heightfield.depth * 4);
+  for z in 0..heightfield.depth {
+    for x in 0..heightfield.width {
+      let height = (heightfield.height(x, z) - low) / (high - low);
+      let steep = 1.0 - heightfield.normal(x, z, spacing)[1];
+      let ramp = |value: f32, from: f32, to: f32| ((value - from) / (to - from)).max(0.0).min(1.0);
+      let rock = ramp(steep, 0.15, 0.3);
+      let snow = ramp(height, 0.7, 0.8) * (1.0 - rock);
+      let sand = (1.0 - ramp(height, 0.2, 0.28)) * (1.0 - rock);
+      let grass = (1.0 - rock - snow - sand).max(0.0);
+      for &weight in &[grass, rock, sand, snow] {
+        pixels.push((weight * 255.0).round() as u8);
+      }
+    }
+  }
+  Image { width: heightfield.width as u32, height: heightfield.depth as u32, pixels, gamma: None }
+}
+
+/// A 64x64 speckled `color`, standing in for a layer photo.
+fn layer(color: [f32; 3], rng: &mut Rng) -> Image {
+  let mut pixels = Vec::with_capacity(64 * 64 * 4);
+  for _ in 0..64 * 64 {
+    let shade = rng.range(0.75, 1.15);
+    for &channel in &color {
+      pixels.push(((channel * shade).min(1.0) * 255.0) as u8);
+    }
+    pixels.push(255);
+  }
+  Image { width: 64, height: 64, pixels, gamma: None }
+}
+
+/// Terrain from a noise heightfield, painted from a weight map of
+/// height and slope, flown over in a circle. The chunk outlines show the
+/// level each is drawn at, from green for the finest through yellow and
+/// blue to red for the coarsest; the skirts keep the seams between
+/// levels closed.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut rng = Rng::new(46);
+  let heights = fractal_noise(SAMPLES, 4, 6, &mut rng).iter().map(|value| value * HEIGHT).collect();
+  let heightfield = Heightfield::new(SAMPLES, SAMPLES, heights)?;
+  let options = TerrainOptions { spacing: 2.0, lod_distance: 60.0, ..TerrainOptions::default() };
+
+  let start = js_sys::Date::now();
+  let terrain = Terrain::new(context, &heightfield, &options)?;
+  console::log_1(&format!(
+    "{} chunks of {} levels in {:.1} ms",
+    terrain.chunks().len(), options.levels(), js_sys::Date::now() - start,
+  ).into());
+
+  let layers = [
+    layer([0.3, 0.5, 0.2], &mut rng),
+    layer([0.45, 0.42, 0.4], &mut rng),
+    layer([0.8, 0.72, 0.5], &mut rng),
+    layer([0.95, 0.95, 1.0], &mut rng),
+  ];
+  let material = SplatMaterial::new(context, &splat_weights(&heightfield, options.spacing), &layers, 32.0)?;
+
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = TerrainProgram::new(context, &uniforms)?;
+  let mut debug = DebugDraw::new(context)?;
+  let light = Light::default();
+
+  let extent = (SAMPLES - 1) as f32 * options.spacing;
+  let center = glm::vec3(extent * 0.5, 0.0, extent * 0.5);
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let mut camera = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, width / height, 0.5, 1000.0),
+    position: glm::vec3(0.0, 0.0, 0.0),
+  };
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.002;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut frame = FrameStats::default();
+
+    let eye = center + glm::vec3(time.cos() * extent * 0.35, HEIGHT * 1.3, time.sin() * extent * 0.35);
+    let ahead = center + glm::vec3((time + 0.4).cos() * extent * 0.3, 0.0, (time + 0.4).sin() * extent * 0.3);
+    camera.view = glm::look_at(&eye, &ahead, &glm::vec3(0.0, 1.0, 0.0));
+    camera.position = eye;
+
+    ctx.clear_color(0.55, 0.7, 0.9, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.draw(&ctx, &uniforms, &terrain, &material, &camera, &mut frame);
+
+    for (index, lod) in terrain.visible(&camera, &mut FrameStats::default()) {
+      let color = [GREEN, YELLOW, BLUE, RED][lod.min(3)];
+      debug.depth_tested.aabb(&terrain.chunks()[index].bounds.aabb, color);
+    }
+    frame.draw_calls += debug.flush(&ctx, &camera.view_projection()).unwrap();
+    stats::publish(frame);
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  CanvasRenderingContext2d,
+  HtmlCanvasElement,
+  WebGlRenderingContext,
+};
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::text::{screen_projection, FontAtlas, TextBatch, TextProgram, TextSpace, TextStyle};
+use renderer::text::font::Font;
+use renderer::text::layout::Align;
+use renderer::text::sdf::distance_field;
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const COLUMNS: u32 = 16;
+const CELL_WIDTH: u32 = 40;
+const CELL_HEIGHT: u32 = 48;
+/// Room around each glyph so the distance field doesn't bleed into the
+/// next cell.
+const PADDING: u32 = 6;
+const SPREAD: usize = 5;
+
+/// Render printable ASCII with the browser's sans-serif font into a 2D
+/// canvas and describe it as a BMFont text file.
+fn build_font() -> Result<(HtmlCanvasElement, Font), JsValue> {
+  let document = window().document().unwrap();
+  let canvas = document
+      .create_element("canvas")?
+      .dyn_into::<HtmlCanvasElement>()?;
+  let rows = (96 + COLUMNS - 1) / COLUMNS;
+  let (width, height) = (COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT);
+  canvas.set_width(width);
+  canvas.set_height(height);
+  let context = canvas
+      .get_context("2d")?
+      .unwrap()
+      .dyn_into::<CanvasRenderingContext2d>()?;
+  context.set_font("30px sans-serif");
+  context.set_text_baseline("top");
+  context.set_fill_style(&JsValue::from_str("white"));
+
+  let mut descriptor = format!(
+    "info face=\"sans-serif\" size=30\ncommon lineHeight=36 base=28 scaleW={} scaleH={} pages=1\nchars count=96\n",
+    width, height,
+  );
+  for (i, code) in (32u32..128).enumerate() {
+    let c = std::char::from_u32(code).unwrap_or('?');
+    let text = c.to_string();
+    let (x, y) = ((i as u32 % COLUMNS) * CELL_WIDTH, (i as u32 / COLUMNS) * CELL_HEIGHT);
+    context.fill_text(&text, (x + PADDING) as f64, (y + PADDING) as f64)?;
+    let advance = context.measure_text(&text)?.width();
+    descriptor.push_str(&format!(
+      "char id={} x={} y={} width={} height={} xoffset=-{} yoffset=-{} xadvance={}\n",
+      code, x, y, CELL_WIDTH, CELL_HEIGHT, PADDING, PADDING, advance,
+    ));
+  }
+  Ok((canvas, Font::from_bmfont_text(&descriptor)?))
+}
+
+/// Spinning cubes with distance field labels floating above them, a
+/// frame counter and a wrapped, centered caption in screen space.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let lit = LitProgram::new(context, &uniforms)?;
+  let cube = Mesh::new(context, &geometry::cube())?;
+
+  let (canvas, font) = build_font()?;
+  let bitmap = FontAtlas::from_canvas(context, font.clone(), &canvas)?;
+
+  // The same glyphs as a distance field, so labels stay sharp however
+  // close the camera gets.
+  let (atlas_width, atlas_height) = (canvas.width(), canvas.height());
+  let context_2d = canvas
+      .get_context("2d")?
+      .unwrap()
+      .dyn_into::<CanvasRenderingContext2d>()?;
+  let image = context_2d.get_image_data(0.0, 0.0, atlas_width as f64, atlas_height as f64)?;
+  let alpha: Vec<u8> = image.data().chunks(4).map(|texel| texel[3]).collect();
+  let field = distance_field(&alpha, atlas_width as usize, atlas_height as usize, SPREAD);
+  let mut pixels = Vec::with_capacity(field.len() * 4);
+  for &distance in &field {
+    pixels.extend_from_slice(&[255, 255, 255, distance]);
+  }
+  let sdf_font = Font { distance_range: Some(SPREAD as f32 * 2.0), ..font };
+  let sdf = FontAtlas::from_rgba(context, sdf_font, &pixels)?;
+
+  let text_program = TextProgram::new(context)?;
+  let mut labels = TextBatch::new(context, TextSpace::Billboard)?;
+  let mut overlay = TextBatch::new(context, TextSpace::Screen)?;
+
+  let positions: Vec<_> = (0..3).map(|i| glm::vec3(i as f32 * 4.0 - 4.0, 0.0, 0.0)).collect();
+  let names = ["Alpha", "Beta", "Gamma"];
+  let label_style = TextStyle { size: 0.8, align: Align::Center, ..TextStyle::default() };
+  for (position, name) in positions.iter().zip(names.iter()) {
+    labels.add(&sdf.font, name, position + glm::vec3(0.0, 2.2, 0.0), &label_style)?;
+  }
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let projection = glm::perspective(field_of_view, width / height, 0.1, 100.0);
+  let overlay_projection = screen_projection(width, height);
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.01;
+  let mut last_time = js_sys::Date::now();
+  let mut fps = 60.0;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let now = js_sys::Date::now();
+    let elapsed = ((now - last_time) / 1000.0).max(1e-3);
+    fps = fps * 0.95 + 0.05 / elapsed;
+    last_time = now;
+
+    ctx.clear_color(0.05, 0.05, 0.1, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    // Dolly in and out to show the labels staying crisp.
+    let distance = 9.0 + (rotation * 0.5).sin() * 5.0;
+    let eye = glm::vec3((rotation * 0.3).sin() * distance, 3.0, (rotation * 0.3).cos() * distance);
+    let camera = Camera {
+      view: glm::look_at(&eye, &glm::vec3(0.0, 0.5, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+      projection,
+      position: eye,
+    };
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    lit.begin(&ctx, &uniforms);
+    for (i, position) in positions.iter().enumerate() {
+      let mut model = glm::translate(&Mat4::identity(), position);
+      model = glm::rotate(&model, rotation + i as f32, &glm::vec3(0.3, 1.0, 0.0));
+      lit.draw(&ctx, &cube, &model, [0.4 + i as f32 * 0.3, 0.5, 1.0 - i as f32 * 0.3, 1.0]);
+    }
+    labels.draw(&ctx, &text_program, &sdf, &camera.view, &camera.projection).unwrap();
+
+    overlay.clear();
+    let small = TextStyle { size: 16.0, color: [0.6, 1.0, 0.6, 1.0], ..TextStyle::default() };
+    overlay.add(&bitmap.font, &format!("{:.0} fps", fps), glm::vec3(8.0, 8.0, 0.0), &small).unwrap();
+    let caption = TextStyle {
+      size: 18.0,
+      align: Align::Center,
+      max_width: Some(width * 0.8),
+      ..TextStyle::default()
+    };
+    overlay.add(
+      &bitmap.font,
+      "Labels are distance field billboards in the world, this caption and the counter are bitmap text in canvas pixels.",
+      glm::vec3(width * 0.5, height - 70.0, 0.0),
+      &caption,
+    ).unwrap();
+    overlay.draw(&ctx, &text_program, &bitmap, &camera.view, &overlay_projection).unwrap();
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::LitProgram;
+use renderer::mesh::Mesh;
+use renderer::scene::Scene;
+use renderer::timeline::{self, MaterialParameters, Timeline};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// Played unless JS defined an animation named `demo` first.
+static DEMO_ANIMATION: &'static str = r#"{
+  "name": "demo",
+  "mode": "pingPong",
+  "tracks": [
+    { "target": { "translation": { "node": "left" } },
+      "keyframes": [
+        { "time": 0, "value": [0, 0, 0], "easing": "elastic" },
+        { "time": 2, "value": [0, 1.5, 0] } ] },
+    { "target": { "rotation": { "node": "middle" } },
+      "keyframes": [
+        { "time": 0, "value": [0, 0, 0], "easing": "bounce" },
+        { "time": 2, "value": [0, 3.14159, 0] } ] },
+    { "target": { "scale": { "node": "right" } },
+      "keyframes": [
+        { "time": 0, "value": [1, 1, 1], "easing": { "cubicBezier": [0.68, -0.6, 0.32, 1.6] } },
+        { "time": 1, "value": [1.5, 0.5, 1.5], "easing": { "cubicBezier": [0.68, -0.6, 0.32, 1.6] } },
+        { "time": 2, "value": [1, 1, 1] } ] },
+    { "target": { "parameter": { "material": "cube", "name": "color" } },
+      "keyframes": [
+        { "time": 0, "value": [1, 0.3, 0.2, 1] },
+        { "time": 2, "value": [0.2, 0.5, 1, 1] } ] }
+  ],
+  "markers": [
+    { "time": 0, "name": "start" },
+    { "time": 2, "name": "end" }
+  ]
+}"#;
+
+/// Three cubes driven by one timeline: an elastic hop, a bouncing spin
+/// and an overshooting squash, all sharing a colour that fades back and
+/// forth. Markers go to the `timeline_set_callback` function.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let meshes = vec![Mesh::new(context, &geometry::cube())?];
+
+  let mut scene = Scene::new();
+  for (i, name) in ["left", "middle", "right"].iter().enumerate() {
+    let offset = glm::vec3(i as f32 * 3.0 - 3.0, 0.0, 0.0);
+    let local = glm::translate(&Mat4::identity(), &offset);
+    scene.add_mesh(name, None, local, 0, meshes[0].bounds);
+  }
+
+  let animation = match timeline::defined("demo") {
+    Some(animation) => animation,
+    None => timeline::animation_from_json(&js_sys::JSON::parse(DEMO_ANIMATION)?)?,
+  };
+  let mut timeline = Timeline::new(animation)?;
+  let mut parameters = MaterialParameters::new();
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let position = glm::vec3(0.0, 1.0, 10.0);
+  let camera = Camera {
+    view: glm::look_at(&position, &glm::vec3(0.0, 0.5, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position,
+  };
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let delta_time = 1.0 / 60.0;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let markers = timeline.update(delta_time);
+    timeline::notify(&timeline, &markers).unwrap();
+    timeline.apply(&mut scene, &mut parameters);
+    scene.update_world();
+
+    ctx.clear_color(0.0, 0.0, 0.0, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    let color = match parameters.get("cube", "color") {
+      Some(value) if value.len() == 4 => [value[0], value[1], value[2], value[3]],
+      _ => [1.0, 1.0, 1.0, 1.0],
+    };
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+    for node in scene.nodes() {
+      program.draw(&ctx, &meshes[node.mesh.unwrap()], &node.world, color);
+    }
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext;
+
+use glm::Mat4;
+
+use renderer::blend::BlendMode;
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::lit::{LitMaterial, LitProgram};
+use renderer::mesh::Mesh;
+use renderer::queue::RenderQueue;
+use renderer::stats::{self, FrameStats};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+const CUBE: usize = 0;
+const SPHERE: usize = 1;
+
+struct Object {
+  mesh: usize,
+  model: Mat4,
+  material: LitMaterial,
+}
+
+/// Opaque cubes on a floor with overlapping spheres in front of and
+/// between them, one row per blend mode: alpha, additive, multiply and
+/// premultiplied. The camera circles the scene so the transparent
+/// spheres keep swapping places in the back to front order.
+pub fn draw (
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let program = LitProgram::new(context, &uniforms)?;
+  let meshes = vec![
+    Mesh::new(context, &geometry::cube())?,
+    Mesh::new(context, &geometry::sphere(24, 16))?,
+  ];
+
+  let mut objects = Vec::new();
+  let floor = glm::scale(&glm::translate(&Mat4::identity(), &glm::vec3(0.0, -1.6, 0.0)), &glm::vec3(8.0, 0.1, 8.0));
+  objects.push(Object { mesh: CUBE, model: floor, material: LitMaterial { color: [0.5, 0.5, 0.5, 1.0], ..LitMaterial::default() } });
+  for i in 0..4 {
+    let x = (i as f32 - 1.5) * 3.0;
+    let model = glm::translate(&Mat4::identity(), &glm::vec3(x, -0.5, -3.0));
+    let color = [0.2 + 0.2 * i as f32, 0.4, 0.9 - 0.2 * i as f32, 1.0];
+    objects.push(Object { mesh: CUBE, model, material: LitMaterial { color, ..LitMaterial::default() } });
+  }
+  let modes = [
+    (BlendMode::Alpha, [0.9, 0.3, 0.2, 0.5]),
+    (BlendMode::Additive, [0.2, 0.6, 1.0, 0.6]),
+    (BlendMode::Multiply, [1.0, 0.8, 0.3, 1.0]),
+    // Half covering white, already multiplied by its alpha.
+    (BlendMode::Premultiplied, [0.5, 0.5, 0.5, 0.5]),
+  ];
+  for (row, &(blend, color)) in modes.iter().enumerate() {
+    for column in 0..3 {
+      let position = glm::vec3((column as f32 - 1.0) * 1.4, 0.0, 3.0 - row as f32 * 2.0);
+      let model = glm::scale(&glm::translate(&Mat4::identity(), &position), &glm::vec3(0.9, 0.9, 0.9));
+      objects.push(Object { mesh: SPHERE, model, material: LitMaterial { color, blend } });
+    }
+  }
+  let centers: Vec<_> = objects.iter().map(|object| {
+    glm::vec3(object.model[(0, 3)], object.model[(1, 3)], object.model[(2, 3)])
+  }).collect();
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let mut camera = Camera {
+    view: Mat4::identity(),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: glm::vec3(0.0, 0.0, 0.0),
+  };
+  let light = Light::default();
+  let mut queue = RenderQueue::new();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut time: f32 = 0.0;
+  let delta_time = 0.005;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut frame = FrameStats::default();
+
+    let eye = glm::vec3(time.sin() * 14.0, 5.0, time.cos() * 14.0);
+    camera.view = glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0));
+    camera.position = eye;
+
+    ctx.clear_color(0.08, 0.08, 0.1, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    program.begin(&ctx, &uniforms);
+
+    queue.begin(&camera.view);
+    for (index, (object, center)) in objects.iter().zip(centers.iter()).enumerate() {
+      queue.push(object.mesh as u32, object.material.blend, center, index);
+    }
+    queue.draw(&ctx, |entry| {
+      let object = &objects[entry.item];
+      let mesh = &meshes[object.mesh];
+      program.draw(&ctx, mesh, &object.model, object.material.color);
+      frame.record_draw(mesh.triangle_count());
+    });
+    stats::publish(frame);
+
+    time += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{console, HtmlCanvasElement, MouseEvent, WebGlRenderingContext};
+
+use glm::Mat4;
+
+use renderer::geometry;
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::stats::{self, FrameStats};
+use renderer::uniforms::{Camera, Light, SceneUniforms};
+use renderer::viewmode::{self, ModeRenderer};
+
+fn window() -> web_sys::Window {
+  web_sys::window().expect("no global `window` exists")
+}
+
+fn request_animation_frame(f: &Closure<FnMut()>) {
+  window()
+      .request_animation_frame(f.as_ref().unchecked_ref())
+      .expect("should register `requestAnimationFrame` OK");
+}
+
+/// Cubes and spheres turning in a row, drawn in the view mode picked
+/// with `view_mode_set` from JS; clicking the canvas moves to the next
+/// one. The meshes are uploaded once and never rebuilt.
+pub fn draw (
+  canvas: &HtmlCanvasElement,
+  context: &Gl,
+  width: f32,
+  height: f32,
+) -> Result<(), JsValue> {
+  let mut uniforms = SceneUniforms::new(context)?;
+  let mut renderer = ModeRenderer::new(context, &uniforms)?;
+  renderer.depth_range = [8.0, 18.0];
+  let meshes = vec![
+    Mesh::new(context, &geometry::cube())?,
+    Mesh::new(context, &geometry::sphere(24, 16))?,
+    Mesh::new(context, &geometry::sphere(8, 6))?,
+  ];
+
+  {
+    let on_mouse_down = Closure::wrap(Box::new(move |_event: MouseEvent| {
+      viewmode::set_current(viewmode::current().next());
+      console::log_1(&format!("view mode: {}", viewmode::current().name()).into());
+    }) as Box<FnMut(MouseEvent)>);
+    canvas.add_event_listener_with_callback("mousedown", on_mouse_down.as_ref().unchecked_ref())?;
+    on_mouse_down.forget();
+  }
+  console::log_1(&format!("view mode: {}, click for the next one", viewmode::current().name()).into());
+
+  let field_of_view = 45.0 * std::f32::consts::PI / 180.0;   // in radians
+  let eye = glm::vec3(0.0, 4.0, 12.0);
+  let camera = Camera {
+    view: glm::look_at(&eye, &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0)),
+    projection: glm::perspective(field_of_view, width / height, 0.1, 100.0),
+    position: eye,
+  };
+  let light = Light::default();
+
+  let f = Rc::new(RefCell::new(None));
+  let g = f.clone();
+
+  let mut rotation: f32 = 0.0;
+  let delta_time = 0.01;
+
+  let ctx = context.clone();
+  *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    let mut frame = FrameStats::default();
+    renderer.mode = viewmode::current();
+
+    ctx.clear_color(0.08, 0.08, 0.1, 1.0);
+    ctx.clear_depth(1.0);
+    ctx.enable(WebGlRenderingContext::DEPTH_TEST);
+    ctx.depth_func(WebGlRenderingContext::LEQUAL);
+    ctx.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    uniforms.update(&ctx, &camera, &light).unwrap();
+    renderer.begin(&ctx, &uniforms);
+    for (i, mesh) in meshes.iter().enumerate() {
+      let position = glm::vec3((i as f32 - 1.0) * 3.5, 0.0, 0.0);
+      let model = glm::rotate(
+        &glm::translate(&Mat4::identity(), &position),
+        rotation * (1.0 + i as f32 * 0.3),
+        &glm::vec3(0.3, 1.0, 0.2),
+      );
+      renderer.draw(&ctx, mesh, &model, [0.9, 0.5, 0.3, 1.0]);
+      frame.record_draw(mesh.triangle_count());
+    }
+    stats::publish(frame);
+
+    rotation += delta_time;
+
+    // Schedule ourself for another requestAnimationFrame callback.
+    request_animation_frame(f.borrow().as_ref().unwrap());
+  }) as Box<FnMut()>));
+
+  request_animation_frame(g.borrow().as_ref().unwrap());
+
+  Ok(())
+}
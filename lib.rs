@@ -17,6 +17,8 @@ use web_sys::{
 // use js_sys::WebAssembly;
 
 mod tutorial;
+pub mod renderer;
+mod demos;
 
 /* Web GL */
 
@@ -29,6 +31,37 @@ pub fn drawwebgl(data: &JsValue) -> Result<(), JsValue> {
   let canvas = document.get_element_by_id("canvas").unwrap();
   let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
 
+  // The renderer demos create their own context, WebGL2 when possible.
+  if sample_id >= 9 {
+    let gl = renderer::gl::Gl::from_canvas(&canvas)?;
+    let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+    match sample_id {
+      9 => demos::postfx::draw(&gl, width, height)?,
+      10 => demos::instancing::draw(&gl, width, height)?,
+      11 => demos::backend::draw(&gl, width, height)?,
+      12 => demos::culling::draw(&gl, width, height)?,
+      13 => demos::picking::draw(&canvas, &gl, width, height)?,
+      14 => demos::idpick::draw(&canvas, &gl, width, height)?,
+      15 => demos::text::draw(&gl, width, height)?,
+      16 => demos::sprites::draw(&gl, width, height)?,
+      17 => demos::particles::draw(&gl, width, height)?,
+      18 => demos::skinning::draw(&gl, width, height)?,
+      19 => demos::timeline::draw(&gl, width, height)?,
+      20 => demos::skybox::draw(&gl, width, height)?,
+      21 => demos::pbr::draw(&gl, width, height)?,
+      22 => demos::atlas::draw(&gl, width, height)?,
+      23 => demos::compressed::draw(&gl, width, height)?,
+      24 => demos::image::draw(&gl, width, height)?,
+      25 => demos::debug::draw(&gl, width, height)?,
+      26 => demos::viewmode::draw(&canvas, &gl, width, height)?,
+      27 => demos::terrain::draw(&gl, width, height)?,
+      28 => demos::transparency::draw(&gl, width, height)?,
+      29 => demos::resources::draw(&canvas, &gl, width, height)?,
+      _ => (),
+    }
+    return Ok(());
+  }
+
   let context = canvas
       .get_context("webgl")?
       .unwrap()
@@ -0,0 +1,202 @@
+//! Many small images packed at runtime into a few atlas textures.
+//!
+//! `packer` decides where images go; `TextureAtlas` keeps a copy of
+//! every image's pixels, uploads each with its edges extruded into the
+//! border around it, and re-uploads everything when a new image makes
+//! the layout repack. Images are looked up by key for their page and
+//! UV rectangle.
+
+pub mod packer;
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  CanvasRenderingContext2d,
+  HtmlCanvasElement,
+  WebGlRenderingContext,
+  WebGlTexture,
+};
+
+use renderer::atlas::packer::{extrude, AtlasLayout, Insertion, Placement};
+use renderer::gl::Gl;
+use renderer::texture::set_sampling;
+use renderer::u8_view;
+
+/// RGBA pixels of one image, kept for repacking.
+struct Image {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+pub struct TextureAtlas {
+  layout: AtlasLayout,
+  images: BTreeMap<String, Image>,
+  pages: Vec<WebGlTexture>,
+}
+
+impl TextureAtlas {
+  /// An empty atlas of `page_size` square RGBA pages; see
+  /// `AtlasLayout` for `padding` and `extrude`. Pages are created as
+  /// images need them.
+  pub fn new(page_size: u32, padding: u32, extrude: u32) -> TextureAtlas {
+    TextureAtlas {
+      layout: AtlasLayout::new(page_size, padding, extrude),
+      images: BTreeMap::new(),
+      pages: Vec::new(),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.layout.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.layout.is_empty()
+  }
+
+  pub fn layout(&self) -> &AtlasLayout {
+    &self.layout
+  }
+
+  pub fn page_count(&self) -> usize {
+    self.pages.len()
+  }
+
+  pub fn page(&self, index: usize) -> &WebGlTexture {
+    &self.pages[index]
+  }
+
+  pub fn get(&self, key: &str) -> Option<Placement> {
+    self.layout.get(key)
+  }
+
+  /// See `AtlasLayout::uv`.
+  pub fn uv(&self, key: &str) -> Option<[f32; 4]> {
+    self.layout.uv(key)
+  }
+
+  /// Add or replace the image under `key` from `width * height * 4`
+  /// bytes of RGBA. Returns true when the atlas repacked, moving every
+  /// image and possibly changing the page count, so cached UVs and
+  /// page textures need looking up again.
+  pub fn add_rgba(
+    &mut self,
+    context: &Gl,
+    key: &str,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+  ) -> Result<bool, JsValue> {
+    let expected = (width as usize).checked_mul(height as usize)
+        .and_then(|texels| texels.checked_mul(4))
+        .ok_or_else(|| format!("{} is {}x{}, too large", key, width, height))?;
+    if pixels.len() != expected {
+      return Err(format!("expected {} bytes of RGBA, got {}", expected, pixels.len()).into());
+    }
+    let insertion = self.layout.insert(key, width, height)?;
+    self.images.insert(key.to_string(), Image { width, height, pixels: pixels.to_vec() });
+    match insertion {
+      Insertion::Placed(_) => {
+        self.upload(context, key)?;
+        Ok(false)
+      },
+      Insertion::Repacked => {
+        self.rebuild(context)?;
+        Ok(true)
+      },
+    }
+  }
+
+  /// Add or replace the image under `key` from everything drawn on a
+  /// 2D `canvas`, see `add_rgba`.
+  pub fn add_canvas(&mut self, context: &Gl, key: &str, canvas: &HtmlCanvasElement) -> Result<bool, JsValue> {
+    let (width, height) = (canvas.width(), canvas.height());
+    let context_2d = canvas
+        .get_context("2d")?
+        .ok_or("canvas has no 2d context")?
+        .dyn_into::<CanvasRenderingContext2d>()?;
+    let image = context_2d.get_image_data(0.0, 0.0, width as f64, height as f64)?;
+    self.add_rgba(context, key, width, height, &image.data())
+  }
+
+  /// Forget the image under `key`. Its pixels stay on the page until
+  /// the next repack.
+  pub fn remove(&mut self, key: &str) -> bool {
+    self.images.remove(key);
+    self.layout.remove(key)
+  }
+
+  /// Pack every image again from scratch and re-upload all pages.
+  pub fn repack(&mut self, context: &Gl) -> Result<(), JsValue> {
+    self.layout.repack();
+    self.rebuild(context)
+  }
+
+  /// Copy the image under `key`, extruded, to its place on its page.
+  fn upload(&self, context: &Gl, key: &str) -> Result<(), JsValue> {
+    let (image, placement) = match (self.images.get(key), self.layout.get(key)) {
+      (Some(image), Some(placement)) => (image, placement),
+      _ => return Ok(()),
+    };
+    if image.width == 0 || image.height == 0 {
+      return Ok(());
+    }
+    let border = self.layout.extrude;
+    let pixels = extrude(&image.pixels, image.width, image.height, border);
+    let view = u8_view(&pixels)?;
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.pages[placement.page]));
+    context.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        (placement.rect.x - border) as i32,
+        (placement.rect.y - border) as i32,
+        (image.width + 2 * border) as i32,
+        (image.height + 2 * border) as i32,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        Some(&view),
+    )
+  }
+
+  /// Clear every page, matching the page count to the layout, and
+  /// upload every image.
+  fn rebuild(&mut self, context: &Gl) -> Result<(), JsValue> {
+    while self.pages.len() > self.layout.page_count() {
+      context.delete_texture(self.pages.pop().as_ref());
+    }
+    while self.pages.len() < self.layout.page_count() {
+      self.pages.push(context.create_texture().ok_or("failed to create texture")?);
+    }
+    let size = self.layout.page_size as i32;
+    for page in &self.pages {
+      context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(page));
+      // No mipmaps: they would blend neighbours no matter the border.
+      set_sampling(context, WebGlRenderingContext::LINEAR);
+      // Allocating anew clears the page to transparent black.
+      context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+          WebGlRenderingContext::TEXTURE_2D,
+          0,
+          WebGlRenderingContext::RGBA as i32,
+          size,
+          size,
+          0,
+          WebGlRenderingContext::RGBA,
+          WebGlRenderingContext::UNSIGNED_BYTE,
+          None,
+      )?;
+    }
+    for key in self.layout.keys() {
+      self.upload(context, key)?;
+    }
+    Ok(())
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    for page in &self.pages {
+      context.delete_texture(Some(page));
+    }
+  }
+}
@@ -0,0 +1,467 @@
+//! Packing rectangles onto fixed size pages, independent of WebGL.
+//!
+//! `Skyline` packs one page bottom-left first, tracking only the top
+//! edge of what has been placed. `AtlasLayout` keeps keyed images
+//! across as many skylines as it needs, with padding between images
+//! and room around each for its edge pixels to be extruded into.
+
+use std::collections::BTreeMap;
+
+/// In pixels from the page's top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// A horizontal run of the skyline: everything under `y` between `x`
+/// and `x + width` is taken.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+  x: u32,
+  y: u32,
+  width: u32,
+}
+
+/// One page packed with the skyline bottom-left heuristic: each
+/// rectangle goes where its bottom edge ends up highest on the page,
+/// leftmost on ties.
+#[derive(Clone, Debug)]
+pub struct Skyline {
+  pub width: u32,
+  pub height: u32,
+  segments: Vec<Segment>,
+  used: u64,
+}
+
+impl Skyline {
+  pub fn new(width: u32, height: u32) -> Skyline {
+    Skyline { width, height, segments: vec![Segment { x: 0, y: 0, width }], used: 0 }
+  }
+
+  /// Fraction of the page covered by placed rectangles.
+  pub fn occupancy(&self) -> f32 {
+    self.used as f32 / (self.width as u64 * self.height as u64) as f32
+  }
+
+  /// The top of a `width` x `height` rectangle with its left edge at
+  /// segment `index`, if it fits there.
+  fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+    let x = self.segments[index].x;
+    if width > self.width - x {
+      return None;
+    }
+    let (mut y, mut covered) = (0, 0);
+    for segment in &self.segments[index..] {
+      if covered >= width {
+        break;
+      }
+      y = y.max(segment.y);
+      covered += segment.width;
+    }
+    if height > self.height - y {
+      None
+    } else {
+      Some(y)
+    }
+  }
+
+  /// Place a `width` x `height` rectangle, or `None` if the page has
+  /// no room for it.
+  pub fn insert(&mut self, width: u32, height: u32) -> Option<Rect> {
+    if width == 0 || height == 0 {
+      return Some(Rect { x: 0, y: 0, width, height });
+    }
+    let mut best: Option<(usize, u32)> = None;
+    for index in 0..self.segments.len() {
+      if let Some(y) = self.fit(index, width, height) {
+        let better = match best {
+          Some((best_index, best_y)) => {
+            y < best_y || (y == best_y && self.segments[index].x < self.segments[best_index].x)
+          },
+          None => true,
+        };
+        if better {
+          best = Some((index, y));
+        }
+      }
+    }
+    let (index, y) = best?;
+    let x = self.segments[index].x;
+    self.segments.insert(index, Segment { x, y: y + height, width });
+
+    // Trim or drop the segments the new one now covers.
+    let right = x + width;
+    let next = index + 1;
+    while next < self.segments.len() && self.segments[next].x < right {
+      let end = self.segments[next].x + self.segments[next].width;
+      if end <= right {
+        self.segments.remove(next);
+      } else {
+        self.segments[next].width = end - right;
+        self.segments[next].x = right;
+        break;
+      }
+    }
+    // Merge neighbours left at the same height.
+    let mut i = 0;
+    while i + 1 < self.segments.len() {
+      if self.segments[i].y == self.segments[i + 1].y {
+        self.segments[i].width += self.segments[i + 1].width;
+        self.segments.remove(i + 1);
+      } else {
+        i += 1;
+      }
+    }
+
+    self.used += width as u64 * height as u64;
+    Some(Rect { x, y, width, height })
+  }
+}
+
+/// Where one image of an `AtlasLayout` ended up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+  pub page: usize,
+  /// The image's own pixels, without its extruded border.
+  pub rect: Rect,
+}
+
+/// What an `AtlasLayout::insert` did to the existing images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Insertion {
+  /// Only the new image was placed; everything else stayed put.
+  Placed(Placement),
+  /// Every image moved, and the page count may have changed.
+  Repacked,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+  width: u32,
+  height: u32,
+  placement: Placement,
+}
+
+/// Images by key on as many `page_size` square pages as they need.
+///
+/// Each image reserves `extrude` pixels on every side for copies of its
+/// edge pixels, so linear filtering at its edges never reads a
+/// neighbour, and `padding` more pixels on its right and bottom. New
+/// images go into the existing pages where they fit; when one doesn't,
+/// everything is repacked from scratch, largest first, which packs
+/// tighter than the order images arrived in.
+#[derive(Clone, Debug)]
+pub struct AtlasLayout {
+  pub page_size: u32,
+  pub padding: u32,
+  pub extrude: u32,
+  pages: Vec<Skyline>,
+  entries: BTreeMap<String, Entry>,
+}
+
+impl AtlasLayout {
+  pub fn new(page_size: u32, padding: u32, extrude: u32) -> AtlasLayout {
+    AtlasLayout { page_size, padding, extrude, pages: Vec::new(), entries: BTreeMap::new() }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn page_count(&self) -> usize {
+    self.pages.len()
+  }
+
+  pub fn page(&self, index: usize) -> &Skyline {
+    &self.pages[index]
+  }
+
+  pub fn get(&self, key: &str) -> Option<Placement> {
+    self.entries.get(key).map(|entry| entry.placement)
+  }
+
+  /// Every key, in order.
+  pub fn keys(&self) -> Vec<&str> {
+    self.entries.keys().map(|key| &key[..]).collect()
+  }
+
+  /// `u0, v0, u1, v1` of the image under `key`, with `v` growing down
+  /// the page like the pixel rows.
+  pub fn uv(&self, key: &str) -> Option<[f32; 4]> {
+    let size = self.page_size as f32;
+    self.get(key).map(|placement| {
+      let rect = placement.rect;
+      [
+        rect.x as f32 / size,
+        rect.y as f32 / size,
+        (rect.x + rect.width) as f32 / size,
+        (rect.y + rect.height) as f32 / size,
+      ]
+    })
+  }
+
+  /// Room taken by a `width` x `height` image. Padding is dropped
+  /// where it would make an image that fits a page too wide for one;
+  /// it is only needed between neighbours.
+  fn cell(&self, width: u32, height: u32) -> (u32, u32) {
+    let border = 2 * self.extrude;
+    (
+      (width + border + self.padding).min(self.page_size),
+      (height + border + self.padding).min(self.page_size),
+    )
+  }
+
+  fn place(&mut self, width: u32, height: u32) -> Option<Placement> {
+    let (cell_width, cell_height) = self.cell(width, height);
+    let extrude = self.extrude;
+    for (page, skyline) in self.pages.iter_mut().enumerate() {
+      if let Some(cell) = skyline.insert(cell_width, cell_height) {
+        let rect = Rect { x: cell.x + extrude, y: cell.y + extrude, width, height };
+        return Some(Placement { page, rect });
+      }
+    }
+    None
+  }
+
+  /// Add a `width` x `height` image under `key`. An image already under
+  /// `key` with the same size keeps its place; one with another size is
+  /// replaced, which repacks.
+  pub fn insert(&mut self, key: &str, width: u32, height: u32) -> Result<Insertion, String> {
+    let border = 2 * self.extrude;
+    if width > self.page_size.saturating_sub(border) || height > self.page_size.saturating_sub(border) {
+      return Err(format!(
+        "{} is {}x{} with a {} pixel border, too big for {} pixel pages",
+        key, width, height, self.extrude, self.page_size,
+      ));
+    }
+    if let Some(entry) = self.entries.get(key) {
+      if entry.width == width && entry.height == height {
+        return Ok(Insertion::Placed(entry.placement));
+      }
+    }
+    let resized = self.entries.contains_key(key);
+    if !resized {
+      if let Some(placement) = self.place(width, height) {
+        self.entries.insert(key.to_string(), Entry { width, height, placement });
+        return Ok(Insertion::Placed(placement));
+      }
+    }
+    let placement = Placement { page: 0, rect: Rect { x: 0, y: 0, width, height } };
+    self.entries.insert(key.to_string(), Entry { width, height, placement });
+    self.repack();
+    Ok(Insertion::Repacked)
+  }
+
+  /// Forget the image under `key`. Its space is reclaimed by the next
+  /// repack.
+  pub fn remove(&mut self, key: &str) -> bool {
+    self.entries.remove(key).is_some()
+  }
+
+  /// Place every image again on fresh pages, tallest first, then
+  /// widest, then by key so the result doesn't depend on the order
+  /// images were added in.
+  pub fn repack(&mut self) {
+    let mut order: Vec<(String, u32, u32)> = self.entries.iter()
+        .map(|(key, entry)| (key.clone(), entry.width, entry.height))
+        .collect();
+    order.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+    self.pages.clear();
+    for (key, width, height) in order {
+      let placement = match self.place(width, height) {
+        Some(placement) => placement,
+        None => {
+          self.pages.push(Skyline::new(self.page_size, self.page_size));
+          // `insert` checked it fits an empty page.
+          self.place(width, height).unwrap()
+        },
+      };
+      self.entries.get_mut(&key).unwrap().placement = placement;
+    }
+  }
+}
+
+/// `width` x `height` RGBA `pixels` surrounded by `border` copies of
+/// their outermost rows and columns.
+pub fn extrude(pixels: &[u8], width: u32, height: u32, border: u32) -> Vec<u8> {
+  let (width, height, border) = (width as usize, height as usize, border as usize);
+  if width == 0 || height == 0 {
+    return Vec::new();
+  }
+  let out_width = width + 2 * border;
+  let out_height = height + 2 * border;
+  let mut out = Vec::with_capacity(out_width * out_height * 4);
+  for y in 0..out_height {
+    let source_y = y.max(border).min(border + height - 1) - border;
+    for x in 0..out_width {
+      let source_x = x.max(border).min(border + width - 1) - border;
+      let i = (source_y * width + source_x) * 4;
+      out.extend_from_slice(&pixels[i..i + 4]);
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Sizes from 1 to `max`, the same every run.
+  fn sizes(count: usize, max: u32) -> Vec<(u32, u32)> {
+    let mut state = 12345u32;
+    let mut next = || {
+      state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+      (state >> 16) % max + 1
+    };
+    (0..count).map(|_| (next(), next())).collect()
+  }
+
+  fn overlap(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+  }
+
+  /// The room `placement` reserves: its extruded border, then padding
+  /// on the right and bottom.
+  fn cell(layout: &AtlasLayout, placement: &Placement) -> Rect {
+    let rect = placement.rect;
+    Rect {
+      x: rect.x - layout.extrude,
+      y: rect.y - layout.extrude,
+      width: rect.width + 2 * layout.extrude + layout.padding,
+      height: rect.height + 2 * layout.extrude + layout.padding,
+    }
+  }
+
+  fn assert_disjoint(layout: &AtlasLayout) {
+    let placements: Vec<Placement> = layout.keys().iter().map(|key| layout.get(key).unwrap()).collect();
+    for (i, a) in placements.iter().enumerate() {
+      let a_cell = cell(layout, a);
+      assert!(a.page < layout.page_count());
+      assert!(a_cell.x + a_cell.width <= layout.page_size && a_cell.y + a_cell.height <= layout.page_size, "{:?}", a);
+      for b in &placements[i + 1..] {
+        assert!(a.page != b.page || !overlap(&a_cell, &cell(layout, b)), "{:?} and {:?} overlap", a, b);
+      }
+    }
+  }
+
+  #[test]
+  fn skyline_rects_stay_apart_and_on_the_page() {
+    let mut skyline = Skyline::new(128, 128);
+    let mut placed: Vec<Rect> = Vec::new();
+    for (width, height) in sizes(200, 24) {
+      if let Some(rect) = skyline.insert(width, height) {
+        assert_eq!((rect.width, rect.height), (width, height));
+        assert!(rect.x + rect.width <= 128 && rect.y + rect.height <= 128, "{:?}", rect);
+        assert!(placed.iter().all(|other| !overlap(&rect, other)), "{:?} overlaps", rect);
+        placed.push(rect);
+      }
+    }
+    assert!(placed.len() > 20);
+    let area: u32 = placed.iter().map(|rect| rect.width * rect.height).sum();
+    assert!((skyline.occupancy() - area as f32 / (128.0 * 128.0)).abs() < 1e-6);
+    assert_eq!(Skyline::new(16, 16).insert(17, 1), None);
+    assert_eq!(Skyline::new(16, 16).insert(u32::max_value(), u32::max_value()), None);
+  }
+
+  #[test]
+  fn keeps_padding_and_extrude_gaps() {
+    let mut layout = AtlasLayout::new(256, 3, 2);
+    for (i, &(width, height)) in sizes(60, 40).iter().enumerate() {
+      layout.insert(&format!("image {}", i), width, height).unwrap();
+      assert_disjoint(&layout);
+    }
+    assert_eq!(layout.len(), 60);
+    for key in layout.keys() {
+      let rect = layout.get(key).unwrap().rect;
+      assert!(rect.x >= 2 && rect.y >= 2);
+    }
+  }
+
+  #[test]
+  fn opens_pages_on_overflow() {
+    let mut layout = AtlasLayout::new(64, 0, 0);
+    assert_eq!(layout.insert("a", 40, 40), Ok(Insertion::Repacked));
+    assert_eq!(layout.page_count(), 1);
+    match layout.insert("b", 20, 20).unwrap() {
+      Insertion::Placed(placement) => assert_eq!(placement.page, 0),
+      Insertion::Repacked => panic!("room was left on the first page"),
+    }
+    assert_eq!(layout.insert("c", 40, 40), Ok(Insertion::Repacked));
+    assert_eq!(layout.page_count(), 2);
+    assert_ne!(layout.get("a").unwrap().page, layout.get("c").unwrap().page);
+    assert_disjoint(&layout);
+  }
+
+  #[test]
+  fn rejects_oversized_images() {
+    let mut layout = AtlasLayout::new(64, 4, 2);
+    assert!(layout.insert("wide", 61, 1).is_err());
+    assert!(layout.insert("tall", 1, 61).is_err());
+    assert!(layout.insert("huge", u32::max_value(), u32::max_value()).is_err());
+    assert!(layout.is_empty());
+    // Padding isn't needed against the page edge.
+    assert_eq!(layout.insert("full", 60, 60), Ok(Insertion::Repacked));
+    assert_eq!(layout.get("full").unwrap().rect, Rect { x: 2, y: 2, width: 60, height: 60 });
+  }
+
+  #[test]
+  fn resizing_repacks() {
+    let mut layout = AtlasLayout::new(64, 1, 0);
+    layout.insert("a", 10, 10).unwrap();
+    let placed = layout.insert("b", 8, 8).unwrap();
+    assert_eq!(layout.insert("b", 8, 8).unwrap(), placed);
+    assert_eq!(layout.insert("b", 12, 8), Ok(Insertion::Repacked));
+    assert_eq!(layout.get("b").unwrap().rect.width, 12);
+    assert_eq!(layout.len(), 2);
+    assert_disjoint(&layout);
+  }
+
+  #[test]
+  fn repacks_independent_of_insertion_order() {
+    let images: Vec<(String, u32, u32)> = sizes(30, 30).into_iter().enumerate()
+        .map(|(i, (width, height))| (format!("{}", i), width, height))
+        .collect();
+    let mut forwards = AtlasLayout::new(128, 1, 1);
+    for &(ref key, width, height) in &images {
+      forwards.insert(key, width, height).unwrap();
+    }
+    let mut backwards = AtlasLayout::new(128, 1, 1);
+    for &(ref key, width, height) in images.iter().rev() {
+      backwards.insert(key, width, height).unwrap();
+    }
+    forwards.repack();
+    backwards.repack();
+    assert_eq!(forwards.page_count(), backwards.page_count());
+    for image in &images {
+      let key = &image.0;
+      assert_eq!(forwards.get(key), backwards.get(key), "{}", key);
+    }
+    assert_disjoint(&forwards);
+  }
+
+  #[test]
+  fn extrudes_edge_pixels() {
+    // 2x2 of one byte-coloured pixel each.
+    let pixels: Vec<u8> = [1u8, 2, 3, 4].iter().flat_map(|&value| vec![value; 4]).collect();
+    let out = extrude(&pixels, 2, 2, 1);
+    let values: Vec<u8> = out.chunks(4).map(|pixel| pixel[0]).collect();
+    assert_eq!(values, vec![
+      1, 1, 2, 2,
+      1, 1, 2, 2,
+      3, 3, 4, 4,
+      3, 3, 4, 4,
+    ]);
+    assert!(out.chunks(4).all(|pixel| pixel.iter().all(|&channel| channel == pixel[0])));
+    assert_eq!(extrude(&pixels, 2, 2, 0), pixels);
+    let wide = extrude(&[9, 8, 7, 6], 1, 1, 2);
+    assert_eq!(wide, [9, 8, 7, 6].iter().cycle().take(5 * 5 * 4).cloned().collect::<Vec<u8>>());
+    assert!(extrude(&[], 0, 3, 2).is_empty());
+  }
+}
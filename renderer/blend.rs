@@ -0,0 +1,61 @@
+//! How a material's colour combines with what is already drawn.
+
+use web_sys::WebGlRenderingContext;
+
+use renderer::gl::Gl;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+  /// Replaces what is behind it; alpha is ignored.
+  Opaque,
+  /// Straight alpha: the colour covers `alpha` of what is behind it.
+  Alpha,
+  /// The colour, scaled by alpha, is added to what is behind it, for
+  /// glows and light. Order independent.
+  Additive,
+  /// What is behind it is multiplied by the colour, for tinted glass
+  /// and shadows. Order independent.
+  Multiply,
+  /// Like `Alpha` for colour already multiplied by its alpha, as from
+  /// premultiplied textures, which also lets alpha 0 add light.
+  Premultiplied,
+}
+
+impl Default for BlendMode {
+  fn default() -> BlendMode {
+    BlendMode::Opaque
+  }
+}
+
+impl BlendMode {
+  /// Drawn after the opaque objects, sorted and without depth writes.
+  pub fn is_transparent(self) -> bool {
+    self != BlendMode::Opaque
+  }
+
+  /// Set up blending for this mode. Destination alpha is kept as
+  /// coverage, so a canvas composited with the page stays correct.
+  pub fn apply(self, context: &Gl) {
+    if self == BlendMode::Opaque {
+      context.disable(WebGlRenderingContext::BLEND);
+      return;
+    }
+    context.enable(WebGlRenderingContext::BLEND);
+    context.blend_equation(WebGlRenderingContext::FUNC_ADD);
+    let (source, destination) = match self {
+      BlendMode::Alpha => (WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA),
+      BlendMode::Additive => (WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE),
+      BlendMode::Multiply => (WebGlRenderingContext::DST_COLOR, WebGlRenderingContext::ZERO),
+      BlendMode::Premultiplied | BlendMode::Opaque => {
+        (WebGlRenderingContext::ONE, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA)
+      },
+    };
+    let (source_alpha, destination_alpha) = match self {
+      BlendMode::Alpha | BlendMode::Premultiplied => {
+        (WebGlRenderingContext::ONE, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA)
+      },
+      _ => (WebGlRenderingContext::ZERO, WebGlRenderingContext::ONE),
+    };
+    context.blend_func_separate(source, destination, source_alpha, destination_alpha);
+  }
+}
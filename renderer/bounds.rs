@@ -0,0 +1,285 @@
+//! Bounding volumes and the view frustum.
+//!
+//! Plain `glm` math without any `web_sys` types, so it builds and runs
+//! natively as well as in the browser.
+
+use glm::{Mat4, Vec3, Vec4};
+
+/// Axis aligned box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+  pub min: Vec3,
+  pub max: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+  pub center: Vec3,
+  pub radius: f32,
+}
+
+/// Both volumes of one mesh. The sphere is the cheap first test, the
+/// box the tighter second one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+  pub aabb: Aabb,
+  pub sphere: Sphere,
+}
+
+impl Aabb {
+  /// The box around `x, y, z` triples. Empty input gives a point at
+  /// the origin.
+  pub fn from_positions(positions: &[f32]) -> Aabb {
+    if positions.len() < 3 {
+      return Aabb { min: glm::zero(), max: glm::zero() };
+    }
+    let mut min = glm::vec3(std::f32::MAX, std::f32::MAX, std::f32::MAX);
+    let mut max = -min;
+    for p in positions.chunks(3) {
+      for k in 0..3 {
+        min[k] = min[k].min(p[k]);
+        max[k] = max[k].max(p[k]);
+      }
+    }
+    Aabb { min, max }
+  }
+
+  pub fn center(&self) -> Vec3 {
+    (self.min + self.max) * 0.5
+  }
+
+  pub fn half_extents(&self) -> Vec3 {
+    (self.max - self.min) * 0.5
+  }
+
+  /// Distance from `point` to the nearest point of the box, 0 inside.
+  pub fn distance_to(&self, point: &Vec3) -> f32 {
+    let mut outside = Vec3::zeros();
+    for k in 0..3 {
+      outside[k] = (self.min[k] - point[k]).max(point[k] - self.max[k]).max(0.0);
+    }
+    outside.norm()
+  }
+
+  /// The box around this box after `matrix`, using the absolute values
+  /// of the rotation part to grow the extents (Arvo's method).
+  pub fn transform(&self, matrix: &Mat4) -> Aabb {
+    let center = transform_point(matrix, &self.center());
+    let extents = self.half_extents();
+    let mut world = Vec3::zeros();
+    for row in 0..3 {
+      for col in 0..3 {
+        world[row] += matrix[(row, col)].abs() * extents[col];
+      }
+    }
+    Aabb { min: center - world, max: center + world }
+  }
+}
+
+impl Sphere {
+  /// Centered on `aabb`, just large enough to hold every position.
+  pub fn from_positions(positions: &[f32], aabb: &Aabb) -> Sphere {
+    let center = aabb.center();
+    let mut radius_squared: f32 = 0.0;
+    for p in positions.chunks(3) {
+      if p.len() == 3 {
+        let offset = glm::vec3(p[0], p[1], p[2]) - center;
+        radius_squared = radius_squared.max(glm::dot(&offset, &offset));
+      }
+    }
+    Sphere { center, radius: radius_squared.sqrt() }
+  }
+
+  /// The sphere after `matrix`; non-uniform scale grows the radius by
+  /// the largest axis scale.
+  pub fn transform(&self, matrix: &Mat4) -> Sphere {
+    let mut scale_squared: f32 = 0.0;
+    for col in 0..3 {
+      let axis = glm::vec3(matrix[(0, col)], matrix[(1, col)], matrix[(2, col)]);
+      scale_squared = scale_squared.max(glm::dot(&axis, &axis));
+    }
+    Sphere {
+      center: transform_point(matrix, &self.center),
+      radius: self.radius * scale_squared.sqrt(),
+    }
+  }
+}
+
+impl Bounds {
+  pub fn from_positions(positions: &[f32]) -> Bounds {
+    let aabb = Aabb::from_positions(positions);
+    Bounds { sphere: Sphere::from_positions(positions, &aabb), aabb }
+  }
+
+  pub fn transform(&self, matrix: &Mat4) -> Bounds {
+    Bounds {
+      aabb: self.aabb.transform(matrix),
+      sphere: self.sphere.transform(matrix),
+    }
+  }
+}
+
+/// Six planes `(a, b, c, d)` with normals pointing inwards, so a point
+/// is inside when `a x + b y + c z + d >= 0` for every plane. Order is
+/// left, right, bottom, top, near, far.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+  pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+  /// Extract the planes from a `projection * view` matrix (Gribb and
+  /// Hartmann), OpenGL clip space from -w to w on every axis.
+  pub fn from_matrix(view_projection: &Mat4) -> Frustum {
+    let m = view_projection;
+    let row = |r: usize| glm::vec4(m[(r, 0)], m[(r, 1)], m[(r, 2)], m[(r, 3)]);
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+    let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+    for plane in planes.iter_mut() {
+      let length = glm::vec3(plane.x, plane.y, plane.z).norm();
+      if length > 0.0 {
+        *plane /= length;
+      }
+    }
+    Frustum { planes }
+  }
+
+  /// Signed distance from `point` to plane `index`, positive inside.
+  pub fn distance(&self, index: usize, point: &Vec3) -> f32 {
+    let plane = &self.planes[index];
+    plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+  }
+
+  pub fn contains_point(&self, point: &Vec3) -> bool {
+    (0..6).all(|i| self.distance(i, point) >= 0.0)
+  }
+
+  pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+    (0..6).all(|i| self.distance(i, &sphere.center) >= -sphere.radius)
+  }
+
+  /// Conservative: a box near a frustum corner can pass while being
+  /// outside, but a visible box never fails.
+  pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+    self.planes.iter().enumerate().all(|(i, plane)| {
+      // The corner furthest along the plane normal.
+      let corner = glm::vec3(
+        if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+        if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+        if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+      );
+      self.distance(i, &corner) >= 0.0
+    })
+  }
+
+  /// Sphere first, then box.
+  pub fn intersects(&self, bounds: &Bounds) -> bool {
+    self.intersects_sphere(&bounds.sphere) && self.intersects_aabb(&bounds.aabb)
+  }
+}
+
+pub fn transform_point(matrix: &Mat4, point: &Vec3) -> Vec3 {
+  let p = matrix * glm::vec4(point.x, point.y, point.z, 1.0);
+  glm::vec3(p.x, p.y, p.z) / p.w
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// From `(0, 0, 10)` towards the origin with a 90 degree field of
+  /// view, so the sides are at 45 degrees, near 1 and far 100.
+  fn frustum() -> Frustum {
+    let projection = glm::perspective(1.0, std::f32::consts::FRAC_PI_2, 1.0, 100.0);
+    let view = glm::look_at(&glm::vec3(0.0, 0.0, 10.0), &glm::vec3(0.0, 0.0, 0.0), &glm::vec3(0.0, 1.0, 0.0));
+    Frustum::from_matrix(&(projection * view))
+  }
+
+  fn sphere(x: f32, y: f32, z: f32, radius: f32) -> Sphere {
+    Sphere { center: glm::vec3(x, y, z), radius }
+  }
+
+  fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+    Aabb { min: glm::vec3(min[0], min[1], min[2]), max: glm::vec3(max[0], max[1], max[2]) }
+  }
+
+  fn near(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-3
+  }
+
+  #[test]
+  fn planes_are_normalised() {
+    let frustum = frustum();
+    for plane in frustum.planes.iter() {
+      assert!(near(glm::vec3(plane.x, plane.y, plane.z).norm(), 1.0), "{:?}", plane);
+    }
+    // So distances are in world units.
+    let origin = glm::vec3(0.0, 0.0, 0.0);
+    assert!(near(frustum.distance(4, &origin), 9.0));
+    assert!(near(frustum.distance(5, &origin), 90.0));
+    let side = 10.0 * std::f32::consts::FRAC_1_SQRT_2;
+    for i in 0..4 {
+      assert!(near(frustum.distance(i, &origin), side), "plane {}", i);
+    }
+    assert!(near(frustum.distance(0, &glm::vec3(-10.0, 0.0, 0.0)), 0.0));
+    assert!(near(frustum.distance(3, &glm::vec3(0.0, 10.0, 0.0)), 0.0));
+  }
+
+  #[test]
+  fn contains_points() {
+    let frustum = frustum();
+    assert!(frustum.contains_point(&glm::vec3(0.0, 0.0, 0.0)));
+    assert!(frustum.contains_point(&glm::vec3(9.0, -9.0, 0.0)));
+    assert!(!frustum.contains_point(&glm::vec3(0.0, 0.0, 9.5)));
+    assert!(!frustum.contains_point(&glm::vec3(0.0, 0.0, -91.0)));
+    assert!(!frustum.contains_point(&glm::vec3(11.0, 0.0, 0.0)));
+  }
+
+  #[test]
+  fn classifies_spheres() {
+    let frustum = frustum();
+    // Fully inside.
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, 0.0, 1.0)));
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -50.0, 20.0)));
+    // Fully outside one plane each.
+    assert!(!frustum.intersects_sphere(&sphere(-15.0, 0.0, 0.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(15.0, 0.0, 0.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, -15.0, 0.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 15.0, 0.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 12.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -92.0, 1.0)));
+    // Straddling a plane.
+    assert!(frustum.intersects_sphere(&sphere(-10.5, 0.0, 0.0, 1.0)));
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, 9.5, 1.0)));
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -90.5, 1.0)));
+  }
+
+  #[test]
+  fn classifies_boxes() {
+    let frustum = frustum();
+    // Fully inside.
+    assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0])));
+    // Fully outside one plane each.
+    assert!(!frustum.intersects_aabb(&aabb([12.0, -1.0, -1.0], [14.0, 1.0, 1.0])));
+    assert!(!frustum.intersects_aabb(&aabb([-1.0, -14.0, -1.0], [1.0, -12.0, 1.0])));
+    assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, 10.0], [1.0, 1.0, 12.0])));
+    assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, -95.0], [1.0, 1.0, -91.0])));
+    // Straddling a plane.
+    assert!(frustum.intersects_aabb(&aabb([9.0, -1.0, -1.0], [12.0, 1.0, 1.0])));
+    assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, 8.0], [1.0, 1.0, 12.0])));
+    assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -95.0], [1.0, 1.0, -85.0])));
+    // Larger than the whole frustum.
+    assert!(frustum.intersects_aabb(&aabb([-500.0, -500.0, -500.0], [500.0, 500.0, 500.0])));
+  }
+
+  #[test]
+  fn transformed_bounds() {
+    let frustum = frustum();
+    let bounds = Bounds::from_positions(&[-1.0, -1.0, -1.0, 1.0, 1.0, 1.0]);
+    assert!(near(bounds.sphere.radius, 3f32.sqrt()));
+    assert!(frustum.intersects(&bounds));
+    let moved = bounds.transform(&glm::translate(&Mat4::identity(), &glm::vec3(30.0, 0.0, 0.0)));
+    assert_eq!(moved.aabb, aabb([29.0, -1.0, -1.0], [31.0, 1.0, 1.0]));
+    assert!(!frustum.intersects(&moved));
+  }
+}
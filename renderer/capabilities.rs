@@ -0,0 +1,314 @@
+//! What the context can do: extensions, limits, shader precision and,
+//! where the browser allows it, the real GPU behind the context.
+//!
+//! Optional paths check the `features` flags before they use an
+//! extension, and JS can fetch the whole report through
+//! `renderer_capabilities` to attach it to bug reports.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde_derive::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGl2RenderingContext,
+  WebGlRenderingContext,
+  WebglDebugRendererInfo,
+};
+
+use renderer::gl::{Backend, Gl};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Capabilities {
+  pub backend: Backend,
+  pub version: String,
+  pub shading_language_version: String,
+  pub vendor: String,
+  pub renderer: String,
+  /// From `WEBGL_debug_renderer_info`, `None` when it is hidden.
+  pub unmasked_vendor: Option<String>,
+  pub unmasked_renderer: Option<String>,
+  pub extensions: Vec<String>,
+  pub limits: Limits,
+  pub vertex_precision: Precisions,
+  pub fragment_precision: Precisions,
+  pub features: Features,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Limits {
+  pub max_texture_size: i32,
+  pub max_cube_map_texture_size: i32,
+  pub max_renderbuffer_size: i32,
+  pub max_texture_image_units: i32,
+  pub max_vertex_texture_image_units: i32,
+  pub max_combined_texture_image_units: i32,
+  pub max_vertex_attribs: i32,
+  pub max_vertex_uniform_vectors: i32,
+  pub max_fragment_uniform_vectors: i32,
+  pub max_varying_vectors: i32,
+  pub max_viewport_dims: [f32; 2],
+  pub aliased_line_width_range: [f32; 2],
+  pub aliased_point_size_range: [f32; 2],
+  /// 1 without WebGL2 or `WEBGL_draw_buffers`.
+  pub max_draw_buffers: i32,
+  /// 1 without WebGL2 or `EXT_texture_filter_anisotropic`.
+  pub max_anisotropy: f32,
+  /// WebGL2 only, 0 otherwise.
+  pub max_3d_texture_size: i32,
+  pub max_array_texture_layers: i32,
+  pub max_samples: i32,
+  pub max_uniform_block_size: i32,
+  pub max_uniform_buffer_bindings: i32,
+}
+
+/// One `getShaderPrecisionFormat` answer. `precision` is in bits, the
+/// range is log2 of the magnitude.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct PrecisionFormat {
+  pub range_min: i32,
+  pub range_max: i32,
+  pub precision: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Precisions {
+  pub low_float: PrecisionFormat,
+  pub medium_float: PrecisionFormat,
+  pub high_float: PrecisionFormat,
+  pub low_int: PrecisionFormat,
+  pub medium_int: PrecisionFormat,
+  pub high_int: PrecisionFormat,
+}
+
+/// Optional functionality, core in WebGL2 or behind an extension in
+/// WebGL1.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Features {
+  pub instancing: bool,
+  pub vertex_array_objects: bool,
+  /// `UNSIGNED_INT` indices.
+  pub element_index_uint: bool,
+  pub float_textures: bool,
+  pub half_float_textures: bool,
+  pub float_linear: bool,
+  pub float_render_targets: bool,
+  pub depth_textures: bool,
+  pub draw_buffers: bool,
+  pub integer_textures: bool,
+  pub standard_derivatives: bool,
+  pub high_precision_fragment: bool,
+  pub s3tc: bool,
+  pub etc: bool,
+  pub etc1: bool,
+  pub astc: bool,
+  pub pvrtc: bool,
+}
+
+impl Capabilities {
+  /// Ask the context for everything. Prefer `capabilities()` which
+  /// caches the answer.
+  pub fn query(context: &Gl) -> Capabilities {
+    let backend = context.backend();
+    let webgl2 = backend == Backend::WebGl2;
+
+    let extensions: Vec<String> = supported_extensions(context);
+    let has = |name: &str| extensions.iter().any(|e| e == name);
+
+    let mut limits = Limits {
+      max_texture_size: get_i32(context, WebGlRenderingContext::MAX_TEXTURE_SIZE),
+      max_cube_map_texture_size: get_i32(context, WebGlRenderingContext::MAX_CUBE_MAP_TEXTURE_SIZE),
+      max_renderbuffer_size: get_i32(context, WebGlRenderingContext::MAX_RENDERBUFFER_SIZE),
+      max_texture_image_units: get_i32(context, WebGlRenderingContext::MAX_TEXTURE_IMAGE_UNITS),
+      max_vertex_texture_image_units: get_i32(context, WebGlRenderingContext::MAX_VERTEX_TEXTURE_IMAGE_UNITS),
+      max_combined_texture_image_units: get_i32(context, WebGlRenderingContext::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+      max_vertex_attribs: get_i32(context, WebGlRenderingContext::MAX_VERTEX_ATTRIBS),
+      max_vertex_uniform_vectors: get_i32(context, WebGlRenderingContext::MAX_VERTEX_UNIFORM_VECTORS),
+      max_fragment_uniform_vectors: get_i32(context, WebGlRenderingContext::MAX_FRAGMENT_UNIFORM_VECTORS),
+      max_varying_vectors: get_i32(context, WebGlRenderingContext::MAX_VARYING_VECTORS),
+      max_viewport_dims: get_pair(context, WebGlRenderingContext::MAX_VIEWPORT_DIMS),
+      aliased_line_width_range: get_pair(context, WebGlRenderingContext::ALIASED_LINE_WIDTH_RANGE),
+      aliased_point_size_range: get_pair(context, WebGlRenderingContext::ALIASED_POINT_SIZE_RANGE),
+      max_draw_buffers: 1,
+      max_anisotropy: 1.0,
+      ..Limits::default()
+    };
+
+    if webgl2 {
+      limits.max_draw_buffers = get_i32(context, WebGl2RenderingContext::MAX_DRAW_BUFFERS);
+      limits.max_3d_texture_size = get_i32(context, WebGl2RenderingContext::MAX_3D_TEXTURE_SIZE);
+      limits.max_array_texture_layers = get_i32(context, WebGl2RenderingContext::MAX_ARRAY_TEXTURE_LAYERS);
+      limits.max_samples = get_i32(context, WebGl2RenderingContext::MAX_SAMPLES);
+      limits.max_uniform_block_size = get_i32(context, WebGl2RenderingContext::MAX_UNIFORM_BLOCK_SIZE);
+      limits.max_uniform_buffer_bindings = get_i32(context, WebGl2RenderingContext::MAX_UNIFORM_BUFFER_BINDINGS);
+    } else if has("WEBGL_draw_buffers") && enable(context, "WEBGL_draw_buffers") {
+      // MAX_DRAW_BUFFERS_WEBGL, same value as WebGL2's MAX_DRAW_BUFFERS
+      limits.max_draw_buffers = get_i32(context, WebGl2RenderingContext::MAX_DRAW_BUFFERS);
+    }
+    if has("EXT_texture_filter_anisotropic") && enable(context, "EXT_texture_filter_anisotropic") {
+      // MAX_TEXTURE_MAX_ANISOTROPY_EXT
+      limits.max_anisotropy = get_f32(context, 0x84FF);
+    }
+
+    let (unmasked_vendor, unmasked_renderer) = if has("WEBGL_debug_renderer_info")
+        && enable(context, "WEBGL_debug_renderer_info") {
+      (
+        get_string(context, WebglDebugRendererInfo::UNMASKED_VENDOR_WEBGL),
+        get_string(context, WebglDebugRendererInfo::UNMASKED_RENDERER_WEBGL),
+      )
+    } else {
+      (None, None)
+    };
+
+    let vertex_precision = precisions(context, WebGlRenderingContext::VERTEX_SHADER);
+    let fragment_precision = precisions(context, WebGlRenderingContext::FRAGMENT_SHADER);
+
+    let float_textures = webgl2 || has("OES_texture_float");
+    let features = Features {
+      instancing: webgl2 || has("ANGLE_instanced_arrays"),
+      vertex_array_objects: webgl2 || has("OES_vertex_array_object"),
+      element_index_uint: webgl2 || has("OES_element_index_uint"),
+      float_textures,
+      half_float_textures: webgl2 || has("OES_texture_half_float"),
+      float_linear: has("OES_texture_float_linear"),
+      float_render_targets: if webgl2 {
+        has("EXT_color_buffer_float")
+      } else {
+        // Many WebGL1 implementations render to float textures without
+        // advertising WEBGL_color_buffer_float, check completeness too.
+        float_textures
+      },
+      depth_textures: webgl2 || has("WEBGL_depth_texture"),
+      draw_buffers: webgl2 || has("WEBGL_draw_buffers"),
+      integer_textures: webgl2,
+      standard_derivatives: webgl2 || has("OES_standard_derivatives"),
+      high_precision_fragment: fragment_precision.high_float.precision > 0,
+      s3tc: has("WEBGL_compressed_texture_s3tc"),
+      etc: has("WEBGL_compressed_texture_etc"),
+      etc1: has("WEBGL_compressed_texture_etc1"),
+      astc: has("WEBGL_compressed_texture_astc"),
+      pvrtc: has("WEBGL_compressed_texture_pvrtc") || has("WEBKIT_WEBGL_compressed_texture_pvrtc"),
+    };
+
+    Capabilities {
+      backend,
+      version: get_string(context, WebGlRenderingContext::VERSION).unwrap_or_default(),
+      shading_language_version: get_string(context, WebGlRenderingContext::SHADING_LANGUAGE_VERSION).unwrap_or_default(),
+      vendor: get_string(context, WebGlRenderingContext::VENDOR).unwrap_or_default(),
+      renderer: get_string(context, WebGlRenderingContext::RENDERER).unwrap_or_default(),
+      unmasked_vendor,
+      unmasked_renderer,
+      extensions,
+      limits,
+      vertex_precision,
+      fragment_precision,
+      features,
+    }
+  }
+
+  pub fn has_extension(&self, name: &str) -> bool {
+    self.extensions.iter().any(|e| e == name)
+  }
+}
+
+thread_local! {
+  static CAPABILITIES: RefCell<Option<Rc<Capabilities>>> = RefCell::new(None);
+}
+
+/// The capabilities of `context`, queried on first use.
+///
+/// The page only ever has one renderer context; `Gl::from_canvas`
+/// calls `reset` so a new context is asked again.
+pub fn capabilities(context: &Gl) -> Rc<Capabilities> {
+  CAPABILITIES.with(|cached| {
+    let mut cached = cached.borrow_mut();
+    if let Some(ref caps) = *cached {
+      if caps.backend == context.backend() {
+        return caps.clone();
+      }
+    }
+    let caps = Rc::new(Capabilities::query(context));
+    *cached = Some(caps.clone());
+    caps
+  })
+}
+
+/// Forget the cached capabilities.
+pub fn reset() {
+  CAPABILITIES.with(|cached| *cached.borrow_mut() = None);
+}
+
+/// The capability report of the current renderer context, or `null`
+/// when none was created yet.
+#[wasm_bindgen]
+pub fn renderer_capabilities() -> JsValue {
+  CAPABILITIES.with(|cached| match *cached.borrow() {
+    Some(ref caps) => JsValue::from_serde(&**caps).unwrap(),
+    None => JsValue::NULL,
+  })
+}
+
+fn supported_extensions(context: &Gl) -> Vec<String> {
+  let mut extensions = Vec::new();
+  if let Some(list) = context.get_supported_extensions() {
+    for i in 0..list.length() {
+      if let Some(name) = list.get(i).as_string() {
+        extensions.push(name);
+      }
+    }
+  }
+  extensions.sort();
+  extensions
+}
+
+/// Some parameters only answer once their extension is enabled.
+fn enable(context: &Gl, name: &str) -> bool {
+  context.get_extension(name).ok().and_then(|ext| ext).is_some()
+}
+
+fn get_f32(context: &Gl, pname: u32) -> f32 {
+  context.get_parameter(pname).ok().and_then(|v| v.as_f64()).unwrap_or(0.0) as f32
+}
+
+fn get_i32(context: &Gl, pname: u32) -> i32 {
+  get_f32(context, pname) as i32
+}
+
+fn get_string(context: &Gl, pname: u32) -> Option<String> {
+  context.get_parameter(pname).ok().and_then(|v| v.as_string())
+}
+
+/// Read a two element typed array parameter.
+fn get_pair(context: &Gl, pname: u32) -> [f32; 2] {
+  match context.get_parameter(pname) {
+    Ok(ref value) if !value.is_null() && !value.is_undefined() => {
+      let values = js_sys::Array::from(value);
+      [
+        values.get(0).as_f64().unwrap_or(0.0) as f32,
+        values.get(1).as_f64().unwrap_or(0.0) as f32,
+      ]
+    },
+    _ => [0.0, 0.0],
+  }
+}
+
+fn precisions(context: &Gl, shader_type: u32) -> Precisions {
+  let format = |precision_type: u32| {
+    context
+        .get_shader_precision_format(shader_type, precision_type)
+        .map(|f| PrecisionFormat {
+          range_min: f.range_min(),
+          range_max: f.range_max(),
+          precision: f.precision(),
+        })
+        .unwrap_or_default()
+  };
+  Precisions {
+    low_float: format(WebGlRenderingContext::LOW_FLOAT),
+    medium_float: format(WebGlRenderingContext::MEDIUM_FLOAT),
+    high_float: format(WebGlRenderingContext::HIGH_FLOAT),
+    low_int: format(WebGlRenderingContext::LOW_INT),
+    medium_int: format(WebGlRenderingContext::MEDIUM_INT),
+    high_int: format(WebGlRenderingContext::HIGH_INT),
+  }
+}
@@ -0,0 +1,547 @@
+//! KTX 1.1 and DDS texture containers: the pixel format, size and every
+//! mip level's bytes, independent of WebGL.
+//!
+//! Only plain 2D textures are read; cube maps, arrays and volumes are
+//! rejected. Uncompressed levels are converted to tightly packed RGBA8
+//! on the way in, so `Format::Rgba8` is the only uncompressed format.
+
+/// `COMPRESSED_*` values from the WebGL compressed texture extensions.
+pub mod gl_formats {
+  pub const RGB_S3TC_DXT1: u32 = 0x83F0;
+  pub const RGBA_S3TC_DXT1: u32 = 0x83F1;
+  pub const RGBA_S3TC_DXT3: u32 = 0x83F2;
+  pub const RGBA_S3TC_DXT5: u32 = 0x83F3;
+  pub const RGB_ETC1: u32 = 0x8D64;
+  pub const RGB8_ETC2: u32 = 0x9274;
+  pub const RGB8_PUNCHTHROUGH_ALPHA1_ETC2: u32 = 0x9276;
+  pub const RGBA8_ETC2_EAC: u32 = 0x9278;
+  pub const RGB_PVRTC_4BPPV1: u32 = 0x8C00;
+  pub const RGB_PVRTC_2BPPV1: u32 = 0x8C01;
+  pub const RGBA_PVRTC_4BPPV1: u32 = 0x8C02;
+  pub const RGBA_PVRTC_2BPPV1: u32 = 0x8C03;
+  /// `COMPRESSED_RGBA_ASTC_4x4_KHR`, the first of the 14 ASTC block
+  /// sizes in `ASTC_BLOCKS` order.
+  pub const RGBA_ASTC_4X4: u32 = 0x93B0;
+
+  pub const UNSIGNED_BYTE: u32 = 0x1401;
+  pub const RGB: u32 = 0x1907;
+  pub const RGBA: u32 = 0x1908;
+}
+
+/// ASTC block footprints, in the order of their `COMPRESSED_*` values.
+pub const ASTC_BLOCKS: [(u32, u32); 14] = [
+  (4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6),
+  (8, 8), (10, 5), (10, 6), (10, 8), (10, 10), (12, 10), (12, 12),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  Rgba8,
+  /// DXT1, `alpha` when the block's punch-through transparency is
+  /// meant to be used.
+  Bc1 { alpha: bool },
+  /// DXT3.
+  Bc2,
+  /// DXT5.
+  Bc3,
+  Etc1,
+  Etc2Rgb,
+  Etc2Rgba1,
+  Etc2Rgba,
+  /// One of `ASTC_BLOCKS`.
+  Astc { block_width: u32, block_height: u32 },
+  /// `bits` per pixel, 2 or 4.
+  Pvrtc { bits: u32, alpha: bool },
+}
+
+impl Format {
+  /// The format of a GL internal format, if it is one of ours.
+  pub fn from_gl(internal_format: u32) -> Option<Format> {
+    Some(match internal_format {
+      gl_formats::RGB_S3TC_DXT1 => Format::Bc1 { alpha: false },
+      gl_formats::RGBA_S3TC_DXT1 => Format::Bc1 { alpha: true },
+      gl_formats::RGBA_S3TC_DXT3 => Format::Bc2,
+      gl_formats::RGBA_S3TC_DXT5 => Format::Bc3,
+      gl_formats::RGB_ETC1 => Format::Etc1,
+      gl_formats::RGB8_ETC2 => Format::Etc2Rgb,
+      gl_formats::RGB8_PUNCHTHROUGH_ALPHA1_ETC2 => Format::Etc2Rgba1,
+      gl_formats::RGBA8_ETC2_EAC => Format::Etc2Rgba,
+      gl_formats::RGB_PVRTC_4BPPV1 => Format::Pvrtc { bits: 4, alpha: false },
+      gl_formats::RGB_PVRTC_2BPPV1 => Format::Pvrtc { bits: 2, alpha: false },
+      gl_formats::RGBA_PVRTC_4BPPV1 => Format::Pvrtc { bits: 4, alpha: true },
+      gl_formats::RGBA_PVRTC_2BPPV1 => Format::Pvrtc { bits: 2, alpha: true },
+      format if format >= gl_formats::RGBA_ASTC_4X4 && format < gl_formats::RGBA_ASTC_4X4 + ASTC_BLOCKS.len() as u32 => {
+        let (block_width, block_height) = ASTC_BLOCKS[(format - gl_formats::RGBA_ASTC_4X4) as usize];
+        Format::Astc { block_width, block_height }
+      },
+      _ => return None,
+    })
+  }
+
+  /// The internal format to upload with; `RGBA` for `Rgba8`.
+  pub fn gl_internal_format(&self) -> u32 {
+    match *self {
+      Format::Rgba8 => gl_formats::RGBA,
+      Format::Bc1 { alpha: false } => gl_formats::RGB_S3TC_DXT1,
+      Format::Bc1 { alpha: true } => gl_formats::RGBA_S3TC_DXT1,
+      Format::Bc2 => gl_formats::RGBA_S3TC_DXT3,
+      Format::Bc3 => gl_formats::RGBA_S3TC_DXT5,
+      Format::Etc1 => gl_formats::RGB_ETC1,
+      Format::Etc2Rgb => gl_formats::RGB8_ETC2,
+      Format::Etc2Rgba1 => gl_formats::RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+      Format::Etc2Rgba => gl_formats::RGBA8_ETC2_EAC,
+      Format::Astc { block_width, block_height } => {
+        let index = ASTC_BLOCKS.iter()
+            .position(|&block| block == (block_width, block_height))
+            .unwrap_or(0);
+        gl_formats::RGBA_ASTC_4X4 + index as u32
+      },
+      Format::Pvrtc { bits: 4, alpha: false } => gl_formats::RGB_PVRTC_4BPPV1,
+      Format::Pvrtc { bits: 4, alpha: true } => gl_formats::RGBA_PVRTC_4BPPV1,
+      Format::Pvrtc { alpha: false, .. } => gl_formats::RGB_PVRTC_2BPPV1,
+      Format::Pvrtc { alpha: true, .. } => gl_formats::RGBA_PVRTC_2BPPV1,
+    }
+  }
+
+  /// The WebGL extension needed to upload the format as is; `None`
+  /// for `Rgba8`.
+  pub fn extension(&self) -> Option<&'static str> {
+    match *self {
+      Format::Rgba8 => None,
+      Format::Bc1 { .. } | Format::Bc2 | Format::Bc3 => Some("WEBGL_compressed_texture_s3tc"),
+      Format::Etc1 => Some("WEBGL_compressed_texture_etc1"),
+      Format::Etc2Rgb | Format::Etc2Rgba1 | Format::Etc2Rgba => Some("WEBGL_compressed_texture_etc"),
+      Format::Astc { .. } => Some("WEBGL_compressed_texture_astc"),
+      Format::Pvrtc { .. } => Some("WEBGL_compressed_texture_pvrtc"),
+    }
+  }
+
+  pub fn is_compressed(&self) -> bool {
+    *self != Format::Rgba8
+  }
+
+  /// Bytes of one `width` x `height` level, or an error when that
+  /// doesn't fit in a `usize`.
+  pub fn level_size(&self, width: u32, height: u32) -> Result<usize, String> {
+    let too_large = || format!("{:?} level of {}x{} is too large", self, width, height);
+    let blocks = |block_width: u32, block_height: u32, bytes: usize| {
+      let across = width.checked_add(block_width - 1).ok_or_else(too_large)? / block_width;
+      let down = height.checked_add(block_height - 1).ok_or_else(too_large)? / block_height;
+      (across as usize).checked_mul(down as usize)
+          .and_then(|count| count.checked_mul(bytes))
+          .ok_or_else(too_large)
+    };
+    // Texels at `bits` each, rounded up to whole bytes.
+    let texels = |width: u32, height: u32, bits: usize| {
+      (width as usize).checked_mul(height as usize)
+          .and_then(|count| count.checked_mul(bits))
+          .and_then(|count| count.checked_add(7))
+          .map(|count| count / 8)
+          .ok_or_else(too_large)
+    };
+    match *self {
+      Format::Rgba8 => texels(width, height, 32),
+      Format::Bc1 { .. } | Format::Etc1 | Format::Etc2Rgb | Format::Etc2Rgba1 => blocks(4, 4, 8),
+      Format::Bc2 | Format::Bc3 | Format::Etc2Rgba => blocks(4, 4, 16),
+      Format::Astc { block_width, block_height } => blocks(block_width, block_height, 16),
+      // PVRTC pads small levels up to a minimum of 2x2 blocks.
+      Format::Pvrtc { bits: 4, .. } => texels(width.max(8), height.max(8), 4),
+      Format::Pvrtc { .. } => texels(width.max(16), height.max(8), 2),
+    }
+  }
+}
+
+/// Size of mip `level` of a `width` x `height` texture.
+pub fn mip_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+  ((width >> level).max(1), (height >> level).max(1))
+}
+
+/// Levels in a full mip chain down to 1x1.
+pub fn full_mip_count(width: u32, height: u32) -> u32 {
+  32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Reject a header claiming more levels than a full chain has, before
+/// reading them.
+fn check_mip_levels(width: u32, height: u32, levels: u32) -> Result<(), String> {
+  if levels > full_mip_count(width, height) {
+    return Err(format!("{} mip levels for a {}x{} texture", levels, width, height));
+  }
+  Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+  pub width: u32,
+  pub height: u32,
+  pub data: Vec<u8>,
+}
+
+/// A parsed texture: its format and mip levels, largest first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Container {
+  pub format: Format,
+  pub width: u32,
+  pub height: u32,
+  pub levels: Vec<Level>,
+}
+
+impl Container {
+  /// Check every level has the size and byte count its index implies.
+  fn validate(self) -> Result<Container, String> {
+    if self.width == 0 || self.height == 0 {
+      return Err(format!("texture is {}x{}", self.width, self.height));
+    }
+    if self.levels.is_empty() || self.levels.len() as u32 > full_mip_count(self.width, self.height) {
+      return Err(format!("{} mip levels for a {}x{} texture", self.levels.len(), self.width, self.height));
+    }
+    for (i, level) in self.levels.iter().enumerate() {
+      let (width, height) = mip_size(self.width, self.height, i as u32);
+      let expected = self.format.level_size(width, height)?;
+      if level.width != width || level.height != height || level.data.len() != expected {
+        return Err(format!(
+          "mip level {} is {}x{} with {} bytes, expected {}x{} with {}",
+          i, level.width, level.height, level.data.len(), width, height, expected,
+        ));
+      }
+    }
+    Ok(self)
+  }
+}
+
+/// Parse a KTX or DDS file, told apart by their magic numbers.
+pub fn parse(bytes: &[u8]) -> Result<Container, String> {
+  if bytes.starts_with(&KTX_IDENTIFIER) {
+    parse_ktx(bytes)
+  } else if bytes.starts_with(b"DDS ") {
+    parse_dds(bytes)
+  } else {
+    Err("neither a KTX nor a DDS file".to_string())
+  }
+}
+
+/// Reads little or big endian `u32`s with bounds checks.
+struct Reader<'a> {
+  bytes: &'a [u8],
+  offset: usize,
+  big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+    let end = self.offset.checked_add(count)
+        .ok_or_else(|| format!("{} bytes at {} is too large", count, self.offset))?;
+    if end > self.bytes.len() {
+      return Err(format!("file ends at byte {}, needed {} more at {}", self.bytes.len(), count, self.offset));
+    }
+    let slice = &self.bytes[self.offset..end];
+    self.offset = end;
+    Ok(slice)
+  }
+
+  fn u32(&mut self) -> Result<u32, String> {
+    let b = self.take(4)?;
+    let (b0, b1, b2, b3) = (b[0] as u32, b[1] as u32, b[2] as u32, b[3] as u32);
+    Ok(if self.big_endian {
+      b0 << 24 | b1 << 16 | b2 << 8 | b3
+    } else {
+      b3 << 24 | b2 << 16 | b1 << 8 | b0
+    })
+  }
+}
+
+const KTX_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Parse a KTX 1.1 file.
+pub fn parse_ktx(bytes: &[u8]) -> Result<Container, String> {
+  if !bytes.starts_with(&KTX_IDENTIFIER) {
+    return Err("not a KTX 1.1 file".to_string());
+  }
+  let mut reader = Reader { bytes, offset: KTX_IDENTIFIER.len(), big_endian: false };
+  match reader.u32()? {
+    0x04030201 => (),
+    0x01020304 => reader.big_endian = true,
+    other => return Err(format!("bad KTX endianness marker {:#010x}", other)),
+  }
+  let gl_type = reader.u32()?;
+  let _gl_type_size = reader.u32()?;
+  let gl_format = reader.u32()?;
+  let gl_internal_format = reader.u32()?;
+  let _gl_base_internal_format = reader.u32()?;
+  let width = reader.u32()?;
+  let height = reader.u32()?;
+  let depth = reader.u32()?;
+  let array_elements = reader.u32()?;
+  let faces = reader.u32()?;
+  let mip_levels = reader.u32()?.max(1);
+  let key_value_bytes = reader.u32()?;
+  if height == 0 || depth != 0 || array_elements != 0 || faces != 1 {
+    return Err(format!(
+      "only 2D KTX textures are supported, got height {}, depth {}, {} array elements and {} faces",
+      height, depth, array_elements, faces,
+    ));
+  }
+  reader.take(key_value_bytes as usize)?;
+
+  let format = if gl_type == 0 {
+    Format::from_gl(gl_internal_format)
+        .ok_or_else(|| format!("unsupported KTX internal format {:#06x}", gl_internal_format))?
+  } else if gl_type == gl_formats::UNSIGNED_BYTE && (gl_format == gl_formats::RGBA || gl_format == gl_formats::RGB) {
+    Format::Rgba8
+  } else {
+    return Err(format!("unsupported KTX type {:#06x} and format {:#06x}", gl_type, gl_format));
+  };
+
+  check_mip_levels(width, height, mip_levels)?;
+  let mut levels = Vec::new();
+  for level in 0..mip_levels {
+    let (level_width, level_height) = mip_size(width, height, level);
+    let size = reader.u32()? as usize;
+    let data = reader.take(size)?;
+    let data = if gl_format == gl_formats::RGB && format == Format::Rgba8 {
+      // Rows are padded to 4 bytes; add opaque alpha.
+      let stride = (level_width as usize * 3 + 3) / 4 * 4;
+      let expected = stride.checked_mul(level_height as usize)
+          .ok_or_else(|| format!("KTX level {} of {}x{} is too large", level, level_width, level_height))?;
+      if size < expected {
+        return Err(format!("KTX level {} has {} bytes, expected {}", level, size, expected));
+      }
+      let mut rgba = Vec::with_capacity(level_width as usize * level_height as usize * 4);
+      for row in 0..level_height as usize {
+        for texel in data[row * stride..row * stride + level_width as usize * 3].chunks(3) {
+          rgba.extend_from_slice(&[texel[0], texel[1], texel[2], 255]);
+        }
+      }
+      rgba
+    } else {
+      data.to_vec()
+    };
+    levels.push(Level { width: level_width, height: level_height, data });
+    // Each level is padded to 4 bytes.
+    reader.take((4 - size % 4) % 4)?;
+  }
+  Container { format, width, height, levels }.validate()
+}
+
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+fn four_cc(code: &[u8; 4]) -> u32 {
+  code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+/// The DXGI formats of the `DX10` extended header that map to ours.
+fn dxgi_format(format: u32) -> Option<Format> {
+  Some(match format {
+    28 | 29 => Format::Rgba8,
+    71 | 72 => Format::Bc1 { alpha: true },
+    74 | 75 => Format::Bc2,
+    77 | 78 => Format::Bc3,
+    _ => return None,
+  })
+}
+
+/// Parse a DDS file.
+pub fn parse_dds(bytes: &[u8]) -> Result<Container, String> {
+  if !bytes.starts_with(b"DDS ") {
+    return Err("not a DDS file".to_string());
+  }
+  let mut reader = Reader { bytes, offset: 4, big_endian: false };
+  let header_size = reader.u32()?;
+  if header_size != 124 {
+    return Err(format!("bad DDS header size {}", header_size));
+  }
+  let flags = reader.u32()?;
+  let height = reader.u32()?;
+  let width = reader.u32()?;
+  let _pitch_or_linear_size = reader.u32()?;
+  let _depth = reader.u32()?;
+  let mip_count = reader.u32()?;
+  reader.take(11 * 4)?;
+  let _pixel_format_size = reader.u32()?;
+  let pixel_flags = reader.u32()?;
+  let four_cc_code = reader.u32()?;
+  let bit_count = reader.u32()?;
+  let masks = [reader.u32()?, reader.u32()?, reader.u32()?, reader.u32()?];
+  let _caps = reader.u32()?;
+  let caps2 = reader.u32()?;
+  reader.take(3 * 4)?;
+  if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+    return Err("only 2D DDS textures are supported, not cube maps or volumes".to_string());
+  }
+  let mip_levels = if flags & DDSD_MIPMAPCOUNT != 0 { mip_count.max(1) } else { 1 };
+
+  // Uncompressed 32 bit texels are swizzled to RGBA through the masks.
+  let mut swizzle = None;
+  let format = if pixel_flags & DDPF_FOURCC != 0 {
+    match four_cc_code {
+      code if code == four_cc(b"DXT1") => Format::Bc1 { alpha: true },
+      code if code == four_cc(b"DXT2") || code == four_cc(b"DXT3") => Format::Bc2,
+      code if code == four_cc(b"DXT4") || code == four_cc(b"DXT5") => Format::Bc3,
+      code if code == four_cc(b"ETC1") => Format::Etc1,
+      code if code == four_cc(b"DX10") => {
+        let dxgi = reader.u32()?;
+        let dimension = reader.u32()?;
+        reader.take(3 * 4)?;
+        // D3D10_RESOURCE_DIMENSION_TEXTURE2D
+        if dimension != 3 {
+          return Err(format!("only 2D DDS textures are supported, got resource dimension {}", dimension));
+        }
+        let format = dxgi_format(dxgi).ok_or_else(|| format!("unsupported DXGI format {}", dxgi))?;
+        if format == Format::Rgba8 {
+          swizzle = Some([0xFF, 0xFF00, 0xFF0000, 0xFF000000]);
+        }
+        format
+      },
+      code => {
+        let name: String = (0..4).map(|i| ((code >> (i * 8)) & 0xFF) as u8 as char).collect();
+        return Err(format!("unsupported DDS four character code {:?}", name));
+      },
+    }
+  } else if pixel_flags & DDPF_RGB != 0 && bit_count == 32 {
+    let alpha = if pixel_flags & DDPF_ALPHAPIXELS != 0 { masks[3] } else { 0 };
+    swizzle = Some([masks[0], masks[1], masks[2], alpha]);
+    Format::Rgba8
+  } else {
+    return Err(format!("unsupported DDS pixel format, flags {:#x} with {} bits", pixel_flags, bit_count));
+  };
+
+  check_mip_levels(width, height, mip_levels)?;
+  let mut levels = Vec::new();
+  for level in 0..mip_levels {
+    let (level_width, level_height) = mip_size(width, height, level);
+    let data = reader.take(format.level_size(level_width, level_height)?)?;
+    let data = match swizzle {
+      Some(masks) => data.chunks(4).flat_map(|texel| {
+        let value = texel[0] as u32 | (texel[1] as u32) << 8 | (texel[2] as u32) << 16 | (texel[3] as u32) << 24;
+        let channel = |mask: u32| if mask == 0 { 255 } else { ((value & mask) >> mask.trailing_zeros()) as u8 };
+        vec![channel(masks[0]), channel(masks[1]), channel(masks[2]), channel(masks[3])]
+      }).collect(),
+      None => data.to_vec(),
+    };
+    levels.push(Level { width: level_width, height: level_height, data });
+  }
+  Container { format, width, height, levels }.validate()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+  }
+
+  /// A little endian KTX header for a compressed 2D texture, without
+  /// its levels.
+  fn ktx_header(internal_format: u32, width: u32, height: u32, mip_levels: u32) -> Vec<u8> {
+    let mut bytes = KTX_IDENTIFIER.to_vec();
+    for &value in &[0x04030201, 0, 1, 0, internal_format, 0, width, height, 0, 0, 1, mip_levels, 0] {
+      push_u32(&mut bytes, value);
+    }
+    bytes
+  }
+
+  fn ktx(internal_format: u32, width: u32, height: u32, level_sizes: &[u32]) -> Vec<u8> {
+    let mut bytes = ktx_header(internal_format, width, height, level_sizes.len() as u32);
+    for (i, &size) in level_sizes.iter().enumerate() {
+      push_u32(&mut bytes, size);
+      bytes.extend((0..size).map(|j| (i as u32 + j) as u8));
+    }
+    bytes
+  }
+
+  fn dds_header(four_cc_code: &[u8; 4], width: u32, height: u32, mip_count: u32) -> Vec<u8> {
+    let mut bytes = b"DDS ".to_vec();
+    for &value in &[124, 0x1007 | DDSD_MIPMAPCOUNT, height, width, 0, 0, mip_count] {
+      push_u32(&mut bytes, value);
+    }
+    bytes.extend_from_slice(&[0; 11 * 4]);
+    for &value in &[32, DDPF_FOURCC, four_cc(four_cc_code), 0, 0, 0, 0, 0, 0x401008, 0] {
+      push_u32(&mut bytes, value);
+    }
+    bytes.extend_from_slice(&[0; 3 * 4]);
+    bytes
+  }
+
+  #[test]
+  fn parses_ktx() {
+    let container = parse(&ktx(gl_formats::RGB_S3TC_DXT1, 8, 8, &[32, 8, 8, 8])).unwrap();
+    assert_eq!(container.format, Format::Bc1 { alpha: false });
+    assert_eq!((container.width, container.height), (8, 8));
+    let levels: Vec<(u32, u32, usize)> =
+        container.levels.iter().map(|level| (level.width, level.height, level.data.len())).collect();
+    assert_eq!(levels, vec![(8, 8, 32), (4, 4, 8), (2, 2, 8), (1, 1, 8)]);
+    assert_eq!(container.levels[1].data[0], 1);
+  }
+
+  #[test]
+  fn parses_dds() {
+    let mut bytes = dds_header(b"DXT5", 8, 4, 3);
+    bytes.extend((0..32 + 16 + 16).map(|i| i as u8));
+    let container = parse(&bytes).unwrap();
+    assert_eq!(container.format, Format::Bc3);
+    assert_eq!((container.width, container.height), (8, 4));
+    let levels: Vec<(u32, u32, usize)> =
+        container.levels.iter().map(|level| (level.width, level.height, level.data.len())).collect();
+    assert_eq!(levels, vec![(8, 4, 32), (4, 2, 16), (2, 1, 16)]);
+    assert_eq!(container.levels[2].data[0], 48);
+  }
+
+  #[test]
+  fn rejects_truncated_files() {
+    let bytes = ktx(gl_formats::RGB_ETC1, 4, 4, &[8, 8, 8]);
+    for end in &[0, 20, 60, bytes.len() - 1] {
+      assert!(parse(&bytes[..*end]).is_err(), "{} bytes", end);
+    }
+    let mut bytes = dds_header(b"DXT1", 4, 4, 1);
+    bytes.extend_from_slice(&[0; 7]);
+    assert!(parse(&bytes).is_err());
+  }
+
+  #[test]
+  fn rejects_bad_magic() {
+    let mut bytes = ktx(gl_formats::RGB_ETC1, 4, 4, &[8]);
+    bytes[1] = b'X';
+    assert!(parse(&bytes).is_err());
+    assert!(parse_ktx(&bytes).is_err());
+    let mut bytes = dds_header(b"DXT1", 4, 4, 1);
+    bytes[0] = b'S';
+    assert!(parse(&bytes).is_err());
+    assert!(parse_dds(&bytes).is_err());
+  }
+
+  #[test]
+  fn rejects_too_many_mip_levels() {
+    assert!(parse(&ktx(gl_formats::RGB_ETC1, 4, 4, &[8, 8, 8, 8])).is_err());
+    for &levels in &[33, 40, u32::max_value()] {
+      assert!(parse(&ktx_header(gl_formats::RGB_ETC1, 4, 4, levels)).is_err());
+      assert!(parse(&dds_header(b"DXT1", 4, 4, levels)).is_err());
+    }
+  }
+
+  #[test]
+  fn rejects_sizes_too_large() {
+    let huge = u32::max_value();
+    assert!(Format::Bc1 { alpha: false }.level_size(huge, 4).is_err());
+    assert!(Format::Astc { block_width: 12, block_height: 12 }.level_size(4, huge).is_err());
+    assert!(parse(&dds_header(b"DXT5", huge, huge, 1)).is_err());
+    let mut bytes = ktx_header(gl_formats::RGB_ETC1, 4, 4, 1);
+    push_u32(&mut bytes, huge);
+    assert!(parse(&bytes).is_err());
+  }
+
+  #[test]
+  fn rounds_small_levels_up_to_whole_blocks() {
+    let sizes = [(Format::Bc1 { alpha: true }, 8), (Format::Bc3, 16), (Format::Etc1, 8)];
+    for &(format, block_bytes) in &sizes {
+      assert_eq!(format.level_size(1, 1), Ok(block_bytes), "{:?}", format);
+      assert_eq!(format.level_size(2, 2), Ok(block_bytes), "{:?}", format);
+      assert_eq!(format.level_size(5, 4), Ok(block_bytes * 2), "{:?}", format);
+    }
+  }
+}
@@ -0,0 +1,175 @@
+//! Software decoding of the block formats cheap enough to expand on the
+//! CPU, for contexts without the matching extension: S3TC (BC1 to 3)
+//! and ETC1.
+
+use renderer::compressed::container::Format;
+
+/// Whether `decode` handles `format`.
+pub fn can_decode(format: Format) -> bool {
+  match format {
+    Format::Rgba8 | Format::Bc1 { .. } | Format::Bc2 | Format::Bc3 | Format::Etc1 => true,
+    _ => false,
+  }
+}
+
+/// Expand one `width` x `height` level of `format` to RGBA8.
+pub fn decode(format: Format, width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>, String> {
+  if !can_decode(format) {
+    return Err(format!("{:?} can't be decoded in software", format));
+  }
+  let expected = format.level_size(width, height)?;
+  if data.len() != expected {
+    return Err(format!("{:?} level of {}x{} needs {} bytes, got {}", format, width, height, expected, data.len()));
+  }
+  if format == Format::Rgba8 {
+    return Ok(data.to_vec());
+  }
+
+  let block_bytes = expected / (((width as usize + 3) / 4) * ((height as usize + 3) / 4));
+  let mut out = vec![0u8; width as usize * height as usize * 4];
+  let mut texels = [[0u8; 4]; 16];
+  for (i, block) in data.chunks(block_bytes).enumerate() {
+    match format {
+      Format::Bc1 { alpha } => bc1(block, alpha, true, &mut texels),
+      Format::Bc2 => {
+        bc1(&block[8..], false, false, &mut texels);
+        for (j, texel) in texels.iter_mut().enumerate() {
+          let nibble = (block[j / 2] >> (4 * (j % 2))) & 0xF;
+          texel[3] = nibble * 17;
+        }
+      },
+      Format::Bc3 => {
+        bc1(&block[8..], false, false, &mut texels);
+        bc3_alpha(block, &mut texels);
+      },
+      Format::Etc1 => etc1(block, &mut texels),
+      _ => unreachable!(),
+    }
+    // Copy the block in, clipping at the right and bottom edges.
+    let blocks_across = (width as usize + 3) / 4;
+    let (bx, by) = ((i % blocks_across) * 4, (i / blocks_across) * 4);
+    for y in 0..4 {
+      for x in 0..4 {
+        let (px, py) = (bx + x, by + y);
+        if px < width as usize && py < height as usize {
+          let at = (py * width as usize + px) * 4;
+          out[at..at + 4].copy_from_slice(&texels[y * 4 + x]);
+        }
+      }
+    }
+  }
+  Ok(out)
+}
+
+fn rgb565(value: u16) -> [u32; 3] {
+  let (r, g, b) = ((value >> 11) as u32 & 0x1F, (value >> 5) as u32 & 0x3F, value as u32 & 0x1F);
+  [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+/// An 8 byte BC1 colour block. `three_color` allows the mode where the
+/// fourth colour is black, transparent when `alpha`; BC2 and BC3 always
+/// use four colours.
+fn bc1(block: &[u8], alpha: bool, three_color: bool, texels: &mut [[u8; 4]; 16]) {
+  let c0 = block[0] as u16 | (block[1] as u16) << 8;
+  let c1 = block[2] as u16 | (block[3] as u16) << 8;
+  let (a, b) = (rgb565(c0), rgb565(c1));
+  let mix = |wa: u32, wb: u32| {
+    let total = wa + wb;
+    [
+      ((a[0] * wa + b[0] * wb) / total) as u8,
+      ((a[1] * wa + b[1] * wb) / total) as u8,
+      ((a[2] * wa + b[2] * wb) / total) as u8,
+      255,
+    ]
+  };
+  let palette = if c0 > c1 || !three_color {
+    [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+  } else {
+    [mix(1, 0), mix(0, 1), mix(1, 1), [0, 0, 0, if alpha { 0 } else { 255 }]]
+  };
+  let indices = block[4] as u32 | (block[5] as u32) << 8 | (block[6] as u32) << 16 | (block[7] as u32) << 24;
+  for (i, texel) in texels.iter_mut().enumerate() {
+    *texel = palette[((indices >> (2 * i)) & 3) as usize];
+  }
+}
+
+/// The interpolated alpha half of a BC3 block.
+fn bc3_alpha(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+  let (a0, a1) = (block[0] as u32, block[1] as u32);
+  let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+  if a0 > a1 {
+    for i in 1..7 {
+      palette[i + 1] = ((7 - i as u32) * a0 + i as u32 * a1) / 7;
+    }
+  } else {
+    for i in 1..5 {
+      palette[i + 1] = ((5 - i as u32) * a0 + i as u32 * a1) / 5;
+    }
+    palette[6] = 0;
+  }
+  let mut indices = 0u64;
+  for k in 0..6 {
+    indices |= (block[2 + k] as u64) << (8 * k);
+  }
+  for (i, texel) in texels.iter_mut().enumerate() {
+    texel[3] = palette[((indices >> (3 * i)) & 7) as usize] as u8;
+  }
+}
+
+const ETC1_MODIFIERS: [[i32; 4]; 8] = [
+  [2, 8, -2, -8],
+  [5, 17, -5, -17],
+  [9, 29, -9, -29],
+  [13, 42, -13, -42],
+  [18, 60, -18, -60],
+  [24, 80, -24, -80],
+  [33, 106, -33, -106],
+  [47, 183, -47, -183],
+];
+
+/// An 8 byte ETC1 block: two half-block base colours, each with a
+/// modifier table, and a 2 bit modifier index per texel.
+fn etc1(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+  let mut bits = 0u64;
+  for &byte in &block[..8] {
+    bits = bits << 8 | byte as u64;
+  }
+  let field = |shift: u32, width: u32| ((bits >> shift) & ((1 << width) - 1)) as i32;
+  let differential = field(33, 1) == 1;
+  let flipped = field(32, 1) == 1;
+
+  let mut bases = [[0i32; 3]; 2];
+  for (channel, &shift) in [59u32, 51, 43].iter().enumerate() {
+    if differential {
+      let first = field(shift, 5);
+      // Three bit two's complement delta.
+      let delta = (field(shift - 3, 3) << 29) >> 29;
+      let second = (first + delta).max(0).min(31);
+      bases[0][channel] = (first << 3) | (first >> 2);
+      bases[1][channel] = (second << 3) | (second >> 2);
+    } else {
+      let (first, second) = (field(shift + 1, 4), field(shift - 3, 4));
+      bases[0][channel] = first << 4 | first;
+      bases[1][channel] = second << 4 | second;
+    }
+  }
+  let tables = [field(37, 3) as usize, field(34, 3) as usize];
+
+  for y in 0..4 {
+    for x in 0..4 {
+      let half = if flipped { (y >= 2) as usize } else { (x >= 2) as usize };
+      // Texels are numbered down the columns.
+      let index = x * 4 + y;
+      let msb = field(16 + index as u32, 1);
+      let lsb = field(index as u32, 1);
+      let modifier = ETC1_MODIFIERS[tables[half]][(msb << 1 | lsb) as usize];
+      let base = bases[half];
+      texels[y * 4 + x] = [
+        (base[0] + modifier).max(0).min(255) as u8,
+        (base[1] + modifier).max(0).min(255) as u8,
+        (base[2] + modifier).max(0).min(255) as u8,
+        255,
+      ];
+    }
+  }
+}
@@ -0,0 +1,102 @@
+//! Textures from KTX 1.1 and DDS files, with their mip levels.
+//!
+//! `container` parses the files and does the mip-chain math, `decode`
+//! expands S3TC and ETC1 on the CPU. `CompressedTexture::new` uploads
+//! the levels in the file's own format when the context has the
+//! extension for it, decodes to RGBA when it doesn't and the format
+//! allows, and otherwise fails naming the extension it would need.
+
+pub mod container;
+pub mod decode;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlRenderingContext, WebGlTexture};
+
+use renderer::compressed::container::{Container, Format};
+use renderer::gl::Gl;
+use renderer::texture::set_mip_sampling;
+use renderer::u8_view;
+
+pub struct CompressedTexture {
+  pub texture: WebGlTexture,
+  pub width: u32,
+  pub height: u32,
+  /// The format in the file.
+  pub format: Format,
+  pub levels: usize,
+  /// The levels were decoded to RGBA because the context lacked the
+  /// extension for `format`.
+  pub decoded: bool,
+}
+
+impl CompressedTexture {
+  /// Parse a KTX or DDS file and upload it; see `new`.
+  pub fn from_bytes(context: &Gl, bytes: &[u8]) -> Result<CompressedTexture, JsValue> {
+    CompressedTexture::new(context, &container::parse(bytes)?)
+  }
+
+  /// Upload every level of `texture`, sampled as `set_mip_sampling`
+  /// sets up.
+  pub fn new(context: &Gl, texture: &Container) -> Result<CompressedTexture, JsValue> {
+    let format = texture.format;
+    let native = match format.extension() {
+      None => true,
+      Some(extension) => context.get_extension(extension)?.is_some()
+          || (extension == "WEBGL_compressed_texture_pvrtc"
+              && context.get_extension("WEBKIT_WEBGL_compressed_texture_pvrtc")?.is_some()),
+    };
+    if !native && !decode::can_decode(format) {
+      return Err(format!(
+        "{:?} textures need {}, which this context doesn't support",
+        format, format.extension().unwrap_or(""),
+      ).into());
+    }
+
+    let handle = context.create_texture().ok_or("failed to create texture")?;
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&handle));
+    for (level, data) in texture.levels.iter().enumerate() {
+      if native && format.is_compressed() {
+        let view = u8_view(&data.data)?;
+        context.compressed_tex_image_2d_with_array_buffer_view(
+            WebGlRenderingContext::TEXTURE_2D,
+            level as i32,
+            format.gl_internal_format(),
+            data.width as i32,
+            data.height as i32,
+            0,
+            &view,
+        );
+      } else {
+        let pixels = decode::decode(format, data.width, data.height, &data.data)?;
+        let view = u8_view(&pixels)?;
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            WebGlRenderingContext::TEXTURE_2D,
+            level as i32,
+            WebGlRenderingContext::RGBA as i32,
+            data.width as i32,
+            data.height as i32,
+            0,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            Some(&view),
+        )?;
+      }
+    }
+
+    let levels = texture.levels.len();
+    set_mip_sampling(context, texture.width, texture.height, levels);
+
+    Ok(CompressedTexture {
+      texture: handle,
+      width: texture.width,
+      height: texture.height,
+      format,
+      levels,
+      decoded: !native,
+    })
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.texture));
+  }
+}
@@ -0,0 +1,122 @@
+//! Surviving a lost WebGL context.
+//!
+//! The browser may drop the context at any time, after a GPU reset or
+//! when a page holds too many. Every object made on it goes stale.
+//! `ContextLoss` asks for the context back, tells the loop to pause
+//! while it's gone and, once restored, rebuilds everything made
+//! through `Resources` from its description. What a sample made on its
+//! own, uniform locations included, is for it to rebuild when
+//! `take_restored` says so.
+//!
+//! `renderer_lose_context` and `renderer_restore_context` drive this
+//! through `WEBGL_lose_context` for testing.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{console, Event, HtmlCanvasElement, WebglLoseContext};
+
+use renderer::capabilities;
+use renderer::gl::Gl;
+use renderer::resources;
+
+#[derive(Default)]
+struct Status {
+  lost: Cell<bool>,
+  restored: Cell<bool>,
+  /// Times the context was lost so far.
+  losses: Cell<u32>,
+}
+
+thread_local! {
+  /// `WEBGL_lose_context` of the watched context. Fetched up front,
+  /// since a lost context hands out no extensions.
+  static LOSE_CONTEXT: RefCell<Option<WebglLoseContext>> = RefCell::new(None);
+}
+
+/// Watches one canvas for `webglcontextlost` and
+/// `webglcontextrestored`. Clones share the state.
+#[derive(Clone)]
+pub struct ContextLoss {
+  status: Rc<Status>,
+}
+
+impl ContextLoss {
+  pub fn watch(canvas: &HtmlCanvasElement, context: &Gl) -> Result<ContextLoss, JsValue> {
+    let status = Rc::new(Status::default());
+
+    {
+      let status = status.clone();
+      let on_lost = Closure::wrap(Box::new(move |event: Event| {
+        // Without this the browser never restores the context.
+        event.prevent_default();
+        status.lost.set(true);
+        status.losses.set(status.losses.get() + 1);
+        console::warn_1(&"WebGL context lost, paused until it is restored".into());
+      }) as Box<FnMut(Event)>);
+      canvas.add_event_listener_with_callback("webglcontextlost", on_lost.as_ref().unchecked_ref())?;
+      on_lost.forget();
+    }
+
+    {
+      let status = status.clone();
+      let context = context.clone();
+      let on_restored = Closure::wrap(Box::new(move |_event: Event| {
+        // The new context starts at the defaults, without extensions.
+        context.forget_state();
+        capabilities::reset();
+        capabilities::capabilities(&context);
+        let report = resources::restore_all(&context);
+        console::log_1(&format!(
+          "WebGL context restored: {} resources rebuilt, {} lost", report.restored, report.lost,
+        ).into());
+        status.lost.set(false);
+        status.restored.set(true);
+      }) as Box<FnMut(Event)>);
+      canvas.add_event_listener_with_callback("webglcontextrestored", on_restored.as_ref().unchecked_ref())?;
+      on_restored.forget();
+    }
+
+    let extension = context
+        .get_extension("WEBGL_lose_context")?
+        .map(|extension| extension.unchecked_into::<WebglLoseContext>());
+    LOSE_CONTEXT.with(|lose| *lose.borrow_mut() = extension);
+
+    Ok(ContextLoss { status })
+  }
+
+  /// The loop should skip drawing, but keep scheduling itself.
+  pub fn is_lost(&self) -> bool {
+    self.status.lost.get()
+  }
+
+  /// True once after each restore.
+  pub fn take_restored(&self) -> bool {
+    self.status.restored.replace(false)
+  }
+
+  pub fn losses(&self) -> u32 {
+    self.status.losses.get()
+  }
+}
+
+fn lose_context() -> Result<WebglLoseContext, JsValue> {
+  LOSE_CONTEXT.with(|lose| lose.borrow().clone())
+      .ok_or_else(|| "no watched context with WEBGL_lose_context".into())
+}
+
+/// Simulate losing the watched context.
+#[wasm_bindgen]
+pub fn renderer_lose_context() -> Result<(), JsValue> {
+  lose_context()?.lose_context();
+  Ok(())
+}
+
+/// Give back a context lost through `renderer_lose_context`.
+#[wasm_bindgen]
+pub fn renderer_restore_context() -> Result<(), JsValue> {
+  lose_context()?.restore_context();
+  Ok(())
+}
@@ -0,0 +1,17 @@
+precision highp float;
+
+uniform sampler2D uSource;
+
+varying vec3 vDirection;
+
+const float PI = 3.14159265359;
+
+void main(void) {
+  vec3 direction = normalize(vDirection);
+  // Longitude across, latitude down from the top row.
+  vec2 uv = vec2(
+    atan(direction.z, direction.x) / (2.0 * PI) + 0.5,
+    0.5 - asin(clamp(direction.y, -1.0, 1.0)) / PI
+  );
+  gl_FragColor = texture2D(uSource, uv);
+}
@@ -0,0 +1,14 @@
+attribute vec2 aPosition;
+
+// Axes of the cube face being rendered: the face's outward direction
+// and the directions of increasing s and t texture coordinates.
+uniform vec3 uFaceForward;
+uniform vec3 uFaceRight;
+uniform vec3 uFaceUp;
+
+varying vec3 vDirection;
+
+void main(void) {
+  vDirection = uFaceForward + aPosition.x * uFaceRight + aPosition.y * uFaceUp;
+  gl_Position = vec4(aPosition, 0.0, 1.0);
+}
@@ -0,0 +1,359 @@
+//! Cube map textures, a skybox and a reflective material.
+//!
+//! A `TextureCube` comes from six face images or from one
+//! equirectangular panorama, converted on the GPU by rendering each
+//! face with a shader that samples the panorama along the face's
+//! directions. `render_faces` does that rendering for any such shader.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  HtmlImageElement,
+  WebGl2RenderingContext,
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::f32_view;
+use renderer::geometry;
+use renderer::gl::{Backend, Gl};
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program, build_program_variant, ShaderSource, ShaderVariants};
+use renderer::uniforms::{Camera, SceneUniforms};
+
+pub static FACE_VERTEX_SHADER: &'static str = include_str!("face_v.glsl");
+pub static EQUIRECT_FRAGMENT_SHADER: &'static str = include_str!("equirect_f.glsl");
+pub static SKYBOX_VERTEX_SHADER: &'static str = include_str!("skybox_v.glsl");
+pub static SKYBOX_FRAGMENT_SHADER: &'static str = include_str!("skybox_f.glsl");
+
+pub static REFLECTIVE_SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("reflective_v.glsl"),
+    fragment: include_str!("reflective_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("reflective_v300.glsl"),
+    fragment: include_str!("reflective_f300.glsl"),
+  },
+};
+
+/// Forward, `s` and `t` axes of each face, in the order of the
+/// `TEXTURE_CUBE_MAP_POSITIVE_X` + i targets.
+const FACE_AXES: [[[f32; 3]; 3]; 6] = [
+  [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]],
+  [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+  [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+  [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+  [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+  [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+];
+
+fn is_power_of_two(value: i32) -> bool {
+  value > 0 && value & (value - 1) == 0
+}
+
+pub struct TextureCube {
+  pub texture: WebGlTexture,
+  /// Width and height of each face at level 0.
+  pub size: i32,
+  pub mipmapped: bool,
+}
+
+impl TextureCube {
+  /// An empty cube of `size` x `size` RGBA faces, `data_type`
+  /// `UNSIGNED_BYTE` or `FLOAT`, for rendering into.
+  pub fn new(context: &Gl, size: i32, data_type: u32) -> Result<TextureCube, JsValue> {
+    let texture = context.create_texture().ok_or("failed to create texture")?;
+    let cube = TextureCube { texture, size, mipmapped: false };
+    cube.allocate(context, 0, size, data_type)?;
+    cube.set_sampling(context);
+    Ok(cube)
+  }
+
+  /// Storage for one mip level of every face.
+  pub fn allocate(&self, context: &Gl, level: i32, size: i32, data_type: u32) -> Result<(), JsValue> {
+    let (internal_format, data_type) = match (context.backend(), data_type) {
+      // Half floats render with EXT_color_buffer_float and filter
+      // without OES_texture_float_linear.
+      (Backend::WebGl2, WebGlRenderingContext::FLOAT) => {
+        (WebGl2RenderingContext::RGBA16F, WebGl2RenderingContext::HALF_FLOAT)
+      },
+      _ => (WebGlRenderingContext::RGBA, data_type),
+    };
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&self.texture));
+    for face in 0..6 {
+      context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+          WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+          level,
+          internal_format as i32,
+          size,
+          size,
+          0,
+          WebGlRenderingContext::RGBA,
+          data_type,
+          None,
+      )?;
+    }
+    Ok(())
+  }
+
+  /// From six square images of the same size, in the order +X, -X,
+  /// +Y, -Y, +Z, -Z. Mipmapped when the size allows it.
+  pub fn from_images(context: &Gl, faces: &[HtmlImageElement; 6]) -> Result<TextureCube, JsValue> {
+    let size = faces[0].natural_width() as i32;
+    for face in faces.iter() {
+      if face.natural_width() as i32 != size || face.natural_height() as i32 != size {
+        return Err(format!(
+            "cube faces must be square and {}x{}, got {}x{}",
+            size, size, face.natural_width(), face.natural_height(),
+        ).into());
+      }
+    }
+    let texture = context.create_texture().ok_or("failed to create texture")?;
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&texture));
+    for (i, face) in faces.iter().enumerate() {
+      context.tex_image_2d_with_image(
+          WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+          0,
+          WebGlRenderingContext::RGBA as i32,
+          WebGlRenderingContext::RGBA,
+          WebGlRenderingContext::UNSIGNED_BYTE,
+          face,
+      )?;
+    }
+    let mut cube = TextureCube { texture, size, mipmapped: false };
+    cube.generate_mipmaps(context);
+    Ok(cube)
+  }
+
+  /// Resample the 2D equirectangular `source`, twice as wide as high,
+  /// into a cube of `size` faces. Leaves the default framebuffer bound
+  /// with a full canvas viewport, and depth testing and blending off.
+  pub fn from_equirectangular(context: &Gl, source: &WebGlTexture, size: i32) -> Result<TextureCube, JsValue> {
+    let mut cube = TextureCube::new(context, size, WebGlRenderingContext::UNSIGNED_BYTE)?;
+    let program = build_program(context, FACE_VERTEX_SHADER, EQUIRECT_FRAGMENT_SHADER)?;
+    context.use_program(Some(&program));
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(source));
+    context.uniform1i(context.get_uniform_location(&program, "uSource").as_ref(), 0);
+    let result = render_faces(context, &cube, 0, &program);
+    context.delete_program(Some(&program));
+    result?;
+    cube.generate_mipmaps(context);
+    Ok(cube)
+  }
+
+  /// Build the mip chain if the context can for this size, and pick
+  /// trilinear filtering if it did.
+  pub fn generate_mipmaps(&mut self, context: &Gl) {
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&self.texture));
+    if context.backend() == Backend::WebGl2 || is_power_of_two(self.size) {
+      context.generate_mipmap(WebGlRenderingContext::TEXTURE_CUBE_MAP);
+      self.mipmapped = true;
+    }
+    self.set_sampling(context);
+  }
+
+  /// Filtering to match `mipmapped`, edges clamped.
+  pub fn set_sampling(&self, context: &Gl) {
+    let min_filter = if self.mipmapped {
+      WebGlRenderingContext::LINEAR_MIPMAP_LINEAR
+    } else {
+      WebGlRenderingContext::LINEAR
+    };
+    for &(name, value) in &[
+      (WebGlRenderingContext::TEXTURE_MIN_FILTER, min_filter),
+      (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::LINEAR),
+      (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+      (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+    ] {
+      context.tex_parameteri(WebGlRenderingContext::TEXTURE_CUBE_MAP, name, value as i32);
+    }
+  }
+
+  /// Bind to texture `unit` and point `location` at it.
+  pub fn bind(&self, context: &Gl, unit: u32, location: Option<&WebGlUniformLocation>) {
+    context.active_texture(WebGlRenderingContext::TEXTURE0 + unit);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&self.texture));
+    context.uniform1i(location, unit as i32);
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.texture));
+  }
+}
+
+/// Render every face of mip `level` of `cube` with `program`, which
+/// must be in use and take `face_v.glsl`'s attribute and uniforms; its
+/// other uniforms and textures are the caller's to set. Leaves the
+/// default framebuffer bound with a full canvas viewport, and depth
+/// testing and blending off.
+pub fn render_faces(context: &Gl, cube: &TextureCube, level: i32, program: &WebGlProgram) -> Result<(), JsValue> {
+  let size = (cube.size >> level).max(1);
+  let framebuffer = context.create_framebuffer().ok_or("failed to create framebuffer")?;
+  let quad = fullscreen_quad(context)?;
+  context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+  context.viewport(0, 0, size, size);
+  context.disable(WebGlRenderingContext::DEPTH_TEST);
+  context.disable(WebGlRenderingContext::BLEND);
+
+  let position = context.get_attrib_location(program, "aPosition") as u32;
+  context.vertex_attrib_pointer_with_i32(position, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+  context.enable_vertex_attrib_array(position);
+  let forward = context.get_uniform_location(program, "uFaceForward");
+  let right = context.get_uniform_location(program, "uFaceRight");
+  let up = context.get_uniform_location(program, "uFaceUp");
+
+  let mut result = Ok(());
+  for (face, axes) in FACE_AXES.iter().enumerate() {
+    context.framebuffer_texture_2d(
+        WebGlRenderingContext::FRAMEBUFFER,
+        WebGlRenderingContext::COLOR_ATTACHMENT0,
+        WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+        Some(&cube.texture),
+        level,
+    );
+    let status = context.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+    if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+      result = Err(format!("cube face framebuffer incomplete: 0x{:x}", status).into());
+      break;
+    }
+    context.uniform3f(forward.as_ref(), axes[0][0], axes[0][1], axes[0][2]);
+    context.uniform3f(right.as_ref(), axes[1][0], axes[1][1], axes[1][2]);
+    context.uniform3f(up.as_ref(), axes[2][0], axes[2][1], axes[2][2]);
+    context.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+  }
+
+  context.disable_vertex_attrib_array(position);
+  context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+  context.viewport(0, 0, context.drawing_buffer_width(), context.drawing_buffer_height());
+  context.delete_framebuffer(Some(&framebuffer));
+  context.delete_buffer(Some(&quad));
+  result
+}
+
+/// A bound `ARRAY_BUFFER` with a `-1..1` triangle strip.
+pub fn fullscreen_quad(context: &Gl) -> Result<WebGlBuffer, JsValue> {
+  let positions: [f32; 8] = [
+    -1.0, -1.0,
+     1.0, -1.0,
+    -1.0,  1.0,
+     1.0,  1.0,
+  ];
+  let quad = context.create_buffer().ok_or("failed to create buffer")?;
+  context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad));
+  let view = f32_view(&positions)?;
+  context.buffer_data_with_array_buffer_view(
+      WebGlRenderingContext::ARRAY_BUFFER,
+      &view,
+      WebGlRenderingContext::STATIC_DRAW,
+  );
+  Ok(quad)
+}
+
+/// Draws a cube map around the camera, behind everything else.
+pub struct Skybox {
+  program: WebGlProgram,
+  vertices: WebGlBuffer,
+  view_rotation: Option<WebGlUniformLocation>,
+  projection_matrix: Option<WebGlUniformLocation>,
+  sampler: Option<WebGlUniformLocation>,
+}
+
+impl Skybox {
+  pub fn new(context: &Gl) -> Result<Skybox, JsValue> {
+    let program = build_program(context, SKYBOX_VERTEX_SHADER, SKYBOX_FRAGMENT_SHADER)?;
+    let vertices = context.create_buffer().ok_or("failed to create buffer")?;
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&vertices));
+    let positions = geometry::cube().unindexed_positions();
+    let view = f32_view(&positions)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STATIC_DRAW,
+    );
+    Ok(Skybox {
+      view_rotation: context.get_uniform_location(&program, "uViewRotation"),
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      sampler: context.get_uniform_location(&program, "uSkybox"),
+      program,
+      vertices,
+    })
+  }
+
+  /// Draw `cube` at the far plane. Best drawn after the opaque scene so
+  /// the depth test skips covered pixels; doesn't write depth.
+  pub fn draw(&self, context: &Gl, cube: &TextureCube, camera: &Camera) {
+    let mut rotation = camera.view;
+    rotation[(0, 3)] = 0.0;
+    rotation[(1, 3)] = 0.0;
+    rotation[(2, 3)] = 0.0;
+
+    context.use_program(Some(&self.program));
+    let data: JsValue = JsValue::from_serde(&rotation).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.view_rotation.as_ref(), false, &data);
+    let data: JsValue = JsValue::from_serde(&camera.projection).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.projection_matrix.as_ref(), false, &data);
+    cube.bind(context, 0, self.sampler.as_ref());
+
+    context.enable(WebGlRenderingContext::DEPTH_TEST);
+    // Depth 1 only passes against a cleared buffer with LEQUAL.
+    context.depth_func(WebGlRenderingContext::LEQUAL);
+    context.depth_mask(false);
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.vertices));
+    context.vertex_attrib_pointer_with_i32(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+    context.enable_vertex_attrib_array(0);
+    context.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 36);
+    context.depth_mask(true);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_program(Some(&self.program));
+    context.delete_buffer(Some(&self.vertices));
+  }
+}
+
+/// Diffuse lit like `LitProgram`, mixed with the environment reflected
+/// about the surface normal.
+pub struct ReflectiveProgram {
+  pub program: WebGlProgram,
+  model_matrix: Option<WebGlUniformLocation>,
+  color: Option<WebGlUniformLocation>,
+  reflectivity: Option<WebGlUniformLocation>,
+  environment: Option<WebGlUniformLocation>,
+}
+
+impl ReflectiveProgram {
+  pub fn new(context: &Gl, uniforms: &SceneUniforms) -> Result<ReflectiveProgram, JsValue> {
+    let program = build_program_variant(context, &REFLECTIVE_SHADERS)?;
+    uniforms.attach(context, &program);
+    Ok(ReflectiveProgram {
+      model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+      color: context.get_uniform_location(&program, "uColor"),
+      reflectivity: context.get_uniform_location(&program, "uReflectivity"),
+      environment: context.get_uniform_location(&program, "uEnvironment"),
+      program,
+    })
+  }
+
+  /// Start drawing with this program, reflecting `environment`.
+  pub fn begin(&self, context: &Gl, uniforms: &SceneUniforms, environment: &TextureCube) {
+    context.use_program(Some(&self.program));
+    uniforms.apply(context, &self.program);
+    environment.bind(context, 0, self.environment.as_ref());
+  }
+
+  /// Draw `mesh`; `reflectivity` 0 is plain diffuse, 1 a mirror.
+  pub fn draw(&self, context: &Gl, mesh: &Mesh, model: &Mat4, color: [f32; 4], reflectivity: f32) {
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.model_matrix.as_ref(), false, &data);
+    context.uniform4f(self.color.as_ref(), color[0], color[1], color[2], color[3]);
+    context.uniform1f(self.reflectivity.as_ref(), reflectivity);
+    mesh.draw(context);
+  }
+}
@@ -0,0 +1,19 @@
+precision mediump float;
+
+uniform vec4 uColor;
+uniform float uReflectivity;
+uniform samplerCube uEnvironment;
+uniform vec4 uLightDirection;
+uniform vec4 uLightColor;
+uniform vec4 uAmbientColor;
+
+varying highp vec3 vNormal;
+varying highp vec3 vViewDirection;
+
+void main(void) {
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  vec3 lit = uColor.rgb * (uAmbientColor.rgb + uLightColor.rgb * diffuse);
+  vec3 reflected = textureCube(uEnvironment, reflect(normalize(vViewDirection), normal)).rgb;
+  gl_FragColor = vec4(mix(lit, reflected, uReflectivity), uColor.a);
+}
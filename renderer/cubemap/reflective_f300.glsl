@@ -0,0 +1,25 @@
+#version 300 es
+precision mediump float;
+
+layout(std140) uniform Light {
+  vec4 uLightDirection;
+  vec4 uLightColor;
+  vec4 uAmbientColor;
+};
+
+uniform vec4 uColor;
+uniform float uReflectivity;
+uniform samplerCube uEnvironment;
+
+in highp vec3 vNormal;
+in highp vec3 vViewDirection;
+
+out vec4 fragColor;
+
+void main(void) {
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  vec3 lit = uColor.rgb * (uAmbientColor.rgb + uLightColor.rgb * diffuse);
+  vec3 reflected = texture(uEnvironment, reflect(normalize(vViewDirection), normal)).rgb;
+  fragColor = vec4(mix(lit, reflected, uReflectivity), uColor.a);
+}
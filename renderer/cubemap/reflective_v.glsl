@@ -0,0 +1,17 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+uniform vec4 uCameraPosition;
+
+varying highp vec3 vNormal;
+varying highp vec3 vViewDirection;
+
+void main(void) {
+  vec4 world = uModelMatrix * aVertexPosition;
+  vNormal = (uModelMatrix * vec4(aVertexNormal, 0.0)).xyz;
+  vViewDirection = world.xyz - uCameraPosition.xyz;
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
@@ -0,0 +1,9 @@
+precision mediump float;
+
+uniform samplerCube uSkybox;
+
+varying vec3 vDirection;
+
+void main(void) {
+  gl_FragColor = textureCube(uSkybox, vDirection);
+}
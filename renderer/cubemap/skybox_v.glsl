@@ -0,0 +1,15 @@
+attribute vec4 aVertexPosition;
+
+// View matrix with its translation removed, so the box stays centred
+// on the camera.
+uniform mat4 uViewRotation;
+uniform mat4 uProjectionMatrix;
+
+varying vec3 vDirection;
+
+void main(void) {
+  vDirection = aVertexPosition.xyz;
+  vec4 position = uProjectionMatrix * uViewRotation * vec4(aVertexPosition.xyz, 1.0);
+  // z = w puts every fragment at depth 1, behind everything drawn.
+  gl_Position = position.xyww;
+}
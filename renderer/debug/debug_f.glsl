@@ -0,0 +1,7 @@
+precision mediump float;
+
+varying lowp vec4 vColor;
+
+void main(void) {
+  gl_FragColor = vColor;
+}
@@ -0,0 +1,11 @@
+attribute vec3 aVertexPosition;
+attribute vec4 aColor;
+
+uniform mat4 uViewProjection;
+
+varying lowp vec4 vColor;
+
+void main(void) {
+  gl_Position = uViewProjection * vec4(aVertexPosition, 1.0);
+  vColor = aColor;
+}
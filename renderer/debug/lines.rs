@@ -0,0 +1,206 @@
+//! Line lists for debug drawing, independent of WebGL.
+//!
+//! Every shape is broken down into coloured line segments appended to a
+//! flat vertex list, `x, y, z, r, g, b, a` per end, ready to upload and
+//! draw as `LINES`.
+
+use glm::{Mat4, Vec3};
+
+use renderer::bounds::{transform_point, Aabb, Bounds, Frustum, Sphere};
+use renderer::geometry::Geometry;
+use renderer::scene::Scene;
+
+/// Floats per line end: position and colour.
+pub const VERTEX_FLOATS: usize = 3 + 4;
+
+pub const RED: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+pub const GREEN: [f32; 4] = [0.2, 1.0, 0.2, 1.0];
+pub const BLUE: [f32; 4] = [0.3, 0.4, 1.0, 1.0];
+pub const YELLOW: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+pub const CYAN: [f32; 4] = [0.2, 0.9, 1.0, 1.0];
+pub const GREY: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// Segments per circle of `sphere`.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// The edges of a box as pairs of corner indices, with corner `i` at
+/// `x` from bit 0, `y` from bit 1 and `z` from bit 2.
+const BOX_EDGES: [(usize, usize); 12] = [
+  (0, 1), (2, 3), (4, 5), (6, 7),
+  (0, 2), (1, 3), (4, 6), (5, 7),
+  (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct LineList {
+  vertices: Vec<f32>,
+}
+
+impl LineList {
+  pub fn new() -> LineList {
+    LineList::default()
+  }
+
+  /// Number of line segments.
+  pub fn len(&self) -> usize {
+    self.vertices.len() / (2 * VERTEX_FLOATS)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.vertices.is_empty()
+  }
+
+  pub fn vertices(&self) -> &[f32] {
+    &self.vertices
+  }
+
+  pub fn clear(&mut self) {
+    self.vertices.clear();
+  }
+
+  pub fn line(&mut self, from: &Vec3, to: &Vec3, color: [f32; 4]) {
+    for point in &[from, to] {
+      self.vertices.extend_from_slice(&[point.x, point.y, point.z]);
+      self.vertices.extend_from_slice(&color);
+    }
+  }
+
+  /// The twelve edges between eight corners ordered as in `BOX_EDGES`.
+  fn box_edges(&mut self, corners: &[Vec3; 8], color: [f32; 4]) {
+    for &(a, b) in &BOX_EDGES {
+      self.line(&corners[a], &corners[b], color);
+    }
+  }
+
+  pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+    self.oriented_box(aabb, &Mat4::identity(), color);
+  }
+
+  /// `aabb` moved by `matrix`, as a box that turns with it rather than
+  /// the larger axis aligned box around it.
+  pub fn oriented_box(&mut self, aabb: &Aabb, matrix: &Mat4, color: [f32; 4]) {
+    let mut corners = [Vec3::zeros(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+      let local = glm::vec3(
+        if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+        if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+        if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+      );
+      *corner = transform_point(matrix, &local);
+    }
+    self.box_edges(&corners, color);
+  }
+
+  /// A circle of `radius` around `center` in the plane spanned by the
+  /// unit vectors `u` and `v`.
+  pub fn circle(&mut self, center: &Vec3, u: &Vec3, v: &Vec3, radius: f32, color: [f32; 4]) {
+    let point = |i: usize| {
+      let angle = i as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+      center + (u * angle.cos() + v * angle.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+      self.line(&point(i), &point(i + 1), color);
+    }
+  }
+
+  /// Three circles around the axes.
+  pub fn sphere(&mut self, sphere: &Sphere, color: [f32; 4]) {
+    let (x, y, z) = (glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0));
+    self.circle(&sphere.center, &x, &y, sphere.radius, color);
+    self.circle(&sphere.center, &y, &z, sphere.radius, color);
+    self.circle(&sphere.center, &z, &x, sphere.radius, color);
+  }
+
+  /// Both volumes, the box in `color` and the sphere dimmed.
+  pub fn bounds(&mut self, bounds: &Bounds, color: [f32; 4]) {
+    self.aabb(&bounds.aabb, color);
+    self.sphere(&bounds.sphere, [color[0] * 0.5, color[1] * 0.5, color[2] * 0.5, color[3]]);
+  }
+
+  /// The volume a camera with `view_projection` sees, found by taking
+  /// the corners of clip space back through its inverse. Nothing is
+  /// drawn for a singular matrix.
+  pub fn frustum(&mut self, view_projection: &Mat4, color: [f32; 4]) {
+    let inverse = match view_projection.try_inverse() {
+      Some(inverse) => inverse,
+      None => return,
+    };
+    let mut corners = [Vec3::zeros(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+      let clip = glm::vec3(
+        if i & 1 == 0 { -1.0 } else { 1.0 },
+        if i & 2 == 0 { -1.0 } else { 1.0 },
+        if i & 4 == 0 { -1.0 } else { 1.0 },
+      );
+      *corner = transform_point(&inverse, &clip);
+    }
+    self.box_edges(&corners, color);
+  }
+
+  /// The x, y and z axes of `matrix` from its origin, `length` long
+  /// before its scale, in red, green and blue.
+  pub fn axes(&mut self, matrix: &Mat4, length: f32) {
+    let origin = transform_point(matrix, &Vec3::zeros());
+    for (axis, &color) in [RED, GREEN, BLUE].iter().enumerate() {
+      let mut end = Vec3::zeros();
+      end[axis] = length;
+      self.line(&origin, &transform_point(matrix, &end), color);
+    }
+  }
+
+  /// Lines every `spacing` across the `y = 0` plane out to `extent`
+  /// from the origin each way. The two through the origin are drawn as
+  /// the x (red) and z (blue) axes.
+  pub fn grid(&mut self, extent: f32, spacing: f32, color: [f32; 4]) {
+    if spacing <= 0.0 {
+      return;
+    }
+    let steps = (extent / spacing).floor() as i32;
+    for i in -steps..steps + 1 {
+      let offset = i as f32 * spacing;
+      let (along_x, along_z) = if i == 0 { (RED, BLUE) } else { (color, color) };
+      self.line(&glm::vec3(-extent, 0.0, offset), &glm::vec3(extent, 0.0, offset), along_x);
+      self.line(&glm::vec3(offset, 0.0, -extent), &glm::vec3(offset, 0.0, extent), along_z);
+    }
+  }
+
+  /// A line of `length` along every vertex normal of `geometry` drawn
+  /// with `model`. Normals go through the inverse transpose, so they
+  /// stay perpendicular under non-uniform scale.
+  pub fn normals(&mut self, geometry: &Geometry, model: &Mat4, length: f32, color: [f32; 4]) {
+    let normal_matrix = match model.try_inverse() {
+      Some(inverse) => inverse.transpose(),
+      None => return,
+    };
+    for (position, normal) in geometry.positions.chunks(3).zip(geometry.normals.chunks(3)) {
+      let start = transform_point(model, &glm::vec3(position[0], position[1], position[2]));
+      let direction = normal_matrix * glm::vec4(normal[0], normal[1], normal[2], 0.0);
+      let direction = glm::vec3(direction.x, direction.y, direction.z);
+      if direction.norm() > 0.0 {
+        self.line(&start, &(start + direction.normalize() * length), color);
+      }
+    }
+  }
+
+  /// The world box of every mesh node of `scene`, green when it is
+  /// inside `frustum` and red when it would be culled. Call
+  /// `Scene::update_world` first.
+  pub fn scene_bounds(&mut self, scene: &Scene, frustum: Option<&Frustum>) {
+    for node in scene.nodes() {
+      if let Some(ref bounds) = node.world_bounds {
+        let visible = frustum.map_or(true, |frustum| frustum.intersects(bounds));
+        self.aabb(&bounds.aabb, if visible { GREEN } else { RED });
+      }
+    }
+  }
+
+  /// The normals of every mesh node of `scene`, where `geometries`
+  /// holds the geometry behind each mesh index the nodes use.
+  pub fn scene_normals(&mut self, scene: &Scene, geometries: &[Geometry], length: f32, color: [f32; 4]) {
+    for node in scene.nodes() {
+      if let Some(geometry) = node.mesh.and_then(|mesh| geometries.get(mesh)) {
+        self.normals(geometry, &node.world, length, color);
+      }
+    }
+  }
+}
@@ -0,0 +1,111 @@
+//! Immediate-mode debug drawing.
+//!
+//! Shapes are added to one of two `LineList`s during the frame, one
+//! hidden behind the scene by the depth buffer and one drawn over it,
+//! then `flush` uploads both into a dynamic buffer, draws them as
+//! `LINES` and forgets them, ready for the next frame.
+
+pub mod lines;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::debug::lines::{LineList, VERTEX_FLOATS};
+use renderer::f32_view;
+use renderer::gl::Gl;
+use renderer::shader::build_program;
+
+pub static VERTEX_SHADER: &'static str = include_str!("debug_v.glsl");
+pub static FRAGMENT_SHADER: &'static str = include_str!("debug_f.glsl");
+
+const VERTEX_STRIDE: i32 = (VERTEX_FLOATS * 4) as i32;
+
+pub struct DebugDraw {
+  /// Lines the scene in the depth buffer can hide.
+  pub depth_tested: LineList,
+  /// Lines drawn over everything.
+  pub on_top: LineList,
+  program: WebGlProgram,
+  color: u32,
+  view_projection: Option<WebGlUniformLocation>,
+  vertex_buffer: WebGlBuffer,
+}
+
+impl DebugDraw {
+  pub fn new(context: &Gl) -> Result<DebugDraw, JsValue> {
+    let program = build_program(context, VERTEX_SHADER, FRAGMENT_SHADER)?;
+    Ok(DebugDraw {
+      depth_tested: LineList::new(),
+      on_top: LineList::new(),
+      color: context.get_attrib_location(&program, "aColor") as u32,
+      view_projection: context.get_uniform_location(&program, "uViewProjection"),
+      program,
+      vertex_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+    })
+  }
+
+  /// Draw and clear both lists, after the scene so the depth tested
+  /// lines have its depth buffer to test against. Leaves depth testing
+  /// and the depth function as it found them and returns the number of
+  /// draw calls used.
+  pub fn flush(&mut self, context: &Gl, view_projection: &Mat4) -> Result<u32, JsValue> {
+    if self.depth_tested.is_empty() && self.on_top.is_empty() {
+      return Ok(0);
+    }
+    context.use_program(Some(&self.program));
+    let data: JsValue = JsValue::from_serde(view_projection).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.view_projection.as_ref(), false, &data);
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.vertex_buffer));
+
+    let depth_test = context.is_enabled(WebGlRenderingContext::DEPTH_TEST);
+    let depth_func = context.current_depth_func();
+    let mut draw_calls = 0;
+    context.enable(WebGlRenderingContext::DEPTH_TEST);
+    // Lines on a surface they outline tie with it.
+    context.depth_func(WebGlRenderingContext::LEQUAL);
+    draw_calls += self.draw(context, &self.depth_tested)?;
+    context.depth_func(depth_func);
+    context.disable(WebGlRenderingContext::DEPTH_TEST);
+    draw_calls += self.draw(context, &self.on_top)?;
+    if depth_test {
+      context.enable(WebGlRenderingContext::DEPTH_TEST);
+    }
+    context.disable_vertex_attrib_array(self.color);
+
+    self.depth_tested.clear();
+    self.on_top.clear();
+    Ok(draw_calls)
+  }
+
+  fn draw(&self, context: &Gl, lines: &LineList) -> Result<u32, JsValue> {
+    if lines.is_empty() {
+      return Ok(0);
+    }
+    let view = f32_view(lines.vertices())?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STREAM_DRAW,
+    );
+    for &(location, components, offset) in &[(0, 3, 0), (self.color, 4, 3)] {
+      context.vertex_attrib_pointer_with_i32(
+          location, components, WebGlRenderingContext::FLOAT, false, VERTEX_STRIDE, offset * 4
+      );
+      context.enable_vertex_attrib_array(location);
+    }
+    context.draw_arrays(WebGlRenderingContext::LINES, 0, (lines.len() * 2) as i32);
+    Ok(1)
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_program(Some(&self.program));
+    context.delete_buffer(Some(&self.vertex_buffer));
+  }
+}
@@ -0,0 +1,148 @@
+//! CPU-side vertex data for the primitives the demos draw.
+
+use std::collections::HashSet;
+
+use renderer::bounds::Bounds;
+use renderer::tangents;
+
+/// Non-interleaved vertex attributes plus triangle indices.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+  /// `x, y, z` per vertex.
+  pub positions: Vec<f32>,
+  /// `x, y, z` per vertex, unit length.
+  pub normals: Vec<f32>,
+  /// `u, v` per vertex.
+  pub uvs: Vec<f32>,
+  /// `x, y, z, w` per vertex, `w` the bitangent's handedness. Empty
+  /// until `generate_tangents` fills it.
+  pub tangents: Vec<f32>,
+  /// Three indices per triangle.
+  pub indices: Vec<u16>,
+}
+
+impl Geometry {
+  pub fn vertex_count(&self) -> usize {
+    self.positions.len() / 3
+  }
+
+  pub fn triangle_count(&self) -> usize {
+    self.indices.len() / 3
+  }
+
+  /// Fill `tangents` from the normals and UVs, see `tangents`.
+  pub fn generate_tangents(&mut self) -> Result<(), String> {
+    self.tangents = tangents::generate(self)?;
+    Ok(())
+  }
+
+  pub fn bounds(&self) -> Bounds {
+    Bounds::from_positions(&self.positions)
+  }
+
+  pub fn position(&self, index: usize) -> [f32; 3] {
+    [
+      self.positions[index * 3],
+      self.positions[index * 3 + 1],
+      self.positions[index * 3 + 2],
+    ]
+  }
+
+  /// Expand the indexed triangles into a flat position list, for
+  /// `draw_arrays` users.
+  pub fn unindexed_positions(&self) -> Vec<f32> {
+    let mut out = Vec::with_capacity(self.indices.len() * 3);
+    for &i in &self.indices {
+      out.extend_from_slice(&self.position(i as usize));
+    }
+    out
+  }
+
+  /// Two indices per triangle edge, for drawing as `LINES`. An edge
+  /// shared by two triangles is listed once; vertices split at a seam
+  /// still give one edge per side of it.
+  pub fn edge_indices(&self) -> Vec<u16> {
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for triangle in self.indices.chunks(3) {
+      if triangle.len() < 3 {
+        break;
+      }
+      for &(a, b) in &[(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+        if a != b && seen.insert((a.min(b), a.max(b))) {
+          edges.extend_from_slice(&[a, b]);
+        }
+      }
+    }
+    edges
+  }
+
+  /// Same as `unindexed_positions`, for normals.
+  pub fn unindexed_normals(&self) -> Vec<f32> {
+    let mut out = Vec::with_capacity(self.indices.len() * 3);
+    for &i in &self.indices {
+      let i = i as usize;
+      out.extend_from_slice(&self.normals[i * 3..i * 3 + 3]);
+    }
+    out
+  }
+}
+
+/// A cube from -1 to 1 on every axis, four vertices per face so every
+/// face gets its own normal and UVs. Face order matches sample5: front,
+/// back, top, bottom, right, left.
+pub fn cube() -> Geometry {
+  let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    // normal, u axis, v axis
+    ([ 0.0,  0.0,  1.0], [ 1.0,  0.0,  0.0], [ 0.0,  1.0,  0.0]),
+    ([ 0.0,  0.0, -1.0], [-1.0,  0.0,  0.0], [ 0.0,  1.0,  0.0]),
+    ([ 0.0,  1.0,  0.0], [ 1.0,  0.0,  0.0], [ 0.0,  0.0, -1.0]),
+    ([ 0.0, -1.0,  0.0], [ 1.0,  0.0,  0.0], [ 0.0,  0.0,  1.0]),
+    ([ 1.0,  0.0,  0.0], [ 0.0,  0.0, -1.0], [ 0.0,  1.0,  0.0]),
+    ([-1.0,  0.0,  0.0], [ 0.0,  0.0,  1.0], [ 0.0,  1.0,  0.0]),
+  ];
+  let corners: [(f32, f32); 4] = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+  let mut geometry = Geometry::default();
+  for (face, &(n, u, v)) in faces.iter().enumerate() {
+    for &(cu, cv) in &corners {
+      for k in 0..3 {
+        geometry.positions.push(n[k] + u[k] * cu + v[k] * cv);
+        geometry.normals.push(n[k]);
+      }
+      geometry.uvs.push((cu + 1.0) * 0.5);
+      geometry.uvs.push((cv + 1.0) * 0.5);
+    }
+    let base = (face * 4) as u16;
+    geometry.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+  }
+  geometry
+}
+
+/// A unit sphere of `rings` bands from pole to pole and `segments`
+/// around, with the UV seam duplicated.
+pub fn sphere(segments: usize, rings: usize) -> Geometry {
+  let mut geometry = Geometry::default();
+  for ring in 0..rings + 1 {
+    let v = ring as f32 / rings as f32;
+    let (sin_theta, cos_theta) = (v * std::f32::consts::PI).sin_cos();
+    for segment in 0..segments + 1 {
+      let u = segment as f32 / segments as f32;
+      let (sin_phi, cos_phi) = (u * 2.0 * std::f32::consts::PI).sin_cos();
+      let normal = [cos_phi * sin_theta, cos_theta, -sin_phi * sin_theta];
+      geometry.positions.extend_from_slice(&normal);
+      geometry.normals.extend_from_slice(&normal);
+      geometry.uvs.push(u);
+      geometry.uvs.push(1.0 - v);
+    }
+  }
+  let row = segments + 1;
+  for ring in 0..rings {
+    for segment in 0..segments {
+      let a = (ring * row + segment) as u16;
+      let (b, c, d) = (a + 1, a + row as u16, a + row as u16 + 1);
+      geometry.indices.extend_from_slice(&[a, c, b, b, c, d]);
+    }
+  }
+  geometry
+}
@@ -0,0 +1,439 @@
+//! One handle over either a WebGL2 or a WebGL1 context.
+//!
+//! Calls that exist with the same signature on both contexts are
+//! forwarded as is, so the rest of the renderer doesn't care which one
+//! it got. The few that differ get a hand written method here, and
+//! WebGL2-only paths reach for `webgl2()`.
+//!
+//! Enum values are the same numbers on both contexts, so code keeps
+//! spelling them as `WebGlRenderingContext::*`.
+//!
+//! Bindings, capabilities, blend, depth, cull and viewport calls go
+//! through a `GlState` shared by every clone and are skipped when they
+//! wouldn't change anything; `FrameStats::saved_calls` counts them.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde_derive::Serialize;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  HtmlCanvasElement,
+  HtmlImageElement,
+  WebGl2RenderingContext,
+  WebGlBuffer,
+  WebGlFramebuffer,
+  WebGlProgram,
+  WebGlRenderbuffer,
+  WebGlRenderingContext,
+  WebGlShader,
+  WebGlShaderPrecisionFormat,
+  WebGlTexture,
+  WebGlUniformLocation,
+  WebGlVertexArrayObject,
+};
+
+use renderer::capabilities;
+use renderer::state::GlState;
+use renderer::stats;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum Backend {
+  WebGl1,
+  WebGl2,
+}
+
+impl Backend {
+  pub fn as_str(&self) -> &'static str {
+    match *self {
+      Backend::WebGl1 => "webgl",
+      Backend::WebGl2 => "webgl2",
+    }
+  }
+}
+
+thread_local! {
+  static CHOSEN_BACKEND: Cell<Option<Backend>> = Cell::new(None);
+}
+
+/// The backend picked by the last `Gl::from_canvas`, `"webgl2"`,
+/// `"webgl"` or `null` if no renderer context was created yet.
+#[wasm_bindgen]
+pub fn renderer_backend() -> JsValue {
+  CHOSEN_BACKEND.with(|chosen| match chosen.get() {
+    Some(backend) => JsValue::from_str(backend.as_str()),
+    None => JsValue::NULL,
+  })
+}
+
+#[derive(Clone)]
+enum Context {
+  WebGl1(WebGlRenderingContext),
+  WebGl2(WebGl2RenderingContext),
+}
+
+#[derive(Clone)]
+pub struct Gl {
+  context: Context,
+  state: Rc<RefCell<GlState>>,
+}
+
+/// Call `$name` on whichever context `$gl` holds.
+macro_rules! call {
+  ($gl:expr, $name:ident($($arg:expr),*)) => {
+    match $gl.context {
+      Context::WebGl1(ref gl) => gl.$name($($arg),*),
+      Context::WebGl2(ref gl) => gl.$name($($arg),*),
+    }
+  }
+}
+
+macro_rules! forward {
+  ($( fn $name:ident(&self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)*; )*) => {
+    impl Gl {
+      $(
+        pub fn $name(&self $(, $arg: $ty)*) $(-> $ret)* {
+          call!(self, $name($($arg),*))
+        }
+      )*
+    }
+  }
+}
+
+forward! {
+  fn attach_shader(&self, program: &WebGlProgram, shader: &WebGlShader);
+  fn bind_attrib_location(&self, program: &WebGlProgram, index: u32, name: &str);
+  fn bind_framebuffer(&self, target: u32, framebuffer: Option<&WebGlFramebuffer>);
+  fn bind_renderbuffer(&self, target: u32, renderbuffer: Option<&WebGlRenderbuffer>);
+  fn buffer_data_with_array_buffer_view(&self, target: u32, data: &js_sys::Object, usage: u32);
+  fn buffer_data_with_i32(&self, target: u32, size: i32, usage: u32);
+  fn buffer_sub_data_with_i32_and_array_buffer_view(&self, target: u32, offset: i32, data: &js_sys::Object);
+  fn check_framebuffer_status(&self, target: u32) -> u32;
+  fn clear(&self, mask: u32);
+  fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+  fn clear_depth(&self, depth: f32);
+  fn color_mask(&self, red: bool, green: bool, blue: bool, alpha: bool);
+  fn compile_shader(&self, shader: &WebGlShader);
+  fn compressed_tex_image_2d_with_array_buffer_view(&self, target: u32, level: i32, internalformat: u32, width: i32, height: i32, border: i32, data: &js_sys::Object);
+  fn create_buffer(&self) -> Option<WebGlBuffer>;
+  fn create_framebuffer(&self) -> Option<WebGlFramebuffer>;
+  fn create_program(&self) -> Option<WebGlProgram>;
+  fn create_renderbuffer(&self) -> Option<WebGlRenderbuffer>;
+  fn create_shader(&self, type_: u32) -> Option<WebGlShader>;
+  fn create_texture(&self) -> Option<WebGlTexture>;
+  fn delete_framebuffer(&self, framebuffer: Option<&WebGlFramebuffer>);
+  fn delete_program(&self, program: Option<&WebGlProgram>);
+  fn delete_renderbuffer(&self, renderbuffer: Option<&WebGlRenderbuffer>);
+  fn delete_shader(&self, shader: Option<&WebGlShader>);
+  fn detach_shader(&self, program: &WebGlProgram, shader: &WebGlShader);
+  fn drawing_buffer_height(&self) -> i32;
+  fn drawing_buffer_width(&self) -> i32;
+  fn draw_arrays(&self, mode: u32, first: i32, count: i32);
+  fn draw_elements_with_i32(&self, mode: u32, count: i32, type_: u32, offset: i32);
+  fn framebuffer_renderbuffer(&self, target: u32, attachment: u32, renderbuffertarget: u32, renderbuffer: Option<&WebGlRenderbuffer>);
+  fn framebuffer_texture_2d(&self, target: u32, attachment: u32, textarget: u32, texture: Option<&WebGlTexture>, level: i32);
+  fn generate_mipmap(&self, target: u32);
+  fn get_attrib_location(&self, program: &WebGlProgram, name: &str) -> i32;
+  fn get_error(&self) -> u32;
+  fn get_extension(&self, name: &str) -> Result<Option<js_sys::Object>, JsValue>;
+  fn get_parameter(&self, pname: u32) -> Result<JsValue, JsValue>;
+  fn get_program_info_log(&self, program: &WebGlProgram) -> Option<String>;
+  fn get_program_parameter(&self, program: &WebGlProgram, pname: u32) -> JsValue;
+  fn get_shader_info_log(&self, shader: &WebGlShader) -> Option<String>;
+  fn get_shader_parameter(&self, shader: &WebGlShader, pname: u32) -> JsValue;
+  fn get_shader_precision_format(&self, shadertype: u32, precisiontype: u32) -> Option<WebGlShaderPrecisionFormat>;
+  fn get_supported_extensions(&self) -> Option<js_sys::Array>;
+  fn get_uniform_location(&self, program: &WebGlProgram, name: &str) -> Option<WebGlUniformLocation>;
+  fn is_context_lost(&self) -> bool;
+  fn line_width(&self, width: f32);
+  fn link_program(&self, program: &WebGlProgram);
+  fn pixel_storei(&self, pname: u32, param: i32);
+  fn polygon_offset(&self, factor: f32, units: f32);
+  fn read_pixels_with_opt_u8_array(&self, x: i32, y: i32, width: i32, height: i32, format: u32, type_: u32, pixels: Option<&mut [u8]>) -> Result<(), JsValue>;
+  fn renderbuffer_storage(&self, target: u32, internalformat: u32, width: i32, height: i32);
+  fn scissor(&self, x: i32, y: i32, width: i32, height: i32);
+  fn shader_source(&self, shader: &WebGlShader, source: &str);
+  fn tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(&self, target: u32, level: i32, internalformat: i32, width: i32, height: i32, border: i32, format: u32, type_: u32, pixels: Option<&js_sys::Object>) -> Result<(), JsValue>;
+  fn tex_parameteri(&self, target: u32, pname: u32, param: i32);
+  fn tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(&self, target: u32, level: i32, xoffset: i32, yoffset: i32, width: i32, height: i32, format: u32, type_: u32, pixels: Option<&js_sys::Object>) -> Result<(), JsValue>;
+  fn uniform1f(&self, location: Option<&WebGlUniformLocation>, x: f32);
+  fn uniform1i(&self, location: Option<&WebGlUniformLocation>, x: i32);
+  fn uniform2f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32);
+  fn uniform3f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32);
+  fn uniform4f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32, w: f32);
+  fn uniform_matrix4fv_with_f32_sequence(&self, location: Option<&WebGlUniformLocation>, transpose: bool, data: &JsValue);
+  fn vertex_attrib_pointer_with_i32(&self, index: u32, size: i32, type_: u32, normalized: bool, stride: i32, offset: i32);
+}
+
+impl Gl {
+  /// Create a context on `canvas`, preferring WebGL2.
+  ///
+  /// A canvas keeps the first kind of context it handed out, so if a
+  /// tutorial sample already asked for `"webgl"` we get WebGL1 here.
+  pub fn from_canvas(canvas: &HtmlCanvasElement) -> Result<Gl, JsValue> {
+    let context = match canvas.get_context("webgl2")? {
+      Some(context) => Context::WebGl2(context.dyn_into::<WebGl2RenderingContext>()?),
+      None => {
+        let context = canvas
+            .get_context("webgl")?
+            .ok_or("WebGL is not supported")?;
+        Context::WebGl1(context.dyn_into::<WebGlRenderingContext>()?)
+      },
+    };
+    let gl = Gl { context, state: Rc::new(RefCell::new(GlState::default())) };
+    CHOSEN_BACKEND.with(|chosen| chosen.set(Some(gl.backend())));
+
+    // Query up front so JS can fetch the report right away.
+    capabilities::reset();
+    capabilities::capabilities(&gl);
+
+    Ok(gl)
+  }
+
+  /// Both handles are clones of one `from_canvas` context.
+  pub fn same_context(&self, other: &Gl) -> bool {
+    Rc::ptr_eq(&self.state, &other.state)
+  }
+
+  pub fn backend(&self) -> Backend {
+    match self.context {
+      Context::WebGl1(_) => Backend::WebGl1,
+      Context::WebGl2(_) => Backend::WebGl2,
+    }
+  }
+
+  pub fn webgl1(&self) -> Option<&WebGlRenderingContext> {
+    match self.context {
+      Context::WebGl1(ref gl) => Some(gl),
+      Context::WebGl2(_) => None,
+    }
+  }
+
+  pub fn webgl2(&self) -> Option<&WebGl2RenderingContext> {
+    match self.context {
+      Context::WebGl1(_) => None,
+      Context::WebGl2(ref gl) => Some(gl),
+    }
+  }
+
+  pub fn tex_image_2d_with_image(
+    &self,
+    target: u32,
+    level: i32,
+    internalformat: i32,
+    format: u32,
+    type_: u32,
+    image: &HtmlImageElement,
+  ) -> Result<(), JsValue> {
+    match self.context {
+      Context::WebGl1(ref gl) => {
+        gl.tex_image_2d_with_u32_and_u32_and_image(target, level, internalformat, format, type_, image)
+      },
+      Context::WebGl2(ref gl) => {
+        gl.tex_image_2d_with_u32_and_u32_and_html_image_element(target, level, internalformat, format, type_, image)
+      },
+    }
+  }
+
+  pub fn tex_image_2d_with_canvas(
+    &self,
+    target: u32,
+    level: i32,
+    internalformat: i32,
+    format: u32,
+    type_: u32,
+    canvas: &HtmlCanvasElement,
+  ) -> Result<(), JsValue> {
+    match self.context {
+      Context::WebGl1(ref gl) => {
+        gl.tex_image_2d_with_u32_and_u32_and_canvas(target, level, internalformat, format, type_, canvas)
+      },
+      Context::WebGl2(ref gl) => {
+        gl.tex_image_2d_with_u32_and_u32_and_html_canvas_element(target, level, internalformat, format, type_, canvas)
+      },
+    }
+  }
+
+  /// A vertex array object on WebGL2, `None` on WebGL1 where callers
+  /// set attributes up on every draw instead.
+  pub fn create_vertex_array(&self) -> Option<WebGlVertexArrayObject> {
+    self.webgl2().and_then(|gl| gl.create_vertex_array())
+  }
+
+  pub fn bind_vertex_array(&self, array: Option<&WebGlVertexArrayObject>) {
+    if let Some(gl) = self.webgl2() {
+      if self.changed(self.state.borrow_mut().bind_vertex_array(array)) {
+        gl.bind_vertex_array(array);
+      }
+    }
+  }
+
+  pub fn delete_vertex_array(&self, array: Option<&WebGlVertexArrayObject>) {
+    if let Some(gl) = self.webgl2() {
+      if let Some(array) = array {
+        self.state.borrow_mut().delete_vertex_array(array);
+      }
+      gl.delete_vertex_array(array);
+    }
+  }
+}
+
+/* Tracked state */
+
+impl Gl {
+  /// Drop everything known about the context's state, after changing
+  /// it without going through `Gl`. The next call of each kind goes
+  /// through again.
+  pub fn forget_state(&self) {
+    *self.state.borrow_mut() = GlState::default();
+  }
+
+  /// Pass `changed` on, counting the call skipped when it's false.
+  fn changed(&self, changed: bool) -> bool {
+    if !changed {
+      stats::record_saved_call();
+    }
+    changed
+  }
+
+  pub fn use_program(&self, program: Option<&WebGlProgram>) {
+    if self.changed(self.state.borrow_mut().use_program(program)) {
+      call!(self, use_program(program));
+    }
+  }
+
+  pub fn bind_buffer(&self, target: u32, buffer: Option<&WebGlBuffer>) {
+    if self.changed(self.state.borrow_mut().bind_buffer(target, buffer)) {
+      call!(self, bind_buffer(target, buffer));
+    }
+  }
+
+  pub fn delete_buffer(&self, buffer: Option<&WebGlBuffer>) {
+    if let Some(buffer) = buffer {
+      self.state.borrow_mut().delete_buffer(buffer);
+    }
+    call!(self, delete_buffer(buffer));
+  }
+
+  pub fn enable_vertex_attrib_array(&self, index: u32) {
+    if self.changed(self.state.borrow_mut().set_attribute(index, true)) {
+      call!(self, enable_vertex_attrib_array(index));
+    }
+  }
+
+  pub fn disable_vertex_attrib_array(&self, index: u32) {
+    if self.changed(self.state.borrow_mut().set_attribute(index, false)) {
+      call!(self, disable_vertex_attrib_array(index));
+    }
+  }
+
+  pub fn active_texture(&self, texture: u32) {
+    if self.changed(self.state.borrow_mut().active_texture(texture)) {
+      call!(self, active_texture(texture));
+    }
+  }
+
+  pub fn bind_texture(&self, target: u32, texture: Option<&WebGlTexture>) {
+    if self.changed(self.state.borrow_mut().bind_texture(target, texture)) {
+      call!(self, bind_texture(target, texture));
+    }
+  }
+
+  pub fn delete_texture(&self, texture: Option<&WebGlTexture>) {
+    if let Some(texture) = texture {
+      self.state.borrow_mut().delete_texture(texture);
+    }
+    call!(self, delete_texture(texture));
+  }
+
+  pub fn enable(&self, cap: u32) {
+    if self.changed(self.state.borrow_mut().set_capability(cap, true)) {
+      call!(self, enable(cap));
+    }
+  }
+
+  pub fn disable(&self, cap: u32) {
+    if self.changed(self.state.borrow_mut().set_capability(cap, false)) {
+      call!(self, disable(cap));
+    }
+  }
+
+  /// Answered without asking the context once the capability is known.
+  pub fn is_enabled(&self, cap: u32) -> bool {
+    let known = self.state.borrow().capability(cap);
+    match known {
+      Some(enabled) => {
+        stats::record_saved_call();
+        enabled
+      },
+      None => {
+        let enabled = call!(self, is_enabled(cap));
+        self.state.borrow_mut().set_capability(cap, enabled);
+        enabled
+      },
+    }
+  }
+
+  pub fn blend_equation(&self, mode: u32) {
+    if self.changed(self.state.borrow_mut().blend_equation(mode)) {
+      call!(self, blend_equation(mode));
+    }
+  }
+
+  pub fn blend_func(&self, sfactor: u32, dfactor: u32) {
+    if self.changed(self.state.borrow_mut().blend_func([sfactor, dfactor, sfactor, dfactor])) {
+      call!(self, blend_func(sfactor, dfactor));
+    }
+  }
+
+  pub fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+    if self.changed(self.state.borrow_mut().blend_func([src_rgb, dst_rgb, src_alpha, dst_alpha])) {
+      call!(self, blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha));
+    }
+  }
+
+  pub fn depth_func(&self, func: u32) {
+    if self.changed(self.state.borrow_mut().depth_func(func)) {
+      call!(self, depth_func(func));
+    }
+  }
+
+  /// Answered without asking the context once the function is known,
+  /// like `is_enabled`.
+  pub fn current_depth_func(&self) -> u32 {
+    let known = self.state.borrow().known_depth_func();
+    match known {
+      Some(func) => {
+        stats::record_saved_call();
+        func
+      },
+      None => {
+        let func = call!(self, get_parameter(WebGlRenderingContext::DEPTH_FUNC))
+            .ok()
+            .and_then(|value| value.as_f64())
+            .map_or(WebGlRenderingContext::LESS, |value| value as u32);
+        self.state.borrow_mut().depth_func(func);
+        func
+      },
+    }
+  }
+
+  pub fn depth_mask(&self, flag: bool) {
+    if self.changed(self.state.borrow_mut().depth_mask(flag)) {
+      call!(self, depth_mask(flag));
+    }
+  }
+
+  pub fn cull_face(&self, mode: u32) {
+    if self.changed(self.state.borrow_mut().cull_face(mode)) {
+      call!(self, cull_face(mode));
+    }
+  }
+
+  pub fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+    if self.changed(self.state.borrow_mut().viewport([x, y, width, height])) {
+      call!(self, viewport(x, y, width, height));
+    }
+  }
+}
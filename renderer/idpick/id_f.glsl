@@ -0,0 +1,8 @@
+precision mediump float;
+
+// The object id packed into RGB, see `idpick::encode_id`.
+uniform vec4 uId;
+
+void main(void) {
+  gl_FragColor = uId;
+}
@@ -0,0 +1,8 @@
+precision mediump float;
+
+// Only needs 8 bits per channel, mediump is enough.
+varying vec3 vId;
+
+void main(void) {
+  gl_FragColor = vec4(vId, 1.0);
+}
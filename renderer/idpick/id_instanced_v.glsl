@@ -0,0 +1,21 @@
+attribute vec4 aVertexPosition;
+attribute mat4 aInstanceMatrix;
+attribute float aInstanceIndex;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+// Id of instance 0; instance i gets uIdBase + i.
+uniform float uIdBase;
+
+varying vec3 vId;
+
+void main(void) {
+  gl_Position = uProjectionMatrix * uViewMatrix * aInstanceMatrix * aVertexPosition;
+
+  // Same packing as `idpick::encode_id`: id + 1, low byte in red.
+  highp float id = uIdBase + aInstanceIndex + 1.0;
+  highp float r = mod(id, 256.0);
+  highp float g = mod(floor(id / 256.0), 256.0);
+  highp float b = floor(id / 65536.0);
+  vId = vec3(r, g, b) / 255.0;
+}
@@ -0,0 +1,9 @@
+attribute vec4 aVertexPosition;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+void main(void) {
+  gl_Position = uProjectionMatrix * uViewMatrix * uModelMatrix * aVertexPosition;
+}
@@ -0,0 +1,198 @@
+//! Pixel exact picking through an id buffer.
+//!
+//! Every object, or every instance of an instanced mesh, is drawn with
+//! its id packed into an RGB colour into an offscreen target, then
+//! `read_pixels` reads back the ids under a point or across a
+//! rectangle. Nothing here runs unless asked, so callers render the
+//! pass only when the user actually clicks or drags.
+
+use std::collections::BTreeSet;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::gl::Gl;
+use renderer::instancing::{InstancedMesh, InstancedProgram, Instancing};
+use renderer::mesh::Mesh;
+use renderer::shader::build_program;
+use renderer::target::RenderTarget;
+use renderer::uniforms::Camera;
+
+pub static VERTEX_SHADER: &'static str = include_str!("id_v.glsl");
+pub static FRAGMENT_SHADER: &'static str = include_str!("id_f.glsl");
+pub static INSTANCED_VERTEX_SHADER: &'static str = include_str!("id_instanced_v.glsl");
+pub static INSTANCED_FRAGMENT_SHADER: &'static str = include_str!("id_instanced_f.glsl");
+
+/// Largest id that fits in 24 bits next to the reserved background.
+pub const MAX_ID: u32 = 0xff_ff_fe;
+
+/// The colour `id` is drawn with. Stored as `id + 1` so the cleared
+/// background, black, reads back as no object.
+pub fn encode_id(id: u32) -> [f32; 4] {
+  let value = id + 1;
+  [
+    (value & 0xff) as f32 / 255.0,
+    ((value >> 8) & 0xff) as f32 / 255.0,
+    ((value >> 16) & 0xff) as f32 / 255.0,
+    1.0,
+  ]
+}
+
+/// The id of one RGBA pixel, `None` for the background.
+pub fn decode_id(pixel: &[u8]) -> Option<u32> {
+  let value = pixel[0] as u32 | (pixel[1] as u32) << 8 | (pixel[2] as u32) << 16;
+  if value == 0 { None } else { Some(value - 1) }
+}
+
+pub struct IdPass {
+  target: RenderTarget,
+  program: WebGlProgram,
+  model_matrix: Option<WebGlUniformLocation>,
+  view_matrix: Option<WebGlUniformLocation>,
+  projection_matrix: Option<WebGlUniformLocation>,
+  id: Option<WebGlUniformLocation>,
+  instanced: InstancedProgram,
+  id_base: Option<WebGlUniformLocation>,
+}
+
+impl IdPass {
+  /// An id buffer of `width` x `height`, normally the canvas size so
+  /// canvas pixels map one to one.
+  pub fn new(context: &Gl, width: i32, height: i32) -> Result<IdPass, JsValue> {
+    let target = RenderTarget::new(context, width, height, WebGlRenderingContext::UNSIGNED_BYTE, true)?;
+    let program = build_program(context, VERTEX_SHADER, FRAGMENT_SHADER)?;
+    let instanced = InstancedProgram::with_shaders(
+        context,
+        INSTANCED_VERTEX_SHADER,
+        INSTANCED_FRAGMENT_SHADER,
+    )?;
+    Ok(IdPass {
+      target,
+      model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+      view_matrix: context.get_uniform_location(&program, "uViewMatrix"),
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      id: context.get_uniform_location(&program, "uId"),
+      program,
+      id_base: context.get_uniform_location(&instanced.program, "uIdBase"),
+      instanced,
+    })
+  }
+
+  pub fn resize(&mut self, context: &Gl, width: i32, height: i32) -> Result<(), JsValue> {
+    self.target.resize(context, width, height)
+  }
+
+  /// Bind and clear the id buffer; draw with `draw` and
+  /// `draw_instanced`, then `end`.
+  pub fn begin(&self, context: &Gl, camera: &Camera) {
+    self.target.bind(context);
+    context.clear_color(0.0, 0.0, 0.0, 0.0);
+    context.clear_depth(1.0);
+    context.enable(WebGlRenderingContext::DEPTH_TEST);
+    context.depth_func(WebGlRenderingContext::LEQUAL);
+    // Blending would mix neighbouring ids into made up ones.
+    context.disable(WebGlRenderingContext::BLEND);
+    context.clear(
+      WebGlRenderingContext::COLOR_BUFFER_BIT |
+      WebGlRenderingContext::DEPTH_BUFFER_BIT
+    );
+
+    let view: JsValue = JsValue::from_serde(&camera.view).unwrap().into();
+    let projection: JsValue = JsValue::from_serde(&camera.projection).unwrap().into();
+    context.use_program(Some(&self.instanced.program));
+    context.uniform_matrix4fv_with_f32_sequence(self.instanced.view_matrix.as_ref(), false, &view);
+    context.uniform_matrix4fv_with_f32_sequence(self.instanced.projection_matrix.as_ref(), false, &projection);
+    context.use_program(Some(&self.program));
+    context.uniform_matrix4fv_with_f32_sequence(self.view_matrix.as_ref(), false, &view);
+    context.uniform_matrix4fv_with_f32_sequence(self.projection_matrix.as_ref(), false, &projection);
+  }
+
+  /// Draw `mesh` as object `id`.
+  pub fn draw(&self, context: &Gl, mesh: &Mesh, model: &Mat4, id: u32) {
+    let color = encode_id(id.min(MAX_ID));
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.use_program(Some(&self.program));
+    context.uniform_matrix4fv_with_f32_sequence(self.model_matrix.as_ref(), false, &data);
+    context.uniform4f(self.id.as_ref(), color[0], color[1], color[2], color[3]);
+    mesh.draw(context);
+  }
+
+  /// Draw every instance of `mesh`, instance `i` as id `first_id + i`.
+  pub fn draw_instanced(
+    &self,
+    context: &Gl,
+    instancing: &Instancing,
+    mesh: &mut InstancedMesh,
+    first_id: u32,
+  ) -> Result<(), JsValue> {
+    if first_id as usize + mesh.len() > MAX_ID as usize + 1 {
+      return Err("too many ids for a 24 bit id buffer".into());
+    }
+    context.use_program(Some(&self.instanced.program));
+    context.uniform1f(self.id_base.as_ref(), first_id as f32);
+    mesh.draw(context, instancing, &self.instanced)
+  }
+
+  /// Go back to drawing into the canvas.
+  pub fn end(&self, context: &Gl, width: i32, height: i32) {
+    RenderTarget::unbind(context, width, height);
+  }
+
+  /// The id under canvas pixel `x, y`, top-left origin.
+  pub fn read_point(&self, context: &Gl, x: i32, y: i32) -> Result<Option<u32>, JsValue> {
+    Ok(self.read_rect(context, x, y, 1, 1)?.into_iter().next())
+  }
+
+  /// Every distinct id inside the rectangle with corner `x, y` (canvas
+  /// pixels, top-left origin) and size `width` x `height`, in order.
+  /// Negative sizes are allowed, as a marquee dragged up or left gives.
+  pub fn read_rect(
+    &self,
+    context: &Gl,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) -> Result<Vec<u32>, JsValue> {
+    let (left, right) = (x.min(x + width), x.max(x + width).max(x + 1));
+    let (top, bottom) = (y.min(y + height), y.max(y + height).max(y + 1));
+    let left = left.max(0).min(self.target.width);
+    let right = right.max(0).min(self.target.width);
+    let top = top.max(0).min(self.target.height);
+    let bottom = bottom.max(0).min(self.target.height);
+    if left == right || top == bottom {
+      return Ok(Vec::new());
+    }
+    let (width, height) = (right - left, bottom - top);
+
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.target.framebuffer));
+    let result = context.read_pixels_with_opt_u8_array(
+        left,
+        // GL rows start at the bottom.
+        self.target.height - bottom,
+        width,
+        height,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        Some(&mut pixels),
+    );
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+    result?;
+
+    let ids: BTreeSet<u32> = pixels.chunks(4).filter_map(decode_id).collect();
+    Ok(ids.into_iter().collect())
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    self.target.delete(context);
+    context.delete_program(Some(&self.program));
+    context.delete_program(Some(&self.instanced.program));
+  }
+}
@@ -0,0 +1,329 @@
+//! zlib and raw DEFLATE (RFC 1950 and 1951) decompression, enough for
+//! the `IDAT` stream of a PNG.
+
+/// Reads the stream least significant bit first, as DEFLATE packs it.
+struct Bits<'a> {
+  data: &'a [u8],
+  position: usize,
+  buffer: u32,
+  count: u32,
+}
+
+impl<'a> Bits<'a> {
+  fn new(data: &'a [u8]) -> Bits<'a> {
+    Bits { data, position: 0, buffer: 0, count: 0 }
+  }
+
+  fn bits(&mut self, count: u32) -> Result<u32, String> {
+    while self.count < count {
+      let byte = *self.data.get(self.position).ok_or("deflate stream ends early")?;
+      self.position += 1;
+      self.buffer |= (byte as u32) << self.count;
+      self.count += 8;
+    }
+    let value = self.buffer & ((1u64 << count) - 1) as u32;
+    self.buffer >>= count;
+    self.count -= count;
+    Ok(value)
+  }
+
+  /// Drop the rest of the current byte.
+  fn align(&mut self) {
+    self.buffer = 0;
+    self.count = 0;
+  }
+}
+
+/// A canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+  /// How many codes there are of each length.
+  counts: [u16; 16],
+  /// Symbols ordered by code.
+  symbols: Vec<u16>,
+}
+
+impl Huffman {
+  fn new(lengths: &[u8]) -> Result<Huffman, String> {
+    let mut counts = [0u16; 16];
+    for &length in lengths {
+      counts[length as usize] += 1;
+    }
+    counts[0] = 0;
+    // Reject over-subscribed codes; incomplete ones are allowed.
+    let mut left = 1i32;
+    for &count in &counts[1..] {
+      left = (left << 1) - count as i32;
+      if left < 0 {
+        return Err("bad Huffman code lengths".to_string());
+      }
+    }
+    let mut offsets = [0u16; 16];
+    for length in 1..15 {
+      offsets[length + 1] = offsets[length] + counts[length];
+    }
+    let mut symbols = vec![0u16; lengths.len()];
+    for (symbol, &length) in lengths.iter().enumerate() {
+      if length != 0 {
+        symbols[offsets[length as usize] as usize] = symbol as u16;
+        offsets[length as usize] += 1;
+      }
+    }
+    Ok(Huffman { counts, symbols })
+  }
+
+  fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+    let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+    for length in 1..16 {
+      code |= bits.bits(1)? as i32;
+      let count = self.counts[length] as i32;
+      if code - first < count {
+        return Ok(self.symbols[(index + code - first) as usize]);
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+    Err("bad Huffman code".to_string())
+  }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+  3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+  35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+  0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+  3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+  1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+  257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+  0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+  7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// The order code length code lengths are sent in.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompress a zlib stream, checking its header and checksum.
+pub fn zlib(data: &[u8]) -> Result<Vec<u8>, String> {
+  if data.len() < 6 {
+    return Err("zlib stream too short".to_string());
+  }
+  let (cmf, flags) = (data[0], data[1]);
+  if cmf & 0x0F != 8 || (cmf as u16 * 256 + flags as u16) % 31 != 0 {
+    return Err("not a zlib deflate stream".to_string());
+  }
+  if flags & 0x20 != 0 {
+    return Err("zlib preset dictionaries aren't supported".to_string());
+  }
+  let (out, used) = deflate(&data[2..])?;
+  let end = 2 + used;
+  let stored = data.get(end..end + 4).ok_or("zlib stream is missing its checksum")?;
+  let stored = (stored[0] as u32) << 24 | (stored[1] as u32) << 16 | (stored[2] as u32) << 8 | stored[3] as u32;
+  if adler32(&out) != stored {
+    return Err("zlib checksum mismatch".to_string());
+  }
+  Ok(out)
+}
+
+/// Decompress raw DEFLATE, returning the output and how many input
+/// bytes it took.
+pub fn deflate(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+  let mut bits = Bits::new(data);
+  let mut out = Vec::with_capacity(data.len() * 4);
+  loop {
+    let last = bits.bits(1)? == 1;
+    match bits.bits(2)? {
+      0 => {
+        bits.align();
+        let header = data.get(bits.position..bits.position + 4).ok_or("deflate stream ends early")?;
+        let length = header[0] as usize | (header[1] as usize) << 8;
+        let complement = header[2] as usize | (header[3] as usize) << 8;
+        if length != !complement & 0xFFFF {
+          return Err("bad stored block length".to_string());
+        }
+        let start = bits.position + 4;
+        out.extend_from_slice(data.get(start..start + length).ok_or("deflate stream ends early")?);
+        bits.position = start + length;
+      },
+      1 => {
+        let mut lengths = [8u8; 288];
+        for length in lengths[144..256].iter_mut() {
+          *length = 9;
+        }
+        for length in lengths[256..280].iter_mut() {
+          *length = 7;
+        }
+        let literals = Huffman::new(&lengths)?;
+        let distances = Huffman::new(&[5u8; 30])?;
+        block(&mut bits, &literals, &distances, &mut out)?;
+      },
+      2 => {
+        let (literals, distances) = dynamic_codes(&mut bits)?;
+        block(&mut bits, &literals, &distances, &mut out)?;
+      },
+      _ => return Err("bad deflate block type".to_string()),
+    }
+    if last {
+      return Ok((out, bits.position));
+    }
+  }
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+  let literal_count = bits.bits(5)? as usize + 257;
+  let distance_count = bits.bits(5)? as usize + 1;
+  let code_count = bits.bits(4)? as usize + 4;
+  let mut code_lengths = [0u8; 19];
+  for &symbol in &CODE_LENGTH_ORDER[..code_count] {
+    code_lengths[symbol] = bits.bits(3)? as u8;
+  }
+  let code = Huffman::new(&code_lengths)?;
+
+  let mut lengths = Vec::with_capacity(literal_count + distance_count);
+  while lengths.len() < literal_count + distance_count {
+    let symbol = code.decode(bits)?;
+    let (value, repeat) = match symbol {
+      0..=15 => (symbol as u8, 1),
+      16 => (*lengths.last().ok_or("length repeat with nothing to repeat")?, 3 + bits.bits(2)?),
+      17 => (0, 3 + bits.bits(3)?),
+      _ => (0, 11 + bits.bits(7)?),
+    };
+    for _ in 0..repeat {
+      lengths.push(value);
+    }
+  }
+  if lengths.len() > literal_count + distance_count {
+    return Err("code lengths overrun".to_string());
+  }
+  if lengths[256] == 0 {
+    return Err("no end of block code".to_string());
+  }
+  Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?))
+}
+
+fn block(bits: &mut Bits, literals: &Huffman, distances: &Huffman, out: &mut Vec<u8>) -> Result<(), String> {
+  loop {
+    let symbol = literals.decode(bits)? as usize;
+    if symbol < 256 {
+      out.push(symbol as u8);
+    } else if symbol == 256 {
+      return Ok(());
+    } else {
+      let index = symbol - 257;
+      if index >= LENGTH_BASE.len() {
+        return Err("bad length code".to_string());
+      }
+      let length = LENGTH_BASE[index] as usize + bits.bits(LENGTH_EXTRA[index] as u32)? as usize;
+      let index = distances.decode(bits)? as usize;
+      if index >= DISTANCE_BASE.len() {
+        return Err("bad distance code".to_string());
+      }
+      let distance = DISTANCE_BASE[index] as usize + bits.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+      if distance > out.len() {
+        return Err("distance reaches before the start".to_string());
+      }
+      // Byte by byte: the copy may overlap what it writes.
+      let start = out.len() - distance;
+      for i in 0..length {
+        let byte = out[start + i];
+        out.push(byte);
+      }
+    }
+  }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+  let (mut a, mut b) = (1u32, 0u32);
+  for chunk in data.chunks(5552) {
+    for &byte in chunk {
+      a += byte as u32;
+      b += a;
+    }
+    a %= 65521;
+    b %= 65521;
+  }
+  b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const STORED: [u8; 23] = [
+    0x78, 0x01, 0x01, 0x0c, 0x00, 0xf3, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20, 0x62, 0x6c,
+    0x6f, 0x63, 0x6b, 0x1f, 0x80, 0x04, 0xbd,
+  ];
+  const SENTENCE: &[u8] =
+      b"It was the best of times, it was the worst of times, it was the age of wisdom, it was the age of foolishness";
+  /// `SENTENCE` in one fixed Huffman block, with back references.
+  const FIXED: [u8; 66] = [
+    0x78, 0xda, 0xf3, 0x2c, 0x51, 0x28, 0x4f, 0x2c, 0x56, 0x28, 0xc9, 0x48, 0x55, 0x48, 0x4a, 0x2d,
+    0x2e, 0x51, 0xc8, 0x4f, 0x53, 0x28, 0xc9, 0xcc, 0x4d, 0x2d, 0xd6, 0x51, 0xc8, 0x44, 0xc8, 0x94,
+    0xe7, 0x17, 0xe1, 0x92, 0x4a, 0x4c, 0x4f, 0x05, 0x49, 0x94, 0x67, 0x16, 0xa7, 0xe4, 0xe7, 0x62,
+    0x93, 0x49, 0xcb, 0xcf, 0xcf, 0xc9, 0x2c, 0xce, 0xc8, 0x4b, 0x2d, 0x2e, 0x06, 0x00, 0xfe, 0x23,
+    0x26, 0x08,
+  ];
+  const RUNS: &[u8] = b"aaaaaaaaaaaaaaaaaaaabbbbbbbbbbcccccd";
+  /// Raw DEFLATE of `RUNS` in one dynamic Huffman block.
+  const DYNAMIC: [u8; 22] = [
+    0x05, 0xc1, 0x01, 0x01, 0x00, 0x00, 0x08, 0xc3, 0xa0, 0xac, 0xec, 0xf6, 0xcf, 0x20, 0x00, 0x00,
+    0x40, 0x55, 0x55, 0x6d, 0xdb, 0xee,
+  ];
+
+  #[test]
+  fn inflates_stored_blocks() {
+    assert_eq!(zlib(&STORED).unwrap(), b"stored block".to_vec());
+  }
+
+  #[test]
+  fn inflates_fixed_blocks() {
+    assert_eq!(zlib(&FIXED).unwrap(), SENTENCE.to_vec());
+  }
+
+  #[test]
+  fn inflates_dynamic_blocks() {
+    let mut data = DYNAMIC.to_vec();
+    data.push(0x01);
+    data.extend_from_slice(b"trailing");
+    let (out, used) = deflate(&data).unwrap();
+    assert_eq!(out, RUNS.to_vec());
+    assert_eq!(used, DYNAMIC.len() + 1);
+  }
+
+  #[test]
+  fn inflates_several_blocks() {
+    // A stored block that isn't the last, then the fixed one of `FIXED`.
+    let mut data = vec![0x00, 0x03, 0x00, 0xfc, 0xff, b'>', b'>', b' '];
+    data.extend_from_slice(&FIXED[2..FIXED.len() - 4]);
+    let (out, used) = deflate(&data).unwrap();
+    assert_eq!(&out[..3], b">> ");
+    assert_eq!(&out[3..], SENTENCE);
+    assert_eq!(used, data.len());
+  }
+
+  #[test]
+  fn rejects_truncated_and_corrupt_streams() {
+    for end in 0..FIXED.len() {
+      assert!(zlib(&FIXED[..end]).is_err(), "{} bytes", end);
+    }
+    for end in 0..DYNAMIC.len() {
+      assert!(deflate(&DYNAMIC[..end]).is_err(), "{} bytes", end);
+    }
+    let mut checksum = FIXED;
+    checksum[FIXED.len() - 1] ^= 1;
+    assert!(zlib(&checksum).is_err());
+    let mut header = STORED;
+    header[0] = 0x79;
+    assert!(zlib(&header).is_err());
+    let mut length = STORED;
+    length[5] ^= 1;
+    assert!(zlib(&length).is_err());
+    // Block type 3 is reserved.
+    assert!(deflate(&[0x07, 0x00]).is_err());
+    // A fixed block copying from before the start.
+    assert!(deflate(&[0x03, 0x02, 0x00]).is_err());
+  }
+}
@@ -0,0 +1,836 @@
+//! JPEG decoding to RGBA8: baseline and progressive Huffman-coded
+//! files with 8 bit samples, any chroma subsampling, restart markers,
+//! and greyscale, YCbCr, RGB and Adobe CMYK / YCCK colour.
+//!
+//! Every scan only fills in DCT coefficients; the inverse DCT and
+//! colour conversion run once all scans are read, which is what
+//! progressive files need and costs baseline ones nothing.
+
+use renderer::image::pipeline::Image;
+
+/// The natural (row major) index of each coefficient in zigzag order.
+const ZIGZAG: [usize; 64] = [
+  0, 1, 8, 16, 9, 2, 3, 10,
+  17, 24, 32, 25, 18, 11, 4, 5,
+  12, 19, 26, 33, 40, 48, 41, 34,
+  27, 20, 13, 6, 7, 14, 21, 28,
+  35, 42, 49, 56, 57, 50, 43, 36,
+  29, 22, 15, 23, 30, 37, 44, 51,
+  58, 59, 52, 45, 38, 31, 39, 46,
+  53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+#[derive(Clone)]
+struct Huffman {
+  /// The largest code of each length, or -1 when there are none.
+  max_code: [i32; 17],
+  /// What to subtract from a code of each length to index `values`.
+  offset: [i32; 17],
+  values: Vec<u8>,
+}
+
+impl Huffman {
+  fn new(counts: &[u8], values: &[u8]) -> Huffman {
+    let (mut max_code, mut offset) = ([-1i32; 17], [0i32; 17]);
+    let (mut code, mut index) = (0i32, 0i32);
+    for length in 1..17 {
+      let count = counts[length - 1] as i32;
+      if count > 0 {
+        offset[length] = code - index;
+        code += count;
+        index += count;
+        max_code[length] = code - 1;
+      }
+      code <<= 1;
+    }
+    Huffman { max_code, offset, values: values.to_vec() }
+  }
+
+  fn decode(&self, bits: &mut Entropy) -> Result<u8, String> {
+    let mut code = 0i32;
+    for length in 1..17 {
+      code = code << 1 | bits.bit() as i32;
+      if code <= self.max_code[length] {
+        return self.values.get((code - self.offset[length]) as usize)
+            .cloned()
+            .ok_or_else(|| "bad JPEG Huffman code".to_string());
+      }
+    }
+    Err("bad JPEG Huffman code".to_string())
+  }
+}
+
+/// The entropy coded data after a scan header, with stuffed zero bytes
+/// removed. Reaching a marker or the end reads zeros and sets
+/// `overrun`, until `restart` moves past the marker.
+struct Entropy<'a> {
+  data: &'a [u8],
+  position: usize,
+  byte: u32,
+  count: u32,
+  overrun: bool,
+}
+
+impl<'a> Entropy<'a> {
+  fn bit(&mut self) -> u32 {
+    if self.count == 0 {
+      self.byte = match (self.data.get(self.position), self.data.get(self.position + 1)) {
+        (Some(&0xFF), Some(&0)) => {
+          self.position += 2;
+          0xFF
+        },
+        (Some(&0xFF), _) | (None, _) => {
+          self.overrun = true;
+          0
+        },
+        (Some(&byte), _) => {
+          self.position += 1;
+          byte as u32
+        },
+      };
+      self.count = 8;
+    }
+    self.count -= 1;
+    (self.byte >> self.count) & 1
+  }
+
+  fn bits(&mut self, count: u32) -> i32 {
+    let mut value = 0;
+    for _ in 0..count {
+      value = value << 1 | self.bit() as i32;
+    }
+    value
+  }
+
+  /// `count` bits read as a signed coefficient or difference.
+  fn signed(&mut self, count: u32) -> i32 {
+    if count == 0 {
+      return 0;
+    }
+    let value = self.bits(count);
+    if value < 1 << (count - 1) {
+      value - (1 << count) + 1
+    } else {
+      value
+    }
+  }
+
+  /// Skip the rest of the byte and the restart marker after it.
+  fn restart(&mut self) {
+    self.count = 0;
+    while self.position + 1 < self.data.len() {
+      let (first, second) = (self.data[self.position], self.data[self.position + 1]);
+      self.position += 1;
+      if first == 0xFF && second >= 0xD0 && second <= 0xD7 {
+        self.position += 1;
+        return;
+      }
+    }
+  }
+}
+
+struct Component {
+  id: u8,
+  h: usize,
+  v: usize,
+  quantization: usize,
+  dc_table: usize,
+  ac_table: usize,
+  /// Blocks across and down, padded to whole MCUs.
+  blocks_wide: usize,
+  blocks_high: usize,
+  coefficients: Vec<i32>,
+  predictor: i32,
+}
+
+struct Frame {
+  width: usize,
+  height: usize,
+  progressive: bool,
+  components: Vec<Component>,
+  h_max: usize,
+  v_max: usize,
+  mcus_wide: usize,
+  mcus_high: usize,
+}
+
+/// One scan's spectral selection `start..=end` and successive
+/// approximation bits `high` (previous) and `low` (this scan).
+struct Scan {
+  components: Vec<usize>,
+  start: usize,
+  end: usize,
+  high: u32,
+  low: u32,
+}
+
+fn be16(bytes: &[u8], at: usize) -> Result<usize, String> {
+  match (bytes.get(at), bytes.get(at + 1)) {
+    (Some(&high), Some(&low)) => Ok((high as usize) << 8 | low as usize),
+    _ => Err("JPEG ends inside a segment".to_string()),
+  }
+}
+
+/// Decode a JPEG file.
+pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+  if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] != 0xD8 {
+    return Err("not a JPEG file".to_string());
+  }
+  let mut quantization = [[0u16; 64]; 4];
+  let mut dc_tables: Vec<Option<Huffman>> = vec![None; 4];
+  let mut ac_tables: Vec<Option<Huffman>> = vec![None; 4];
+  let mut frame: Option<Frame> = None;
+  let mut restart_interval = 0;
+  let mut adobe_transform = None;
+  let mut ended = false;
+  let mut scanned = false;
+  let mut position = 2;
+
+  while position + 1 < bytes.len() {
+    // Skip anything that isn't a marker, including fill bytes.
+    let marker = bytes[position + 1];
+    if bytes[position] != 0xFF || marker == 0xFF || marker == 0 || (marker >= 0xD0 && marker <= 0xD7) {
+      position += 1;
+      continue;
+    }
+    if marker == 0xD9 {
+      ended = true;
+      break;
+    }
+    if marker == 0xD8 || marker == 0x01 {
+      position += 2;
+      continue;
+    }
+    let length = be16(bytes, position + 2)?;
+    let segment = bytes.get(position + 4..position + 2 + length).ok_or("JPEG segment runs past the end")?;
+    position += 2 + length;
+
+    match marker {
+      0xDB => {
+        let mut at = 0;
+        while at < segment.len() {
+          let (precision, table) = (segment[at] >> 4, (segment[at] & 15) as usize);
+          let size = if precision == 0 { 64 } else { 128 };
+          let values = segment.get(at + 1..at + 1 + size).ok_or("short JPEG quantization table")?;
+          if table > 3 {
+            return Err("bad JPEG quantization table id".to_string());
+          }
+          for k in 0..64 {
+            quantization[table][ZIGZAG[k]] = if precision == 0 {
+              values[k] as u16
+            } else {
+              (values[2 * k] as u16) << 8 | values[2 * k + 1] as u16
+            };
+          }
+          at += 1 + size;
+        }
+      },
+      0xC4 => {
+        let mut at = 0;
+        while at < segment.len() {
+          let (class, table) = (segment[at] >> 4, (segment[at] & 15) as usize);
+          let counts = segment.get(at + 1..at + 17).ok_or("short JPEG Huffman table")?;
+          let total: usize = counts.iter().map(|&count| count as usize).sum();
+          let values = segment.get(at + 17..at + 17 + total).ok_or("short JPEG Huffman table")?;
+          if table > 3 || class > 1 {
+            return Err("bad JPEG Huffman table id".to_string());
+          }
+          let tables = if class == 0 { &mut dc_tables } else { &mut ac_tables };
+          tables[table] = Some(Huffman::new(counts, values));
+          at += 17 + total;
+        }
+      },
+      0xC0 | 0xC1 | 0xC2 => frame = Some(read_frame(segment, marker == 0xC2)?),
+      0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+        return Err("lossless, hierarchical and arithmetic coded JPEGs aren't supported".to_string());
+      },
+      0xDD => restart_interval = be16(segment, 0)?,
+      0xEE => {
+        if segment.len() >= 12 && &segment[..5] == b"Adobe" {
+          adobe_transform = Some(segment[11]);
+        }
+      },
+      0xDA => {
+        let frame = frame.as_mut().ok_or("JPEG scan before its frame header")?;
+        let scan = read_scan(segment, frame)?;
+        for &index in &scan.components {
+          let component = &frame.components[index];
+          let needs_dc = scan.start == 0 && scan.high == 0;
+          let needs_ac = scan.start > 0 || !frame.progressive;
+          if (needs_dc && dc_tables[component.dc_table].is_none())
+              || (needs_ac && ac_tables[component.ac_table].is_none()) {
+            return Err("JPEG scan uses an undefined Huffman table".to_string());
+          }
+        }
+        position = decode_scan(bytes, position, frame, &scan, &dc_tables, &ac_tables, restart_interval)?;
+        scanned = true;
+      },
+      _ => (),
+    }
+  }
+
+  if !ended {
+    return Err("JPEG ends before its end of image marker".to_string());
+  }
+  let frame = frame.ok_or("JPEG has no frame header")?;
+  if !scanned {
+    return Err("JPEG has no scans".to_string());
+  }
+  let planes: Vec<Vec<u8>> = frame.components.iter()
+      .map(|component| idct_component(component, &quantization[component.quantization]))
+      .collect();
+  Ok(Image {
+    width: frame.width as u32,
+    height: frame.height as u32,
+    pixels: convert(&frame, &planes, adobe_transform),
+    gamma: None,
+  })
+}
+
+fn read_frame(segment: &[u8], progressive: bool) -> Result<Frame, String> {
+  if segment.len() < 6 || segment[0] != 8 {
+    return Err("only 8 bit JPEGs are supported".to_string());
+  }
+  let height = be16(segment, 1)?;
+  let width = be16(segment, 3)?;
+  if width == 0 || height == 0 {
+    return Err("JPEG has no pixels, or its height comes later in a DNL marker".to_string());
+  }
+  let count = segment[5] as usize;
+  if count != 1 && count != 3 && count != 4 {
+    return Err(format!("JPEGs with {} components aren't supported", count));
+  }
+  let mut components = Vec::with_capacity(count);
+  for i in 0..count {
+    let spec = segment.get(6 + 3 * i..9 + 3 * i).ok_or("short JPEG frame header")?;
+    let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 15) as usize);
+    if h == 0 || h > 4 || v == 0 || v > 4 || spec[2] > 3 {
+      return Err("bad JPEG component sampling or table".to_string());
+    }
+    components.push(Component {
+      id: spec[0],
+      h,
+      v,
+      quantization: spec[2] as usize,
+      dc_table: 0,
+      ac_table: 0,
+      blocks_wide: 0,
+      blocks_high: 0,
+      coefficients: Vec::new(),
+      predictor: 0,
+    });
+  }
+  let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+  let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+  let mcus_wide = (width + 8 * h_max - 1) / (8 * h_max);
+  let mcus_high = (height + 8 * v_max - 1) / (8 * v_max);
+  for component in &mut components {
+    component.blocks_wide = mcus_wide * component.h;
+    component.blocks_high = mcus_high * component.v;
+    component.coefficients = vec![0; component.blocks_wide * component.blocks_high * 64];
+  }
+  Ok(Frame { width, height, progressive, components, h_max, v_max, mcus_wide, mcus_high })
+}
+
+fn read_scan(segment: &[u8], frame: &mut Frame) -> Result<Scan, String> {
+  let count = *segment.first().ok_or("short JPEG scan header")? as usize;
+  let mut components = Vec::with_capacity(count);
+  for i in 0..count {
+    let spec = segment.get(1 + 2 * i..3 + 2 * i).ok_or("short JPEG scan header")?;
+    let index = frame.components.iter().position(|c| c.id == spec[0]).ok_or("JPEG scan names an unknown component")?;
+    let component = &mut frame.components[index];
+    component.dc_table = (spec[1] >> 4) as usize & 3;
+    component.ac_table = (spec[1] & 15) as usize & 3;
+    components.push(index);
+  }
+  let tail = segment.get(1 + 2 * count..4 + 2 * count).ok_or("short JPEG scan header")?;
+  let scan = Scan {
+    components,
+    start: tail[0] as usize,
+    end: tail[1] as usize,
+    high: (tail[2] >> 4) as u32,
+    low: (tail[2] & 15) as u32,
+  };
+  if !frame.progressive && (scan.start != 0 || scan.end != 63 || scan.high != 0 || scan.low != 0) {
+    return Err("bad baseline JPEG scan".to_string());
+  }
+  if scan.end > 63 || scan.start > scan.end || (scan.start == 0 && scan.end != 0 && frame.progressive) {
+    return Err("bad progressive JPEG scan".to_string());
+  }
+  if scan.start > 0 && scan.components.len() != 1 {
+    return Err("progressive AC scans must have one component".to_string());
+  }
+  Ok(scan)
+}
+
+/// Read one scan's entropy coded data starting at `position`, returning
+/// where it stopped. Data that doesn't decode, or runs into a marker
+/// before the last block, is an error.
+fn decode_scan(
+  bytes: &[u8],
+  position: usize,
+  frame: &mut Frame,
+  scan: &Scan,
+  dc_tables: &[Option<Huffman>],
+  ac_tables: &[Option<Huffman>],
+  restart_interval: usize,
+) -> Result<usize, String> {
+  let mut bits = Entropy { data: bytes, position, byte: 0, count: 0, overrun: false };
+  let mut end_of_band_run = 0;
+  for &index in &scan.components {
+    frame.components[index].predictor = 0;
+  }
+
+  // A lone component is coded in its own block order, not by MCU.
+  let (units_wide, units_high) = if scan.components.len() == 1 {
+    let component = &frame.components[scan.components[0]];
+    let width = (frame.width * component.h + frame.h_max - 1) / frame.h_max;
+    let height = (frame.height * component.v + frame.v_max - 1) / frame.v_max;
+    ((width + 7) / 8, (height + 7) / 8)
+  } else {
+    (frame.mcus_wide, frame.mcus_high)
+  };
+
+  let units = units_wide * units_high;
+  for unit in 0..units {
+    if restart_interval > 0 && unit > 0 && unit % restart_interval == 0 {
+      bits.restart();
+      end_of_band_run = 0;
+      for &index in &scan.components {
+        frame.components[index].predictor = 0;
+      }
+    }
+    let (unit_x, unit_y) = (unit % units_wide, unit / units_wide);
+    for &index in &scan.components {
+      let component = &mut frame.components[index];
+      let blocks: Vec<(usize, usize)> = if scan.components.len() == 1 {
+        vec![(unit_x, unit_y)]
+      } else {
+        let mut blocks = Vec::with_capacity(component.h * component.v);
+        for v in 0..component.v {
+          for h in 0..component.h {
+            blocks.push((unit_x * component.h + h, unit_y * component.v + v));
+          }
+        }
+        blocks
+      };
+      for (x, y) in blocks {
+        let at = (y * component.blocks_wide + x) * 64;
+        let dc = dc_tables[component.dc_table].as_ref();
+        let ac = ac_tables[component.ac_table].as_ref();
+        let (predictor, block) = (&mut component.predictor, &mut component.coefficients[at..at + 64]);
+        if !frame.progressive {
+          decode_baseline(&mut bits, dc, ac, predictor, block)?;
+        } else if scan.start == 0 {
+          decode_dc(&mut bits, dc, scan, predictor, block)?;
+        } else if scan.high == 0 {
+          decode_ac_first(&mut bits, ac, scan, &mut end_of_band_run, block)?;
+        } else {
+          decode_ac_refine(&mut bits, ac, scan, &mut end_of_band_run, block)?;
+        }
+        if bits.overrun {
+          return Err("JPEG scan data ends early".to_string());
+        }
+      }
+    }
+  }
+  Ok(bits.position)
+}
+
+fn table(table: Option<&Huffman>) -> Result<&Huffman, String> {
+  table.ok_or_else(|| "JPEG scan uses an undefined Huffman table".to_string())
+}
+
+/// Magnitude categories past these would not fit eight bit samples,
+/// and would overflow `Entropy::signed`.
+const MAX_DC_CATEGORY: u32 = 11;
+const MAX_AC_CATEGORY: u32 = 10;
+
+fn dc_category(symbol: u8) -> Result<u32, String> {
+  if symbol as u32 > MAX_DC_CATEGORY {
+    return Err(format!("JPEG DC magnitude category {} is invalid", symbol));
+  }
+  Ok(symbol as u32)
+}
+
+fn ac_category(size: u32) -> Result<u32, String> {
+  if size > MAX_AC_CATEGORY {
+    return Err(format!("JPEG AC magnitude category {} is invalid", size));
+  }
+  Ok(size)
+}
+
+fn decode_baseline(
+  bits: &mut Entropy,
+  dc: Option<&Huffman>,
+  ac: Option<&Huffman>,
+  predictor: &mut i32,
+  block: &mut [i32],
+) -> Result<(), String> {
+  let size = dc_category(table(dc)?.decode(bits)?)?;
+  *predictor = predictor.wrapping_add(bits.signed(size));
+  block[0] = *predictor;
+  let ac = table(ac)?;
+  let mut k = 1;
+  while k < 64 {
+    let symbol = ac.decode(bits)?;
+    let (run, size) = ((symbol >> 4) as usize, (symbol & 15) as u32);
+    if size == 0 {
+      if run != 15 {
+        break;
+      }
+      k += 16;
+      continue;
+    }
+    k += run;
+    if k > 63 {
+      return Err("JPEG coefficient past the end of a block".to_string());
+    }
+    block[ZIGZAG[k]] = bits.signed(ac_category(size)?);
+    k += 1;
+  }
+  Ok(())
+}
+
+fn decode_dc(
+  bits: &mut Entropy,
+  dc: Option<&Huffman>,
+  scan: &Scan,
+  predictor: &mut i32,
+  block: &mut [i32],
+) -> Result<(), String> {
+  if scan.high == 0 {
+    let size = dc_category(table(dc)?.decode(bits)?)?;
+    *predictor = predictor.wrapping_add(bits.signed(size));
+    block[0] = *predictor << scan.low;
+  } else if bits.bit() == 1 {
+    block[0] |= 1 << scan.low;
+  }
+  Ok(())
+}
+
+fn decode_ac_first(
+  bits: &mut Entropy,
+  ac: Option<&Huffman>,
+  scan: &Scan,
+  end_of_band_run: &mut u32,
+  block: &mut [i32],
+) -> Result<(), String> {
+  if *end_of_band_run > 0 {
+    *end_of_band_run -= 1;
+    return Ok(());
+  }
+  let ac = table(ac)?;
+  let mut k = scan.start;
+  while k <= scan.end {
+    let symbol = ac.decode(bits)?;
+    let (run, size) = ((symbol >> 4) as u32, (symbol & 15) as u32);
+    if size == 0 {
+      if run < 15 {
+        // This block and `run` more end here.
+        *end_of_band_run = (1 << run) - 1 + bits.bits(run) as u32;
+        break;
+      }
+      k += 16;
+      continue;
+    }
+    k += run as usize;
+    if k > 63 {
+      return Err("JPEG coefficient past the end of a block".to_string());
+    }
+    block[ZIGZAG[k]] = bits.signed(ac_category(size)?) * (1 << scan.low);
+    k += 1;
+  }
+  Ok(())
+}
+
+/// Add one more bit of precision to the coefficients already non-zero,
+/// and place the ones that become non-zero at this precision, following
+/// the structure of libjpeg's `decode_mcu_AC_refine`.
+fn decode_ac_refine(
+  bits: &mut Entropy,
+  ac: Option<&Huffman>,
+  scan: &Scan,
+  end_of_band_run: &mut u32,
+  block: &mut [i32],
+) -> Result<(), String> {
+  let (plus, minus) = (1 << scan.low, -1 << scan.low);
+  let refine = |bits: &mut Entropy, coefficient: &mut i32| {
+    if bits.bit() == 1 && *coefficient & plus == 0 {
+      *coefficient += if *coefficient >= 0 { plus } else { minus };
+    }
+  };
+  let mut k = scan.start;
+  if *end_of_band_run == 0 {
+    let ac = table(ac)?;
+    while k <= scan.end {
+      let symbol = ac.decode(bits)?;
+      let (mut run, size) = ((symbol >> 4) as i32, (symbol & 15) as u32);
+      let mut value = 0;
+      if size != 0 {
+        value = if bits.bit() == 1 { plus } else { minus };
+      } else if run != 15 {
+        *end_of_band_run = (1 << run) + bits.bits(run as u32) as u32;
+        break;
+      }
+      // Skip `run` zero coefficients, refining non-zero ones on the way.
+      while k <= scan.end {
+        let coefficient = &mut block[ZIGZAG[k]];
+        if *coefficient != 0 {
+          refine(bits, coefficient);
+        } else {
+          if run == 0 {
+            break;
+          }
+          run -= 1;
+        }
+        k += 1;
+      }
+      if value != 0 && k <= scan.end {
+        block[ZIGZAG[k]] = value;
+      }
+      k += 1;
+    }
+  }
+  if *end_of_band_run > 0 {
+    while k <= scan.end {
+      let coefficient = &mut block[ZIGZAG[k]];
+      if *coefficient != 0 {
+        refine(bits, coefficient);
+      }
+      k += 1;
+    }
+    *end_of_band_run -= 1;
+  }
+  Ok(())
+}
+
+/// Dequantize and inverse transform every block of `component` into a
+/// plane of samples `blocks_wide * 8` wide.
+fn idct_component(component: &Component, quantization: &[u16; 64]) -> Vec<u8> {
+  // basis[x][u] = C(u) / 2 * cos((2x + 1) u pi / 16)
+  let mut basis = [[0f32; 8]; 8];
+  for x in 0..8 {
+    for u in 0..8 {
+      let scale = if u == 0 { 0.5f32.sqrt() } else { 1.0 };
+      basis[x][u] = scale / 2.0 * ((2 * x + 1) as f32 * u as f32 * ::std::f32::consts::PI / 16.0).cos();
+    }
+  }
+  let stride = component.blocks_wide * 8;
+  let mut plane = vec![0u8; stride * component.blocks_high * 8];
+  let mut rows = [0f32; 64];
+  for (index, block) in component.coefficients.chunks(64).enumerate() {
+    let (block_x, block_y) = (index % component.blocks_wide, index / component.blocks_wide);
+    // Rows first: rows[v][x] = sum over u of F[v][u] basis[x][u].
+    for v in 0..8 {
+      for x in 0..8 {
+        let mut sum = 0.0;
+        for u in 0..8 {
+          sum += (block[v * 8 + u] * quantization[v * 8 + u] as i32) as f32 * basis[x][u];
+        }
+        rows[v * 8 + x] = sum;
+      }
+    }
+    for y in 0..8 {
+      for x in 0..8 {
+        let mut sum = 128.0;
+        for v in 0..8 {
+          sum += rows[v * 8 + x] * basis[y][v];
+        }
+        let at = (block_y * 8 + y) * stride + block_x * 8 + x;
+        plane[at] = sum.round().max(0.0).min(255.0) as u8;
+      }
+    }
+  }
+  plane
+}
+
+fn clamp(value: f32) -> u8 {
+  value.round().max(0.0).min(255.0) as u8
+}
+
+fn ycc_to_rgb(y: f32, cb: f32, cr: f32) -> [u8; 3] {
+  let (cb, cr) = (cb - 128.0, cr - 128.0);
+  [clamp(y + 1.402 * cr), clamp(y - 0.344_136 * cb - 0.714_136 * cr), clamp(y + 1.772 * cb)]
+}
+
+/// Resample `component`'s plane to one sample per pixel, interpolating
+/// linearly between subsampled ones like libjpeg's fancy upsampling.
+fn upsample(frame: &Frame, component: &Component, plane: &[u8]) -> Vec<u8> {
+  let stride = component.blocks_wide * 8;
+  let mut out = Vec::with_capacity(frame.width * frame.height);
+  if component.h == frame.h_max && component.v == frame.v_max {
+    for y in 0..frame.height {
+      out.extend_from_slice(&plane[y * stride..y * stride + frame.width]);
+    }
+    return out;
+  }
+  // The last samples that cover part of the image.
+  let last_x = (frame.width * component.h + frame.h_max - 1) / frame.h_max - 1;
+  let last_y = (frame.height * component.v + frame.v_max - 1) / frame.v_max - 1;
+  let (scale_x, scale_y) = (component.h as f32 / frame.h_max as f32, component.v as f32 / frame.v_max as f32);
+  let taps = |i: usize, scale: f32, last: usize| {
+    let position = ((i as f32 + 0.5) * scale - 0.5).max(0.0);
+    let low = (position as usize).min(last);
+    (low, (low + 1).min(last), position - low as f32)
+  };
+  for y in 0..frame.height {
+    let (top, bottom, fy) = taps(y, scale_y, last_y);
+    for x in 0..frame.width {
+      let (left, right, fx) = taps(x, scale_x, last_x);
+      let sample = |row: usize, column: usize| plane[row * stride + column] as f32;
+      let upper = sample(top, left) * (1.0 - fx) + sample(top, right) * fx;
+      let lower = sample(bottom, left) * (1.0 - fx) + sample(bottom, right) * fx;
+      out.push((upper * (1.0 - fy) + lower * fy).round() as u8);
+    }
+  }
+  out
+}
+
+/// Upsample each plane to the image size and convert to RGBA.
+fn convert(frame: &Frame, planes: &[Vec<u8>], adobe_transform: Option<u8>) -> Vec<u8> {
+  let count = frame.components.len();
+  // Without an Adobe marker, three components are RGB only when their
+  // ids spell it out.
+  let ids: Vec<u8> = frame.components.iter().map(|c| c.id).collect();
+  let rgb = count == 3 && (adobe_transform == Some(0) || (adobe_transform.is_none() && ids[..] == b"RGB"[..]));
+  let planes: Vec<Vec<u8>> = frame.components.iter().zip(planes)
+      .map(|(component, plane)| upsample(frame, component, plane))
+      .collect();
+  let mut out = Vec::with_capacity(frame.width * frame.height * 4);
+  let mut samples = [0f32; 4];
+  for pixel in 0..frame.width * frame.height {
+    for (sample, plane) in samples.iter_mut().zip(&planes) {
+      *sample = plane[pixel] as f32;
+    }
+    let color = match count {
+      1 => [samples[0] as u8; 3],
+      3 if rgb => [samples[0] as u8, samples[1] as u8, samples[2] as u8],
+      3 => ycc_to_rgb(samples[0], samples[1], samples[2]),
+      _ => {
+        // Adobe stores CMYK inverted, so these are really 255 - C etc.
+        let cmy = if adobe_transform == Some(2) {
+          ycc_to_rgb(samples[0], samples[1], samples[2])
+        } else {
+          [samples[0] as u8, samples[1] as u8, samples[2] as u8]
+        };
+        let k = samples[3];
+        [
+          clamp(cmy[0] as f32 * k / 255.0),
+          clamp(cmy[1] as f32 * k / 255.0),
+          clamp(cmy[2] as f32 * k / 255.0),
+        ]
+      },
+    };
+    out.extend_from_slice(&[color[0], color[1], color[2], 255]);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Baseline, 4:2:0 chroma.
+  const PY: &[u8] = include_bytes!("testdata/py.jpg");
+  /// Progressive, 4:2:2 chroma.
+  const F3: &[u8] = include_bytes!("testdata/f3.jpg");
+
+  /// Colour within rounding of what libjpeg-style decoders give.
+  fn assert_near(image: &Image, x: usize, y: usize, expected: [u8; 3]) {
+    let i = (y * image.width as usize + x) * 4;
+    let pixel = &image.pixels[i..i + 4];
+    for channel in 0..3 {
+      assert!(
+        (pixel[channel] as i32 - expected[channel] as i32).abs() <= 2,
+        "({}, {}) is {:?}, expected {:?}", x, y, pixel, expected,
+      );
+    }
+    assert_eq!(pixel[3], 255);
+  }
+
+  #[test]
+  fn decodes_baseline() {
+    let image = decode(PY).unwrap();
+    assert_eq!((image.width, image.height, image.gamma), (16, 16, None));
+    assert_eq!(image.pixels.len(), 16 * 16 * 4);
+    assert_near(&image, 0, 0, [0, 2, 8]);
+    assert_near(&image, 5, 7, [28, 50, 63]);
+    assert_near(&image, 8, 5, [56, 110, 148]);
+    assert_near(&image, 5, 8, [226, 221, 165]);
+    assert_near(&image, 15, 15, [0, 1, 6]);
+  }
+
+  #[test]
+  fn decodes_progressive() {
+    let image = decode(F3).unwrap();
+    assert_eq!((image.width, image.height), (720, 477));
+    assert_near(&image, 0, 0, [235, 240, 234]);
+    assert_near(&image, 5, 7, [229, 231, 230]);
+    assert_near(&image, 360, 159, [121, 163, 153]);
+    assert_near(&image, 240, 238, [45, 49, 35]);
+    assert_near(&image, 719, 476, [224, 223, 229]);
+  }
+
+  #[test]
+  fn rejects_truncated_files() {
+    for file in &[PY, F3] {
+      for &end in &[0, 2, 3, 20, 200, file.len() / 2, file.len() - 2] {
+        assert!(decode(&file[..end]).is_err(), "{} of {} bytes", end, file.len());
+      }
+    }
+  }
+
+  #[test]
+  fn rejects_corrupt_files() {
+    let mut magic = PY.to_vec();
+    magic[1] = 0xD9;
+    assert!(decode(&magic).is_err());
+    // The frame header's precision.
+    let frame = PY.windows(2).position(|pair| pair == [0xFF, 0xC0]).unwrap();
+    let mut precision = PY.to_vec();
+    precision[frame + 4] = 12;
+    assert!(decode(&precision).is_err());
+    // A marker in the middle of the entropy coded data.
+    let scan = PY.windows(2).position(|pair| pair == [0xFF, 0xDA]).unwrap();
+    let mut marker = PY.to_vec();
+    let middle = scan + (PY.len() - scan) / 2;
+    marker[middle] = 0xFF;
+    marker[middle + 1] = 0xD9;
+    assert!(decode(&marker).is_err());
+  }
+
+  /// `PY` with every symbol of its DC (class 0) or AC (class 1) Huffman
+  /// tables replaced by `symbol`.
+  fn with_huffman_symbols(class: u8, symbol: u8) -> Vec<u8> {
+    let mut file = PY.to_vec();
+    let mut at = 2;
+    while file[at + 1] != 0xDA {
+      let length = (file[at + 2] as usize) << 8 | file[at + 3] as usize;
+      if file[at + 1] == 0xC4 {
+        let mut table = at + 4;
+        while table < at + 2 + length {
+          let total: usize = file[table + 1..table + 17].iter().map(|&count| count as usize).sum();
+          if file[table] >> 4 == class {
+            for value in &mut file[table + 17..table + 17 + total] {
+              *value = symbol;
+            }
+          }
+          table += 17 + total;
+        }
+      }
+      at += 2 + length;
+    }
+    file
+  }
+
+  #[test]
+  fn rejects_corrupt_huffman_tables() {
+    for &symbol in &[12, 16, 200, 255] {
+      assert!(decode(&with_huffman_symbols(0, symbol)).is_err(), "DC category {}", symbol);
+    }
+    for &symbol in &[0x0B, 0x0F, 0x1F, 0xFF] {
+      assert!(decode(&with_huffman_symbols(1, symbol)).is_err(), "AC symbol {:#x}", symbol);
+    }
+  }
+}
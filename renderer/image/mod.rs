@@ -0,0 +1,95 @@
+//! Textures from PNG and JPEG bytes decoded in Rust instead of by an
+//! `Image` element, so the pixels can be processed and checked.
+//!
+//! `png`, `jpeg` and `pipeline` don't touch the DOM or WebGL: they run
+//! natively and in workers, where `image_decode` exposes them to JS.
+//! `ImageTexture` uploads the prepared levels with `tex_image_2d`.
+
+pub mod inflate;
+pub mod png;
+pub mod jpeg;
+pub mod pipeline;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlRenderingContext, WebGlTexture};
+
+use renderer::gl::{Backend, Gl};
+use renderer::image::pipeline::{DecodeOptions, Image};
+use renderer::texture::set_mip_sampling;
+use renderer::u8_view;
+
+pub struct ImageTexture {
+  pub texture: WebGlTexture,
+  pub width: u32,
+  pub height: u32,
+  pub levels: usize,
+}
+
+impl ImageTexture {
+  /// Decode a PNG or JPEG file and upload it. On WebGL1, asking for
+  /// mipmaps also turns on `power_of_two`, since it can't sample the
+  /// mips of other sizes.
+  pub fn from_bytes(context: &Gl, bytes: &[u8], options: &DecodeOptions) -> Result<ImageTexture, JsValue> {
+    let mut options = *options;
+    if options.mipmaps && context.backend() == Backend::WebGl1 {
+      options.power_of_two = true;
+    }
+    ImageTexture::new(context, &pipeline::load(bytes, &options)?)
+  }
+
+  /// Upload `levels` as prepared by `pipeline::prepare`, sampled as
+  /// `set_mip_sampling` sets up.
+  pub fn new(context: &Gl, levels: &[Image]) -> Result<ImageTexture, JsValue> {
+    let first = levels.first().ok_or("no image levels to upload")?;
+    let texture = context.create_texture().ok_or("failed to create texture")?;
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+    for (level, image) in levels.iter().enumerate() {
+      let view = u8_view(&image.pixels)?;
+      context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+          WebGlRenderingContext::TEXTURE_2D,
+          level as i32,
+          WebGlRenderingContext::RGBA as i32,
+          image.width as i32,
+          image.height as i32,
+          0,
+          WebGlRenderingContext::RGBA,
+          WebGlRenderingContext::UNSIGNED_BYTE,
+          Some(&view),
+      )?;
+    }
+    set_mip_sampling(context, first.width, first.height, levels.len());
+    Ok(ImageTexture { texture, width: first.width, height: first.height, levels: levels.len() })
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.texture));
+  }
+}
+
+/// Decode a PNG or JPEG file and prepare its levels without WebGL, for
+/// use from a worker. `options` is a `DecodeOptions` with camelCase
+/// names, any of which may be left out, or `undefined`. Returns
+/// `[{ width, height, pixels }]`, largest level first, with the RGBA
+/// `pixels` in a `Uint8Array` of their own that can be transferred back
+/// to the main thread for `texImage2D`.
+#[wasm_bindgen]
+pub fn image_decode(bytes: &[u8], options: JsValue) -> Result<JsValue, JsValue> {
+  let options: DecodeOptions = if options.is_undefined() || options.is_null() {
+    DecodeOptions::default()
+  } else {
+    options
+        .into_serde()
+        .map_err(|e| JsValue::from(format!("bad decode options: {}", e)))?
+  };
+  let levels = js_sys::Array::new();
+  for image in pipeline::load(bytes, &options)? {
+    let level = js_sys::Object::new();
+    js_sys::Reflect::set(&level, &"width".into(), &image.width.into())?;
+    js_sys::Reflect::set(&level, &"height".into(), &image.height.into())?;
+    // Copied out of wasm memory, which the next allocation may move.
+    let pixels = u8_view(&image.pixels)?.slice(0, image.pixels.len() as u32);
+    js_sys::Reflect::set(&level, &"pixels".into(), &pixels)?;
+    levels.push(&level);
+  }
+  Ok(levels.into())
+}
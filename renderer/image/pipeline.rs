@@ -0,0 +1,434 @@
+//! Everything between image file bytes and texture levels, without
+//! WebGL, so it runs the same natively, on the main thread and in a
+//! worker.
+//!
+//! `load` decodes a PNG or JPEG and `prepare` turns the RGBA pixels into
+//! the levels to upload: flipped, re-encoded for the gamma wanted,
+//! premultiplied, resized to a power of two when asked, and mipmapped
+//! down to 1x1 at any size. Resizing and mipmapping average linear,
+//! alpha-weighted colour, so darks don't swell and transparent texels
+//! don't bleed their colour into the opaque ones next to them.
+
+use serde_derive::Deserialize;
+
+use renderer::image::{jpeg, png};
+
+/// RGBA8 pixels, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>,
+  /// The exponent the values are encoded with, such as 0.45455 for
+  /// 1 / 2.2, as the file says or as `prepare` re-encoded them. `None`
+  /// means sRGB.
+  pub gamma: Option<f32>,
+}
+
+/// What values the prepared pixels hold.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Gamma {
+  /// Whatever the file stored.
+  Keep,
+  /// sRGB encoded, converting files that declare another gamma the way
+  /// browsers do when they display them.
+  Srgb,
+  /// Linear light. Eight bits lose shades in the darks this way, so
+  /// prefer `Srgb` with an sRGB texture format or a decode in the
+  /// shader where those are available.
+  Linear,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DecodeOptions {
+  /// Put the last row first, for texture coordinates with `v` growing
+  /// up, like `UNPACK_FLIP_Y_WEBGL` does for images from the browser.
+  pub flip_y: bool,
+  /// Multiply colour by alpha, like `UNPACK_PREMULTIPLY_ALPHA_WEBGL`.
+  pub premultiply: bool,
+  pub gamma: Gamma,
+  /// Build the full mip chain.
+  pub mipmaps: bool,
+  /// Scale up to the next power of two on each side first, which WebGL1
+  /// needs to mipmap or repeat a texture.
+  pub power_of_two: bool,
+}
+
+impl Default for DecodeOptions {
+  fn default() -> DecodeOptions {
+    DecodeOptions {
+      flip_y: false,
+      premultiply: false,
+      gamma: Gamma::Srgb,
+      mipmaps: true,
+      power_of_two: false,
+    }
+  }
+}
+
+/// Decode a PNG or JPEG file, told apart by its first bytes.
+pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+  if bytes.starts_with(&png::SIGNATURE) {
+    png::decode(bytes)
+  } else if bytes.starts_with(&[0xFF, 0xD8]) {
+    jpeg::decode(bytes)
+  } else {
+    Err("not a PNG or JPEG file".to_string())
+  }
+}
+
+/// Decode and `prepare` in one go.
+pub fn load(bytes: &[u8], options: &DecodeOptions) -> Result<Vec<Image>, String> {
+  Ok(prepare(&decode(bytes)?, options))
+}
+
+/// The texture levels for `image`, largest first: one unless
+/// `options.mipmaps`, in which case each is half the size of the one
+/// before, rounded down, until both sides are 1.
+pub fn prepare(image: &Image, options: &DecodeOptions) -> Vec<Image> {
+  let (width, height) = (image.width as usize, image.height as usize);
+  let mut pixels = image.pixels.clone();
+  if options.flip_y {
+    flip_rows(&mut pixels, width, height);
+  }
+  let linear = options.gamma == Gamma::Linear;
+  let stored_gamma = match options.gamma {
+    Gamma::Keep => image.gamma,
+    _ => None,
+  };
+  if options.gamma != Gamma::Keep {
+    reencode(&mut pixels, image.gamma, linear);
+  }
+  let curve = Curve::new(stored_gamma, linear);
+  let gamma = if linear { Some(1.0) } else { stored_gamma };
+
+  let resize = options.power_of_two && !(width.is_power_of_two() && height.is_power_of_two());
+  let mut levels = Vec::new();
+  let (mut width, mut height) = (width, height);
+  let mut working = if resize || options.mipmaps {
+    Some(curve.linearize(&pixels))
+  } else {
+    None
+  };
+  if resize {
+    let (new_width, new_height) = (width.next_power_of_two(), height.next_power_of_two());
+    let resized = bilinear(working.as_ref().unwrap(), width, height, new_width, new_height);
+    width = new_width;
+    height = new_height;
+    pixels = curve.delinearize(&resized, false);
+    working = Some(resized);
+  }
+  if options.premultiply {
+    premultiply(&mut pixels);
+  }
+  levels.push(Image { width: width as u32, height: height as u32, pixels, gamma });
+
+  if options.mipmaps {
+    let mut working = working.unwrap();
+    while width > 1 || height > 1 {
+      let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+      working = area_downsample(&working, width, height, next_width, next_height);
+      width = next_width;
+      height = next_height;
+      levels.push(Image {
+        width: width as u32,
+        height: height as u32,
+        pixels: curve.delinearize(&working, options.premultiply),
+        gamma,
+      });
+    }
+  }
+  levels
+}
+
+/// Reverse the order of the rows of an RGBA image in place.
+pub fn flip_rows(pixels: &mut [u8], width: usize, height: usize) {
+  let stride = width * 4;
+  for y in 0..height / 2 {
+    let (top, bottom) = pixels.split_at_mut((height - 1 - y) * stride);
+    top[y * stride..(y + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+  }
+}
+
+/// Multiply the colour of RGBA pixels by their alpha in place.
+pub fn premultiply(pixels: &mut [u8]) {
+  for pixel in pixels.chunks_mut(4) {
+    let alpha = pixel[3] as u32;
+    for channel in &mut pixel[..3] {
+      *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+    }
+  }
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.040_45 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+  if value <= 0.003_130_8 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// How stored values map to linear light: the sRGB curve, a plain
+/// exponent, or nothing when they already are linear.
+struct Curve {
+  to_linear: Vec<f32>,
+  gamma: Option<f32>,
+  linear: bool,
+}
+
+impl Curve {
+  fn new(gamma: Option<f32>, linear: bool) -> Curve {
+    let to_linear = (0..256).map(|value| {
+      let value = value as f32 / 255.0;
+      match (linear, gamma) {
+        (true, _) => value,
+        (false, Some(gamma)) => value.powf(1.0 / gamma),
+        (false, None) => srgb_to_linear(value),
+      }
+    }).collect();
+    Curve { to_linear, gamma, linear }
+  }
+
+  fn encode(&self, value: f32) -> u8 {
+    let value = value.max(0.0).min(1.0);
+    let encoded = match (self.linear, self.gamma) {
+      (true, _) => value,
+      (false, Some(gamma)) => value.powf(gamma),
+      (false, None) => linear_to_srgb(value),
+    };
+    (encoded * 255.0).round() as u8
+  }
+
+  /// Linear RGB premultiplied by alpha, and alpha, as floats.
+  fn linearize(&self, pixels: &[u8]) -> Vec<f32> {
+    let mut working = Vec::with_capacity(pixels.len());
+    for pixel in pixels.chunks(4) {
+      let alpha = pixel[3] as f32 / 255.0;
+      for &channel in &pixel[..3] {
+        working.push(self.to_linear[channel as usize] * alpha);
+      }
+      working.push(alpha);
+    }
+    working
+  }
+
+  fn delinearize(&self, working: &[f32], premultiply: bool) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(working.len());
+    for texel in working.chunks(4) {
+      let alpha = texel[3].max(0.0).min(1.0);
+      for &channel in &texel[..3] {
+        let straight = if alpha > 0.0 { channel / alpha } else { 0.0 };
+        let mut value = self.encode(straight);
+        if premultiply {
+          value = (value as f32 * alpha).round() as u8;
+        }
+        pixels.push(value);
+      }
+      pixels.push((alpha * 255.0).round() as u8);
+    }
+    pixels
+  }
+}
+
+/// Convert stored values encoded with `gamma` (`None` for sRGB) to sRGB,
+/// or to linear when `linear`. Alpha is left alone.
+fn reencode(pixels: &mut [u8], gamma: Option<f32>, linear: bool) {
+  let table: Vec<u8> = match (gamma, linear) {
+    // Within rounding of the sRGB curve already.
+    (None, false) => return,
+    (Some(gamma), false) if (gamma - 1.0 / 2.2).abs() < 0.01 => return,
+    _ => {
+      let source = Curve::new(gamma, false);
+      let target = Curve::new(None, linear);
+      source.to_linear.iter().map(|&value| target.encode(value)).collect()
+    },
+  };
+  for pixel in pixels.chunks_mut(4) {
+    for channel in &mut pixel[..3] {
+      *channel = table[*channel as usize];
+    }
+  }
+}
+
+/// Shrink a working image so each new texel is the area-weighted average
+/// of the old texels under it. Texels straddling two new ones are split
+/// between them, so halving an odd size drops no row or column.
+fn area_downsample(source: &[f32], width: usize, height: usize, new_width: usize, new_height: usize) -> Vec<f32> {
+  let footprint = |i: usize, old: usize, new: usize| {
+    let scale = old as f32 / new as f32;
+    let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+    let mut taps = Vec::with_capacity(4);
+    let mut position = start.floor() as usize;
+    while (position as f32) < end && position < old {
+      let weight = end.min(position as f32 + 1.0) - start.max(position as f32);
+      if weight > 0.0 {
+        taps.push((position, weight / scale));
+      }
+      position += 1;
+    }
+    taps
+  };
+  let columns: Vec<Vec<(usize, f32)>> = (0..new_width).map(|x| footprint(x, width, new_width)).collect();
+  let mut out = Vec::with_capacity(new_width * new_height * 4);
+  for y in 0..new_height {
+    let rows = footprint(y, height, new_height);
+    for taps in &columns {
+      let mut sum = [0f32; 4];
+      for &(row, row_weight) in &rows {
+        for &(column, column_weight) in taps {
+          let at = (row * width + column) * 4;
+          let weight = row_weight * column_weight;
+          for channel in 0..4 {
+            sum[channel] += source[at + channel] * weight;
+          }
+        }
+      }
+      out.extend_from_slice(&sum);
+    }
+  }
+  out
+}
+
+/// Scale a working image with bilinear filtering, sampling texel
+/// centres and clamping at the edges.
+fn bilinear(source: &[f32], width: usize, height: usize, new_width: usize, new_height: usize) -> Vec<f32> {
+  let taps = |i: usize, old: usize, new: usize| {
+    let position = ((i as f32 + 0.5) * old as f32 / new as f32 - 0.5).max(0.0);
+    let low = (position.floor() as usize).min(old - 1);
+    let high = (low + 1).min(old - 1);
+    (low, high, position - low as f32)
+  };
+  let mut out = Vec::with_capacity(new_width * new_height * 4);
+  for y in 0..new_height {
+    let (top, bottom, fy) = taps(y, height, new_height);
+    for x in 0..new_width {
+      let (left, right, fx) = taps(x, width, new_width);
+      for channel in 0..4 {
+        let texel = |row: usize, column: usize| source[(row * width + column) * 4 + channel];
+        let upper = texel(top, left) * (1.0 - fx) + texel(top, right) * fx;
+        let lower = texel(bottom, left) * (1.0 - fx) + texel(bottom, right) * fx;
+        out.push(upper * (1.0 - fy) + lower * fy);
+      }
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn image(width: u32, height: u32, pixels: &[u8]) -> Image {
+    Image { width, height, pixels: pixels.to_vec(), gamma: None }
+  }
+
+  fn single() -> DecodeOptions {
+    DecodeOptions { mipmaps: false, ..DecodeOptions::default() }
+  }
+
+  #[test]
+  fn loads_png_and_jpeg() {
+    let levels = load(include_bytes!("../../tutorial/sample6/cubetexture.png"), &DecodeOptions::default()).unwrap();
+    let sizes: Vec<(u32, u32)> = levels.iter().map(|level| (level.width, level.height)).collect();
+    assert_eq!(sizes, vec![(256, 256), (128, 128), (64, 64), (32, 32), (16, 16), (8, 8), (4, 4), (2, 2), (1, 1)]);
+    let levels = load(include_bytes!("testdata/py.jpg"), &single()).unwrap();
+    assert_eq!(levels.len(), 1);
+    assert_eq!((levels[0].width, levels[0].height, levels[0].pixels.len()), (16, 16, 16 * 16 * 4));
+  }
+
+  #[test]
+  fn rejects_unknown_and_broken_files() {
+    assert!(load(b"GIF89a", &DecodeOptions::default()).is_err());
+    assert!(load(&[], &DecodeOptions::default()).is_err());
+    let png = include_bytes!("../../tutorial/sample6/cubetexture.png");
+    assert!(load(&png[..png.len() / 2], &DecodeOptions::default()).is_err());
+    let jpeg = include_bytes!("testdata/py.jpg");
+    assert!(load(&jpeg[..jpeg.len() / 2], &DecodeOptions::default()).is_err());
+  }
+
+  #[test]
+  fn mipmaps_odd_sizes_down_to_one() {
+    let levels = prepare(&image(5, 3, &[128; 5 * 3 * 4]), &DecodeOptions::default());
+    let sizes: Vec<(u32, u32, usize)> =
+        levels.iter().map(|level| (level.width, level.height, level.pixels.len())).collect();
+    assert_eq!(sizes, vec![(5, 3, 60), (2, 1, 8), (1, 1, 4)]);
+    // A flat colour stays that colour.
+    assert!(levels.iter().all(|level| level.pixels.iter().all(|&value| value == 128)));
+  }
+
+  #[test]
+  fn mipmaps_average_linear_light() {
+    let levels = prepare(&image(2, 1, &[0, 0, 0, 255, 255, 255, 255, 255]), &DecodeOptions::default());
+    let value = levels[1].pixels[0];
+    assert!(value == 187 || value == 188, "{}", value);
+    assert_eq!(levels[1].pixels[3], 255);
+  }
+
+  #[test]
+  fn transparent_texels_do_not_bleed() {
+    let levels = prepare(&image(2, 1, &[255, 0, 0, 255, 0, 255, 0, 0]), &DecodeOptions::default());
+    assert_eq!(levels[1].pixels, vec![255, 0, 0, 128]);
+    let premultiplied = DecodeOptions { premultiply: true, ..DecodeOptions::default() };
+    let levels = prepare(&image(2, 1, &[255, 0, 0, 255, 0, 255, 0, 0]), &premultiplied);
+    assert_eq!(levels[0].pixels, vec![255, 0, 0, 255, 0, 0, 0, 0]);
+    assert_eq!(levels[1].pixels, vec![128, 0, 0, 128]);
+  }
+
+  #[test]
+  fn flips_and_premultiplies() {
+    let options = DecodeOptions { flip_y: true, premultiply: true, ..single() };
+    let levels = prepare(&image(1, 3, &[1, 2, 3, 255, 4, 5, 6, 255, 200, 100, 0, 128]), &options);
+    assert_eq!(levels[0].pixels, vec![100, 50, 0, 128, 4, 5, 6, 255, 1, 2, 3, 255]);
+  }
+
+  #[test]
+  fn resizes_to_powers_of_two() {
+    let options = DecodeOptions { power_of_two: true, ..DecodeOptions::default() };
+    let levels = prepare(&image(3, 5, &[90; 3 * 5 * 4]), &options);
+    let sizes: Vec<(u32, u32)> = levels.iter().map(|level| (level.width, level.height)).collect();
+    assert_eq!(sizes, vec![(4, 8), (2, 4), (1, 2), (1, 1)]);
+    assert!(levels[0].pixels.iter().all(|&value| value == 90));
+    // Already a power of two: untouched.
+    let levels = prepare(&image(2, 2, &[7; 16]), &DecodeOptions { power_of_two: true, ..single() });
+    assert_eq!(levels[0].pixels, vec![7; 16]);
+  }
+
+  #[test]
+  fn reencodes_gamma() {
+    let srgb = image(1, 1, &[188, 0, 255, 77]);
+    let linear = prepare(&srgb, &DecodeOptions { gamma: Gamma::Linear, ..single() });
+    assert_eq!(linear[0].pixels, vec![128, 0, 255, 77]);
+    // A file at gamma 1 stored linear values; sRGB brightens them.
+    let mut stored = image(1, 1, &[128, 0, 255, 255]);
+    stored.gamma = Some(1.0);
+    let converted = prepare(&stored, &single());
+    assert_eq!(converted[0].pixels, vec![188, 0, 255, 255]);
+    let kept = prepare(&stored, &DecodeOptions { gamma: Gamma::Keep, ..single() });
+    assert_eq!(kept[0].pixels, vec![128, 0, 255, 255]);
+    assert_eq!(kept[0].gamma, Some(1.0));
+  }
+
+  #[test]
+  fn levels_record_the_gamma_they_are_stored_in() {
+    let mut stored = image(4, 2, &[128; 32]);
+    stored.gamma = Some(1.0);
+    for &(gamma, expected) in &[(Gamma::Srgb, None), (Gamma::Linear, Some(1.0)), (Gamma::Keep, Some(1.0))] {
+      let levels = prepare(&stored, &DecodeOptions { gamma, ..DecodeOptions::default() });
+      assert_eq!(levels.len(), 3);
+      assert!(levels.iter().all(|level| level.gamma == expected), "{:?}", gamma);
+    }
+    let srgb = image(4, 2, &[128; 32]);
+    let levels = prepare(&srgb, &DecodeOptions { gamma: Gamma::Linear, ..DecodeOptions::default() });
+    assert!(levels.iter().all(|level| level.gamma == Some(1.0)));
+  }
+}
@@ -0,0 +1,509 @@
+//! PNG decoding to RGBA8: every colour type and bit depth, palettes and
+//! `tRNS` transparency, Adam7 interlacing, and the `gAMA` / `sRGB`
+//! chunks. Samples of other depths are scaled to eight bits.
+
+use renderer::image::inflate::zlib;
+use renderer::image::pipeline::Image;
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// `x, y` of the first pixel and the step between pixels of each Adam7
+/// pass.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+  (0, 0, 8, 8),
+  (4, 0, 8, 8),
+  (0, 4, 4, 8),
+  (2, 0, 4, 4),
+  (0, 2, 2, 4),
+  (1, 0, 2, 2),
+  (0, 1, 1, 2),
+];
+
+struct Header {
+  width: usize,
+  height: usize,
+  depth: u8,
+  color_type: u8,
+  interlaced: bool,
+}
+
+impl Header {
+  fn channels(&self) -> usize {
+    match self.color_type {
+      0 | 3 => 1,
+      4 => 2,
+      2 => 3,
+      _ => 4,
+    }
+  }
+
+  /// Bytes per complete pixel, rounded up to one, which is how far back
+  /// the filters look.
+  fn filter_step(&self) -> usize {
+    ((self.channels() * self.depth as usize + 7) / 8).max(1)
+  }
+
+  /// `None` when the row is too large to address.
+  fn row_bytes(&self, width: usize) -> Option<usize> {
+    let bits = width.checked_mul(self.channels() * self.depth as usize)?;
+    Some(bits / 8 + (bits % 8 != 0) as usize)
+  }
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+  (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | bytes[3] as u32
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = !0u32;
+  for &byte in bytes {
+    crc ^= byte as u32;
+    for _ in 0..8 {
+      crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+/// Decode a PNG file. `Image::gamma` is set from `gAMA`, or to the sRGB
+/// curve's when there is an `sRGB` chunk.
+pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+  if bytes.len() < 8 || bytes[..8] != SIGNATURE[..] {
+    return Err("not a PNG file".to_string());
+  }
+  let mut header = None;
+  let mut palette: Vec<[u8; 4]> = Vec::new();
+  let mut transparent: Option<Vec<u16>> = None;
+  let mut gamma = None;
+  let mut compressed = Vec::new();
+  let mut position = 8;
+  loop {
+    let chunk_header = bytes.get(position..position + 8).ok_or("PNG ends before IEND")?;
+    let length = be32(chunk_header) as usize;
+    let kind = &chunk_header[4..8];
+    // The data and CRC must fit in what is left, checked before any
+    // offset is added to `length`.
+    let remaining = bytes.len() - position - 8;
+    if length > remaining || remaining - length < 4 {
+      return Err("PNG chunk runs past the end".to_string());
+    }
+    let data = bytes.get(position + 8..position + 8 + length).ok_or("PNG chunk runs past the end")?;
+    let stored_crc = bytes.get(position + 8 + length..position + 12 + length).ok_or("PNG chunk runs past the end")?;
+    if crc32(&bytes[position + 4..position + 8 + length]) != be32(stored_crc) {
+      return Err(format!("PNG {} chunk is corrupt", String::from_utf8_lossy(kind)));
+    }
+    position += 12 + length;
+
+    match kind {
+      b"IHDR" => {
+        if data.len() != 13 {
+          return Err("bad IHDR".to_string());
+        }
+        let parsed = Header {
+          width: be32(&data[0..4]) as usize,
+          height: be32(&data[4..8]) as usize,
+          depth: data[8],
+          color_type: data[9],
+          interlaced: data[12] == 1,
+        };
+        let valid_depth = match parsed.color_type {
+          0 => [1, 2, 4, 8, 16].contains(&parsed.depth),
+          3 => [1, 2, 4, 8].contains(&parsed.depth),
+          2 | 4 | 6 => [8, 16].contains(&parsed.depth),
+          _ => false,
+        };
+        if !valid_depth {
+          return Err(format!("PNG colour type {} with bit depth {} is invalid", parsed.color_type, parsed.depth));
+        }
+        if data[10] != 0 || data[11] != 0 || data[12] > 1 {
+          return Err("unknown PNG compression, filter or interlace method".to_string());
+        }
+        if parsed.width == 0 || parsed.height == 0 {
+          return Err("PNG has no pixels".to_string());
+        }
+        header = Some(parsed);
+      },
+      b"PLTE" => {
+        palette = data.chunks(3).filter(|rgb| rgb.len() == 3).map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect();
+      },
+      b"tRNS" => {
+        match header.as_ref().map(|header| header.color_type) {
+          Some(3) => {
+            for (entry, &alpha) in palette.iter_mut().zip(data) {
+              entry[3] = alpha;
+            }
+          },
+          Some(0) | Some(2) => {
+            transparent = Some(data.chunks(2).filter(|pair| pair.len() == 2)
+                .map(|pair| (pair[0] as u16) << 8 | pair[1] as u16).collect());
+          },
+          _ => (),
+        }
+      },
+      b"gAMA" if data.len() == 4 => gamma = Some(be32(data) as f32 / 100_000.0),
+      b"sRGB" => gamma = Some(1.0 / 2.2),
+      b"IDAT" => compressed.extend_from_slice(data),
+      b"IEND" => break,
+      _ => {
+        // Unknown critical chunks change how the image must be read.
+        if kind[0] & 0x20 == 0 {
+          return Err(format!("unsupported critical PNG chunk {}", String::from_utf8_lossy(kind)));
+        }
+      },
+    }
+  }
+
+  let header = header.ok_or("PNG has no IHDR")?;
+  if header.color_type == 3 && palette.is_empty() {
+    return Err("palette PNG has no PLTE".to_string());
+  }
+  let raw = zlib(&compressed)?;
+  let (width, height) = (header.width, header.height);
+  let too_large = || "PNG image is too large".to_string();
+  let passes: Vec<(usize, usize, usize, usize)> = if header.interlaced {
+    ADAM7.to_vec()
+  } else {
+    vec![(0, 0, 1, 1)]
+  };
+  // Work out every pass's size first so a header promising more than
+  // the data holds fails before the pixels are allocated.
+  let mut layout = Vec::new();
+  let mut expected = 0usize;
+  for (x0, y0, dx, dy) in passes {
+    if x0 >= width || y0 >= height {
+      continue;
+    }
+    let (pass_width, pass_height) = ((width - x0 - 1) / dx + 1, (height - y0 - 1) / dy + 1);
+    let stride = header.row_bytes(pass_width).ok_or_else(too_large)?;
+    let size = stride.checked_add(1).and_then(|row| row.checked_mul(pass_height)).ok_or_else(too_large)?;
+    expected = expected.checked_add(size).ok_or_else(too_large)?;
+    layout.push((x0, y0, dx, dy, pass_width, stride, size));
+  }
+  if raw.len() < expected {
+    return Err("PNG image data is too short".to_string());
+  }
+  let mut pixels = vec![0u8; width.checked_mul(height).and_then(|count| count.checked_mul(4)).ok_or_else(too_large)?];
+  let mut consumed = 0;
+  for (x0, y0, dx, dy, pass_width, stride, size) in layout {
+    let data = &raw[consumed..consumed + size];
+    consumed += size;
+    let rows = unfilter(data, stride, header.filter_step())?;
+    for (row_index, row) in rows.chunks(stride).enumerate() {
+      let y = y0 + row_index * dy;
+      for column in 0..pass_width {
+        let x = x0 + column * dx;
+        let at = (y * width + x) * 4;
+        pixels[at..at + 4].copy_from_slice(&pixel(&header, row, column, &palette, &transparent));
+      }
+    }
+  }
+  Ok(Image { width: width as u32, height: height as u32, pixels, gamma })
+}
+
+/// Undo the per-row filters of one pass, returning its rows without
+/// their filter type bytes.
+fn unfilter(data: &[u8], stride: usize, step: usize) -> Result<Vec<u8>, String> {
+  let height = data.len() / (stride + 1);
+  let mut out = vec![0u8; stride * height];
+  for y in 0..height {
+    let filter = data[y * (stride + 1)];
+    let source = &data[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+    let (done, rest) = out.split_at_mut(y * stride);
+    let previous = if y > 0 { &done[(y - 1) * stride..] } else { &[][..] };
+    let row = &mut rest[..stride];
+    for i in 0..stride {
+      let left = if i >= step { row[i - step] as i16 } else { 0 };
+      let up = if y > 0 { previous[i] as i16 } else { 0 };
+      let up_left = if y > 0 && i >= step { previous[i - step] as i16 } else { 0 };
+      let prediction = match filter {
+        0 => 0,
+        1 => left,
+        2 => up,
+        3 => (left + up) / 2,
+        4 => {
+          let estimate = left + up - up_left;
+          let (to_left, to_up, to_up_left) =
+              ((estimate - left).abs(), (estimate - up).abs(), (estimate - up_left).abs());
+          if to_left <= to_up && to_left <= to_up_left {
+            left
+          } else if to_up <= to_up_left {
+            up
+          } else {
+            up_left
+          }
+        },
+        _ => return Err(format!("bad PNG filter type {}", filter)),
+      };
+      row[i] = source[i].wrapping_add(prediction as u8);
+    }
+  }
+  Ok(out)
+}
+
+/// Sample `channel` of pixel `column` of a row, at the file's bit depth.
+fn sample(header: &Header, row: &[u8], column: usize, channel: usize) -> u16 {
+  let index = column * header.channels() + channel;
+  match header.depth {
+    16 => (row[index * 2] as u16) << 8 | row[index * 2 + 1] as u16,
+    8 => row[index] as u16,
+    depth => {
+      let bit = index * depth as usize;
+      let shift = 8 - depth as usize - bit % 8;
+      ((row[bit / 8] >> shift) & ((1u8 << depth) - 1)) as u16
+    },
+  }
+}
+
+fn pixel(header: &Header, row: &[u8], column: usize, palette: &[[u8; 4]], transparent: &Option<Vec<u16>>) -> [u8; 4] {
+  let max = ((1u32 << header.depth) - 1) as u32;
+  let to8 = |value: u16| (value as u32 * 255 / max) as u8;
+  let matches = |samples: &[u16]| transparent.as_ref().map_or(false, |key| key.len() == samples.len() && key[..] == *samples);
+  match header.color_type {
+    0 => {
+      let gray = sample(header, row, column, 0);
+      let value = to8(gray);
+      [value, value, value, if matches(&[gray]) { 0 } else { 255 }]
+    },
+    2 => {
+      let rgb = [sample(header, row, column, 0), sample(header, row, column, 1), sample(header, row, column, 2)];
+      [to8(rgb[0]), to8(rgb[1]), to8(rgb[2]), if matches(&rgb) { 0 } else { 255 }]
+    },
+    3 => {
+      let index = sample(header, row, column, 0) as usize;
+      // Out of range indices are an error in the spec; show them black.
+      palette.get(index).cloned().unwrap_or([0, 0, 0, 255])
+    },
+    4 => {
+      let value = to8(sample(header, row, column, 0));
+      [value, value, value, to8(sample(header, row, column, 1))]
+    },
+    _ => [
+      to8(sample(header, row, column, 0)),
+      to8(sample(header, row, column, 1)),
+      to8(sample(header, row, column, 2)),
+      to8(sample(header, row, column, 3)),
+    ],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn chunk(file: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    let length = data.len() as u32;
+    file.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+    let start = file.len();
+    file.extend_from_slice(kind);
+    file.extend_from_slice(data);
+    let crc = crc32(&file[start..]);
+    file.extend_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+  }
+
+  /// `data` as a zlib stream of stored blocks.
+  fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![data] } else { data.chunks(0xFFFF).collect() };
+    for (i, block) in blocks.iter().enumerate() {
+      let length = block.len() as u16;
+      stream.push((i + 1 == blocks.len()) as u8);
+      stream.extend_from_slice(&[length as u8, (length >> 8) as u8, !length as u8, (!length >> 8) as u8]);
+      stream.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+      a = (a + byte as u32) % 65521;
+      b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&[(b >> 8) as u8, b as u8, (a >> 8) as u8, a as u8]);
+    stream
+  }
+
+  /// Filter `rows` of `stride` bytes with `filter(y)` for row `y`.
+  fn filter_rows(rows: &[u8], stride: usize, step: usize, filter: &Fn(usize) -> u8) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (y, row) in rows.chunks(stride).enumerate() {
+      let kind = filter(y);
+      out.push(kind);
+      for i in 0..stride {
+        let left = if i >= step { row[i - step] as i16 } else { 0 };
+        let up = if y > 0 { rows[(y - 1) * stride + i] as i16 } else { 0 };
+        let up_left = if y > 0 && i >= step { rows[(y - 1) * stride + i - step] as i16 } else { 0 };
+        let prediction = match kind {
+          0 => 0,
+          1 => left,
+          2 => up,
+          3 => (left + up) / 2,
+          _ => {
+            let estimate = left + up - up_left;
+            let distances = [(estimate - left).abs(), (estimate - up).abs(), (estimate - up_left).abs()];
+            if distances[0] <= distances[1] && distances[0] <= distances[2] {
+              left
+            } else if distances[1] <= distances[2] {
+              up
+            } else {
+              up_left
+            }
+          },
+        };
+        out.push(row[i].wrapping_sub(prediction as u8));
+      }
+    }
+    out
+  }
+
+  /// A PNG of packed `samples`, one byte each, at `depth` bits, Adam7
+  /// interlaced or not, filtered with `filter(pass, y)`.
+  fn encode(
+    width: usize, height: usize, depth: u8, color_type: u8, samples: &[u8], interlaced: bool,
+    filter: &Fn(usize, usize) -> u8,
+  ) -> Vec<u8> {
+    let header = Header { width, height, depth, color_type, interlaced };
+    let channels = header.channels();
+    let passes = if interlaced { ADAM7.to_vec() } else { vec![(0, 0, 1, 1)] };
+    let mut raw = Vec::new();
+    for (pass, &(x0, y0, dx, dy)) in passes.iter().enumerate() {
+      if x0 >= width || y0 >= height {
+        continue;
+      }
+      let pass_width = (width - x0 + dx - 1) / dx;
+      let stride = header.row_bytes(pass_width).unwrap();
+      let mut rows = Vec::new();
+      for y in (y0..height).step_by(dy) {
+        let mut row = vec![0u8; stride];
+        for (column, x) in (x0..width).step_by(dx).enumerate() {
+          for channel in 0..channels {
+            let bit = (column * channels + channel) * depth as usize;
+            let value = samples[(y * width + x) * channels + channel];
+            row[bit / 8] |= value << (8 - depth as usize - bit % 8);
+          }
+        }
+        rows.extend(row);
+      }
+      raw.extend(filter_rows(&rows, stride, header.filter_step(), &|y| filter(pass, y)));
+    }
+    let mut file = SIGNATURE.to_vec();
+    let (w, h) = (width as u32, height as u32);
+    chunk(&mut file, b"IHDR", &[
+      (w >> 24) as u8, (w >> 16) as u8, (w >> 8) as u8, w as u8,
+      (h >> 24) as u8, (h >> 16) as u8, (h >> 8) as u8, h as u8,
+      depth, color_type, 0, 0, interlaced as u8,
+    ]);
+    chunk(&mut file, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut file, b"IEND", &[]);
+    file
+  }
+
+  /// Samples that differ between neighbours, so every filter predicts
+  /// something else.
+  fn noise(count: usize, max: u8) -> Vec<u8> {
+    (0..count).map(|i| ((i * 7919 + i * i * 31) % (max as usize + 1)) as u8).collect()
+  }
+
+  fn at(image: &Image, x: usize, y: usize) -> [u8; 4] {
+    let i = (y * image.width as usize + x) * 4;
+    [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2], image.pixels[i + 3]]
+  }
+
+  #[test]
+  fn decodes_cube_texture() {
+    let image = decode(include_bytes!("../../tutorial/sample6/cubetexture.png")).unwrap();
+    assert_eq!((image.width, image.height, image.gamma), (256, 256, None));
+    assert_eq!(at(&image, 0, 0), [255, 255, 255, 234]);
+    assert_eq!(at(&image, 5, 7), [255, 255, 255, 255]);
+    assert_eq!(at(&image, 128, 85), [86, 186, 235, 255]);
+    assert_eq!(at(&image, 85, 128), [223, 116, 28, 255]);
+  }
+
+  #[test]
+  fn decodes_idle_icon() {
+    let image = decode(include_bytes!("testdata/idle.png")).unwrap();
+    assert_eq!((image.width, image.height, image.gamma), (48, 48, Some(0.45455)));
+    assert_eq!(at(&image, 0, 0), [0, 0, 0, 0]);
+    assert_eq!(at(&image, 5, 7), [34, 34, 34, 52]);
+    assert_eq!(at(&image, 24, 16), [235, 235, 235, 255]);
+    assert_eq!(at(&image, 16, 24), [245, 245, 245, 255]);
+  }
+
+  #[test]
+  fn undoes_every_filter_type() {
+    let (width, height) = (7, 5);
+    let samples = noise(width * height * 3, 255);
+    let expected: Vec<u8> = samples.chunks(3).flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 255]).collect();
+    for filter in 0..5 {
+      let file = encode(width, height, 8, 2, &samples, false, &|_, _| filter);
+      assert_eq!(decode(&file).unwrap().pixels, expected, "filter {}", filter);
+    }
+    let file = encode(width, height, 8, 2, &samples, false, &|_, y| (y % 5) as u8);
+    assert_eq!(decode(&file).unwrap().pixels, expected);
+  }
+
+  #[test]
+  fn decodes_interlaced_images() {
+    let (width, height) = (11, 9);
+    let samples = noise(width * height * 4, 255);
+    let plain = decode(&encode(width, height, 8, 6, &samples, false, &|_, _| 0)).unwrap();
+    assert_eq!(plain.pixels, samples);
+    let interlaced = encode(width, height, 8, 6, &samples, true, &|pass, y| ((pass + y) % 5) as u8);
+    assert_eq!(decode(&interlaced).unwrap().pixels, samples);
+
+    // Sub-byte samples, and passes smaller than a byte.
+    let samples = noise(width * height, 3);
+    let expected: Vec<u8> = samples.iter().flat_map(|&gray| vec![gray * 85, gray * 85, gray * 85, 255]).collect();
+    let interlaced = encode(width, height, 2, 0, &samples, true, &|pass, _| (pass % 5) as u8);
+    assert_eq!(decode(&interlaced).unwrap().pixels, expected);
+    // Tiny images leave some passes empty.
+    let interlaced = encode(1, 1, 8, 6, &[1, 2, 3, 4], true, &|_, _| 4);
+    assert_eq!(decode(&interlaced).unwrap().pixels, vec![1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn rejects_truncated_and_corrupt_files() {
+    let file = include_bytes!("../../tutorial/sample6/cubetexture.png");
+    for &end in &[0, 7, 8, 20, 33, 100, file.len() / 2, file.len() - 1] {
+      assert!(decode(&file[..end]).is_err(), "{} bytes", end);
+    }
+    let mut corrupt = file.to_vec();
+    corrupt[20] ^= 1;
+    assert!(decode(&corrupt).is_err());
+    let mut signature = file.to_vec();
+    signature[1] = b'Q';
+    assert!(decode(&signature).is_err());
+
+    let samples = noise(4 * 4 * 3, 255);
+    let bad_filter = encode(4, 4, 8, 2, &samples, false, &|_, y| if y == 2 { 5 } else { 0 });
+    assert!(decode(&bad_filter).is_err());
+    // IDAT holding fewer rows than the header promises.
+    let mut short = SIGNATURE.to_vec();
+    chunk(&mut short, b"IHDR", &[0, 0, 0, 4, 0, 0, 0, 4, 8, 2, 0, 0, 0]);
+    chunk(&mut short, b"IDAT", &zlib_stored(&[0; 13 * 3]));
+    chunk(&mut short, b"IEND", &[]);
+    assert!(decode(&short).is_err());
+  }
+
+  #[test]
+  fn rejects_oversized_images() {
+    // 2^31 - 1 pixels a side, far more than the data could hold.
+    let mut huge = SIGNATURE.to_vec();
+    chunk(&mut huge, b"IHDR", &[0x7F, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 8, 6, 0, 0, 0]);
+    chunk(&mut huge, b"IDAT", &zlib_stored(&[0; 64]));
+    chunk(&mut huge, b"IEND", &[]);
+    assert!(decode(&huge).is_err());
+
+    let mut interlaced = huge.clone();
+    interlaced[8 + 8 + 12] = 1;
+    let crc = crc32(&interlaced[12..29]);
+    interlaced[29..33].copy_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+    assert!(decode(&interlaced).is_err());
+  }
+
+  #[test]
+  fn rejects_oversized_chunk_lengths() {
+    let file = encode(2, 2, 8, 2, &noise(2 * 2 * 3, 255), false, &|_, _| 0);
+    for &length in &[0xFFFF_FFFFu32, 0xFFFF_FFF8, 0x7FFF_FFFF, file.len() as u32] {
+      let mut corrupt = file.clone();
+      corrupt[33..37].copy_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+      assert!(decode(&corrupt).is_err(), "length {}", length);
+    }
+  }
+}
@@ -0,0 +1,5 @@
+varying lowp vec4 vColor;
+
+void main(void) {
+  gl_FragColor = vColor;
+}
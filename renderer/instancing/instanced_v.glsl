@@ -0,0 +1,22 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+
+// Per-instance attributes, advanced once per instance. The matrix
+// takes four consecutive attribute locations, one per column.
+attribute mat4 aInstanceMatrix;
+attribute vec4 aInstanceColor;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+varying lowp vec4 vColor;
+
+void main(void) {
+  gl_Position = uProjectionMatrix * uViewMatrix * aInstanceMatrix * aVertexPosition;
+
+  // Cheap directional light so the faces of a cube can be told apart.
+  // Fine as long as instance transforms don't scale non-uniformly.
+  vec3 normal = normalize((aInstanceMatrix * vec4(aVertexNormal, 0.0)).xyz);
+  float light = 0.35 + 0.65 * max(dot(normal, normalize(vec3(0.85, 0.8, 0.75))), 0.0);
+  vColor = vec4(aInstanceColor.rgb * light, aInstanceColor.a);
+}
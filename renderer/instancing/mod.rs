@@ -0,0 +1,341 @@
+//! Instanced drawing: one draw call for many copies of a mesh, with
+//! the per-copy transform and colour streamed from an instance buffer.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  AngleInstancedArrays,
+  WebGl2RenderingContext,
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::capabilities::capabilities;
+use renderer::f32_view;
+use renderer::gl::Gl;
+use renderer::geometry::Geometry;
+use renderer::shader::build_program;
+
+pub static VERTEX_SHADER: &'static str = include_str!("instanced_v.glsl");
+pub static FRAGMENT_SHADER: &'static str = include_str!("instanced_f.glsl");
+
+/// Floats per instance: a column-major mat4 followed by an RGBA colour.
+const INSTANCE_FLOATS: usize = 20;
+const INSTANCE_STRIDE: i32 = (INSTANCE_FLOATS * 4) as i32;
+
+/// Where the instancing entry points come from. WebGL1 needs the
+/// `ANGLE_instanced_arrays` extension, WebGL2 has them built in.
+pub enum Instancing {
+  Angle(AngleInstancedArrays),
+  Native(WebGl2RenderingContext),
+}
+
+impl Instancing {
+  /// Use native instancing on WebGL2 and query
+  /// `ANGLE_instanced_arrays` on WebGL1.
+  pub fn new(context: &Gl) -> Result<Instancing, JsValue> {
+    if let Some(gl) = context.webgl2() {
+      return Ok(Instancing::Native(gl.clone()));
+    }
+    if !capabilities(context).features.instancing {
+      return Err("ANGLE_instanced_arrays is not supported".into());
+    }
+    let extension = context
+        .get_extension("ANGLE_instanced_arrays")?
+        .ok_or("ANGLE_instanced_arrays is not supported")?;
+    Ok(Instancing::Angle(extension.unchecked_into::<AngleInstancedArrays>()))
+  }
+
+  pub fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+    match *self {
+      Instancing::Angle(ref ext) => ext.vertex_attrib_divisor_angle(index, divisor),
+      Instancing::Native(ref gl) => gl.vertex_attrib_divisor(index, divisor),
+    }
+  }
+
+  pub fn draw_arrays_instanced(&self, mode: u32, first: i32, count: i32, instances: i32) {
+    match *self {
+      Instancing::Angle(ref ext) => ext.draw_arrays_instanced_angle(mode, first, count, instances),
+      Instancing::Native(ref gl) => gl.draw_arrays_instanced(mode, first, count, instances),
+    }
+  }
+
+  pub fn draw_elements_instanced(&self, mode: u32, count: i32, type_: u32, offset: i32, instances: i32) {
+    match *self {
+      Instancing::Angle(ref ext) => {
+        ext.draw_elements_instanced_angle_with_i32(mode, count, type_, offset, instances)
+      },
+      Instancing::Native(ref gl) => {
+        gl.draw_elements_instanced_with_i32(mode, count, type_, offset, instances)
+      },
+    }
+  }
+}
+
+/// Attribute and uniform locations of the instanced shader.
+///
+/// Only `aVertexPosition` and `aInstanceMatrix` are required; other
+/// shaders over the same instance data may leave the rest out.
+pub struct InstancedProgram {
+  pub program: WebGlProgram,
+  pub vertex_position: u32,
+  pub vertex_normal: Option<u32>,
+  pub instance_matrix: u32,
+  pub instance_color: Option<u32>,
+  /// `aInstanceIndex`, a float counting instances from 0.
+  pub instance_index: Option<u32>,
+  pub projection_matrix: Option<WebGlUniformLocation>,
+  pub view_matrix: Option<WebGlUniformLocation>,
+}
+
+impl InstancedProgram {
+  pub fn new(context: &Gl) -> Result<InstancedProgram, JsValue> {
+    InstancedProgram::with_shaders(context, VERTEX_SHADER, FRAGMENT_SHADER)
+  }
+
+  pub fn with_shaders(
+    context: &Gl,
+    vertex_source: &str,
+    fragment_source: &str,
+  ) -> Result<InstancedProgram, JsValue> {
+    let program = build_program(context, vertex_source, fragment_source)?;
+    let location = |name: &str| {
+      let location = context.get_attrib_location(&program, name);
+      if location < 0 { None } else { Some(location as u32) }
+    };
+    Ok(InstancedProgram {
+      vertex_position: location("aVertexPosition").ok_or("shader has no aVertexPosition")?,
+      vertex_normal: location("aVertexNormal"),
+      instance_matrix: location("aInstanceMatrix").ok_or("shader has no aInstanceMatrix")?,
+      instance_color: location("aInstanceColor"),
+      instance_index: location("aInstanceIndex"),
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      view_matrix: context.get_uniform_location(&program, "uViewMatrix"),
+      program,
+    })
+  }
+}
+
+/// A mesh drawn `len()` times in one call.
+///
+/// Instance data lives in a CPU copy; setters only mark the touched
+/// range dirty and `flush` uploads just that range.
+pub struct InstancedMesh {
+  positions: WebGlBuffer,
+  normals: WebGlBuffer,
+  vertex_count: i32,
+  instance_buffer: WebGlBuffer,
+  /// `0, 1, 2, ...` for `aInstanceIndex`, made on first use and sized
+  /// like the instance buffer.
+  index_buffer: Option<(WebGlBuffer, usize)>,
+  instances: Vec<f32>,
+  capacity: usize,
+  /// Half-open range of instances changed since the last flush.
+  dirty: Option<(usize, usize)>,
+  /// The GPU buffer is too small and has to be reallocated.
+  grown: bool,
+}
+
+impl InstancedMesh {
+  pub fn new(
+    context: &Gl,
+    geometry: &Geometry,
+    capacity: usize,
+  ) -> Result<InstancedMesh, JsValue> {
+    let positions = geometry.unindexed_positions();
+    let normals = geometry.unindexed_normals();
+
+    let mesh = InstancedMesh {
+      positions: static_buffer(context, &positions)?,
+      normals: static_buffer(context, &normals)?,
+      vertex_count: (positions.len() / 3) as i32,
+      instance_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+      index_buffer: None,
+      instances: Vec::with_capacity(capacity * INSTANCE_FLOATS),
+      capacity: capacity.max(1),
+      dirty: None,
+      grown: true,
+    };
+    Ok(mesh)
+  }
+
+  pub fn len(&self) -> usize {
+    self.instances.len() / INSTANCE_FLOATS
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.instances.is_empty()
+  }
+
+  /// Add an instance and return its index.
+  pub fn push(&mut self, transform: &Mat4, color: [f32; 4]) -> usize {
+    let index = self.len();
+    self.instances.extend_from_slice(transform.as_slice());
+    self.instances.extend_from_slice(&color);
+    if self.len() > self.capacity {
+      self.capacity *= 2;
+      self.grown = true;
+    }
+    self.mark_dirty(index);
+    index
+  }
+
+  pub fn set_transform(&mut self, index: usize, transform: &Mat4) {
+    let start = index * INSTANCE_FLOATS;
+    self.instances[start..start + 16].copy_from_slice(transform.as_slice());
+    self.mark_dirty(index);
+  }
+
+  pub fn set_color(&mut self, index: usize, color: [f32; 4]) {
+    let start = index * INSTANCE_FLOATS + 16;
+    self.instances[start..start + 4].copy_from_slice(&color);
+    self.mark_dirty(index);
+  }
+
+  /// Drop every instance past `len`.
+  pub fn truncate(&mut self, len: usize) {
+    self.instances.truncate(len * INSTANCE_FLOATS);
+    if let Some((start, end)) = self.dirty {
+      self.dirty = if start >= len { None } else { Some((start, end.min(len))) };
+    }
+  }
+
+  fn mark_dirty(&mut self, index: usize) {
+    self.dirty = Some(match self.dirty {
+      Some((start, end)) => (start.min(index), end.max(index + 1)),
+      None => (index, index + 1),
+    });
+  }
+
+  /// Upload the instances changed since the last call.
+  pub fn flush(&mut self, context: &Gl) -> Result<(), JsValue> {
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.instance_buffer));
+
+    if self.grown {
+      context.buffer_data_with_i32(
+          WebGlRenderingContext::ARRAY_BUFFER,
+          (self.capacity * INSTANCE_FLOATS * 4) as i32,
+          WebGlRenderingContext::DYNAMIC_DRAW,
+      );
+      self.grown = false;
+      self.dirty = if self.is_empty() { None } else { Some((0, self.len())) };
+    }
+
+    if let Some((start, end)) = self.dirty.take() {
+      let data = &self.instances[start * INSTANCE_FLOATS..end * INSTANCE_FLOATS];
+      let view = f32_view(data)?;
+      context.buffer_sub_data_with_i32_and_array_buffer_view(
+          WebGlRenderingContext::ARRAY_BUFFER,
+          start as i32 * INSTANCE_STRIDE,
+          &view,
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Draw every instance with `program`, which must be in use.
+  pub fn draw(
+    &mut self,
+    context: &Gl,
+    instancing: &Instancing,
+    program: &InstancedProgram,
+  ) -> Result<(), JsValue> {
+    if self.is_empty() {
+      return Ok(());
+    }
+    self.flush(context)?;
+
+    // Per-vertex attributes.
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.positions));
+    context.vertex_attrib_pointer_with_i32(
+        program.vertex_position, 3, WebGlRenderingContext::FLOAT, false, 0, 0
+    );
+    context.enable_vertex_attrib_array(program.vertex_position);
+
+    if let Some(vertex_normal) = program.vertex_normal {
+      context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.normals));
+      context.vertex_attrib_pointer_with_i32(
+          vertex_normal, 3, WebGlRenderingContext::FLOAT, false, 0, 0
+      );
+      context.enable_vertex_attrib_array(vertex_normal);
+    }
+
+    // Per-instance attributes: four columns of the matrix, then colour.
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.instance_buffer));
+    for column in 0..4 {
+      let location = program.instance_matrix + column;
+      context.vertex_attrib_pointer_with_i32(
+          location, 4, WebGlRenderingContext::FLOAT, false, INSTANCE_STRIDE, column as i32 * 16
+      );
+      context.enable_vertex_attrib_array(location);
+      instancing.vertex_attrib_divisor(location, 1);
+    }
+    if let Some(instance_color) = program.instance_color {
+      context.vertex_attrib_pointer_with_i32(
+          instance_color, 4, WebGlRenderingContext::FLOAT, false, INSTANCE_STRIDE, 64
+      );
+      context.enable_vertex_attrib_array(instance_color);
+      instancing.vertex_attrib_divisor(instance_color, 1);
+    }
+    if let Some(instance_index) = program.instance_index {
+      self.bind_index_buffer(context)?;
+      context.vertex_attrib_pointer_with_i32(
+          instance_index, 1, WebGlRenderingContext::FLOAT, false, 0, 0
+      );
+      context.enable_vertex_attrib_array(instance_index);
+      instancing.vertex_attrib_divisor(instance_index, 1);
+    }
+
+    instancing.draw_arrays_instanced(
+        WebGlRenderingContext::TRIANGLES,
+        0,
+        self.vertex_count,
+        self.len() as i32,
+    );
+
+    // Divisors are global state, put them back so other draws don't
+    // pick up per-instance stepping on these locations.
+    for column in 0..4 {
+      instancing.vertex_attrib_divisor(program.instance_matrix + column, 0);
+      context.disable_vertex_attrib_array(program.instance_matrix + column);
+    }
+    for &location in program.instance_color.iter().chain(program.instance_index.iter()) {
+      instancing.vertex_attrib_divisor(location, 0);
+      context.disable_vertex_attrib_array(location);
+    }
+
+    Ok(())
+  }
+
+  fn bind_index_buffer(&mut self, context: &Gl) -> Result<(), JsValue> {
+    if let Some((ref buffer, size)) = self.index_buffer {
+      if size >= self.capacity {
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+        return Ok(());
+      }
+      context.delete_buffer(Some(buffer));
+    }
+    let indices: Vec<f32> = (0..self.capacity).map(|i| i as f32).collect();
+    // Leaves the new buffer bound.
+    let buffer = static_buffer(context, &indices)?;
+    self.index_buffer = Some((buffer, self.capacity));
+    Ok(())
+  }
+}
+
+fn static_buffer(context: &Gl, data: &[f32]) -> Result<WebGlBuffer, JsValue> {
+  let buffer = context.create_buffer().ok_or("failed to create buffer")?;
+  context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+  let view = f32_view(data)?;
+  context.buffer_data_with_array_buffer_view(
+      WebGlRenderingContext::ARRAY_BUFFER,
+      &view,
+      WebGlRenderingContext::STATIC_DRAW,
+  );
+  Ok(buffer)
+}
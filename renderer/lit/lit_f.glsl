@@ -0,0 +1,15 @@
+precision mediump float;
+
+uniform vec4 uColor;
+uniform vec4 uLightDirection;
+uniform vec4 uLightColor;
+uniform vec4 uAmbientColor;
+
+varying highp vec3 vNormal;
+
+void main(void) {
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  vec3 color = uColor.rgb * (uAmbientColor.rgb + uLightColor.rgb * diffuse);
+  gl_FragColor = vec4(color, uColor.a);
+}
@@ -0,0 +1,21 @@
+#version 300 es
+precision mediump float;
+
+layout(std140) uniform Light {
+  vec4 uLightDirection;
+  vec4 uLightColor;
+  vec4 uAmbientColor;
+};
+
+uniform vec4 uColor;
+
+in highp vec3 vNormal;
+
+out vec4 fragColor;
+
+void main(void) {
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  vec3 color = uColor.rgb * (uAmbientColor.rgb + uLightColor.rgb * diffuse);
+  fragColor = vec4(color, uColor.a);
+}
@@ -0,0 +1,14 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+varying highp vec3 vNormal;
+
+void main(void) {
+  vec4 world = uModelMatrix * aVertexPosition;
+  vNormal = (uModelMatrix * vec4(aVertexNormal, 0.0)).xyz;
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
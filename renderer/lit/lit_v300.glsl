@@ -0,0 +1,20 @@
+#version 300 es
+
+layout(std140) uniform Camera {
+  mat4 uViewMatrix;
+  mat4 uProjectionMatrix;
+  vec4 uCameraPosition;
+};
+
+in vec4 aVertexPosition;
+in vec3 aVertexNormal;
+
+uniform mat4 uModelMatrix;
+
+out highp vec3 vNormal;
+
+void main(void) {
+  vec4 world = uModelMatrix * aVertexPosition;
+  vNormal = mat3(uModelMatrix) * aVertexNormal;
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
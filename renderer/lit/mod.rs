@@ -0,0 +1,74 @@
+//! A flat coloured, diffuse lit material, written for both GLSL ES
+//! versions. Camera and light come from `SceneUniforms`.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlProgram,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::blend::BlendMode;
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program_variant, ShaderSource, ShaderVariants};
+use renderer::uniforms::SceneUniforms;
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("lit_v.glsl"),
+    fragment: include_str!("lit_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("lit_v300.glsl"),
+    fragment: include_str!("lit_f300.glsl"),
+  },
+};
+
+/// What `LitProgram` draws a mesh with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LitMaterial {
+  /// Linear RGBA; for `BlendMode::Premultiplied` the RGB is expected
+  /// to be multiplied by alpha already.
+  pub color: [f32; 4],
+  pub blend: BlendMode,
+}
+
+impl Default for LitMaterial {
+  fn default() -> LitMaterial {
+    LitMaterial { color: [1.0, 1.0, 1.0, 1.0], blend: BlendMode::Opaque }
+  }
+}
+
+pub struct LitProgram {
+  pub program: WebGlProgram,
+  model_matrix: Option<WebGlUniformLocation>,
+  color: Option<WebGlUniformLocation>,
+}
+
+impl LitProgram {
+  pub fn new(context: &Gl, uniforms: &SceneUniforms) -> Result<LitProgram, JsValue> {
+    let program = build_program_variant(context, &SHADERS)?;
+    uniforms.attach(context, &program);
+    Ok(LitProgram {
+      model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+      color: context.get_uniform_location(&program, "uColor"),
+      program,
+    })
+  }
+
+  /// Start drawing with this program.
+  pub fn begin(&self, context: &Gl, uniforms: &SceneUniforms) {
+    context.use_program(Some(&self.program));
+    uniforms.apply(context, &self.program);
+  }
+
+  /// Draw `mesh`; `begin` must have been called.
+  pub fn draw(&self, context: &Gl, mesh: &Mesh, model: &Mat4, color: [f32; 4]) {
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.model_matrix.as_ref(), false, &data);
+    context.uniform4f(self.color.as_ref(), color[0], color[1], color[2], color[3]);
+    mesh.draw(context);
+  }
+}
@@ -0,0 +1,187 @@
+//! Geometry uploaded to the GPU.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlRenderingContext,
+  WebGlVertexArrayObject,
+};
+
+use renderer::{f32_view, u16_view};
+use renderer::bounds::Bounds;
+use renderer::geometry::Geometry;
+use renderer::gl::Gl;
+
+/// Vertex buffers of one `Geometry` at the locations from
+/// `shader::ATTRIBUTE_LOCATIONS`.
+///
+/// On WebGL2 the attribute setup is recorded once into a vertex array
+/// object; on WebGL1 it is replayed on every draw.
+pub struct Mesh {
+  vao: Option<WebGlVertexArrayObject>,
+  /// `(location, components, buffer)` of every attribute.
+  attributes: Vec<(u32, i32, WebGlBuffer)>,
+  indices: WebGlBuffer,
+  index_count: i32,
+  /// Every triangle edge once, see `Geometry::edge_indices`.
+  edges: WebGlBuffer,
+  edge_count: i32,
+  /// Local space bounds of the positions, for culling and picking.
+  pub bounds: Bounds,
+}
+
+impl Mesh {
+  pub fn new(context: &Gl, geometry: &Geometry) -> Result<Mesh, JsValue> {
+    Mesh::with_attributes(context, geometry, &[])
+  }
+
+  /// A mesh with more per-vertex data next to the geometry, given as
+  /// `(location, components, data)`, e.g. skinning joints and weights.
+  pub fn with_attributes(
+    context: &Gl,
+    geometry: &Geometry,
+    extra: &[(u32, i32, &[f32])],
+  ) -> Result<Mesh, JsValue> {
+    let mut attributes = vec![(0, 3, array_buffer(context, &geometry.positions)?)];
+    if !geometry.normals.is_empty() {
+      attributes.push((1, 3, array_buffer(context, &geometry.normals)?));
+    }
+    if !geometry.uvs.is_empty() {
+      attributes.push((2, 2, array_buffer(context, &geometry.uvs)?));
+    }
+    if !geometry.tangents.is_empty() {
+      attributes.push((5, 4, array_buffer(context, &geometry.tangents)?));
+    }
+    for &(location, components, data) in extra {
+      if data.len() != geometry.vertex_count() * components as usize {
+        return Err(format!("attribute {} has {} floats for {} vertices", location, data.len(), geometry.vertex_count()).into());
+      }
+      attributes.push((location, components, array_buffer(context, data)?));
+    }
+
+    let indices = element_buffer(context, &geometry.indices)?;
+    let edge_indices = geometry.edge_indices();
+    let edges = element_buffer(context, &edge_indices)?;
+
+    let mut mesh = Mesh {
+      vao: None,
+      attributes,
+      indices,
+      index_count: geometry.indices.len() as i32,
+      edges,
+      edge_count: edge_indices.len() as i32,
+      bounds: geometry.bounds(),
+    };
+
+    if let Some(vao) = context.create_vertex_array() {
+      context.bind_vertex_array(Some(&vao));
+      mesh.set_attributes(context);
+      context.bind_vertex_array(None);
+      mesh.vao = Some(vao);
+    }
+
+    Ok(mesh)
+  }
+
+  fn set_attributes(&self, context: &Gl) {
+    for &(location, components, ref buffer) in &self.attributes {
+      context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+      context.vertex_attrib_pointer_with_i32(
+          location,
+          components,
+          WebGlRenderingContext::FLOAT,
+          false,
+          0,
+          0
+      );
+      context.enable_vertex_attrib_array(location);
+    }
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.indices));
+  }
+
+  pub fn bind(&self, context: &Gl) {
+    match self.vao {
+      Some(ref vao) => context.bind_vertex_array(Some(vao)),
+      None => self.set_attributes(context),
+    }
+  }
+
+  /// Without a vertex array object the optional attributes are
+  /// disabled again, so a later mesh lacking them doesn't read these
+  /// buffers.
+  pub fn unbind(&self, context: &Gl) {
+    if self.vao.is_some() {
+      context.bind_vertex_array(None);
+    } else {
+      for &(location, _, _) in &self.attributes[1..] {
+        context.disable_vertex_attrib_array(location);
+      }
+    }
+  }
+
+  pub fn triangle_count(&self) -> u32 {
+    self.index_count as u32 / 3
+  }
+
+  /// Draw every triangle with the program currently in use.
+  pub fn draw(&self, context: &Gl) {
+    self.bind(context);
+    context.draw_elements_with_i32(
+        WebGlRenderingContext::TRIANGLES,
+        self.index_count,
+        WebGlRenderingContext::UNSIGNED_SHORT,
+        0,
+    );
+    self.unbind(context);
+  }
+
+  /// Draw every triangle edge as a line with the program currently in
+  /// use, for wireframes.
+  pub fn draw_edges(&self, context: &Gl) {
+    self.bind(context);
+    // Part of the vertex array object's state, so put the triangles
+    // back before unbinding.
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.edges));
+    context.draw_elements_with_i32(
+        WebGlRenderingContext::LINES,
+        self.edge_count,
+        WebGlRenderingContext::UNSIGNED_SHORT,
+        0,
+    );
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.indices));
+    self.unbind(context);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_vertex_array(self.vao.as_ref());
+    for &(_, _, ref buffer) in &self.attributes {
+      context.delete_buffer(Some(buffer));
+    }
+    context.delete_buffer(Some(&self.indices));
+    context.delete_buffer(Some(&self.edges));
+  }
+}
+
+fn array_buffer(context: &Gl, data: &[f32]) -> Result<WebGlBuffer, JsValue> {
+  let buffer = context.create_buffer().ok_or("failed to create buffer")?;
+  context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+  let array = f32_view(data)?;
+  context.buffer_data_with_array_buffer_view(
+      WebGlRenderingContext::ARRAY_BUFFER,
+      &array,
+      WebGlRenderingContext::STATIC_DRAW,
+  );
+  Ok(buffer)
+}
+
+fn element_buffer(context: &Gl, data: &[u16]) -> Result<WebGlBuffer, JsValue> {
+  let buffer = context.create_buffer().ok_or("failed to create buffer")?;
+  context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+  let array = u16_view(data)?;
+  context.buffer_data_with_array_buffer_view(
+      WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+      &array,
+      WebGlRenderingContext::STATIC_DRAW,
+  );
+  Ok(buffer)
+}
@@ -0,0 +1,87 @@
+//! Rendering building blocks shared by the demos that go beyond the
+//! MDN tutorial samples.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+
+use js_sys::WebAssembly;
+
+pub mod gl;
+pub mod state;
+pub mod resources;
+pub mod contextloss;
+pub mod capabilities;
+pub mod shader;
+pub mod target;
+pub mod texture;
+pub mod compressed;
+pub mod image;
+pub mod bounds;
+pub mod debug;
+pub mod geometry;
+pub mod tangents;
+pub mod mesh;
+pub mod uniforms;
+pub mod scene;
+pub mod stats;
+pub mod blend;
+pub mod queue;
+pub mod picking;
+pub mod idpick;
+pub mod text;
+pub mod sprites;
+pub mod atlas;
+pub mod random;
+pub mod particles;
+pub mod skinning;
+pub mod timeline;
+pub mod cubemap;
+pub mod pbr;
+pub mod lit;
+pub mod viewmode;
+pub mod terrain;
+pub mod postfx;
+pub mod instancing;
+
+/// Create a `Float32Array` view over `data` inside the wasm memory.
+///
+/// The view aliases Rust memory, so hand it to WebGL right away and
+/// don't allocate in between.
+pub fn f32_view(data: &[f32]) -> Result<js_sys::Float32Array, JsValue> {
+  let memory_buffer = wasm_bindgen::memory()
+      .dyn_into::<WebAssembly::Memory>()?
+      .buffer();
+  let location = data.as_ptr() as u32 / 4;
+  Ok(js_sys::Float32Array::new(&memory_buffer)
+      .subarray(location, location + data.len() as u32))
+}
+
+/// Create a `Uint8Array` view over `data` inside the wasm memory.
+pub fn u8_view(data: &[u8]) -> Result<js_sys::Uint8Array, JsValue> {
+  let memory_buffer = wasm_bindgen::memory()
+      .dyn_into::<WebAssembly::Memory>()?
+      .buffer();
+  let location = data.as_ptr() as u32;
+  Ok(js_sys::Uint8Array::new(&memory_buffer)
+      .subarray(location, location + data.len() as u32))
+}
+
+/// Create a `Uint32Array` view over `data` inside the wasm memory.
+pub fn u32_view(data: &[u32]) -> Result<js_sys::Uint32Array, JsValue> {
+  let memory_buffer = wasm_bindgen::memory()
+      .dyn_into::<WebAssembly::Memory>()?
+      .buffer();
+  let location = data.as_ptr() as u32 / 4;
+  Ok(js_sys::Uint32Array::new(&memory_buffer)
+      .subarray(location, location + data.len() as u32))
+}
+
+/// Create a `Uint16Array` view over `data` inside the wasm memory.
+pub fn u16_view(data: &[u16]) -> Result<js_sys::Uint16Array, JsValue> {
+  let memory_buffer = wasm_bindgen::memory()
+      .dyn_into::<WebAssembly::Memory>()?
+      .buffer();
+  let location = data.as_ptr() as u32 / 2;
+  Ok(js_sys::Uint16Array::new(&memory_buffer)
+      .subarray(location, location + data.len() as u32))
+}
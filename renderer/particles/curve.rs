@@ -0,0 +1,64 @@
+//! Values keyed over a particle's normalized age.
+
+/// Anything a curve can blend between.
+pub trait Lerp: Copy {
+  fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+  fn lerp(&self, other: &f32, t: f32) -> f32 {
+    self + (other - self) * t
+  }
+}
+
+impl Lerp for [f32; 4] {
+  fn lerp(&self, other: &[f32; 4], t: f32) -> [f32; 4] {
+    [
+      self[0].lerp(&other[0], t),
+      self[1].lerp(&other[1], t),
+      self[2].lerp(&other[2], t),
+      self[3].lerp(&other[3], t),
+    ]
+  }
+}
+
+/// Piecewise linear keys `(t, value)` with `t` from 0 (birth) to 1
+/// (death). Before the first key and after the last the end values
+/// hold.
+#[derive(Clone, Debug)]
+pub struct Curve<T: Lerp> {
+  keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+  pub fn constant(value: T) -> Curve<T> {
+    Curve { keys: vec![(0.0, value)] }
+  }
+
+  pub fn linear(start: T, end: T) -> Curve<T> {
+    Curve { keys: vec![(0.0, start), (1.0, end)] }
+  }
+
+  /// Keys in any order; panics on an empty list.
+  pub fn new(mut keys: Vec<(f32, T)>) -> Curve<T> {
+    assert!(!keys.is_empty(), "a curve needs at least one key");
+    keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    Curve { keys }
+  }
+
+  pub fn sample(&self, t: f32) -> T {
+    let first = &self.keys[0];
+    if t <= first.0 {
+      return first.1;
+    }
+    for pair in self.keys.windows(2) {
+      let (t0, ref v0) = pair[0];
+      let (t1, ref v1) = pair[1];
+      if t <= t1 {
+        let span = t1 - t0;
+        return if span > 0.0 { v0.lerp(v1, (t - t0) / span) } else { *v1 };
+      }
+    }
+    self.keys[self.keys.len() - 1].1
+  }
+}
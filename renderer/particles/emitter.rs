@@ -0,0 +1,357 @@
+//! Spawning and simulating particles on the CPU.
+
+use glm::{Mat4, Vec3};
+
+use renderer::bounds::transform_point;
+use renderer::geometry::Geometry;
+use renderer::particles::curve::Curve;
+use renderer::random::Rng;
+
+/// Where particles are born and which way they start moving.
+#[derive(Clone, Debug)]
+pub enum Shape {
+  /// From the origin in every direction.
+  Point,
+  /// Inside a ball, moving outwards.
+  Sphere { radius: f32 },
+  /// From a disc of `radius` around the origin, moving up `+Y` within
+  /// `angle` radians of the axis.
+  Cone { angle: f32, radius: f32 },
+  /// On the triangles of a mesh, evenly by area, moving along the face
+  /// normal. Build with `Shape::mesh_surface`.
+  MeshSurface {
+    triangles: Vec<[Vec3; 3]>,
+    /// Running total of triangle areas, for picking by area.
+    cumulative_area: Vec<f32>,
+  },
+}
+
+impl Shape {
+  pub fn mesh_surface(geometry: &Geometry) -> Shape {
+    let mut triangles = Vec::with_capacity(geometry.triangle_count());
+    let mut cumulative_area = Vec::with_capacity(geometry.triangle_count());
+    let mut total = 0.0;
+    for corners in geometry.indices.chunks(3) {
+      if corners.len() < 3 {
+        break;
+      }
+      let vertex = |i: u16| {
+        let p = geometry.position(i as usize);
+        glm::vec3(p[0], p[1], p[2])
+      };
+      let triangle = [vertex(corners[0]), vertex(corners[1]), vertex(corners[2])];
+      let edge1: Vec3 = triangle[1] - triangle[0];
+      total += edge1.cross(&(triangle[2] - triangle[0])).norm() * 0.5;
+      triangles.push(triangle);
+      cumulative_area.push(total);
+    }
+    Shape::MeshSurface { triangles, cumulative_area }
+  }
+
+  /// A local position and unit direction.
+  fn sample(&self, rng: &mut Rng) -> (Vec3, Vec3) {
+    match *self {
+      Shape::Point => (Vec3::zeros(), rng.unit_vector()),
+      Shape::Sphere { radius } => {
+        let direction = rng.unit_vector();
+        (direction * radius * rng.next_f32().cbrt(), direction)
+      },
+      Shape::Cone { angle, radius } => {
+        let around = rng.range(0.0, 2.0 * std::f32::consts::PI);
+        // Uniform over the spherical cap, not bunched at the axis.
+        let cos_tilt = rng.range(angle.cos(), 1.0);
+        let sin_tilt = (1.0 - cos_tilt * cos_tilt).max(0.0).sqrt();
+        let direction = glm::vec3(sin_tilt * around.cos(), cos_tilt, sin_tilt * around.sin());
+        let disc_angle = rng.range(0.0, 2.0 * std::f32::consts::PI);
+        let disc_radius = radius * rng.next_f32().sqrt();
+        (glm::vec3(disc_angle.cos() * disc_radius, 0.0, disc_angle.sin() * disc_radius), direction)
+      },
+      Shape::MeshSurface { ref triangles, ref cumulative_area } => {
+        let total = match cumulative_area.last() {
+          Some(&total) if total > 0.0 => total,
+          _ => return (Vec3::zeros(), rng.unit_vector()),
+        };
+        let target = rng.next_f32() * total;
+        let index = match cumulative_area.binary_search_by(|area| {
+          area.partial_cmp(&target).unwrap_or(std::cmp::Ordering::Less)
+        }) {
+          Ok(index) | Err(index) => index.min(triangles.len() - 1),
+        };
+        let [a, b, c] = triangles[index];
+        // Uniform barycentric sample.
+        let r1 = rng.next_f32().sqrt();
+        let r2 = rng.next_f32();
+        let position = a * (1.0 - r1) + b * (r1 * (1.0 - r2)) + c * (r1 * r2);
+        let edge1: Vec3 = b - a;
+        let normal = edge1.cross(&(c - a));
+        let length = normal.norm();
+        let direction = if length > 0.0 { normal / length } else { rng.unit_vector() };
+        (position, direction)
+      },
+    }
+  }
+}
+
+/// A random scalar.
+#[derive(Clone, Copy, Debug)]
+pub enum Distribution {
+  Constant(f32),
+  Uniform { min: f32, max: f32 },
+  Normal { mean: f32, deviation: f32 },
+}
+
+impl Distribution {
+  pub fn sample(&self, rng: &mut Rng) -> f32 {
+    match *self {
+      Distribution::Constant(value) => value,
+      Distribution::Uniform { min, max } => rng.range(min, max),
+      Distribution::Normal { mean, deviation } => mean + deviation * rng.normal(),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmitterConfig {
+  pub shape: Shape,
+  /// Particles per second.
+  pub rate: f32,
+  /// Seconds.
+  pub lifetime: Distribution,
+  /// Initial speed along the shape's direction.
+  pub speed: Distribution,
+  /// Added to every particle's starting velocity, world space.
+  pub velocity: Vec3,
+  /// World space acceleration.
+  pub gravity: Vec3,
+  /// Fraction of velocity lost per second, roughly; applied as
+  /// `exp(-drag * dt)` so it doesn't depend on the step size.
+  pub drag: f32,
+  /// World units over normalized age.
+  pub size: Curve<f32>,
+  /// RGBA over normalized age.
+  pub color: Curve<[f32; 4]>,
+  /// Spawning pauses while this many particles are alive.
+  pub max_particles: usize,
+  pub seed: u64,
+}
+
+impl Default for EmitterConfig {
+  fn default() -> EmitterConfig {
+    EmitterConfig {
+      shape: Shape::Point,
+      rate: 50.0,
+      lifetime: Distribution::Constant(2.0),
+      speed: Distribution::Constant(1.0),
+      velocity: Vec3::zeros(),
+      gravity: Vec3::zeros(),
+      drag: 0.0,
+      size: Curve::constant(0.1),
+      color: Curve::constant([1.0, 1.0, 1.0, 1.0]),
+      max_particles: 1000,
+      seed: 1,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+  pub position: Vec3,
+  pub velocity: Vec3,
+  /// Seconds alive.
+  pub age: f32,
+  pub lifetime: f32,
+  /// Sampled from the config curves on each update.
+  pub size: f32,
+  pub color: [f32; 4],
+}
+
+impl Particle {
+  /// 0 at birth, 1 at death.
+  pub fn normalized_age(&self) -> f32 {
+    if self.lifetime > 0.0 { (self.age / self.lifetime).min(1.0) } else { 1.0 }
+  }
+}
+
+/// One emitter and its live particles.
+pub struct Emitter {
+  pub config: EmitterConfig,
+  /// Places the shape in the world.
+  pub transform: Mat4,
+  /// Turn spawning off to let the live particles die out.
+  pub emitting: bool,
+  particles: Vec<Particle>,
+  rng: Rng,
+  /// Fractional particles owed from previous steps.
+  pending: f32,
+}
+
+impl Emitter {
+  pub fn new(config: EmitterConfig) -> Emitter {
+    Emitter {
+      rng: Rng::new(config.seed),
+      config,
+      transform: Mat4::identity(),
+      emitting: true,
+      particles: Vec::new(),
+      pending: 0.0,
+    }
+  }
+
+  pub fn particles(&self) -> &[Particle] {
+    &self.particles
+  }
+
+  /// Start over from the seed, as if just created.
+  pub fn reset(&mut self) {
+    self.rng = Rng::new(self.config.seed);
+    self.particles.clear();
+    self.pending = 0.0;
+  }
+
+  /// Spawn `count` particles right away, up to `max_particles`.
+  pub fn burst(&mut self, count: usize) {
+    for _ in 0..count {
+      if self.particles.len() >= self.config.max_particles {
+        break;
+      }
+      let particle = self.spawn();
+      self.particles.push(particle);
+    }
+  }
+
+  fn spawn(&mut self) -> Particle {
+    let (position, direction) = self.config.shape.sample(&mut self.rng);
+    let speed = self.config.speed.sample(&mut self.rng);
+    let lifetime = self.config.lifetime.sample(&mut self.rng).max(0.0);
+    let world_position = transform_point(&self.transform, &position);
+    let rotated = self.transform * glm::vec4(direction.x, direction.y, direction.z, 0.0);
+    let world_direction = glm::vec3(rotated.x, rotated.y, rotated.z);
+    Particle {
+      position: world_position,
+      velocity: world_direction * speed + self.config.velocity,
+      age: 0.0,
+      lifetime,
+      size: self.config.size.sample(0.0),
+      color: self.config.color.sample(0.0),
+    }
+  }
+
+  /// Advance every particle by `dt` seconds, retire the dead ones and
+  /// spawn new ones at `rate`.
+  pub fn update(&mut self, dt: f32) {
+    let damping = (-self.config.drag * dt).exp();
+    let gravity = self.config.gravity * dt;
+    let mut i = 0;
+    while i < self.particles.len() {
+      let particle = &mut self.particles[i];
+      particle.age += dt;
+      if particle.age >= particle.lifetime {
+        // Order doesn't matter, the renderer sorts when it needs to.
+        self.particles.swap_remove(i);
+        continue;
+      }
+      particle.velocity = (particle.velocity + gravity) * damping;
+      particle.position += particle.velocity * dt;
+      let t = particle.normalized_age();
+      particle.size = self.config.size.sample(t);
+      particle.color = self.config.color.sample(t);
+      i += 1;
+    }
+
+    if self.emitting {
+      self.pending += self.config.rate * dt;
+      let count = self.pending.floor();
+      self.pending -= count;
+      self.burst(count as usize);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(seed: u64) -> EmitterConfig {
+    EmitterConfig {
+      shape: Shape::Cone { angle: 0.5, radius: 0.2 },
+      rate: 120.0,
+      lifetime: Distribution::Uniform { min: 0.5, max: 1.0 },
+      speed: Distribution::Normal { mean: 2.0, deviation: 0.5 },
+      gravity: glm::vec3(0.0, -9.8, 0.0),
+      drag: 0.3,
+      seed,
+      ..EmitterConfig::default()
+    }
+  }
+
+  fn run(emitter: &mut Emitter, steps: usize) {
+    for _ in 0..steps {
+      emitter.update(1.0 / 60.0);
+    }
+  }
+
+  /// Every particle's state, bit for bit.
+  fn state(emitter: &Emitter) -> Vec<u32> {
+    emitter.particles().iter().flat_map(|p| {
+      vec![
+        p.position.x, p.position.y, p.position.z, p.velocity.x, p.velocity.y, p.velocity.z,
+        p.age, p.lifetime, p.size,
+      ].into_iter().map(f32::to_bits).collect::<Vec<u32>>()
+    }).collect()
+  }
+
+  #[test]
+  fn same_seed_replays() {
+    let (mut a, mut b) = (Emitter::new(config(9)), Emitter::new(config(9)));
+    run(&mut a, 200);
+    run(&mut b, 200);
+    assert!(!a.particles().is_empty());
+    assert_eq!(state(&a), state(&b));
+
+    a.reset();
+    run(&mut a, 200);
+    assert_eq!(state(&a), state(&b));
+  }
+
+  #[test]
+  fn different_seeds_diverge() {
+    let (mut a, mut b) = (Emitter::new(config(9)), Emitter::new(config(10)));
+    run(&mut a, 200);
+    run(&mut b, 200);
+    assert_ne!(state(&a), state(&b));
+  }
+
+  #[test]
+  fn dead_particles_are_recycled() {
+    let mut emitter = Emitter::new(EmitterConfig {
+      rate: 100.0,
+      lifetime: Distribution::Constant(0.5),
+      ..EmitterConfig::default()
+    });
+    let mut capacity = 0;
+    for step in 0..1200 {
+      emitter.update(1.0 / 60.0);
+      // 100 a second living half a second, give or take one step.
+      assert!(emitter.particles().len() <= 52, "{} alive", emitter.particles().len());
+      assert!(emitter.particles().iter().all(|particle| particle.age < particle.lifetime));
+      if step == 120 {
+        capacity = emitter.particles.capacity();
+      }
+    }
+    assert!(emitter.particles().len() >= 48);
+    assert_eq!(emitter.particles.capacity(), capacity);
+
+    emitter.emitting = false;
+    run(&mut emitter, 31);
+    assert!(emitter.particles().is_empty());
+  }
+
+  #[test]
+  fn caps_live_particles() {
+    let mut emitter = Emitter::new(EmitterConfig { rate: 10_000.0, max_particles: 64, ..config(3) });
+    run(&mut emitter, 30);
+    assert_eq!(emitter.particles().len(), 64);
+    emitter.burst(10);
+    assert_eq!(emitter.particles().len(), 64);
+  }
+}
@@ -0,0 +1,252 @@
+//! Particle effects: emitters simulated on the CPU with a seeded
+//! random number generator, drawn as point sprites or camera facing
+//! quads.
+//!
+//! Points are the cheapest but are capped by the context's
+//! `aliased_point_size_range`; quads have no size limit.
+
+pub mod curve;
+pub mod emitter;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use renderer::{f32_view, u16_view};
+use renderer::gl::Gl;
+use renderer::particles::emitter::{Emitter, Particle};
+use renderer::shader::build_program;
+use renderer::uniforms::Camera;
+
+pub static POINT_VERTEX_SHADER: &'static str = include_str!("point_v.glsl");
+pub static POINT_FRAGMENT_SHADER: &'static str = include_str!("point_f.glsl");
+pub static QUAD_VERTEX_SHADER: &'static str = include_str!("quad_v.glsl");
+pub static QUAD_FRAGMENT_SHADER: &'static str = include_str!("quad_f.glsl");
+
+/// Floats per point: position, size, colour.
+const POINT_FLOATS: usize = 3 + 1 + 4;
+/// Floats per quad corner: position, corner, size, colour.
+const QUAD_FLOATS: usize = 3 + 2 + 1 + 4;
+/// Four vertices per quad and 16 bit indices.
+const MAX_QUADS: usize = 65536 / 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+  Points,
+  Quads,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+  /// Order independent, for fire, sparks and glows.
+  Additive,
+  /// Sorted back to front, for smoke and dust.
+  Alpha,
+}
+
+struct ParticleProgram {
+  program: WebGlProgram,
+  size: u32,
+  color: u32,
+  corner: Option<u32>,
+  view_matrix: Option<WebGlUniformLocation>,
+  projection_matrix: Option<WebGlUniformLocation>,
+  point_scale: Option<WebGlUniformLocation>,
+}
+
+impl ParticleProgram {
+  fn new(context: &Gl, vertex_source: &str, fragment_source: &str) -> Result<ParticleProgram, JsValue> {
+    let program = build_program(context, vertex_source, fragment_source)?;
+    let corner = context.get_attrib_location(&program, "aCorner");
+    Ok(ParticleProgram {
+      size: context.get_attrib_location(&program, "aSize") as u32,
+      color: context.get_attrib_location(&program, "aColor") as u32,
+      corner: if corner < 0 { None } else { Some(corner as u32) },
+      view_matrix: context.get_uniform_location(&program, "uViewMatrix"),
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      point_scale: context.get_uniform_location(&program, "uPointScale"),
+      program,
+    })
+  }
+
+  fn begin(&self, context: &Gl, camera: &Camera) {
+    context.use_program(Some(&self.program));
+    let data: JsValue = JsValue::from_serde(&camera.view).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.view_matrix.as_ref(), false, &data);
+    let data: JsValue = JsValue::from_serde(&camera.projection).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.projection_matrix.as_ref(), false, &data);
+    // projection[1][1] is 1 / tan(fov / 2).
+    let height = context.drawing_buffer_height() as f32;
+    context.uniform1f(self.point_scale.as_ref(), camera.projection[(1, 1)] * height * 0.5);
+  }
+
+  /// Point `location`s at interleaved floats in the bound buffer.
+  fn set_attributes(&self, context: &Gl, layout: &[(u32, i32, i32)], floats: usize) {
+    for &(location, components, offset) in layout {
+      context.vertex_attrib_pointer_with_i32(
+          location, components, WebGlRenderingContext::FLOAT, false, (floats * 4) as i32, offset * 4
+      );
+      context.enable_vertex_attrib_array(location);
+    }
+  }
+
+  fn disable_attributes(&self, context: &Gl) {
+    context.disable_vertex_attrib_array(self.size);
+    context.disable_vertex_attrib_array(self.color);
+    if let Some(corner) = self.corner {
+      context.disable_vertex_attrib_array(corner);
+    }
+  }
+}
+
+/// Draws emitters; one renderer serves any number of them.
+pub struct ParticleRenderer {
+  points: ParticleProgram,
+  quads: ParticleProgram,
+  vertices: Vec<f32>,
+  vertex_buffer: WebGlBuffer,
+  index_buffer: WebGlBuffer,
+}
+
+impl ParticleRenderer {
+  pub fn new(context: &Gl) -> Result<ParticleRenderer, JsValue> {
+    let mut indices = Vec::with_capacity(MAX_QUADS * 6);
+    for i in 0..MAX_QUADS {
+      let base = (i * 4) as u16;
+      indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    let index_buffer = context.create_buffer().ok_or("failed to create buffer")?;
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
+    let view = u16_view(&indices)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STATIC_DRAW,
+    );
+
+    Ok(ParticleRenderer {
+      points: ParticleProgram::new(context, POINT_VERTEX_SHADER, POINT_FRAGMENT_SHADER)?,
+      quads: ParticleProgram::new(context, QUAD_VERTEX_SHADER, QUAD_FRAGMENT_SHADER)?,
+      vertices: Vec::new(),
+      vertex_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+      index_buffer,
+    })
+  }
+
+  /// Draw the live particles of `emitter`. Depth tests against the
+  /// scene without writing depth, and leaves blending off.
+  pub fn draw(
+    &mut self,
+    context: &Gl,
+    emitter: &Emitter,
+    camera: &Camera,
+    mode: RenderMode,
+    blend: Blend,
+  ) -> Result<(), JsValue> {
+    let mut particles: Vec<&Particle> = emitter.particles().iter().collect();
+    if particles.is_empty() {
+      return Ok(());
+    }
+    if blend == Blend::Alpha {
+      // Furthest first: view space z is negative in front of the camera.
+      let depth = |particle: &Particle| {
+        let p = &particle.position;
+        camera.view[(2, 0)] * p.x + camera.view[(2, 1)] * p.y + camera.view[(2, 2)] * p.z + camera.view[(2, 3)]
+      };
+      particles.sort_by(|a, b| depth(a).partial_cmp(&depth(b)).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    context.enable(WebGlRenderingContext::BLEND);
+    match blend {
+      Blend::Additive => context.blend_func(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE),
+      Blend::Alpha => context.blend_func(
+          WebGlRenderingContext::SRC_ALPHA,
+          WebGlRenderingContext::ONE_MINUS_SRC_ALPHA,
+      ),
+    }
+    context.enable(WebGlRenderingContext::DEPTH_TEST);
+    context.depth_mask(false);
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.vertex_buffer));
+
+    match mode {
+      RenderMode::Points => self.draw_points(context, &particles, camera)?,
+      RenderMode::Quads => {
+        for chunk in particles.chunks(MAX_QUADS) {
+          self.draw_quads(context, chunk, camera)?;
+        }
+      },
+    }
+
+    context.depth_mask(true);
+    context.disable(WebGlRenderingContext::BLEND);
+    Ok(())
+  }
+
+  fn draw_points(&mut self, context: &Gl, particles: &[&Particle], camera: &Camera) -> Result<(), JsValue> {
+    self.vertices.clear();
+    for particle in particles {
+      let p = &particle.position;
+      self.vertices.extend_from_slice(&[p.x, p.y, p.z, particle.size]);
+      self.vertices.extend_from_slice(&particle.color);
+    }
+    self.upload(context)?;
+
+    let program = &self.points;
+    program.begin(context, camera);
+    program.set_attributes(context, &[(0, 3, 0), (program.size, 1, 3), (program.color, 4, 4)], POINT_FLOATS);
+    context.draw_arrays(WebGlRenderingContext::POINTS, 0, particles.len() as i32);
+    program.disable_attributes(context);
+    Ok(())
+  }
+
+  fn draw_quads(&mut self, context: &Gl, particles: &[&Particle], camera: &Camera) -> Result<(), JsValue> {
+    self.vertices.clear();
+    for particle in particles {
+      let p = &particle.position;
+      for corner in &[[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]] {
+        self.vertices.extend_from_slice(&[p.x, p.y, p.z, corner[0], corner[1], particle.size]);
+        self.vertices.extend_from_slice(&particle.color);
+      }
+    }
+    self.upload(context)?;
+
+    let program = &self.quads;
+    let corner = program.corner.ok_or("quad shader has no aCorner")?;
+    program.begin(context, camera);
+    program.set_attributes(
+        context,
+        &[(0, 3, 0), (corner, 2, 3), (program.size, 1, 5), (program.color, 4, 6)],
+        QUAD_FLOATS,
+    );
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
+    context.draw_elements_with_i32(
+        WebGlRenderingContext::TRIANGLES,
+        (particles.len() * 6) as i32,
+        WebGlRenderingContext::UNSIGNED_SHORT,
+        0,
+    );
+    program.disable_attributes(context);
+    Ok(())
+  }
+
+  fn upload(&self, context: &Gl) -> Result<(), JsValue> {
+    let view = f32_view(&self.vertices)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STREAM_DRAW,
+    );
+    Ok(())
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_program(Some(&self.points.program));
+    context.delete_program(Some(&self.quads.program));
+    context.delete_buffer(Some(&self.vertex_buffer));
+    context.delete_buffer(Some(&self.index_buffer));
+  }
+}
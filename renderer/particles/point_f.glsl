@@ -0,0 +1,13 @@
+precision mediump float;
+
+varying lowp vec4 vColor;
+
+void main(void) {
+  // A soft disc over the square point.
+  float distance = length(gl_PointCoord - 0.5) * 2.0;
+  float alpha = 1.0 - smoothstep(0.5, 1.0, distance);
+  if (alpha <= 0.0) {
+    discard;
+  }
+  gl_FragColor = vec4(vColor.rgb, vColor.a * alpha);
+}
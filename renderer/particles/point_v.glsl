@@ -0,0 +1,18 @@
+attribute vec4 aVertexPosition;
+attribute float aSize;
+attribute vec4 aColor;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+// Pixels per world unit at distance 1, turns a world size into a
+// point size.
+uniform float uPointScale;
+
+varying lowp vec4 vColor;
+
+void main(void) {
+  vec4 position = uViewMatrix * vec4(aVertexPosition.xyz, 1.0);
+  gl_Position = uProjectionMatrix * position;
+  gl_PointSize = aSize * uPointScale / max(-position.z, 0.001);
+  vColor = aColor;
+}
@@ -0,0 +1,21 @@
+// Particle center, the same for all four corners.
+attribute vec4 aVertexPosition;
+// -0.5 to 0.5 on both axes.
+attribute vec2 aCorner;
+attribute float aSize;
+attribute vec4 aColor;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+varying mediump vec2 vCorner;
+varying lowp vec4 vColor;
+
+void main(void) {
+  // Expand in view space so the quad faces the camera.
+  vec4 position = uViewMatrix * vec4(aVertexPosition.xyz, 1.0);
+  position.xy += aCorner * aSize;
+  gl_Position = uProjectionMatrix * position;
+  vCorner = aCorner;
+  vColor = aColor;
+}
@@ -0,0 +1,44 @@
+const float PI = 3.14159265359;
+
+float distributionGGX(float nDotH, float roughness) {
+  float a = roughness * roughness;
+  float a2 = a * a;
+  float d = nDotH * nDotH * (a2 - 1.0) + 1.0;
+  return a2 / (PI * d * d);
+}
+
+float geometrySchlickGGX(float nDotX, float roughness) {
+  // k for direct lighting.
+  float r = roughness + 1.0;
+  float k = r * r / 8.0;
+  return nDotX / (nDotX * (1.0 - k) + k);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 f0) {
+  return f0 + (1.0 - f0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Fresnel for ambient light, where rough surfaces reflect less at
+// grazing angles.
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 f0, float roughness) {
+  return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Cook-Torrance specular plus Lambert diffuse for one light.
+vec3 directLight(vec3 normal, vec3 view, vec3 light, vec3 radiance, vec3 baseColor, float metallic, float roughness, vec3 f0) {
+  vec3 halfway = normalize(view + light);
+  float nDotL = max(dot(normal, light), 0.0);
+  float nDotV = max(dot(normal, view), 0.0);
+  vec3 fresnel = fresnelSchlick(max(dot(halfway, view), 0.0), f0);
+  float d = distributionGGX(max(dot(normal, halfway), 0.0), roughness);
+  float g = geometrySchlickGGX(nDotV, roughness) * geometrySchlickGGX(nDotL, roughness);
+  vec3 specular = d * g * fresnel / (4.0 * nDotV * nDotL + 0.0001);
+  vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * baseColor / PI;
+  return (diffuse + specular) * radiance * nDotL;
+}
+
+// Reinhard tone mapping and gamma encoding.
+vec3 toDisplay(vec3 color) {
+  color = color / (color + 1.0);
+  return pow(color, vec3(1.0 / 2.2));
+}
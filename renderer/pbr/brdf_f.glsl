@@ -0,0 +1,40 @@
+precision highp float;
+
+varying vec2 vTextureCoord;
+
+const int SAMPLE_COUNT = 256;
+
+#include sampling
+
+float geometrySchlickGGX(float nDotV, float roughness) {
+  // k for image based lighting.
+  float k = roughness * roughness / 2.0;
+  return nDotV / (nDotV * (1.0 - k) + k);
+}
+
+// Scale and bias to F0 of the specular integral, for n.v across and
+// roughness up.
+void main(void) {
+  float nDotV = max(vTextureCoord.x, 0.001);
+  float roughness = vTextureCoord.y;
+  vec3 view = vec3(sqrt(1.0 - nDotV * nDotV), 0.0, nDotV);
+  vec3 normal = vec3(0.0, 0.0, 1.0);
+  float scale = 0.0;
+  float bias = 0.0;
+  for (int i = 0; i < SAMPLE_COUNT; i++) {
+    vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radicalInverse(float(i)));
+    vec3 halfway = importanceSampleGGX(xi, normal, roughness);
+    vec3 light = 2.0 * dot(view, halfway) * halfway - view;
+    float nDotL = max(light.z, 0.0);
+    float nDotH = max(halfway.z, 0.0);
+    float vDotH = max(dot(view, halfway), 0.0);
+    if (nDotL > 0.0) {
+      float g = geometrySchlickGGX(nDotV, roughness) * geometrySchlickGGX(nDotL, roughness);
+      float visibility = g * vDotH / (nDotH * nDotV);
+      float fresnel = pow(1.0 - vDotH, 5.0);
+      scale += (1.0 - fresnel) * visibility;
+      bias += fresnel * visibility;
+    }
+  }
+  gl_FragColor = vec4(scale / float(SAMPLE_COUNT), bias / float(SAMPLE_COUNT), 0.0, 1.0);
+}
@@ -0,0 +1,32 @@
+precision highp float;
+
+uniform samplerCube uEnvironment;
+
+varying vec3 vDirection;
+
+const float PI = 3.14159265359;
+const int PHI_STEPS = 32;
+const int THETA_STEPS = 8;
+
+void main(void) {
+  vec3 normal = normalize(vDirection);
+  vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+  vec3 right = normalize(cross(up, normal));
+  up = cross(normal, right);
+
+  // Cosine weighted integral over the hemisphere around the normal.
+  vec3 irradiance = vec3(0.0);
+  for (int i = 0; i < PHI_STEPS; i++) {
+    float phi = (float(i) + 0.5) * 2.0 * PI / float(PHI_STEPS);
+    for (int j = 0; j < THETA_STEPS; j++) {
+      float theta = (float(j) + 0.5) * 0.5 * PI / float(THETA_STEPS);
+      vec3 local = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+      vec3 direction = local.x * right + local.y * up + local.z * normal;
+      // The environment is sRGB, the result linear.
+      vec3 radiance = pow(textureCube(uEnvironment, direction).rgb, vec3(2.2));
+      irradiance += radiance * cos(theta) * sin(theta);
+    }
+  }
+  irradiance = PI * irradiance / float(PHI_STEPS * THETA_STEPS);
+  gl_FragColor = vec4(irradiance, 1.0);
+}
@@ -0,0 +1,331 @@
+//! Metallic-roughness physically based materials lit by one direct
+//! light and by image based lighting.
+//!
+//! `Environment::new` turns any environment cube map into what the
+//! shader needs, all rendered on the GPU at load time: a diffuse
+//! irradiance cube, a specular cube prefiltered for increasing
+//! roughness down its mip chain, and the split sum BRDF lookup table.
+//! Materials follow glTF: base colour, metallic-roughness, normal,
+//! occlusion and emissive maps, each scaled by a factor.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::blend::BlendMode;
+use renderer::capabilities::capabilities;
+use renderer::cubemap::{fullscreen_quad, render_faces, TextureCube, FACE_VERTEX_SHADER};
+use renderer::gl::{Backend, Gl};
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program, with_defines, ShaderSource, ShaderVariants};
+use renderer::target::RenderTarget;
+use renderer::texture::create_rgba_texture;
+use renderer::uniforms::SceneUniforms;
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("pbr_v.glsl"),
+    fragment: include_str!("pbr_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("pbr_v300.glsl"),
+    fragment: include_str!("pbr_f300.glsl"),
+  },
+};
+
+pub static IRRADIANCE_FRAGMENT_SHADER: &'static str = include_str!("irradiance_f.glsl");
+pub static PREFILTER_FRAGMENT_SHADER: &'static str = include_str!("prefilter_f.glsl");
+pub static BRDF_VERTEX_SHADER: &'static str = include_str!("brdf_v.glsl");
+pub static BRDF_FRAGMENT_SHADER: &'static str = include_str!("brdf_f.glsl");
+
+/// Functions shared between shaders, pasted over `#include <name>`
+/// lines since GLSL has no includes of its own.
+static CHUNKS: [(&'static str, &'static str); 2] = [
+  ("sampling", include_str!("sampling.glsl")),
+  ("brdf", include_str!("brdf.glsl")),
+];
+
+fn expand(source: &str) -> String {
+  let mut expanded = source.to_string();
+  for &(name, chunk) in CHUNKS.iter() {
+    expanded = expanded.replace(&format!("#include {}\n", name), chunk);
+  }
+  expanded
+}
+
+const IRRADIANCE_SIZE: i32 = 32;
+const PREFILTERED_SIZE: i32 = 128;
+const BRDF_LUT_SIZE: i32 = 256;
+
+/// Image based lighting derived from one environment cube map.
+pub struct Environment {
+  pub irradiance: TextureCube,
+  pub prefiltered: TextureCube,
+  /// Mip levels of `prefiltered`, roughness 0 at the top to 1 at the
+  /// last.
+  pub prefiltered_levels: i32,
+  pub brdf_lut: RenderTarget,
+  /// Scales all image based light.
+  pub intensity: f32,
+}
+
+impl Environment {
+  /// Convolve `source`, an sRGB cube map, into linear irradiance and
+  /// prefiltered specular cubes, in half float when the context can
+  /// render and filter it. Leaves the default framebuffer bound with a
+  /// full canvas viewport, and depth testing and blending off.
+  ///
+  /// Rendering into the prefiltered mip levels needs WebGL2 or
+  /// `OES_fbo_render_mipmap`.
+  pub fn new(context: &Gl, source: &TextureCube) -> Result<Environment, JsValue> {
+    if context.backend() == Backend::WebGl1 {
+      context
+          .get_extension("OES_fbo_render_mipmap")?
+          .ok_or("prefiltering the environment needs WebGL2 or OES_fbo_render_mipmap")?;
+    }
+    let features = capabilities(context).features;
+    let hdr = features.float_render_targets && (context.backend() == Backend::WebGl2 || features.float_linear);
+    let data_type = if hdr {
+      match context.backend() {
+        Backend::WebGl1 => {
+          context.get_extension("OES_texture_float")?;
+          context.get_extension("OES_texture_float_linear")?;
+        },
+        Backend::WebGl2 => {
+          context.get_extension("EXT_color_buffer_float")?;
+        },
+      }
+      WebGlRenderingContext::FLOAT
+    } else {
+      WebGlRenderingContext::UNSIGNED_BYTE
+    };
+
+    let irradiance = TextureCube::new(context, IRRADIANCE_SIZE, data_type)?;
+    let program = build_program(context, FACE_VERTEX_SHADER, IRRADIANCE_FRAGMENT_SHADER)?;
+    context.use_program(Some(&program));
+    source.bind(context, 0, context.get_uniform_location(&program, "uEnvironment").as_ref());
+    let result = render_faces(context, &irradiance, 0, &program);
+    context.delete_program(Some(&program));
+    result?;
+
+    let mut prefiltered = TextureCube::new(context, PREFILTERED_SIZE, data_type)?;
+    let mut levels = 1;
+    while PREFILTERED_SIZE >> levels > 0 {
+      prefiltered.allocate(context, levels, PREFILTERED_SIZE >> levels, data_type)?;
+      levels += 1;
+    }
+    prefiltered.mipmapped = true;
+    prefiltered.set_sampling(context);
+    let program = build_program(context, FACE_VERTEX_SHADER, &expand(PREFILTER_FRAGMENT_SHADER))?;
+    context.use_program(Some(&program));
+    source.bind(context, 0, context.get_uniform_location(&program, "uEnvironment").as_ref());
+    let roughness = context.get_uniform_location(&program, "uRoughness");
+    let mut result = Ok(());
+    for level in 0..levels {
+      context.uniform1f(roughness.as_ref(), level as f32 / (levels - 1) as f32);
+      result = render_faces(context, &prefiltered, level, &program);
+      if result.is_err() {
+        break;
+      }
+    }
+    context.delete_program(Some(&program));
+    result?;
+
+    let brdf_lut = RenderTarget::new(context, BRDF_LUT_SIZE, BRDF_LUT_SIZE, WebGlRenderingContext::UNSIGNED_BYTE, false)?;
+    let program = build_program(context, BRDF_VERTEX_SHADER, &expand(BRDF_FRAGMENT_SHADER))?;
+    let quad = fullscreen_quad(context)?;
+    brdf_lut.bind(context);
+    context.use_program(Some(&program));
+    let position = context.get_attrib_location(&program, "aPosition") as u32;
+    context.vertex_attrib_pointer_with_i32(position, 2, WebGlRenderingContext::FLOAT, false, 0, 0);
+    context.enable_vertex_attrib_array(position);
+    context.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+    context.disable_vertex_attrib_array(position);
+    RenderTarget::unbind(context, context.drawing_buffer_width(), context.drawing_buffer_height());
+    context.delete_program(Some(&program));
+    context.delete_buffer(Some(&quad));
+
+    Ok(Environment {
+      irradiance,
+      prefiltered,
+      prefiltered_levels: levels,
+      brdf_lut,
+      intensity: 1.0,
+    })
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    self.irradiance.delete(context);
+    self.prefiltered.delete(context);
+    self.brdf_lut.delete(context);
+  }
+}
+
+/// Factors and optional maps of one material. Missing maps count as
+/// white, and as a flat normal for `normal_texture`.
+#[derive(Clone, Debug)]
+pub struct PbrMaterial {
+  pub base_color: [f32; 4],
+  pub metallic: f32,
+  pub roughness: f32,
+  pub emissive: [f32; 3],
+  pub occlusion_strength: f32,
+  pub normal_scale: f32,
+  /// sRGB.
+  pub base_color_texture: Option<WebGlTexture>,
+  /// Roughness in green, metallic in blue.
+  pub metallic_roughness_texture: Option<WebGlTexture>,
+  /// Tangent space, +Y along increasing `v`.
+  pub normal_texture: Option<WebGlTexture>,
+  /// Red channel.
+  pub occlusion_texture: Option<WebGlTexture>,
+  /// sRGB.
+  pub emissive_texture: Option<WebGlTexture>,
+  /// How `base_color` alpha is used, for a `RenderQueue` to apply.
+  pub blend: BlendMode,
+}
+
+impl Default for PbrMaterial {
+  fn default() -> PbrMaterial {
+    PbrMaterial {
+      base_color: [1.0, 1.0, 1.0, 1.0],
+      metallic: 1.0,
+      roughness: 1.0,
+      emissive: [0.0, 0.0, 0.0],
+      occlusion_strength: 1.0,
+      normal_scale: 1.0,
+      base_color_texture: None,
+      metallic_roughness_texture: None,
+      normal_texture: None,
+      occlusion_texture: None,
+      emissive_texture: None,
+      blend: BlendMode::Opaque,
+    }
+  }
+}
+
+/// Texture units: the five material maps, then the environment.
+const IRRADIANCE_UNIT: u32 = 5;
+const PREFILTERED_UNIT: u32 = 6;
+const BRDF_LUT_UNIT: u32 = 7;
+const MAP_SAMPLERS: [&'static str; 5] = [
+  "uBaseColorTexture",
+  "uMetallicRoughnessTexture",
+  "uNormalTexture",
+  "uOcclusionTexture",
+  "uEmissiveTexture",
+];
+
+pub struct PbrProgram {
+  pub program: WebGlProgram,
+  white: WebGlTexture,
+  flat_normal: WebGlTexture,
+  model_matrix: Option<WebGlUniformLocation>,
+  base_color: Option<WebGlUniformLocation>,
+  metallic_roughness: Option<WebGlUniformLocation>,
+  emissive: Option<WebGlUniformLocation>,
+  occlusion_strength: Option<WebGlUniformLocation>,
+  normal_scale: Option<WebGlUniformLocation>,
+}
+
+impl PbrProgram {
+  /// Normal maps use the mesh's tangents when it has them. Otherwise
+  /// the frame comes from screen space derivatives, which WebGL1 needs
+  /// `OES_standard_derivatives` for, ignoring the map without it.
+  /// `EXT_shader_texture_lod` is used when present for exact
+  /// prefiltered levels.
+  pub fn new(context: &Gl, uniforms: &SceneUniforms) -> Result<PbrProgram, JsValue> {
+    let source = SHADERS.for_backend(context.backend());
+    let mut defines = Vec::new();
+    if context.backend() == Backend::WebGl1 {
+      if context.get_extension("OES_standard_derivatives")?.is_some() {
+        defines.push(("HAS_DERIVATIVES", "1".to_string()));
+      }
+      if context.get_extension("EXT_shader_texture_lod")?.is_some() {
+        defines.push(("HAS_TEXTURE_LOD", "1".to_string()));
+      }
+    }
+    let fragment = with_defines(&expand(source.fragment), &defines);
+    let program = build_program(context, source.vertex, &fragment)?;
+    uniforms.attach(context, &program);
+
+    context.use_program(Some(&program));
+    for (unit, name) in MAP_SAMPLERS.iter().enumerate() {
+      context.uniform1i(context.get_uniform_location(&program, name).as_ref(), unit as i32);
+    }
+    for &(name, unit) in &[
+      ("uIrradiance", IRRADIANCE_UNIT),
+      ("uPrefiltered", PREFILTERED_UNIT),
+      ("uBrdfLut", BRDF_LUT_UNIT),
+    ] {
+      context.uniform1i(context.get_uniform_location(&program, name).as_ref(), unit as i32);
+    }
+
+    Ok(PbrProgram {
+      white: create_rgba_texture(context, 1, 1, &[255, 255, 255, 255], WebGlRenderingContext::NEAREST)?,
+      flat_normal: create_rgba_texture(context, 1, 1, &[128, 128, 255, 255], WebGlRenderingContext::NEAREST)?,
+      model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+      base_color: context.get_uniform_location(&program, "uBaseColorFactor"),
+      metallic_roughness: context.get_uniform_location(&program, "uMetallicRoughness"),
+      emissive: context.get_uniform_location(&program, "uEmissiveFactor"),
+      occlusion_strength: context.get_uniform_location(&program, "uOcclusionStrength"),
+      normal_scale: context.get_uniform_location(&program, "uNormalScale"),
+      program,
+    })
+  }
+
+  /// Start drawing with this program, lit by `environment`.
+  pub fn begin(&self, context: &Gl, uniforms: &SceneUniforms, environment: &Environment) {
+    context.use_program(Some(&self.program));
+    uniforms.apply(context, &self.program);
+    let location = |name: &str| context.get_uniform_location(&self.program, name);
+    context.uniform1f(location("uPrefilteredLevels").as_ref(), environment.prefiltered_levels as f32);
+    context.uniform1f(location("uEnvironmentIntensity").as_ref(), environment.intensity);
+    context.active_texture(WebGlRenderingContext::TEXTURE0 + IRRADIANCE_UNIT);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&environment.irradiance.texture));
+    context.active_texture(WebGlRenderingContext::TEXTURE0 + PREFILTERED_UNIT);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_CUBE_MAP, Some(&environment.prefiltered.texture));
+    context.active_texture(WebGlRenderingContext::TEXTURE0 + BRDF_LUT_UNIT);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&environment.brdf_lut.texture));
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+  }
+
+  /// Draw `mesh` with `material`; `begin` must have been called.
+  pub fn draw(&self, context: &Gl, mesh: &Mesh, model: &Mat4, material: &PbrMaterial) {
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.model_matrix.as_ref(), false, &data);
+    let c = material.base_color;
+    context.uniform4f(self.base_color.as_ref(), c[0], c[1], c[2], c[3]);
+    context.uniform2f(self.metallic_roughness.as_ref(), material.metallic, material.roughness);
+    let e = material.emissive;
+    context.uniform3f(self.emissive.as_ref(), e[0], e[1], e[2]);
+    context.uniform1f(self.occlusion_strength.as_ref(), material.occlusion_strength);
+    context.uniform1f(self.normal_scale.as_ref(), material.normal_scale);
+
+    let maps = [
+      (&material.base_color_texture, &self.white),
+      (&material.metallic_roughness_texture, &self.white),
+      (&material.normal_texture, &self.flat_normal),
+      (&material.occlusion_texture, &self.white),
+      (&material.emissive_texture, &self.white),
+    ];
+    for (unit, &(map, fallback)) in maps.iter().enumerate() {
+      context.active_texture(WebGlRenderingContext::TEXTURE0 + unit as u32);
+      context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(map.as_ref().unwrap_or(fallback)));
+    }
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+    mesh.draw(context);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_program(Some(&self.program));
+    context.delete_texture(Some(&self.white));
+    context.delete_texture(Some(&self.flat_normal));
+  }
+}
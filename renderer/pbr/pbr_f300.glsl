@@ -0,0 +1,89 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform Light {
+  vec4 uLightDirection;
+  vec4 uLightColor;
+  vec4 uAmbientColor;
+};
+
+uniform vec4 uBaseColorFactor;
+// Metallic, roughness.
+uniform vec2 uMetallicRoughness;
+uniform vec3 uEmissiveFactor;
+uniform float uOcclusionStrength;
+uniform float uNormalScale;
+
+uniform sampler2D uBaseColorTexture;
+// glTF layout: roughness in green, metallic in blue.
+uniform sampler2D uMetallicRoughnessTexture;
+uniform sampler2D uNormalTexture;
+uniform sampler2D uOcclusionTexture;
+uniform sampler2D uEmissiveTexture;
+
+uniform samplerCube uIrradiance;
+uniform samplerCube uPrefiltered;
+uniform sampler2D uBrdfLut;
+uniform float uPrefilteredLevels;
+uniform float uEnvironmentIntensity;
+
+in highp vec3 vNormal;
+in highp vec3 vViewDirection;
+in highp vec2 vTextureCoord;
+in highp vec4 vTangent;
+
+out vec4 fragColor;
+
+#include brdf
+
+vec3 surfaceNormal() {
+  vec3 normal = normalize(vNormal);
+  vec3 mapped = texture(uNormalTexture, vTextureCoord).xyz * 2.0 - 1.0;
+  mapped.xy *= uNormalScale;
+  if (dot(vTangent.xyz, vTangent.xyz) > 1e-6) {
+    // Generated tangents; the bitangent is rebuilt the way MikkTSpace
+    // expects, per pixel from the interpolated frame.
+    vec3 tangent = normalize(vTangent.xyz - normal * dot(vTangent.xyz, normal));
+    vec3 bitangent = cross(normal, tangent) * (vTangent.w < 0.0 ? -1.0 : 1.0);
+    return normalize(mat3(tangent, bitangent, normal) * mapped);
+  }
+  // No tangent attribute: a tangent frame from screen space
+  // derivatives instead.
+  vec3 dp1 = dFdx(vViewDirection);
+  vec3 dp2 = dFdy(vViewDirection);
+  vec2 duv1 = dFdx(vTextureCoord);
+  vec2 duv2 = dFdy(vTextureCoord);
+  vec3 dp2perp = cross(dp2, normal);
+  vec3 dp1perp = cross(normal, dp1);
+  vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+  vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+  float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+  return normalize(mat3(tangent * scale, bitangent * scale, normal) * mapped);
+}
+
+void main(void) {
+  vec4 baseColor = uBaseColorFactor * texture(uBaseColorTexture, vTextureCoord);
+  baseColor.rgb = pow(baseColor.rgb, vec3(2.2));
+  vec4 metallicRoughness = texture(uMetallicRoughnessTexture, vTextureCoord);
+  float metallic = clamp(uMetallicRoughness.x * metallicRoughness.b, 0.0, 1.0);
+  float roughness = clamp(uMetallicRoughness.y * metallicRoughness.g, 0.04, 1.0);
+  float occlusion = mix(1.0, texture(uOcclusionTexture, vTextureCoord).r, uOcclusionStrength);
+  vec3 emissive = uEmissiveFactor * pow(texture(uEmissiveTexture, vTextureCoord).rgb, vec3(2.2));
+
+  vec3 normal = surfaceNormal();
+  vec3 view = -normalize(vViewDirection);
+  float nDotV = max(dot(normal, view), 0.001);
+  vec3 f0 = mix(vec3(0.04), baseColor.rgb, metallic);
+
+  vec3 color = directLight(normal, view, -uLightDirection.xyz, uLightColor.rgb, baseColor.rgb, metallic, roughness, f0);
+
+  vec3 fresnel = fresnelSchlickRoughness(nDotV, f0, roughness);
+  vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * texture(uIrradiance, normal).rgb * baseColor.rgb;
+  vec2 brdf = texture(uBrdfLut, vec2(nDotV, roughness)).rg;
+  vec3 reflected = textureLod(uPrefiltered, reflect(-view, normal), roughness * (uPrefilteredLevels - 1.0)).rgb;
+  vec3 specular = reflected * (fresnel * brdf.x + brdf.y);
+  color += (diffuse + specular) * occlusion * uEnvironmentIntensity;
+
+  fragColor = vec4(toDisplay(color + emissive), baseColor.a);
+}
@@ -0,0 +1,24 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec2 aTextureCoord;
+// Zero when the mesh has none.
+attribute vec4 aTangent;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+uniform vec4 uCameraPosition;
+
+varying highp vec3 vNormal;
+varying highp vec3 vViewDirection;
+varying highp vec2 vTextureCoord;
+varying highp vec4 vTangent;
+
+void main(void) {
+  vec4 world = uModelMatrix * aVertexPosition;
+  vNormal = (uModelMatrix * vec4(aVertexNormal, 0.0)).xyz;
+  vViewDirection = world.xyz - uCameraPosition.xyz;
+  vTextureCoord = aTextureCoord;
+  vTangent = vec4((uModelMatrix * vec4(aTangent.xyz, 0.0)).xyz, aTangent.w);
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
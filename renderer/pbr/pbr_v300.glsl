@@ -0,0 +1,29 @@
+#version 300 es
+
+layout(std140) uniform Camera {
+  mat4 uViewMatrix;
+  mat4 uProjectionMatrix;
+  vec4 uCameraPosition;
+};
+
+in vec4 aVertexPosition;
+in vec3 aVertexNormal;
+in vec2 aTextureCoord;
+// Zero when the mesh has none.
+in vec4 aTangent;
+
+uniform mat4 uModelMatrix;
+
+out highp vec3 vNormal;
+out highp vec3 vViewDirection;
+out highp vec2 vTextureCoord;
+out highp vec4 vTangent;
+
+void main(void) {
+  vec4 world = uModelMatrix * aVertexPosition;
+  vNormal = mat3(uModelMatrix) * aVertexNormal;
+  vViewDirection = world.xyz - uCameraPosition.xyz;
+  vTextureCoord = aTextureCoord;
+  vTangent = vec4(mat3(uModelMatrix) * aTangent.xyz, aTangent.w);
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
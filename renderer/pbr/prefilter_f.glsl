@@ -0,0 +1,29 @@
+precision highp float;
+
+uniform samplerCube uEnvironment;
+uniform float uRoughness;
+
+varying vec3 vDirection;
+
+const int SAMPLE_COUNT = 128;
+
+#include sampling
+
+void main(void) {
+  // Split sum approximation: assume the view is along the normal.
+  vec3 normal = normalize(vDirection);
+  vec3 view = normal;
+  vec3 color = vec3(0.0);
+  float weight = 0.0;
+  for (int i = 0; i < SAMPLE_COUNT; i++) {
+    vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radicalInverse(float(i)));
+    vec3 halfway = importanceSampleGGX(xi, normal, uRoughness);
+    vec3 light = 2.0 * dot(view, halfway) * halfway - view;
+    float nDotL = dot(normal, light);
+    if (nDotL > 0.0) {
+      color += pow(textureCube(uEnvironment, light).rgb, vec3(2.2)) * nDotL;
+      weight += nDotL;
+    }
+  }
+  gl_FragColor = vec4(color / max(weight, 0.001), 1.0);
+}
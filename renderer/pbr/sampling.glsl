@@ -0,0 +1,27 @@
+const float PI = 3.14159265359;
+
+// Van der Corput sequence without bit operations, which GLSL ES 1.00
+// lacks; `i` is a whole number below 1024.
+float radicalInverse(float i) {
+  float result = 0.0;
+  float scale = 0.5;
+  for (int bit = 0; bit < 10; bit++) {
+    result += scale * mod(i, 2.0);
+    i = floor(i / 2.0);
+    scale *= 0.5;
+  }
+  return result;
+}
+
+// A half vector around `normal` distributed like GGX for `roughness`.
+vec3 importanceSampleGGX(vec2 xi, vec3 normal, float roughness) {
+  float a = roughness * roughness;
+  float phi = 2.0 * PI * xi.x;
+  float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+  float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+  vec3 local = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+  vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+  vec3 tangent = normalize(cross(up, normal));
+  vec3 bitangent = cross(normal, tangent);
+  return normalize(tangent * local.x + bitangent * local.y + normal * local.z);
+}
@@ -0,0 +1,290 @@
+//! Ray-cast picking: a canvas pixel becomes a world space ray, tested
+//! against node bounds first and then against the exact triangles of
+//! the nodes it passes through.
+
+use std::cell::RefCell;
+
+use serde_derive::Serialize;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+
+use glm::{Mat4, Vec3};
+
+use renderer::bounds::{transform_point, Aabb, Sphere};
+use renderer::geometry::Geometry;
+use renderer::scene::{NodeId, Scene};
+use renderer::uniforms::Camera;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+  pub origin: Vec3,
+  /// Unit length.
+  pub direction: Vec3,
+}
+
+/// The closest triangle under a ray.
+#[derive(Clone, Debug, Serialize)]
+pub struct Hit {
+  pub node: NodeId,
+  pub name: String,
+  /// Index into the node geometry's triangles, `indices[3 * triangle..]`.
+  pub triangle: usize,
+  /// Weights of the triangle's three vertices at the hit point.
+  pub barycentric: [f32; 3],
+  pub position: [f32; 3],
+  /// Along the ray, in world units.
+  pub distance: f32,
+}
+
+impl Ray {
+  pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+    Ray { origin, direction: glm::normalize(&direction) }
+  }
+
+  /// The ray through pixel `x, y` of a `width` by `height` canvas, from
+  /// the near plane towards the far plane.
+  pub fn from_screen(x: f32, y: f32, width: f32, height: f32, view: &Mat4, projection: &Mat4) -> Ray {
+    let ndc_x = 2.0 * x / width - 1.0;
+    let ndc_y = 1.0 - 2.0 * y / height;
+    let inverse = glm::inverse(&(projection * view));
+    let near = transform_point(&inverse, &glm::vec3(ndc_x, ndc_y, -1.0));
+    let far = transform_point(&inverse, &glm::vec3(ndc_x, ndc_y, 1.0));
+    Ray::new(near, far - near)
+  }
+
+  pub fn from_camera(x: f32, y: f32, width: f32, height: f32, camera: &Camera) -> Ray {
+    Ray::from_screen(x, y, width, height, &camera.view, &camera.projection)
+  }
+
+  pub fn at(&self, distance: f32) -> Vec3 {
+    self.origin + self.direction * distance
+  }
+
+  /// Distance to the first sphere crossing in front of the origin, 0
+  /// when the origin is inside.
+  pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+    let offset = self.origin - sphere.center;
+    let b = glm::dot(&offset, &self.direction);
+    let c = glm::dot(&offset, &offset) - sphere.radius * sphere.radius;
+    if c <= 0.0 {
+      return Some(0.0);
+    }
+    let discriminant = b * b - c;
+    if b > 0.0 || discriminant < 0.0 {
+      return None;
+    }
+    Some(-b - discriminant.sqrt())
+  }
+
+  /// Slab test, same conventions as `intersect_sphere`.
+  pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+    let mut near = 0.0f32;
+    let mut far = std::f32::MAX;
+    for k in 0..3 {
+      if self.direction[k].abs() < 1e-12 {
+        if self.origin[k] < aabb.min[k] || self.origin[k] > aabb.max[k] {
+          return None;
+        }
+        continue;
+      }
+      let inverse = 1.0 / self.direction[k];
+      let mut t0 = (aabb.min[k] - self.origin[k]) * inverse;
+      let mut t1 = (aabb.max[k] - self.origin[k]) * inverse;
+      if t0 > t1 {
+        std::mem::swap(&mut t0, &mut t1);
+      }
+      near = near.max(t0);
+      far = far.min(t1);
+      if near > far {
+        return None;
+      }
+    }
+    Some(near)
+  }
+
+  /// Möller-Trumbore, both faces. Returns the distance and the
+  /// barycentric weights of `a`, `b` and `c`.
+  pub fn intersect_triangle(&self, a: &Vec3, b: &Vec3, c: &Vec3) -> Option<(f32, [f32; 3])> {
+    let edge1: Vec3 = b - a;
+    let edge2: Vec3 = c - a;
+    let p: Vec3 = self.direction.cross(&edge2);
+    let determinant = glm::dot(&edge1, &p);
+    if determinant.abs() < 1e-12 {
+      return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s: Vec3 = self.origin - a;
+    let u = glm::dot(&s, &p) * inverse;
+    if u < 0.0 || u > 1.0 {
+      return None;
+    }
+    let q: Vec3 = s.cross(&edge1);
+    let v = glm::dot(&self.direction, &q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+      return None;
+    }
+    let distance = glm::dot(&edge2, &q) * inverse;
+    if distance < 0.0 {
+      return None;
+    }
+    Some((distance, [1.0 - u - v, u, v]))
+  }
+}
+
+/// The closest hit among the mesh nodes of `scene`. `geometries` is
+/// indexed like `Node::mesh`; nodes without geometry there have no
+/// triangles to hit and are skipped. Call `Scene::update_world` first.
+pub fn pick(scene: &Scene, geometries: &[Geometry], ray: &Ray) -> Option<Hit> {
+  let mut best: Option<Hit> = None;
+  for (id, node) in scene.nodes().iter().enumerate() {
+    let geometry = match node.mesh.and_then(|mesh| geometries.get(mesh)) {
+      Some(geometry) => geometry,
+      None => continue,
+    };
+    let closest = best.as_ref().map_or(std::f32::MAX, |hit| hit.distance);
+
+    // Cheap rejections before touching any triangle.
+    if let Some(ref bounds) = node.world_bounds {
+      match ray.intersect_sphere(&bounds.sphere) {
+        Some(distance) if distance <= closest => (),
+        _ => continue,
+      }
+      match ray.intersect_aabb(&bounds.aabb) {
+        Some(distance) if distance <= closest => (),
+        _ => continue,
+      }
+    }
+
+    for (triangle, corners) in geometry.indices.chunks(3).enumerate() {
+      if corners.len() < 3 {
+        break;
+      }
+      let world = |i: u16| {
+        let p = geometry.position(i as usize);
+        transform_point(&node.world, &glm::vec3(p[0], p[1], p[2]))
+      };
+      let (a, b, c) = (world(corners[0]), world(corners[1]), world(corners[2]));
+      if let Some((distance, barycentric)) = ray.intersect_triangle(&a, &b, &c) {
+        if best.as_ref().map_or(true, |hit| distance < hit.distance) {
+          let position = ray.at(distance);
+          best = Some(Hit {
+            node: id,
+            name: node.name.clone(),
+            triangle,
+            barycentric,
+            position: [position.x, position.y, position.z],
+            distance,
+          });
+        }
+      }
+    }
+  }
+  best
+}
+
+thread_local! {
+  static PICK_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+}
+
+/// Register a JS function called with each pick result, a `Hit` object
+/// or `null` for a miss. Pass `null` to remove it.
+#[wasm_bindgen]
+pub fn picking_set_callback(callback: JsValue) {
+  PICK_CALLBACK.with(|stored| {
+    *stored.borrow_mut() = callback.dyn_into::<js_sys::Function>().ok();
+  });
+}
+
+/// Hand a pick result to the JS callback, if one is registered.
+pub fn notify(hit: Option<&Hit>) -> Result<(), JsValue> {
+  PICK_CALLBACK.with(|stored| {
+    if let Some(ref callback) = *stored.borrow() {
+      let value = match hit {
+        Some(hit) => JsValue::from_serde(hit).unwrap(),
+        None => JsValue::NULL,
+      };
+      callback.call1(&JsValue::NULL, &value)?;
+    }
+    Ok(())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use renderer::bounds::Bounds;
+  use renderer::geometry;
+
+  fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+    Ray::new(glm::vec3(origin[0], origin[1], origin[2]), glm::vec3(direction[0], direction[1], direction[2]))
+  }
+
+  fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+    Aabb { min: glm::vec3(min[0], min[1], min[2]), max: glm::vec3(max[0], max[1], max[2]) }
+  }
+
+  fn assert_near(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-5, "{} != {}", actual, expected);
+  }
+
+  #[test]
+  fn intersects_spheres() {
+    let sphere = Sphere { center: glm::vec3(0.0, 0.0, 0.0), radius: 2.0 };
+    assert_near(ray([0.0, 0.0, 10.0], [0.0, 0.0, -3.0]).intersect_sphere(&sphere).unwrap(), 8.0);
+    // Grazing the side.
+    assert_near(ray([2.0, 0.0, 10.0], [0.0, 0.0, -1.0]).intersect_sphere(&sphere).unwrap(), 10.0);
+    assert_eq!(ray([2.1, 0.0, 10.0], [0.0, 0.0, -1.0]).intersect_sphere(&sphere), None);
+    // Behind the origin.
+    assert_eq!(ray([0.0, 0.0, 10.0], [0.0, 0.0, 1.0]).intersect_sphere(&sphere), None);
+    assert_eq!(ray([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]).intersect_sphere(&sphere), Some(0.0));
+  }
+
+  #[test]
+  fn intersects_boxes() {
+    let unit = aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+    assert_near(ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&unit).unwrap(), 4.0);
+    assert_near(ray([-5.0, -5.0, 0.0], [1.0, 1.0, 0.0]).intersect_aabb(&unit).unwrap(), 4.0 * 2f32.sqrt());
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, 1.0]).intersect_aabb(&unit), None);
+    assert_eq!(ray([0.0, 3.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&unit), None);
+    // Parallel to a slab, inside it and outside it.
+    assert_near(ray([0.5, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&unit).unwrap(), 4.0);
+    assert_eq!(ray([1.5, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&unit), None);
+    assert_eq!(ray([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]).intersect_aabb(&unit), Some(0.0));
+  }
+
+  #[test]
+  fn intersects_triangles() {
+    let (a, b, c) = (glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+    let (distance, barycentric) = ray([0.25, 0.5, 3.0], [0.0, 0.0, -1.0]).intersect_triangle(&a, &b, &c).unwrap();
+    assert_near(distance, 3.0);
+    for (&actual, &expected) in barycentric.iter().zip(&[0.25, 0.25, 0.5]) {
+      assert_near(actual, expected);
+    }
+    // The back face counts too.
+    let (distance, _) = ray([0.25, 0.25, -2.0], [0.0, 0.0, 1.0]).intersect_triangle(&a, &b, &c).unwrap();
+    assert_near(distance, 2.0);
+    assert_eq!(ray([0.75, 0.75, 3.0], [0.0, 0.0, -1.0]).intersect_triangle(&a, &b, &c), None);
+    assert_eq!(ray([0.25, 0.25, 3.0], [0.0, 0.0, 1.0]).intersect_triangle(&a, &b, &c), None);
+    assert_eq!(ray([0.25, 0.25, 3.0], [1.0, 0.0, 0.0]).intersect_triangle(&a, &b, &c), None);
+  }
+
+  #[test]
+  fn picks_the_closest_node_with_geometry() {
+    let cube = geometry::cube();
+    let bounds = Bounds::from_positions(&cube.positions);
+    let mut scene = Scene::new();
+    scene.add_mesh("far", None, glm::translation(&glm::vec3(0.0, 0.0, -5.0)), 0, bounds);
+    scene.add_mesh("near", None, glm::identity(), 0, bounds);
+    // Closest of all, but its mesh has no geometry to test.
+    scene.add_mesh("unloaded", None, glm::translation(&glm::vec3(0.0, 0.0, 5.0)), 1, bounds);
+    scene.update_world();
+
+    let hit = pick(&scene, &[cube], &ray([0.2, 0.3, 10.0], [0.0, 0.0, -1.0])).unwrap();
+    assert_eq!((hit.node, hit.name.as_str()), (1, "near"));
+    assert_near(hit.distance, 9.0);
+    assert_near(hit.position[0], 0.2);
+    assert_near(hit.position[1], 0.3);
+    assert_near(hit.position[2], 1.0);
+    assert!(pick(&scene, &[geometry::cube()], &ray([3.0, 0.0, 10.0], [0.0, 0.0, -1.0])).is_none());
+  }
+}
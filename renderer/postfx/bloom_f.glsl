@@ -0,0 +1,13 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+uniform sampler2D uBloom;
+uniform float uIntensity;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  vec4 color = texture2D(uSource, vTexCoord);
+  vec3 bloom = texture2D(uBloom, vTexCoord).rgb;
+  gl_FragColor = vec4(color.rgb + bloom * uIntensity, color.a);
+}
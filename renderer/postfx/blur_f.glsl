@@ -0,0 +1,21 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+// One texel along the blur axis, e.g. vec2(1.0 / width, 0.0).
+uniform vec2 uDirection;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  // 9-tap gaussian, sampled symmetrically around the centre.
+  vec3 result = texture2D(uSource, vTexCoord).rgb * 0.227027;
+  result += texture2D(uSource, vTexCoord + uDirection * 1.0).rgb * 0.1945946;
+  result += texture2D(uSource, vTexCoord - uDirection * 1.0).rgb * 0.1945946;
+  result += texture2D(uSource, vTexCoord + uDirection * 2.0).rgb * 0.1216216;
+  result += texture2D(uSource, vTexCoord - uDirection * 2.0).rgb * 0.1216216;
+  result += texture2D(uSource, vTexCoord + uDirection * 3.0).rgb * 0.054054;
+  result += texture2D(uSource, vTexCoord - uDirection * 3.0).rgb * 0.054054;
+  result += texture2D(uSource, vTexCoord + uDirection * 4.0).rgb * 0.016216;
+  result += texture2D(uSource, vTexCoord - uDirection * 4.0).rgb * 0.016216;
+  gl_FragColor = vec4(result, 1.0);
+}
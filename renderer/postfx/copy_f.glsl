@@ -0,0 +1,9 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  gl_FragColor = texture2D(uSource, vTexCoord);
+}
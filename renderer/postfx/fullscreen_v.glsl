@@ -0,0 +1,8 @@
+attribute vec2 aPosition;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  vTexCoord = aPosition * 0.5 + 0.5;
+  gl_Position = vec4(aPosition, 0.0, 1.0);
+}
@@ -0,0 +1,46 @@
+precision highp float;
+
+uniform sampler2D uSource;
+uniform vec2 uTexelSize;
+uniform float uSpanMax;
+uniform float uReduceMul;
+uniform float uReduceMin;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  vec3 luma = vec3(0.299, 0.587, 0.114);
+
+  float lumaNW = dot(texture2D(uSource, vTexCoord + vec2(-1.0, -1.0) * uTexelSize).rgb, luma);
+  float lumaNE = dot(texture2D(uSource, vTexCoord + vec2( 1.0, -1.0) * uTexelSize).rgb, luma);
+  float lumaSW = dot(texture2D(uSource, vTexCoord + vec2(-1.0,  1.0) * uTexelSize).rgb, luma);
+  float lumaSE = dot(texture2D(uSource, vTexCoord + vec2( 1.0,  1.0) * uTexelSize).rgb, luma);
+  vec4 colorM = texture2D(uSource, vTexCoord);
+  float lumaM = dot(colorM.rgb, luma);
+
+  float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+  float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+  // Blur along the edge, i.e. perpendicular to the luma gradient.
+  vec2 dir;
+  dir.x = -((lumaNW + lumaNE) - (lumaSW + lumaSE));
+  dir.y =  ((lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+  float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * (0.25 * uReduceMul), uReduceMin);
+  float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+  dir = min(vec2(uSpanMax), max(vec2(-uSpanMax), dir * rcpDirMin)) * uTexelSize;
+
+  vec3 rgbA = 0.5 * (
+      texture2D(uSource, vTexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+      texture2D(uSource, vTexCoord + dir * (2.0 / 3.0 - 0.5)).rgb);
+  vec3 rgbB = rgbA * 0.5 + 0.25 * (
+      texture2D(uSource, vTexCoord + dir * -0.5).rgb +
+      texture2D(uSource, vTexCoord + dir * 0.5).rgb);
+
+  float lumaB = dot(rgbB, luma);
+  if (lumaB < lumaMin || lumaB > lumaMax) {
+    gl_FragColor = vec4(rgbA, colorM.a);
+  } else {
+    gl_FragColor = vec4(rgbB, colorM.a);
+  }
+}
@@ -0,0 +1,30 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+// A size^3 LUT laid out as `size` slices of size x size side by side,
+// blue picks the slice, red and green address texels inside it.
+uniform sampler2D uLut;
+uniform float uLutSize;
+uniform float uAmount;
+
+varying highp vec2 vTexCoord;
+
+vec3 sampleLut(vec3 color) {
+  float n = uLutSize;
+  float blue = color.b * (n - 1.0);
+  float slice0 = floor(blue);
+  float slice1 = min(slice0 + 1.0, n - 1.0);
+
+  float x = (color.r * (n - 1.0) + 0.5) / (n * n);
+  float y = (color.g * (n - 1.0) + 0.5) / n;
+
+  vec3 a = texture2D(uLut, vec2(x + slice0 / n, y)).rgb;
+  vec3 b = texture2D(uLut, vec2(x + slice1 / n, y)).rgb;
+  return mix(a, b, blue - slice0);
+}
+
+void main(void) {
+  vec4 color = texture2D(uSource, vTexCoord);
+  vec3 graded = sampleLut(clamp(color.rgb, 0.0, 1.0));
+  gl_FragColor = vec4(mix(color.rgb, graded, uAmount), color.a);
+}
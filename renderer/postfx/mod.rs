@@ -0,0 +1,497 @@
+//! Post-processing stack.
+//!
+//! The scene is rendered into the first of two offscreen targets, then
+//! every enabled pass draws a fullscreen quad that reads the previous
+//! target and writes the other one. The last enabled pass writes
+//! straight to the canvas.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde_derive::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  HtmlImageElement,
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+};
+
+use renderer::capabilities::capabilities;
+use renderer::f32_view;
+use renderer::gl::{Backend, Gl};
+use renderer::u8_view;
+use renderer::shader::build_program;
+use renderer::target::RenderTarget;
+use renderer::texture::set_sampling;
+
+static FULLSCREEN_VERTEX_SHADER: &'static str = include_str!("fullscreen_v.glsl");
+static COPY_FRAGMENT_SHADER: &'static str = include_str!("copy_f.glsl");
+static FXAA_FRAGMENT_SHADER: &'static str = include_str!("fxaa_f.glsl");
+static THRESHOLD_FRAGMENT_SHADER: &'static str = include_str!("threshold_f.glsl");
+static BLUR_FRAGMENT_SHADER: &'static str = include_str!("blur_f.glsl");
+static BLOOM_FRAGMENT_SHADER: &'static str = include_str!("bloom_f.glsl");
+static VIGNETTE_FRAGMENT_SHADER: &'static str = include_str!("vignette_f.glsl");
+static GRADE_FRAGMENT_SHADER: &'static str = include_str!("grade_f.glsl");
+static TONEMAP_FRAGMENT_SHADER: &'static str = include_str!("tonemap_f.glsl");
+
+/// Edge length of the identity LUT used until a real one is loaded.
+const DEFAULT_LUT_SIZE: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum PassKind {
+  /// Threshold + separable blur, added back on top of the source.
+  Bloom,
+  /// Reinhard or ACES, see the `operator` parameter.
+  ToneMap,
+  /// Colour grading through a LUT texture.
+  ColorGrade,
+  Vignette,
+  Fxaa,
+  /// A user supplied fragment shader.
+  Custom,
+}
+
+/// One fullscreen pass of the chain.
+///
+/// Every entry of `params` is uploaded as a float uniform named after
+/// the key, `intensity` becomes `uIntensity`.
+pub struct PostPass {
+  pub name: String,
+  pub kind: PassKind,
+  pub enabled: bool,
+  pub params: BTreeMap<String, f32>,
+  program: WebGlProgram,
+}
+
+/// What JS gets back from `postfx_passes`.
+#[derive(Serialize)]
+struct PassInfo<'a> {
+  name: &'a str,
+  kind: PassKind,
+  enabled: bool,
+  params: &'a BTreeMap<String, f32>,
+}
+
+pub struct PostChain {
+  passes: Vec<PostPass>,
+  targets: [RenderTarget; 2],
+  bloom_targets: [RenderTarget; 2],
+  copy_program: WebGlProgram,
+  threshold_program: WebGlProgram,
+  blur_program: WebGlProgram,
+  lut: WebGlTexture,
+  lut_size: f32,
+  quad: WebGlBuffer,
+  width: i32,
+  height: i32,
+}
+
+impl PostChain {
+  /// Build the chain with the built-in passes, in the order
+  /// bloom, tone mapping, colour grading, vignette and FXAA.
+  pub fn new(
+    context: &Gl,
+    width: i32,
+    height: i32,
+  ) -> Result<PostChain, JsValue> {
+    // Keep HDR values around for bloom and tone mapping if we can
+    // both render to and filter float textures.
+    let features = capabilities(context).features;
+    let hdr = features.float_render_targets && features.float_linear;
+    if hdr {
+      let float_textures = match context.backend() {
+        Backend::WebGl1 => "OES_texture_float",
+        Backend::WebGl2 => "EXT_color_buffer_float",
+      };
+      context.get_extension(float_textures)?;
+      context.get_extension("OES_texture_float_linear")?;
+    }
+    let targets = if hdr {
+      create_targets(context, width, height, WebGlRenderingContext::FLOAT)
+          .or_else(|_| create_targets(context, width, height, WebGlRenderingContext::UNSIGNED_BYTE))?
+    } else {
+      create_targets(context, width, height, WebGlRenderingContext::UNSIGNED_BYTE)?
+    };
+    let bloom_targets = [
+      RenderTarget::new(context, (width / 2).max(1), (height / 2).max(1), WebGlRenderingContext::UNSIGNED_BYTE, false)?,
+      RenderTarget::new(context, (width / 2).max(1), (height / 2).max(1), WebGlRenderingContext::UNSIGNED_BYTE, false)?,
+    ];
+
+    let positions: [f32; 8] = [
+      -1.0, -1.0,
+       1.0, -1.0,
+      -1.0,  1.0,
+       1.0,  1.0,
+    ];
+    let quad = context.create_buffer().ok_or("failed to create buffer")?;
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad));
+    let vert_array = f32_view(&positions)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &vert_array,
+        WebGlRenderingContext::STATIC_DRAW,
+    );
+
+    let lut = context.create_texture().ok_or("failed to create texture")?;
+    upload_lut(context, &lut, &identity_lut(DEFAULT_LUT_SIZE), DEFAULT_LUT_SIZE)?;
+
+    let mut chain = PostChain {
+      passes: Vec::new(),
+      targets,
+      bloom_targets,
+      copy_program: build_program(context, FULLSCREEN_VERTEX_SHADER, COPY_FRAGMENT_SHADER)?,
+      threshold_program: build_program(context, FULLSCREEN_VERTEX_SHADER, THRESHOLD_FRAGMENT_SHADER)?,
+      blur_program: build_program(context, FULLSCREEN_VERTEX_SHADER, BLUR_FRAGMENT_SHADER)?,
+      lut,
+      lut_size: DEFAULT_LUT_SIZE as f32,
+      quad,
+      width,
+      height,
+    };
+
+    chain.push_pass(context, "bloom", PassKind::Bloom, BLOOM_FRAGMENT_SHADER,
+        &[("threshold", 0.8), ("intensity", 0.6), ("iterations", 2.0)])?;
+    chain.push_pass(context, "tonemap", PassKind::ToneMap, TONEMAP_FRAGMENT_SHADER,
+        &[("exposure", 1.0), ("operator", 1.0), ("gamma", 2.2)])?;
+    chain.push_pass(context, "grade", PassKind::ColorGrade, GRADE_FRAGMENT_SHADER,
+        &[("amount", 1.0)])?;
+    chain.push_pass(context, "vignette", PassKind::Vignette, VIGNETTE_FRAGMENT_SHADER,
+        &[("intensity", 0.8), ("radius", 0.75), ("softness", 0.45)])?;
+    chain.push_pass(context, "fxaa", PassKind::Fxaa, FXAA_FRAGMENT_SHADER,
+        &[("spanMax", 8.0), ("reduceMul", 1.0 / 8.0), ("reduceMin", 1.0 / 128.0)])?;
+
+    Ok(chain)
+  }
+
+  /// Append a user pass. `fragment_source` gets `uSource`, `uTexelSize`
+  /// and `vTexCoord` like the built-in ones.
+  pub fn add_pass(
+    &mut self,
+    context: &Gl,
+    name: &str,
+    fragment_source: &str,
+    params: &[(&str, f32)],
+  ) -> Result<(), JsValue> {
+    self.push_pass(context, name, PassKind::Custom, fragment_source, params)
+  }
+
+  fn push_pass(
+    &mut self,
+    context: &Gl,
+    name: &str,
+    kind: PassKind,
+    fragment_source: &str,
+    params: &[(&str, f32)],
+  ) -> Result<(), JsValue> {
+    if self.pass(name).is_some() {
+      return Err(format!("post pass `{}` already exists", name).into());
+    }
+    let program = build_program(context, FULLSCREEN_VERTEX_SHADER, fragment_source)?;
+    self.passes.push(PostPass {
+      name: name.to_string(),
+      kind,
+      enabled: true,
+      params: params.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+      program,
+    });
+    Ok(())
+  }
+
+  pub fn passes(&self) -> &[PostPass] {
+    &self.passes
+  }
+
+  pub fn pass(&self, name: &str) -> Option<&PostPass> {
+    self.passes.iter().find(|p| p.name == name)
+  }
+
+  pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+    self.passes.iter_mut().find(|p| p.name == name)
+  }
+
+  /// Returns `false` if there is no pass called `name`.
+  pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+    match self.pass_mut(name) {
+      Some(pass) => { pass.enabled = enabled; true },
+      None => false,
+    }
+  }
+
+  /// Returns `false` if there is no pass called `name`.
+  pub fn set_param(&mut self, name: &str, param: &str, value: f32) -> bool {
+    match self.pass_mut(name) {
+      Some(pass) => { pass.params.insert(param.to_string(), value); true },
+      None => false,
+    }
+  }
+
+  /// Replace the colour grading LUT. `image` holds `size` slices of
+  /// `size` x `size` texels next to each other, blue picking the slice.
+  pub fn set_lut_image(
+    &mut self,
+    context: &Gl,
+    image: &HtmlImageElement,
+    size: u32,
+  ) -> Result<(), JsValue> {
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.lut));
+    context.tex_image_2d_with_image(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        WebGlRenderingContext::RGBA as i32,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        image,
+    )?;
+    self.lut_size = size as f32;
+    Ok(())
+  }
+
+  /// Redirect rendering into the scene target. Draw the scene after
+  /// this and call `present` once it is done.
+  pub fn begin_scene(&self, context: &Gl) {
+    self.targets[0].bind(context);
+  }
+
+  pub fn resize(
+    &mut self,
+    context: &Gl,
+    width: i32,
+    height: i32,
+  ) -> Result<(), JsValue> {
+    self.width = width;
+    self.height = height;
+    for target in self.targets.iter_mut() {
+      target.resize(context, width, height)?;
+    }
+    for target in self.bloom_targets.iter_mut() {
+      target.resize(context, (width / 2).max(1), (height / 2).max(1))?;
+    }
+    Ok(())
+  }
+
+  /// Run every enabled pass over the scene target and put the result
+  /// on the canvas.
+  pub fn present(&self, context: &Gl) {
+    context.disable(WebGlRenderingContext::DEPTH_TEST);
+    context.disable(WebGlRenderingContext::BLEND);
+
+    let enabled: Vec<&PostPass> = self.passes.iter().filter(|p| p.enabled).collect();
+    if enabled.is_empty() {
+      RenderTarget::unbind(context, self.width, self.height);
+      self.draw(context, &self.copy_program, &self.targets[0]);
+      return;
+    }
+
+    let mut source = 0;
+    for (n, pass) in enabled.iter().enumerate() {
+      if pass.kind == PassKind::Bloom {
+        self.bloom_prepass(context, pass, &self.targets[source]);
+      }
+
+      if n + 1 == enabled.len() {
+        RenderTarget::unbind(context, self.width, self.height);
+      } else {
+        self.targets[1 - source].bind(context);
+      }
+
+      context.use_program(Some(&pass.program));
+      set_params(context, &pass.program, &pass.params);
+      match pass.kind {
+        PassKind::Bloom => {
+          bind_sampler(context, &pass.program, "uBloom", 1, &self.bloom_targets[0].texture);
+        },
+        PassKind::ColorGrade => {
+          bind_sampler(context, &pass.program, "uLut", 1, &self.lut);
+          context.uniform1f(
+              context.get_uniform_location(&pass.program, "uLutSize").as_ref(),
+              self.lut_size,
+          );
+        },
+        _ => (),
+      }
+      self.draw(context, &pass.program, &self.targets[source]);
+
+      source = 1 - source;
+    }
+  }
+
+  /// Bright-pass the source into the half resolution bloom targets and
+  /// blur it there, horizontally then vertically.
+  fn bloom_prepass(&self, context: &Gl, pass: &PostPass, source: &RenderTarget) {
+    let a = &self.bloom_targets[0];
+    let b = &self.bloom_targets[1];
+
+    a.bind(context);
+    context.use_program(Some(&self.threshold_program));
+    set_params(context, &self.threshold_program, &pass.params);
+    self.draw(context, &self.threshold_program, source);
+
+    let iterations = pass.params.get("iterations").cloned().unwrap_or(1.0).max(1.0) as u32;
+    context.use_program(Some(&self.blur_program));
+    let direction = context.get_uniform_location(&self.blur_program, "uDirection");
+    for _ in 0..iterations {
+      b.bind(context);
+      context.uniform2f(direction.as_ref(), 1.0 / a.width as f32, 0.0);
+      self.draw(context, &self.blur_program, a);
+
+      a.bind(context);
+      context.uniform2f(direction.as_ref(), 0.0, 1.0 / b.height as f32);
+      self.draw(context, &self.blur_program, b);
+    }
+  }
+
+  /// Draw the fullscreen quad with `program`, sampling `source`
+  /// through `uSource`.
+  fn draw(&self, context: &Gl, program: &WebGlProgram, source: &RenderTarget) {
+    context.use_program(Some(program));
+    bind_sampler(context, program, "uSource", 0, &source.texture);
+    context.uniform2f(
+        context.get_uniform_location(program, "uTexelSize").as_ref(),
+        1.0 / source.width as f32,
+        1.0 / source.height as f32,
+    );
+
+    let position = context.get_attrib_location(program, "aPosition") as u32;
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.quad));
+    context.vertex_attrib_pointer_with_i32(
+        position,
+        2,
+        WebGlRenderingContext::FLOAT,
+        false,
+        0,
+        0
+    );
+    context.enable_vertex_attrib_array(position);
+
+    context.draw_arrays(WebGlRenderingContext::TRIANGLE_STRIP, 0, 4);
+  }
+}
+
+fn create_targets(
+  context: &Gl,
+  width: i32,
+  height: i32,
+  data_type: u32,
+) -> Result<[RenderTarget; 2], JsValue> {
+  // Only the first target receives the scene, so only it needs depth.
+  Ok([
+    RenderTarget::new(context, width, height, data_type, true)?,
+    RenderTarget::new(context, width, height, data_type, false)?,
+  ])
+}
+
+/// Upload every parameter of a pass as a float uniform. Parameters the
+/// program doesn't declare are skipped by WebGL.
+fn set_params(context: &Gl, program: &WebGlProgram, params: &BTreeMap<String, f32>) {
+  for (name, value) in params {
+    let location = context.get_uniform_location(program, &uniform_name(name));
+    context.uniform1f(location.as_ref(), *value);
+  }
+}
+
+fn uniform_name(param: &str) -> String {
+  let mut chars = param.chars();
+  match chars.next() {
+    Some(first) => format!("u{}{}", first.to_uppercase(), chars.as_str()),
+    None => String::from("u"),
+  }
+}
+
+fn bind_sampler(
+  context: &Gl,
+  program: &WebGlProgram,
+  name: &str,
+  unit: u32,
+  texture: &WebGlTexture,
+) {
+  context.active_texture(WebGlRenderingContext::TEXTURE0 + unit);
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+  context.uniform1i(context.get_uniform_location(program, name).as_ref(), unit as i32);
+  context.active_texture(WebGlRenderingContext::TEXTURE0);
+}
+
+/// A LUT that maps every colour onto itself.
+fn identity_lut(size: usize) -> Vec<u8> {
+  let mut data = Vec::with_capacity(size * size * size * 4);
+  let scale = 255.0 / (size - 1) as f32;
+  for g in 0..size {
+    for b in 0..size {
+      for r in 0..size {
+        data.push((r as f32 * scale).round() as u8);
+        data.push((g as f32 * scale).round() as u8);
+        data.push((b as f32 * scale).round() as u8);
+        data.push(255);
+      }
+    }
+  }
+  data
+}
+
+fn upload_lut(
+  context: &Gl,
+  texture: &WebGlTexture,
+  data: &[u8],
+  size: usize,
+) -> Result<(), JsValue> {
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+  let pixels = u8_view(data)?;
+  context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+      WebGlRenderingContext::TEXTURE_2D,
+      0,
+      WebGlRenderingContext::RGBA as i32,
+      (size * size) as i32,
+      size as i32,
+      0,
+      WebGlRenderingContext::RGBA,
+      WebGlRenderingContext::UNSIGNED_BYTE,
+      Some(&pixels),
+  )?;
+  set_sampling(context, WebGlRenderingContext::LINEAR);
+  Ok(())
+}
+
+/* JS controls */
+
+thread_local! {
+  static ACTIVE_CHAIN: RefCell<Option<Rc<RefCell<PostChain>>>> = RefCell::new(None);
+}
+
+/// Make `chain` the one the `postfx_*` exports talk to.
+pub fn set_active_chain(chain: Option<Rc<RefCell<PostChain>>>) {
+  ACTIVE_CHAIN.with(|active| *active.borrow_mut() = chain);
+}
+
+fn with_active_chain<R, F: FnOnce(&mut PostChain) -> R>(f: F) -> Option<R> {
+  ACTIVE_CHAIN.with(|active| {
+    active.borrow().as_ref().map(|chain| f(&mut chain.borrow_mut()))
+  })
+}
+
+/// Toggle a pass of the running post chain by name.
+#[wasm_bindgen]
+pub fn postfx_set_enabled(name: &str, enabled: bool) -> bool {
+  with_active_chain(|chain| chain.set_enabled(name, enabled)).unwrap_or(false)
+}
+
+/// Set a parameter of a pass of the running post chain.
+#[wasm_bindgen]
+pub fn postfx_set_param(name: &str, param: &str, value: f32) -> bool {
+  with_active_chain(|chain| chain.set_param(name, param, value)).unwrap_or(false)
+}
+
+/// List the passes of the running post chain, in order.
+#[wasm_bindgen]
+pub fn postfx_passes() -> JsValue {
+  with_active_chain(|chain| {
+    let info: Vec<PassInfo> = chain.passes().iter().map(|p| PassInfo {
+      name: &p.name,
+      kind: p.kind,
+      enabled: p.enabled,
+      params: &p.params,
+    }).collect();
+    JsValue::from_serde(&info).unwrap()
+  }).unwrap_or(JsValue::NULL)
+}
@@ -0,0 +1,13 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+uniform float uThreshold;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  vec4 color = texture2D(uSource, vTexCoord);
+  float brightness = max(color.r, max(color.g, color.b));
+  float contribution = max(brightness - uThreshold, 0.0) / max(brightness, 0.0001);
+  gl_FragColor = vec4(color.rgb * contribution, 1.0);
+}
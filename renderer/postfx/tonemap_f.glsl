@@ -0,0 +1,24 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+uniform float uExposure;
+// 0 - Reinhard, 1 - ACES (Narkowicz fit)
+uniform float uOperator;
+uniform float uGamma;
+
+varying highp vec2 vTexCoord;
+
+vec3 reinhard(vec3 x) {
+  return x / (1.0 + x);
+}
+
+vec3 aces(vec3 x) {
+  return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main(void) {
+  vec4 color = texture2D(uSource, vTexCoord);
+  vec3 hdr = color.rgb * uExposure;
+  vec3 mapped = uOperator < 0.5 ? reinhard(hdr) : aces(hdr);
+  gl_FragColor = vec4(pow(mapped, vec3(1.0 / uGamma)), color.a);
+}
@@ -0,0 +1,15 @@
+precision mediump float;
+
+uniform sampler2D uSource;
+uniform float uIntensity;
+uniform float uRadius;
+uniform float uSoftness;
+
+varying highp vec2 vTexCoord;
+
+void main(void) {
+  vec4 color = texture2D(uSource, vTexCoord);
+  float dist = distance(vTexCoord, vec2(0.5));
+  float vignette = smoothstep(uRadius, uRadius - uSoftness, dist);
+  gl_FragColor = vec4(color.rgb * mix(1.0, vignette, uIntensity), color.a);
+}
@@ -0,0 +1,112 @@
+//! A render queue that orders a frame's draws.
+//!
+//! Opaque draws go first, grouped by state so program and texture
+//! changes are rare, front to back within a group so the depth test
+//! rejects hidden pixels early. Transparent draws follow back to front
+//! by view depth, which blending needs, with depth writes off so they
+//! don't hide each other, blend state changing only between modes.
+
+use std::cmp::Ordering;
+
+use glm::{Mat4, Vec3};
+
+use renderer::blend::BlendMode;
+use renderer::gl::Gl;
+
+#[derive(Clone, Debug)]
+pub struct QueueEntry<T> {
+  /// Whatever the caller wants grouped: a program, material or mesh id.
+  pub state: u32,
+  pub blend: BlendMode,
+  /// Distance in front of the camera along its view direction.
+  pub depth: f32,
+  pub item: T,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderQueue<T> {
+  view: Mat4,
+  opaque: Vec<QueueEntry<T>>,
+  transparent: Vec<QueueEntry<T>>,
+}
+
+impl<T> Default for RenderQueue<T> {
+  fn default() -> RenderQueue<T> {
+    RenderQueue { view: Mat4::identity(), opaque: Vec::new(), transparent: Vec::new() }
+  }
+}
+
+impl<T> RenderQueue<T> {
+  pub fn new() -> RenderQueue<T> {
+    RenderQueue::default()
+  }
+
+  /// Start a frame seen through `view`, dropping the last frame's draws.
+  pub fn begin(&mut self, view: &Mat4) {
+    self.view = *view;
+    self.opaque.clear();
+    self.transparent.clear();
+  }
+
+  /// Queue `item`, drawn with `state` and `blend` around the world
+  /// position `center`, usually its bounds' center.
+  pub fn push(&mut self, state: u32, blend: BlendMode, center: &Vec3, item: T) {
+    let view = &self.view;
+    // View space z is negative in front of the camera.
+    let depth = -(view[(2, 0)] * center.x + view[(2, 1)] * center.y + view[(2, 2)] * center.z + view[(2, 3)]);
+    let entry = QueueEntry { state, blend, depth, item };
+    if blend.is_transparent() {
+      self.transparent.push(entry);
+    } else {
+      self.opaque.push(entry);
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.opaque.len() + self.transparent.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.opaque.is_empty() && self.transparent.is_empty()
+  }
+
+  /// Put both lists in drawing order. Equal keys keep the order they
+  /// were pushed in.
+  pub fn sort(&mut self) {
+    let depth = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    self.opaque.sort_by(|a, b| a.state.cmp(&b.state).then(depth(a.depth, b.depth)));
+    self.transparent.sort_by(|a, b| depth(b.depth, a.depth).then(a.state.cmp(&b.state)));
+  }
+
+  pub fn opaque(&self) -> &[QueueEntry<T>] {
+    &self.opaque
+  }
+
+  pub fn transparent(&self) -> &[QueueEntry<T>] {
+    &self.transparent
+  }
+
+  /// Sort, then call `draw` for every entry in order with blending and
+  /// depth writes set up for it. Depth testing is left to the caller.
+  /// Afterwards blending is off and depth writes are on again.
+  pub fn draw<F: FnMut(&QueueEntry<T>)>(&mut self, context: &Gl, mut draw: F) {
+    self.sort();
+    BlendMode::Opaque.apply(context);
+    context.depth_mask(true);
+    for entry in &self.opaque {
+      draw(entry);
+    }
+
+    context.depth_mask(false);
+    let mut current = None;
+    for entry in &self.transparent {
+      if current != Some(entry.blend) {
+        entry.blend.apply(context);
+        current = Some(entry.blend);
+      }
+      draw(entry);
+    }
+    context.depth_mask(true);
+    BlendMode::Opaque.apply(context);
+  }
+}
@@ -0,0 +1,109 @@
+//! A small seeded random number generator, so anything built on it
+//! replays the same way for the same seed.
+
+use glm::Vec3;
+
+/// xorshift64*, plenty for effects and procedural content; not for
+/// anything security related.
+#[derive(Clone, Debug)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Rng {
+    // Zero would stay zero forever; mix the seed so nearby seeds don't
+    // start out correlated.
+    let mut rng = Rng { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 };
+    rng.next_u64();
+    rng
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+
+  pub fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+
+  /// Uniform in `[0, 1)`.
+  pub fn next_f32(&mut self) -> f32 {
+    (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+  }
+
+  /// Uniform in `[min, max)`.
+  pub fn range(&mut self, min: f32, max: f32) -> f32 {
+    min + (max - min) * self.next_f32()
+  }
+
+  /// Standard normal, Box-Muller.
+  pub fn normal(&mut self) -> f32 {
+    let u = 1.0 - self.next_f32();
+    let v = self.next_f32();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+  }
+
+  /// Uniform on the unit sphere.
+  pub fn unit_vector(&mut self) -> Vec3 {
+    let z = self.range(-1.0, 1.0);
+    let angle = self.range(0.0, 2.0 * std::f32::consts::PI);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    glm::vec3(r * angle.cos(), r * angle.sin(), z)
+  }
+
+  /// Uniform inside the unit ball.
+  pub fn in_unit_sphere(&mut self) -> Vec3 {
+    self.unit_vector() * self.next_f32().cbrt()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn same_seed_same_sequence() {
+    let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+    for _ in 0..1000 {
+      assert_eq!(a.next_u64(), b.next_u64());
+    }
+  }
+
+  #[test]
+  fn different_seeds_differ() {
+    for &(first, second) in &[(1, 2), (0, 1), (42, 43)] {
+      let (mut a, mut b) = (Rng::new(first), Rng::new(second));
+      let same = (0..100).filter(|_| a.next_u64() == b.next_u64()).count();
+      assert_eq!(same, 0, "seeds {} and {}", first, second);
+    }
+  }
+
+  #[test]
+  fn zero_seed_does_not_stick() {
+    let mut rng = Rng::new(0);
+    let values: Vec<u64> = (0..10).map(|_| rng.next_u64()).collect();
+    assert!(values.iter().all(|&value| value != 0));
+    assert!(values.windows(2).all(|pair| pair[0] != pair[1]));
+  }
+
+  #[test]
+  fn stays_in_range() {
+    let mut rng = Rng::new(7);
+    let mut sum = 0.0;
+    for _ in 0..10_000 {
+      let value = rng.next_f32();
+      assert!(value >= 0.0 && value < 1.0);
+      sum += value;
+      let ranged = rng.range(-3.0, 5.0);
+      assert!(ranged >= -3.0 && ranged < 5.0);
+      assert!((rng.unit_vector().norm() - 1.0).abs() < 1e-5);
+      assert!(rng.in_unit_sphere().norm() <= 1.0 + 1e-5);
+      assert!(rng.normal().is_finite());
+    }
+    assert!((sum / 10_000.0 - 0.5).abs() < 0.02);
+  }
+}
@@ -0,0 +1,328 @@
+//! GPU objects owned through reference counted handles.
+//!
+//! `Resources` hands out a `Handle` per buffer, texture, program or
+//! framebuffer. Clones of a handle share the object, which is deleted
+//! when the last one drops, or for all of them at once by `teardown`,
+//! after which the remaining handles point at deleted objects.
+//! `renderer_resources` reports what is alive across every manager,
+//! for finding leaks.
+//!
+//! Objects made from a `Description` keep it, so `restore` can build
+//! them again after the context was lost; see `contextloss`.
+
+pub mod registry;
+
+use std::cell::{Ref, RefCell};
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlFramebuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+};
+
+use renderer::{f32_view, u16_view};
+use renderer::gl::Gl;
+use renderer::resources::registry::{texture_bytes, Registry, ResourceKind, ResourceStats, RestoreReport};
+use renderer::shader::{build_program_variant, ShaderVariants};
+use renderer::texture::create_rgba_texture;
+
+/// A WebGL object type `Resources` can own.
+pub trait Resource: Into<JsValue> + JsCast {
+  const KIND: ResourceKind;
+}
+
+impl Resource for WebGlBuffer {
+  const KIND: ResourceKind = ResourceKind::Buffer;
+}
+
+impl Resource for WebGlTexture {
+  const KIND: ResourceKind = ResourceKind::Texture;
+}
+
+impl Resource for WebGlProgram {
+  const KIND: ResourceKind = ResourceKind::Program;
+}
+
+impl Resource for WebGlFramebuffer {
+  const KIND: ResourceKind = ResourceKind::Framebuffer;
+}
+
+/// How to build an object again after a lost context, kept on the CPU
+/// for as long as the object lives.
+#[derive(Clone)]
+pub enum Description {
+  ArrayBuffer(Vec<f32>),
+  ElementBuffer(Vec<u16>),
+  RgbaTexture { width: i32, height: i32, pixels: Vec<u8>, filter: u32 },
+  Program(ShaderVariants),
+  Framebuffer,
+}
+
+impl Description {
+  fn kind(&self) -> ResourceKind {
+    match *self {
+      Description::ArrayBuffer(_) | Description::ElementBuffer(_) => ResourceKind::Buffer,
+      Description::RgbaTexture { .. } => ResourceKind::Texture,
+      Description::Program(_) => ResourceKind::Program,
+      Description::Framebuffer => ResourceKind::Framebuffer,
+    }
+  }
+
+  /// Estimated GPU memory of what `build` makes.
+  fn bytes(&self) -> usize {
+    match *self {
+      Description::ArrayBuffer(ref data) => data.len() * 4,
+      Description::ElementBuffer(ref data) => data.len() * 2,
+      Description::RgbaTexture { width, height, .. } => texture_bytes(width as u32, height as u32, 4, false),
+      Description::Program(_) | Description::Framebuffer => 0,
+    }
+  }
+
+  /// Create and fill the object. Buffers and textures are left bound.
+  fn build(&self, context: &Gl) -> Result<JsValue, JsValue> {
+    Ok(match *self {
+      Description::ArrayBuffer(ref data) => {
+        let buffer = context.create_buffer().ok_or("failed to create buffer")?;
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
+        let array = f32_view(data)?;
+        context.buffer_data_with_array_buffer_view(
+            WebGlRenderingContext::ARRAY_BUFFER,
+            &array,
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+        buffer.into()
+      },
+      Description::ElementBuffer(ref data) => {
+        let buffer = context.create_buffer().ok_or("failed to create buffer")?;
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+        let array = u16_view(data)?;
+        context.buffer_data_with_array_buffer_view(
+            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+            &array,
+            WebGlRenderingContext::STATIC_DRAW,
+        );
+        buffer.into()
+      },
+      Description::RgbaTexture { width, height, ref pixels, filter } => {
+        create_rgba_texture(context, width, height, pixels, filter)?.into()
+      },
+      Description::Program(ref variants) => build_program_variant(context, variants)?.into(),
+      Description::Framebuffer => context.create_framebuffer().ok_or("failed to create framebuffer")?.into(),
+    })
+  }
+}
+
+struct Shared {
+  context: Gl,
+  registry: RefCell<Registry<JsValue, Description>>,
+}
+
+impl Shared {
+  fn release(&self, id: u32) {
+    let removed = self.registry.borrow_mut().remove(id);
+    if let Some((kind, object)) = removed {
+      delete(&self.context, kind, &object);
+    }
+  }
+}
+
+fn delete(context: &Gl, kind: ResourceKind, object: &JsValue) {
+  match kind {
+    ResourceKind::Buffer => context.delete_buffer(Some(object.unchecked_ref())),
+    ResourceKind::Texture => context.delete_texture(Some(object.unchecked_ref())),
+    ResourceKind::Program => context.delete_program(Some(object.unchecked_ref())),
+    ResourceKind::Framebuffer => context.delete_framebuffer(Some(object.unchecked_ref())),
+  }
+}
+
+thread_local! {
+  /// Every manager, for `renderer_resources` and `restore_all`.
+  static MANAGERS: RefCell<Vec<Weak<Shared>>> = RefCell::new(Vec::new());
+}
+
+struct Owned {
+  id: u32,
+  owner: Rc<Shared>,
+}
+
+impl Drop for Owned {
+  fn drop(&mut self) {
+    self.owner.release(self.id);
+  }
+}
+
+/// Shared ownership of one GPU object. The object itself lives in the
+/// manager, which swaps in a new one when a lost context is restored,
+/// so fetch it with `get` where it's used rather than keeping it.
+pub struct Handle<T> {
+  owned: Rc<Owned>,
+  kind: PhantomData<T>,
+}
+
+impl<T> Clone for Handle<T> {
+  fn clone(&self) -> Handle<T> {
+    Handle { owned: self.owned.clone(), kind: PhantomData }
+  }
+}
+
+impl<T: Resource> Handle<T> {
+  /// The current object, e.g. `Some(&*buffer.get())` to bind it. The
+  /// manager is borrowed until the result drops.
+  pub fn get<'a>(&'a self) -> Ref<'a, T> {
+    let id = self.owned.id;
+    Ref::map(self.owned.owner.registry.borrow(), |registry| {
+      registry.object(id).expect("a handle outlived its object").unchecked_ref()
+    })
+  }
+}
+
+impl<T> Handle<T> {
+  /// Unique within its manager.
+  pub fn id(&self) -> u32 {
+    self.owned.id
+  }
+
+  /// Handles sharing the object, this one included.
+  pub fn references(&self) -> usize {
+    Rc::strong_count(&self.owned)
+  }
+
+  /// False once the manager was torn down, or the object couldn't be
+  /// restored after a lost context.
+  pub fn is_live(&self) -> bool {
+    self.owned.owner.registry.borrow().is_live(self.owned.id)
+  }
+}
+
+/// Creates and owns the GPU objects of one context. Clones share the
+/// same objects.
+#[derive(Clone)]
+pub struct Resources {
+  shared: Rc<Shared>,
+}
+
+impl Resources {
+  pub fn new(context: &Gl) -> Resources {
+    let shared = Rc::new(Shared { context: context.clone(), registry: RefCell::new(Registry::new()) });
+    MANAGERS.with(|managers| {
+      let mut managers = managers.borrow_mut();
+      managers.retain(|manager| manager.upgrade().is_some());
+      managers.push(Rc::downgrade(&shared));
+    });
+    Resources { shared }
+  }
+
+  fn insert<T: Resource>(&self, object: JsValue, bytes: usize, description: Option<Description>) -> Handle<T> {
+    let id = self.shared.registry.borrow_mut().insert(T::KIND, object, bytes, description);
+    Handle { owned: Rc::new(Owned { id, owner: self.shared.clone() }), kind: PhantomData }
+  }
+
+  /// Take ownership of `object`, created elsewhere, estimated at
+  /// `bytes` of GPU memory. Without a description it dies with a lost
+  /// context instead of coming back.
+  pub fn adopt<T: Resource>(&self, object: T, bytes: usize) -> Handle<T> {
+    self.insert(object.into(), bytes, None)
+  }
+
+  /// Build `description`, keeping it to build again after a lost
+  /// context. `T` must be the kind it describes.
+  pub fn create<T: Resource>(&self, description: Description) -> Result<Handle<T>, JsValue> {
+    if description.kind() != T::KIND {
+      return Err(format!("a {:?} description can't make a {:?}", description.kind(), T::KIND).into());
+    }
+    let object = description.build(&self.shared.context)?;
+    let bytes = description.bytes();
+    Ok(self.insert(object, bytes, Some(description)))
+  }
+
+  /// Change the memory estimate of `handle`'s object.
+  pub fn set_bytes<T>(&self, handle: &Handle<T>, bytes: usize) {
+    self.shared.registry.borrow_mut().set_bytes(handle.id(), bytes);
+  }
+
+  /// An `ARRAY_BUFFER` holding `data`, left bound.
+  pub fn array_buffer(&self, data: &[f32]) -> Result<Handle<WebGlBuffer>, JsValue> {
+    self.create(Description::ArrayBuffer(data.to_vec()))
+  }
+
+  /// An `ELEMENT_ARRAY_BUFFER` holding `data`, left bound.
+  pub fn element_buffer(&self, data: &[u16]) -> Result<Handle<WebGlBuffer>, JsValue> {
+    self.create(Description::ElementBuffer(data.to_vec()))
+  }
+
+  /// See `texture::create_rgba_texture`.
+  pub fn rgba_texture(&self, width: i32, height: i32, pixels: &[u8], filter: u32) -> Result<Handle<WebGlTexture>, JsValue> {
+    self.create(Description::RgbaTexture { width, height, pixels: pixels.to_vec(), filter })
+  }
+
+  /// The variant of `variants` for the context, see
+  /// `shader::build_program_variant`.
+  pub fn program(&self, variants: &ShaderVariants) -> Result<Handle<WebGlProgram>, JsValue> {
+    self.create(Description::Program(*variants))
+  }
+
+  /// An empty framebuffer; its attachments are counted on their own.
+  pub fn framebuffer(&self) -> Result<Handle<WebGlFramebuffer>, JsValue> {
+    self.create(Description::Framebuffer)
+  }
+
+  pub fn stats(&self) -> ResourceStats {
+    self.shared.registry.borrow().stats()
+  }
+
+  /// Delete every object still alive, oldest first, whatever handles
+  /// remain. Returns how many there were.
+  pub fn teardown(&self) -> usize {
+    let registry = &mut *self.shared.registry.borrow_mut();
+    let live = registry.teardown();
+    for &(kind, object) in &live {
+      delete(&self.shared.context, kind, object);
+    }
+    live.len()
+  }
+
+  /// Build every live object again on the restored context; handles
+  /// get the new objects. What was adopted without a description dies.
+  pub fn restore(&self) -> RestoreReport {
+    let context = &self.shared.context;
+    self.shared.registry.borrow_mut().restore(|_, description| description.build(context))
+  }
+}
+
+/// `Resources::restore` for every manager of `context`.
+pub fn restore_all(context: &Gl) -> RestoreReport {
+  let managers: Vec<Rc<Shared>> = MANAGERS.with(|managers| {
+    managers.borrow().iter().filter_map(|manager| manager.upgrade()).collect()
+  });
+  let mut report = RestoreReport::default();
+  for shared in managers.into_iter().filter(|shared| shared.context.same_context(context)) {
+    let restored = Resources { shared }.restore();
+    report.restored += restored.restored;
+    report.lost += restored.lost;
+  }
+  report
+}
+
+/// Live objects and estimated bytes summed over every manager.
+pub fn live_resources() -> ResourceStats {
+  let mut total = ResourceStats::default();
+  MANAGERS.with(|managers| {
+    for manager in managers.borrow().iter().filter_map(|manager| manager.upgrade()) {
+      total.add(&manager.registry.borrow().stats());
+    }
+  });
+  total
+}
+
+/// `{ buffers, textures, programs, framebuffers, bytes }` alive right
+/// now in every resource manager.
+#[wasm_bindgen]
+pub fn renderer_resources() -> JsValue {
+  JsValue::from_serde(&live_resources()).unwrap()
+}
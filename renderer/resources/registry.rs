@@ -0,0 +1,316 @@
+//! Bookkeeping of GPU objects: what kind each is, roughly how much
+//! memory it holds and, when known, the description to build it again
+//! from after a lost context. Doesn't touch WebGL, the objects and
+//! descriptions are whatever `T` and `D` the caller stores.
+
+use std::collections::BTreeMap;
+
+use serde_derive::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum ResourceKind {
+  Buffer,
+  Texture,
+  Program,
+  Framebuffer,
+}
+
+/// Live objects by kind and their estimated size in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct ResourceStats {
+  pub buffers: u32,
+  pub textures: u32,
+  pub programs: u32,
+  pub framebuffers: u32,
+  pub bytes: usize,
+}
+
+impl ResourceStats {
+  pub fn count(&self, kind: ResourceKind) -> u32 {
+    match kind {
+      ResourceKind::Buffer => self.buffers,
+      ResourceKind::Texture => self.textures,
+      ResourceKind::Program => self.programs,
+      ResourceKind::Framebuffer => self.framebuffers,
+    }
+  }
+
+  pub fn total(&self) -> u32 {
+    self.buffers + self.textures + self.programs + self.framebuffers
+  }
+
+  fn count_mut(&mut self, kind: ResourceKind) -> &mut u32 {
+    match kind {
+      ResourceKind::Buffer => &mut self.buffers,
+      ResourceKind::Texture => &mut self.textures,
+      ResourceKind::Program => &mut self.programs,
+      ResourceKind::Framebuffer => &mut self.framebuffers,
+    }
+  }
+
+  pub fn add(&mut self, other: &ResourceStats) {
+    self.buffers += other.buffers;
+    self.textures += other.textures;
+    self.programs += other.programs;
+    self.framebuffers += other.framebuffers;
+    self.bytes += other.bytes;
+  }
+}
+
+/// What `Registry::restore` did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct RestoreReport {
+  /// Built again from their description.
+  pub restored: u32,
+  /// Without a description, or failed to build; now dead.
+  pub lost: u32,
+}
+
+struct Entry<T, D> {
+  kind: ResourceKind,
+  bytes: usize,
+  object: T,
+  description: Option<D>,
+  /// False once torn down or lost; the object is kept so handles
+  /// still have something to point at.
+  live: bool,
+}
+
+/// Objects by id, from creation until their last handle drops. Ids are
+/// never reused, so a stale id can't reach a newer object.
+pub struct Registry<T, D> {
+  next: u32,
+  entries: BTreeMap<u32, Entry<T, D>>,
+  stats: ResourceStats,
+}
+
+impl<T, D> Default for Registry<T, D> {
+  fn default() -> Registry<T, D> {
+    Registry { next: 0, entries: BTreeMap::new(), stats: ResourceStats::default() }
+  }
+}
+
+impl<T, D> Registry<T, D> {
+  pub fn new() -> Registry<T, D> {
+    Registry::default()
+  }
+
+  /// Start tracking `object`, built from `description` if it can be
+  /// built again, returning its id.
+  pub fn insert(&mut self, kind: ResourceKind, object: T, bytes: usize, description: Option<D>) -> u32 {
+    let id = self.next;
+    self.next += 1;
+    self.entries.insert(id, Entry { kind, bytes, object, description, live: true });
+    *self.stats.count_mut(kind) += 1;
+    self.stats.bytes += bytes;
+    id
+  }
+
+  pub fn object(&self, id: u32) -> Option<&T> {
+    self.entries.get(&id).map(|entry| &entry.object)
+  }
+
+  /// Change the size estimate of an object, e.g. after it was uploaded
+  /// again at another size.
+  pub fn set_bytes(&mut self, id: u32, bytes: usize) {
+    if let Some(entry) = self.entries.get_mut(&id) {
+      if entry.live {
+        self.stats.bytes = self.stats.bytes - entry.bytes + bytes;
+      }
+      entry.bytes = bytes;
+    }
+  }
+
+  pub fn is_live(&self, id: u32) -> bool {
+    self.entries.get(&id).map_or(false, |entry| entry.live)
+  }
+
+  fn kill(stats: &mut ResourceStats, entry: &mut Entry<T, D>) {
+    if entry.live {
+      entry.live = false;
+      *stats.count_mut(entry.kind) -= 1;
+      stats.bytes -= entry.bytes;
+    }
+  }
+
+  /// Forget `id` for good. Hands back the object when it was still
+  /// live and so needs deleting.
+  pub fn remove(&mut self, id: u32) -> Option<(ResourceKind, T)> {
+    let mut entry = self.entries.remove(&id)?;
+    if !entry.live {
+      return None;
+    }
+    Registry::kill(&mut self.stats, &mut entry);
+    Some((entry.kind, entry.object))
+  }
+
+  /// Mark every live object dead, oldest first, handing them back to
+  /// delete. They stay known until their handles drop.
+  pub fn teardown(&mut self) -> Vec<(ResourceKind, &T)> {
+    let stats = &mut self.stats;
+    self.entries.values_mut().filter(|entry| entry.live).map(|entry| {
+      Registry::kill(stats, entry);
+      (entry.kind, &entry.object)
+    }).collect()
+  }
+
+  /// Build every live object again with `create` from its description,
+  /// oldest first, after the old ones went with a lost context. Those
+  /// without a description, or that `create` fails on, die.
+  pub fn restore<E, F: FnMut(ResourceKind, &D) -> Result<T, E>>(&mut self, mut create: F) -> RestoreReport {
+    let mut report = RestoreReport::default();
+    let stats = &mut self.stats;
+    for entry in self.entries.values_mut().filter(|entry| entry.live) {
+      let created = entry.description.as_ref().map(|description| create(entry.kind, description));
+      match created {
+        Some(Ok(object)) => {
+          entry.object = object;
+          report.restored += 1;
+        },
+        _ => {
+          Registry::kill(stats, entry);
+          report.lost += 1;
+        },
+      }
+    }
+    report
+  }
+
+  /// Objects tracked, live or dead.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Live objects only.
+  pub fn stats(&self) -> ResourceStats {
+    self.stats
+  }
+}
+
+/// Bytes of a `width` x `height` texture at `bytes_per_pixel`, with
+/// its mip chain down to 1x1 when `mipmapped`.
+pub fn texture_bytes(width: u32, height: u32, bytes_per_pixel: usize, mipmapped: bool) -> usize {
+  let (mut width, mut height) = (width.max(1) as usize, height.max(1) as usize);
+  let mut bytes = width * height * bytes_per_pixel;
+  while mipmapped && (width > 1 || height > 1) {
+    width = (width / 2).max(1);
+    height = (height / 2).max(1);
+    bytes += width * height * bytes_per_pixel;
+  }
+  bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Stands in for a context: objects are `(generation, description)`,
+  /// and creating one fails for descriptions starting with `!`.
+  struct Context {
+    generation: u32,
+    created: Vec<String>,
+  }
+
+  impl Context {
+    fn create(&mut self, description: &str) -> Result<(u32, String), String> {
+      self.created.push(description.to_string());
+      if description.starts_with('!') {
+        Err(format!("can't create {}", description))
+      } else {
+        Ok((self.generation, description.to_string()))
+      }
+    }
+  }
+
+  fn insert(registry: &mut Registry<(u32, String), String>, kind: ResourceKind, name: &str, bytes: usize) -> u32 {
+    registry.insert(kind, (0, name.to_string()), bytes, Some(name.to_string()))
+  }
+
+  #[test]
+  fn restores_every_live_object_once() {
+    let mut registry = Registry::new();
+    let buffer = insert(&mut registry, ResourceKind::Buffer, "buffer", 64);
+    let texture = insert(&mut registry, ResourceKind::Texture, "texture", 256);
+    let program = insert(&mut registry, ResourceKind::Program, "program", 0);
+    let before = registry.stats();
+
+    let mut context = Context { generation: 1, created: Vec::new() };
+    let report = registry.restore(|_, description| context.create(description));
+    assert_eq!(report, RestoreReport { restored: 3, lost: 0 });
+    assert_eq!(context.created, vec!["buffer", "texture", "program"]);
+    for &id in &[buffer, texture, program] {
+      assert!(registry.is_live(id));
+      assert_eq!(registry.object(id).unwrap().0, 1);
+    }
+    assert_eq!(registry.stats(), before);
+  }
+
+  #[test]
+  fn released_objects_are_not_restored() {
+    let mut registry = Registry::new();
+    let kept = insert(&mut registry, ResourceKind::Buffer, "kept", 16);
+    let released = insert(&mut registry, ResourceKind::Buffer, "released", 32);
+    assert_eq!(registry.remove(released), Some((ResourceKind::Buffer, (0, "released".to_string()))));
+    assert_eq!(registry.remove(released), None);
+
+    let mut context = Context { generation: 1, created: Vec::new() };
+    let report = registry.restore(|_, description| context.create(description));
+    assert_eq!(report, RestoreReport { restored: 1, lost: 0 });
+    assert_eq!(context.created, vec!["kept"]);
+    assert_eq!(registry.object(kept), Some(&(1, "kept".to_string())));
+    assert_eq!(registry.object(released), None);
+    assert_eq!(registry.stats(), ResourceStats { buffers: 1, bytes: 16, ..ResourceStats::default() });
+  }
+
+  #[test]
+  fn teardown_leaves_nothing_live() {
+    let mut registry = Registry::new();
+    let buffer = insert(&mut registry, ResourceKind::Buffer, "buffer", 64);
+    insert(&mut registry, ResourceKind::Texture, "texture", 256);
+    insert(&mut registry, ResourceKind::Framebuffer, "framebuffer", 0);
+    assert_eq!(registry.stats().total(), 3);
+
+    let deleted: Vec<ResourceKind> = registry.teardown().into_iter().map(|(kind, _)| kind).collect();
+    assert_eq!(deleted, vec![ResourceKind::Buffer, ResourceKind::Texture, ResourceKind::Framebuffer]);
+    assert_eq!(registry.stats(), ResourceStats::default());
+    assert!(registry.teardown().is_empty());
+    // Handles still hold their ids until they drop, but have nothing to
+    // delete and nothing to restore.
+    assert_eq!(registry.len(), 3);
+    assert_eq!(registry.remove(buffer), None);
+    let mut context = Context { generation: 1, created: Vec::new() };
+    assert_eq!(registry.restore(|_, description| context.create(description)), RestoreReport::default());
+    assert!(context.created.is_empty());
+    assert_eq!(registry.stats(), ResourceStats::default());
+  }
+
+  #[test]
+  fn reports_what_fails_to_restore() {
+    let mut registry = Registry::new();
+    let good = insert(&mut registry, ResourceKind::Buffer, "good", 8);
+    let bad = insert(&mut registry, ResourceKind::Program, "!bad", 0);
+    let adopted = registry.insert(ResourceKind::Texture, (0, "adopted".to_string()), 100, None);
+
+    let mut context = Context { generation: 1, created: Vec::new() };
+    let report = registry.restore(|_, description| context.create(description));
+    assert_eq!(report, RestoreReport { restored: 1, lost: 2 });
+    assert_eq!(context.created, vec!["good", "!bad"]);
+    assert!(registry.is_live(good) && !registry.is_live(bad) && !registry.is_live(adopted));
+    assert_eq!(registry.stats(), ResourceStats { buffers: 1, bytes: 8, ..ResourceStats::default() });
+    // Lost objects were never recreated, so dropping them deletes nothing.
+    assert_eq!(registry.remove(bad), None);
+    assert_eq!(registry.remove(adopted), None);
+  }
+
+  #[test]
+  fn counts_texture_bytes() {
+    assert_eq!(texture_bytes(4, 4, 4, false), 64);
+    assert_eq!(texture_bytes(4, 4, 4, true), 84);
+    assert_eq!(texture_bytes(8, 2, 1, true), 16 + 4 + 2 + 1);
+    assert_eq!(texture_bytes(0, 0, 4, true), 4);
+  }
+}
@@ -0,0 +1,126 @@
+//! A flat node hierarchy with world transforms and world bounds.
+//!
+//! Nodes refer to their parent by index and a parent always comes
+//! before its children, so a single pass over the list updates every
+//! world transform.
+
+use glm::Mat4;
+
+use renderer::bounds::{Bounds, Frustum};
+use renderer::stats::FrameStats;
+
+pub type NodeId = usize;
+
+#[derive(Clone, Debug)]
+pub struct Node {
+  pub name: String,
+  pub parent: Option<NodeId>,
+  /// Relative to the parent.
+  pub local: Mat4,
+  /// Filled in by `Scene::update_world`.
+  pub world: Mat4,
+  /// Index into whatever mesh list the caller keeps, `None` for pure
+  /// transform nodes.
+  pub mesh: Option<usize>,
+  /// Mesh bounds in local space.
+  pub bounds: Option<Bounds>,
+  /// `bounds` through `world`, filled in by `Scene::update_world`.
+  pub world_bounds: Option<Bounds>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+  nodes: Vec<Node>,
+}
+
+impl Scene {
+  pub fn new() -> Scene {
+    Scene::default()
+  }
+
+  /// A transform only node.
+  pub fn add(&mut self, name: &str, parent: Option<NodeId>, local: Mat4) -> NodeId {
+    self.push(name, parent, local, None, None)
+  }
+
+  /// A node drawing `mesh`, culled with `bounds` (usually `Mesh::bounds`).
+  pub fn add_mesh(
+    &mut self,
+    name: &str,
+    parent: Option<NodeId>,
+    local: Mat4,
+    mesh: usize,
+    bounds: Bounds,
+  ) -> NodeId {
+    self.push(name, parent, local, Some(mesh), Some(bounds))
+  }
+
+  fn push(
+    &mut self,
+    name: &str,
+    parent: Option<NodeId>,
+    local: Mat4,
+    mesh: Option<usize>,
+    bounds: Option<Bounds>,
+  ) -> NodeId {
+    if let Some(parent) = parent {
+      assert!(parent < self.nodes.len(), "parent {} does not exist", parent);
+    }
+    self.nodes.push(Node {
+      name: name.to_string(),
+      parent,
+      local,
+      world: local,
+      mesh,
+      bounds,
+      world_bounds: None,
+    });
+    self.nodes.len() - 1
+  }
+
+  pub fn nodes(&self) -> &[Node] {
+    &self.nodes
+  }
+
+  pub fn node(&self, id: NodeId) -> &Node {
+    &self.nodes[id]
+  }
+
+  pub fn set_local(&mut self, id: NodeId, local: Mat4) {
+    self.nodes[id].local = local;
+  }
+
+  pub fn find(&self, name: &str) -> Option<NodeId> {
+    self.nodes.iter().position(|node| node.name == name)
+  }
+
+  /// Recompute world transforms and world bounds, parents first.
+  pub fn update_world(&mut self) {
+    for i in 0..self.nodes.len() {
+      let world = match self.nodes[i].parent {
+        Some(parent) => self.nodes[parent].world * self.nodes[i].local,
+        None => self.nodes[i].local,
+      };
+      let node = &mut self.nodes[i];
+      node.world = world;
+      node.world_bounds = node.bounds.map(|bounds| bounds.transform(&world));
+    }
+  }
+
+  /// Ids of the mesh nodes inside `frustum`, counting the rest as
+  /// culled in `stats`. Call `update_world` first.
+  pub fn visible(&self, frustum: &Frustum, stats: &mut FrameStats) -> Vec<NodeId> {
+    let mut visible = Vec::new();
+    for (id, node) in self.nodes.iter().enumerate() {
+      if node.mesh.is_none() {
+        continue;
+      }
+      stats.objects += 1;
+      match node.world_bounds {
+        Some(ref bounds) if !frustum.intersects(bounds) => stats.culled += 1,
+        _ => visible.push(id),
+      }
+    }
+    visible
+  }
+}
@@ -0,0 +1,159 @@
+use web_sys::{
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlShader,
+};
+
+use renderer::gl::{Backend, Gl};
+
+/// Attribute locations every program gets bound before linking, so a
+/// mesh's vertex array works with any shader that reads them.
+pub const ATTRIBUTE_LOCATIONS: [(u32, &'static str); 6] = [
+  (0, "aVertexPosition"),
+  (1, "aVertexNormal"),
+  (2, "aTextureCoord"),
+  (3, "aJoints"),
+  (4, "aWeights"),
+  (5, "aTangent"),
+];
+
+/// Sources of one vertex/fragment pair.
+#[derive(Clone, Copy)]
+pub struct ShaderSource {
+  pub vertex: &'static str,
+  pub fragment: &'static str,
+}
+
+/// The same program written for GLSL ES 1.00 (WebGL1) and
+/// GLSL ES 3.00 (WebGL2).
+#[derive(Clone, Copy)]
+pub struct ShaderVariants {
+  pub es100: ShaderSource,
+  pub es300: ShaderSource,
+}
+
+impl ShaderVariants {
+  pub fn for_backend(&self, backend: Backend) -> ShaderSource {
+    match backend {
+      Backend::WebGl1 => self.es100,
+      Backend::WebGl2 => self.es300,
+    }
+  }
+}
+
+pub fn compile_shader(
+    context: &Gl,
+    shader_type: u32,
+    source: &str,
+) -> Result<WebGlShader, String> {
+  let shader = context
+      .create_shader(shader_type)
+      .ok_or_else(|| String::from("Unable to create shader object"))?;
+  context.shader_source(&shader, source);
+  context.compile_shader(&shader);
+
+  if context
+      .get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS)
+      .as_bool()
+      .unwrap_or(false)
+  {
+    Ok(shader)
+  } else {
+    let log = context
+        .get_shader_info_log(&shader)
+        .unwrap_or_else(|| "Unknown error creating shader".into());
+    context.delete_shader(Some(&shader));
+    Err(log)
+  }
+}
+
+/// Link `shaders` into a program. They are detached again afterwards,
+/// so deleting them frees them; the caller still owns them.
+pub fn link_program<'a, T: IntoIterator<Item = &'a WebGlShader>>(
+    context: &Gl,
+    shaders: T,
+) -> Result<WebGlProgram, String> {
+  let program = context
+      .create_program()
+      .ok_or_else(|| String::from("Unable to create shader object"))?;
+  let shaders: Vec<&WebGlShader> = shaders.into_iter().collect();
+  for shader in &shaders {
+    context.attach_shader(&program, shader)
+  }
+  for &(location, name) in ATTRIBUTE_LOCATIONS.iter() {
+    context.bind_attrib_location(&program, location, name);
+  }
+  context.link_program(&program);
+  for shader in &shaders {
+    context.detach_shader(&program, shader);
+  }
+
+  if context
+      .get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS)
+      .as_bool()
+      .unwrap_or(false)
+  {
+    Ok(program)
+  } else {
+    let log = context
+        .get_program_info_log(&program)
+        .unwrap_or_else(|| "Unknown error creating program object".into());
+    context.delete_program(Some(&program));
+    Err(log)
+  }
+}
+
+/// `source` with a `#define` per entry of `defines`, placed after the
+/// `#version` line if there is one.
+pub fn with_defines(source: &str, defines: &[(&str, String)]) -> String {
+  let mut header = String::new();
+  for &(name, ref value) in defines {
+    header.push_str(&format!("#define {} {}\n", name, value));
+  }
+  if source.starts_with("#version") {
+    let end = source.find('\n').map_or(source.len(), |i| i + 1);
+    format!("{}{}{}", &source[..end], header, &source[end..])
+  } else {
+    format!("{}{}", header, source)
+  }
+}
+
+/// Compile a vertex/fragment pair and link them into a program,
+/// deleting the shaders again.
+pub fn build_program(
+    context: &Gl,
+    vertex_source: &str,
+    fragment_source: &str,
+) -> Result<WebGlProgram, String> {
+  let vert_shader = compile_shader(
+      context,
+      WebGlRenderingContext::VERTEX_SHADER,
+      vertex_source,
+  )?;
+  let frag_shader = match compile_shader(
+      context,
+      WebGlRenderingContext::FRAGMENT_SHADER,
+      fragment_source,
+  ) {
+    Ok(shader) => shader,
+    Err(log) => {
+      context.delete_shader(Some(&vert_shader));
+      return Err(log);
+    },
+  };
+
+  // The program keeps what it needs, the shaders can go.
+  let program = link_program(context, [&vert_shader, &frag_shader].iter().cloned());
+  context.delete_shader(Some(&vert_shader));
+  context.delete_shader(Some(&frag_shader));
+  program
+}
+
+/// Build the variant of `variants` that matches the context.
+pub fn build_program_variant(
+    context: &Gl,
+    variants: &ShaderVariants,
+) -> Result<WebGlProgram, String> {
+  let source = variants.for_backend(context.backend());
+  build_program(context, source.vertex, source.fragment)
+}
@@ -0,0 +1,211 @@
+//! Keyframed animation clips and cross-fading between them.
+
+use renderer::skinning::skeleton::{slerp, Pose, Skeleton};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+  /// Hold each key until the next one.
+  Step,
+  /// Lerp translation and scale, slerp rotation.
+  Linear,
+}
+
+/// The keyed values of a channel, one per key time.
+#[derive(Clone, Debug)]
+pub enum Keys {
+  Translation(Vec<[f32; 3]>),
+  /// Quaternions, `[x, y, z, w]`.
+  Rotation(Vec<[f32; 4]>),
+  Scale(Vec<[f32; 3]>),
+}
+
+impl Keys {
+  fn len(&self) -> usize {
+    match *self {
+      Keys::Translation(ref values) | Keys::Scale(ref values) => values.len(),
+      Keys::Rotation(ref values) => values.len(),
+    }
+  }
+}
+
+/// One property of one joint over time.
+#[derive(Clone, Debug)]
+pub struct Channel {
+  pub joint: usize,
+  pub interpolation: Interpolation,
+  /// Seconds, ascending.
+  pub times: Vec<f32>,
+  pub keys: Keys,
+}
+
+impl Channel {
+  /// The key pair around `time` and how far between them it is.
+  fn locate(&self, time: f32) -> (usize, usize, f32) {
+    let last = self.times.len() - 1;
+    if time <= self.times[0] {
+      return (0, 0, 0.0);
+    }
+    if time >= self.times[last] {
+      return (last, last, 0.0);
+    }
+    let next = match self.times.binary_search_by(|t| t.partial_cmp(&time).unwrap_or(std::cmp::Ordering::Less)) {
+      Ok(exact) => return (exact, exact, 0.0),
+      Err(next) => next,
+    };
+    let previous = next - 1;
+    let t = match self.interpolation {
+      Interpolation::Step => 0.0,
+      Interpolation::Linear => (time - self.times[previous]) / (self.times[next] - self.times[previous]),
+    };
+    (previous, next, t)
+  }
+}
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+  [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+#[derive(Clone, Debug)]
+pub struct Clip {
+  pub name: String,
+  /// Seconds, the time of the last key of any channel.
+  pub duration: f32,
+  pub channels: Vec<Channel>,
+}
+
+impl Clip {
+  pub fn new(name: &str, channels: Vec<Channel>) -> Result<Clip, String> {
+    let mut duration: f32 = 0.0;
+    for channel in &channels {
+      if channel.times.is_empty() || channel.times.len() != channel.keys.len() {
+        return Err(format!(
+            "clip {}: joint {} has {} key times and {} values",
+            name, channel.joint, channel.times.len(), channel.keys.len(),
+        ));
+      }
+      duration = duration.max(channel.times[channel.times.len() - 1]);
+    }
+    Ok(Clip { name: name.to_string(), duration, channels })
+  }
+
+  /// Overwrite the joints this clip animates in `pose` with their value
+  /// at `time`; the others keep what they had.
+  pub fn sample(&self, time: f32, pose: &mut Pose) {
+    for channel in &self.channels {
+      let joint = match pose.joints.get_mut(channel.joint) {
+        Some(joint) => joint,
+        None => continue,
+      };
+      let (a, b, t) = channel.locate(time);
+      match channel.keys {
+        Keys::Translation(ref values) => joint.translation = lerp3(values[a], values[b], t),
+        Keys::Rotation(ref values) => joint.rotation = slerp(values[a], values[b], t),
+        Keys::Scale(ref values) => joint.scale = lerp3(values[a], values[b], t),
+      }
+    }
+  }
+}
+
+/// Where one clip is in its playback.
+#[derive(Clone, Copy, Debug)]
+pub struct Playback {
+  /// Index into the clip list given to the `Animator`.
+  pub clip: usize,
+  pub time: f32,
+  pub speed: f32,
+  pub looping: bool,
+}
+
+impl Playback {
+  pub fn new(clip: usize) -> Playback {
+    Playback { clip, time: 0.0, speed: 1.0, looping: true }
+  }
+
+  fn advance(&mut self, dt: f32, duration: f32) {
+    self.time += dt * self.speed;
+    if duration <= 0.0 {
+      self.time = 0.0;
+    } else if self.looping {
+      self.time %= duration;
+      if self.time < 0.0 {
+        self.time += duration;
+      }
+    } else {
+      self.time = self.time.max(0.0).min(duration);
+    }
+  }
+}
+
+struct Fade {
+  from: Playback,
+  elapsed: f32,
+  duration: f32,
+}
+
+/// Plays clips on a skeleton and cross-fades from one to the next.
+pub struct Animator {
+  pub current: Playback,
+  fade: Option<Fade>,
+}
+
+impl Animator {
+  pub fn new(clip: usize) -> Animator {
+    Animator { current: Playback::new(clip), fade: None }
+  }
+
+  /// Switch to `clip` right away, from its start.
+  pub fn play(&mut self, clip: usize) {
+    self.current = Playback { clip, time: 0.0, ..self.current };
+    self.fade = None;
+  }
+
+  /// Blend from what is playing now to `clip` over `duration` seconds.
+  /// Both keep advancing during the fade.
+  pub fn cross_fade(&mut self, clip: usize, duration: f32) {
+    if duration <= 0.0 {
+      return self.play(clip);
+    }
+    let from = self.current;
+    self.current = Playback { clip, time: 0.0, ..from };
+    self.fade = Some(Fade { from, elapsed: 0.0, duration });
+  }
+
+  pub fn is_fading(&self) -> bool {
+    self.fade.is_some()
+  }
+
+  pub fn update(&mut self, dt: f32, clips: &[Clip]) {
+    let duration = |playback: &Playback| clips.get(playback.clip).map_or(0.0, |clip| clip.duration);
+    let current_duration = duration(&self.current);
+    self.current.advance(dt, current_duration);
+    let finished = match self.fade {
+      Some(ref mut fade) => {
+        let from_duration = duration(&fade.from);
+        fade.from.advance(dt, from_duration);
+        fade.elapsed += dt;
+        fade.elapsed >= fade.duration
+      },
+      None => false,
+    };
+    if finished {
+      self.fade = None;
+    }
+  }
+
+  /// The blended pose, starting from the skeleton's rest pose for
+  /// joints the clips don't animate.
+  pub fn pose(&self, skeleton: &Skeleton, clips: &[Clip]) -> Pose {
+    let sample = |playback: &Playback| {
+      let mut pose = skeleton.rest.clone();
+      if let Some(clip) = clips.get(playback.clip) {
+        clip.sample(playback.time, &mut pose);
+      }
+      pose
+    };
+    let current = sample(&self.current);
+    match self.fade {
+      Some(ref fade) => sample(&fade.from).blend(&current, fade.elapsed / fade.duration),
+      None => current,
+    }
+  }
+}
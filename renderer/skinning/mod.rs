@@ -0,0 +1,202 @@
+//! Skeletal animation: skeletons, keyframed clips and a lit program
+//! that skins meshes on the GPU.
+//!
+//! Meshes carry up to four joint indices and weights per vertex in the
+//! `aJoints` and `aWeights` attributes. Joint matrices go to the shader
+//! as a uniform array when the vertex stage has room for them, and as a
+//! float texture otherwise.
+
+pub mod clip;
+pub mod skeleton;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGl2RenderingContext,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::f32_view;
+use renderer::capabilities::{capabilities, Capabilities};
+use renderer::gl::{Backend, Gl};
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program, with_defines, ShaderSource, ShaderVariants};
+use renderer::texture::set_sampling;
+use renderer::uniforms::SceneUniforms;
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("skinned_v.glsl"),
+    fragment: include_str!("../lit/lit_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("skinned_v300.glsl"),
+    fragment: include_str!("../lit/lit_f300.glsl"),
+  },
+};
+
+/// Vertex uniform vectors the skinned shader uses besides the joints:
+/// model, view and projection matrices, with some slack for the
+/// compiler.
+const RESERVED_VECTORS: i32 = 16;
+
+/// How joint matrices reach the vertex shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkinningMode {
+  /// A `mat4` uniform array, four vectors per joint.
+  Uniforms,
+  /// An RGBA float texture one texel high, four texels per joint.
+  Texture,
+}
+
+impl SkinningMode {
+  /// Uniforms when `joints` fit in the vertex stage, a texture if the
+  /// context can sample float textures there.
+  pub fn choose(capabilities: &Capabilities, joints: usize) -> Result<SkinningMode, String> {
+    let vectors = joints as i32 * 4 + RESERVED_VECTORS;
+    if vectors <= capabilities.limits.max_vertex_uniform_vectors {
+      Ok(SkinningMode::Uniforms)
+    } else if capabilities.features.float_textures
+        && capabilities.limits.max_vertex_texture_image_units > 0
+        && joints as i32 * 4 <= capabilities.limits.max_texture_size {
+      Ok(SkinningMode::Texture)
+    } else {
+      Err(format!(
+          "{} joints need {} vertex uniform vectors, the context has {} and can't sample float textures in vertex shaders",
+          joints, vectors, capabilities.limits.max_vertex_uniform_vectors,
+      ))
+    }
+  }
+}
+
+/// Diffuse lit like `LitProgram`, for meshes skinned to a skeleton of
+/// up to `max_joints` joints.
+pub struct SkinnedProgram {
+  pub program: WebGlProgram,
+  pub mode: SkinningMode,
+  pub max_joints: usize,
+  model_matrix: Option<WebGlUniformLocation>,
+  color: Option<WebGlUniformLocation>,
+  joints: Option<WebGlUniformLocation>,
+  joint_sampler: Option<WebGlUniformLocation>,
+  joint_texture_width: Option<WebGlUniformLocation>,
+  joint_texture: Option<WebGlTexture>,
+}
+
+impl SkinnedProgram {
+  pub fn new(context: &Gl, uniforms: &SceneUniforms, max_joints: usize) -> Result<SkinnedProgram, JsValue> {
+    let mode = SkinningMode::choose(&capabilities(context), max_joints)?;
+    SkinnedProgram::with_mode(context, uniforms, max_joints, mode)
+  }
+
+  /// Force a `mode`, e.g. to try the texture path on a context that
+  /// would pick uniforms.
+  pub fn with_mode(
+    context: &Gl,
+    uniforms: &SceneUniforms,
+    max_joints: usize,
+    mode: SkinningMode,
+  ) -> Result<SkinnedProgram, JsValue> {
+    let max_joints = max_joints.max(1);
+    let defines = match mode {
+      SkinningMode::Uniforms => vec![("MAX_JOINTS", max_joints.to_string())],
+      SkinningMode::Texture => {
+        if context.backend() == Backend::WebGl1 {
+          context.get_extension("OES_texture_float")?;
+        }
+        vec![("JOINT_TEXTURE", "1".to_string())]
+      },
+    };
+    let source = SHADERS.for_backend(context.backend());
+    let program = build_program(context, &with_defines(source.vertex, &defines), source.fragment)?;
+    uniforms.attach(context, &program);
+
+    let joint_texture = match mode {
+      SkinningMode::Uniforms => None,
+      SkinningMode::Texture => Some(context.create_texture().ok_or("failed to create texture")?),
+    };
+    Ok(SkinnedProgram {
+      mode,
+      max_joints,
+      model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+      color: context.get_uniform_location(&program, "uColor"),
+      joints: context.get_uniform_location(&program, "uJoints"),
+      joint_sampler: context.get_uniform_location(&program, "uJointTexture"),
+      joint_texture_width: context.get_uniform_location(&program, "uJointTextureWidth"),
+      joint_texture,
+      program,
+    })
+  }
+
+  /// Start drawing with this program.
+  pub fn begin(&self, context: &Gl, uniforms: &SceneUniforms) {
+    context.use_program(Some(&self.program));
+    uniforms.apply(context, &self.program);
+  }
+
+  /// Draw `mesh` with the skinning matrices from
+  /// `Skeleton::joint_matrices`; `begin` must have been called.
+  pub fn draw(
+    &self,
+    context: &Gl,
+    mesh: &Mesh,
+    model: &Mat4,
+    color: [f32; 4],
+    joint_matrices: &[f32],
+  ) -> Result<(), JsValue> {
+    let joints = joint_matrices.len() / 16;
+    if joints == 0 {
+      return Err("no joint matrices".into());
+    }
+    if joints > self.max_joints {
+      return Err(format!("{} joints, the program was built for {}", joints, self.max_joints).into());
+    }
+    match self.joint_texture {
+      None => {
+        let data: JsValue = JsValue::from_serde(joint_matrices).unwrap().into();
+        context.uniform_matrix4fv_with_f32_sequence(self.joints.as_ref(), false, &data);
+      },
+      Some(ref texture) => {
+        let width = (joints * 4) as i32;
+        let internal_format = match context.backend() {
+          Backend::WebGl1 => WebGlRenderingContext::RGBA,
+          Backend::WebGl2 => WebGl2RenderingContext::RGBA32F,
+        };
+        context.active_texture(WebGlRenderingContext::TEXTURE0);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+        let view = f32_view(&joint_matrices[..joints * 16])?;
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            width,
+            1,
+            0,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::FLOAT,
+            Some(&view),
+        )?;
+        set_sampling(context, WebGlRenderingContext::NEAREST);
+        context.uniform1i(self.joint_sampler.as_ref(), 0);
+        context.uniform1f(self.joint_texture_width.as_ref(), width as f32);
+      },
+    }
+
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.model_matrix.as_ref(), false, &data);
+    context.uniform4f(self.color.as_ref(), color[0], color[1], color[2], color[3]);
+    mesh.draw(context);
+    Ok(())
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_program(Some(&self.program));
+    if let Some(ref texture) = self.joint_texture {
+      context.delete_texture(Some(texture));
+    }
+  }
+}
@@ -0,0 +1,199 @@
+//! Joint hierarchies and poses.
+
+use glm::Mat4;
+
+/// Quaternion as `[x, y, z, w]`.
+pub type Quat = [f32; 4];
+
+pub const IDENTITY_ROTATION: Quat = [0.0, 0.0, 0.0, 1.0];
+
+/// Rotation of `angle` radians around the unit `axis`.
+pub fn quat_from_axis_angle(axis: [f32; 3], angle: f32) -> Quat {
+  let (sin, cos) = (angle * 0.5).sin_cos();
+  [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos]
+}
+
+fn normalize(q: Quat) -> Quat {
+  let length = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+  if length > 0.0 {
+    [q[0] / length, q[1] / length, q[2] / length, q[3] / length]
+  } else {
+    IDENTITY_ROTATION
+  }
+}
+
+/// Spherical interpolation along the shorter arc.
+pub fn slerp(a: Quat, b: Quat, t: f32) -> Quat {
+  let mut cos = a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3];
+  // q and -q are the same rotation; flip to take the short way round.
+  let b = if cos < 0.0 {
+    cos = -cos;
+    [-b[0], -b[1], -b[2], -b[3]]
+  } else {
+    b
+  };
+  let (wa, wb) = if cos > 0.9995 {
+    // Nearly parallel, sin(angle) is too small to divide by; a
+    // normalized lerp is indistinguishable here.
+    (1.0 - t, t)
+  } else {
+    let angle = cos.acos();
+    let sin = angle.sin();
+    (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+  };
+  normalize([
+    a[0] * wa + b[0] * wb,
+    a[1] * wa + b[1] * wb,
+    a[2] * wa + b[2] * wb,
+    a[3] * wa + b[3] * wb,
+  ])
+}
+
+/// Translation, rotation and scale of a joint relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+  pub translation: [f32; 3],
+  pub rotation: Quat,
+  pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+  fn default() -> Transform {
+    Transform {
+      translation: [0.0, 0.0, 0.0],
+      rotation: IDENTITY_ROTATION,
+      scale: [1.0, 1.0, 1.0],
+    }
+  }
+}
+
+impl Transform {
+  /// Translation times rotation times scale.
+  pub fn matrix(&self) -> Mat4 {
+    let [x, y, z, w] = normalize(self.rotation);
+    let [sx, sy, sz] = self.scale;
+    let [tx, ty, tz] = self.translation;
+    Mat4::new(
+      (1.0 - 2.0 * (y * y + z * z)) * sx, 2.0 * (x * y - z * w) * sy, 2.0 * (x * z + y * w) * sz, tx,
+      2.0 * (x * y + z * w) * sx, (1.0 - 2.0 * (x * x + z * z)) * sy, 2.0 * (y * z - x * w) * sz, ty,
+      2.0 * (x * z - y * w) * sx, 2.0 * (y * z + x * w) * sy, (1.0 - 2.0 * (x * x + y * y)) * sz, tz,
+      0.0, 0.0, 0.0, 1.0,
+    )
+  }
+
+  /// `self` at `t = 0`, `other` at `t = 1`.
+  pub fn blend(&self, other: &Transform, t: f32) -> Transform {
+    let lerp = |a: [f32; 3], b: [f32; 3]| {
+      [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+    };
+    Transform {
+      translation: lerp(self.translation, other.translation),
+      rotation: slerp(self.rotation, other.rotation, t),
+      scale: lerp(self.scale, other.scale),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct Joint {
+  pub name: String,
+  /// Index of the parent joint, always lower than this joint's own.
+  pub parent: Option<usize>,
+}
+
+/// A local transform per joint of a skeleton.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+  pub joints: Vec<Transform>,
+}
+
+impl Pose {
+  /// `self` at `t = 0`, `other` at `t = 1`, joint by joint.
+  pub fn blend(&self, other: &Pose, t: f32) -> Pose {
+    Pose {
+      joints: self.joints.iter().zip(other.joints.iter()).map(|(a, b)| a.blend(b, t)).collect(),
+    }
+  }
+}
+
+/// Joints sorted parents first, with the pose the mesh was bound in.
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+  pub joints: Vec<Joint>,
+  pub rest: Pose,
+  /// Takes a bind pose vertex into the joint's space.
+  pub inverse_bind: Vec<Mat4>,
+}
+
+impl Skeleton {
+  /// A skeleton bound in its `rest` pose, the usual case for
+  /// procedural rigs. Loaders with their own inverse bind matrices use
+  /// `with_inverse_bind`.
+  pub fn new(joints: Vec<Joint>, rest: Pose) -> Result<Skeleton, String> {
+    let mut skeleton = Skeleton { joints, rest, inverse_bind: Vec::new() };
+    skeleton.validate()?;
+    skeleton.inverse_bind = skeleton.world_matrices(&skeleton.rest)
+        .iter()
+        .map(|world| glm::inverse(world))
+        .collect();
+    Ok(skeleton)
+  }
+
+  pub fn with_inverse_bind(joints: Vec<Joint>, rest: Pose, inverse_bind: Vec<Mat4>) -> Result<Skeleton, String> {
+    if inverse_bind.len() != joints.len() {
+      return Err(format!("{} inverse bind matrices for {} joints", inverse_bind.len(), joints.len()));
+    }
+    let skeleton = Skeleton { joints, rest, inverse_bind };
+    skeleton.validate()?;
+    Ok(skeleton)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if self.rest.joints.len() != self.joints.len() {
+      return Err(format!("rest pose has {} joints, skeleton {}", self.rest.joints.len(), self.joints.len()));
+    }
+    for (i, joint) in self.joints.iter().enumerate() {
+      if let Some(parent) = joint.parent {
+        if parent >= i {
+          return Err(format!("joint {} comes before its parent {}", joint.name, parent));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn len(&self) -> usize {
+    self.joints.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.joints.is_empty()
+  }
+
+  pub fn find(&self, name: &str) -> Option<usize> {
+    self.joints.iter().position(|joint| joint.name == name)
+  }
+
+  /// Each joint's transform in model space.
+  pub fn world_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+    let mut world: Vec<Mat4> = Vec::with_capacity(self.joints.len());
+    for (joint, local) in self.joints.iter().zip(pose.joints.iter()) {
+      let local = local.matrix();
+      let matrix = match joint.parent {
+        Some(parent) => world[parent] * local,
+        None => local,
+      };
+      world.push(matrix);
+    }
+    world
+  }
+
+  /// Skinning matrices for `pose`, column-major and back to back, the
+  /// layout the skinning shaders read.
+  pub fn joint_matrices(&self, pose: &Pose, out: &mut Vec<f32>) {
+    out.clear();
+    for (world, inverse_bind) in self.world_matrices(pose).iter().zip(self.inverse_bind.iter()) {
+      out.extend_from_slice((world * inverse_bind).as_slice());
+    }
+  }
+}
@@ -0,0 +1,42 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec4 aJoints;
+attribute vec4 aWeights;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+#ifdef JOINT_TEXTURE
+// Four RGBA float texels per joint, one matrix column each.
+uniform highp sampler2D uJointTexture;
+uniform float uJointTextureWidth;
+
+mat4 jointMatrix(float joint) {
+  float x = joint * 4.0 + 0.5;
+  return mat4(
+    texture2D(uJointTexture, vec2(x / uJointTextureWidth, 0.5)),
+    texture2D(uJointTexture, vec2((x + 1.0) / uJointTextureWidth, 0.5)),
+    texture2D(uJointTexture, vec2((x + 2.0) / uJointTextureWidth, 0.5)),
+    texture2D(uJointTexture, vec2((x + 3.0) / uJointTextureWidth, 0.5))
+  );
+}
+#else
+uniform mat4 uJoints[MAX_JOINTS];
+
+mat4 jointMatrix(float joint) {
+  return uJoints[int(joint)];
+}
+#endif
+
+varying highp vec3 vNormal;
+
+void main(void) {
+  mat4 skin = aWeights.x * jointMatrix(aJoints.x)
+            + aWeights.y * jointMatrix(aJoints.y)
+            + aWeights.z * jointMatrix(aJoints.z)
+            + aWeights.w * jointMatrix(aJoints.w);
+  vec4 world = uModelMatrix * skin * aVertexPosition;
+  vNormal = (uModelMatrix * skin * vec4(aVertexNormal, 0.0)).xyz;
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
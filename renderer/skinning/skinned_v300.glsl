@@ -0,0 +1,47 @@
+#version 300 es
+
+layout(std140) uniform Camera {
+  mat4 uViewMatrix;
+  mat4 uProjectionMatrix;
+  vec4 uCameraPosition;
+};
+
+in vec4 aVertexPosition;
+in vec3 aVertexNormal;
+in vec4 aJoints;
+in vec4 aWeights;
+
+uniform mat4 uModelMatrix;
+
+#ifdef JOINT_TEXTURE
+// Four RGBA32F texels per joint, one matrix column each.
+uniform highp sampler2D uJointTexture;
+
+mat4 jointMatrix(float joint) {
+  int x = int(joint) * 4;
+  return mat4(
+    texelFetch(uJointTexture, ivec2(x, 0), 0),
+    texelFetch(uJointTexture, ivec2(x + 1, 0), 0),
+    texelFetch(uJointTexture, ivec2(x + 2, 0), 0),
+    texelFetch(uJointTexture, ivec2(x + 3, 0), 0)
+  );
+}
+#else
+uniform mat4 uJoints[MAX_JOINTS];
+
+mat4 jointMatrix(float joint) {
+  return uJoints[int(joint)];
+}
+#endif
+
+out highp vec3 vNormal;
+
+void main(void) {
+  mat4 skin = aWeights.x * jointMatrix(aJoints.x)
+            + aWeights.y * jointMatrix(aJoints.y)
+            + aWeights.z * jointMatrix(aJoints.z)
+            + aWeights.w * jointMatrix(aJoints.w);
+  vec4 world = uModelMatrix * skin * aVertexPosition;
+  vNormal = mat3(uModelMatrix) * mat3(skin) * aVertexNormal;
+  gl_Position = uProjectionMatrix * uViewMatrix * world;
+}
@@ -0,0 +1,283 @@
+//! Batched 2D sprites in canvas pixels.
+//!
+//! Sprites are collected during the frame, sorted by layer and then by
+//! texture, written into one dynamic vertex buffer and drawn with one
+//! call per run of sprites sharing a texture.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::{f32_view, u16_view};
+use renderer::gl::Gl;
+use renderer::shader::build_program;
+use renderer::texture::create_rgba_texture;
+
+pub static VERTEX_SHADER: &'static str = include_str!("sprite_v.glsl");
+pub static FRAGMENT_SHADER: &'static str = include_str!("sprite_f.glsl");
+
+/// Floats per vertex: position, texture coordinate, colour.
+const VERTEX_FLOATS: usize = 2 + 2 + 4;
+const VERTEX_STRIDE: i32 = (VERTEX_FLOATS * 4) as i32;
+/// Four vertices per sprite and 16 bit indices.
+const MAX_SPRITES: usize = 65536 / 4;
+
+/// A texture registered with a `SpriteBatch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureId(usize);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+  pub texture: TextureId,
+  /// Where the pivot lands, in canvas pixels from the top-left.
+  pub position: [f32; 2],
+  /// In pixels.
+  pub size: [f32; 2],
+  /// Point of the sprite that sits on `position` and that it rotates
+  /// around, 0 to 1 from the top-left corner.
+  pub pivot: [f32; 2],
+  /// Radians, clockwise on screen.
+  pub rotation: f32,
+  /// `u0, v0, u1, v1` of the texture region shown.
+  pub uv: [f32; 4],
+  /// Multiplied with the texture.
+  pub color: [f32; 4],
+  /// Higher layers draw on top of lower ones.
+  pub layer: i32,
+}
+
+impl Sprite {
+  /// The whole of `texture`, untinted, top-left corner at `position`.
+  pub fn new(texture: TextureId, position: [f32; 2], size: [f32; 2]) -> Sprite {
+    Sprite {
+      texture,
+      position,
+      size,
+      pivot: [0.0, 0.0],
+      rotation: 0.0,
+      uv: [0.0, 0.0, 1.0, 1.0],
+      color: [1.0, 1.0, 1.0, 1.0],
+      layer: 0,
+    }
+  }
+
+  /// A plain coloured rectangle.
+  pub fn quad(position: [f32; 2], size: [f32; 2], color: [f32; 4]) -> Sprite {
+    Sprite { color, ..Sprite::new(SpriteBatch::WHITE, position, size) }
+  }
+
+  /// The four corners in pixels, clockwise from the top-left.
+  fn corners(&self) -> [[f32; 2]; 4] {
+    let (sin, cos) = self.rotation.sin_cos();
+    let mut corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    for corner in corners.iter_mut() {
+      let x = (corner[0] - self.pivot[0]) * self.size[0];
+      let y = (corner[1] - self.pivot[1]) * self.size[1];
+      // With y pointing down this turns clockwise on screen.
+      *corner = [
+        self.position[0] + x * cos - y * sin,
+        self.position[1] + x * sin + y * cos,
+      ];
+    }
+    corners
+  }
+}
+
+/// Where the sprite camera looks: the canvas pixel at the top-left of
+/// the view and how many screen pixels one sprite pixel covers.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelCamera {
+  pub offset: [f32; 2],
+  pub zoom: f32,
+}
+
+impl Default for PixelCamera {
+  fn default() -> PixelCamera {
+    PixelCamera { offset: [0.0, 0.0], zoom: 1.0 }
+  }
+}
+
+impl PixelCamera {
+  /// Orthographic projection for a `width` x `height` canvas, y down.
+  pub fn projection(&self, width: f32, height: f32) -> Mat4 {
+    let (left, top) = (self.offset[0], self.offset[1]);
+    glm::ortho(left, left + width / self.zoom, top + height / self.zoom, top, -1.0, 1.0)
+  }
+}
+
+pub struct SpriteBatch {
+  pub camera: PixelCamera,
+  textures: Vec<(WebGlTexture, i32, i32)>,
+  sprites: Vec<Sprite>,
+  program: WebGlProgram,
+  color: u32,
+  projection_matrix: Option<WebGlUniformLocation>,
+  sampler: Option<WebGlUniformLocation>,
+  vertices: Vec<f32>,
+  vertex_buffer: WebGlBuffer,
+  index_buffer: WebGlBuffer,
+}
+
+impl SpriteBatch {
+  /// A 1x1 white texture, registered by every batch, for untextured
+  /// quads.
+  pub const WHITE: TextureId = TextureId(0);
+
+  pub fn new(context: &Gl) -> Result<SpriteBatch, JsValue> {
+    let program = build_program(context, VERTEX_SHADER, FRAGMENT_SHADER)?;
+
+    // Every batch uses the same quad pattern, fill it in once.
+    let mut indices = Vec::with_capacity(MAX_SPRITES * 6);
+    for i in 0..MAX_SPRITES {
+      let base = (i * 4) as u16;
+      indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    let index_buffer = context.create_buffer().ok_or("failed to create buffer")?;
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
+    let view = u16_view(&indices)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STATIC_DRAW,
+    );
+
+    let white = create_rgba_texture(context, 1, 1, &[255, 255, 255, 255], WebGlRenderingContext::NEAREST)?;
+
+    Ok(SpriteBatch {
+      camera: PixelCamera::default(),
+      textures: vec![(white, 1, 1)],
+      sprites: Vec::new(),
+      color: context.get_attrib_location(&program, "aColor") as u32,
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      sampler: context.get_uniform_location(&program, "uSampler"),
+      program,
+      vertices: Vec::new(),
+      vertex_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+      index_buffer,
+    })
+  }
+
+  /// Make `texture`, `width` x `height` texels, usable by sprites. The
+  /// batch doesn't own it.
+  pub fn register(&mut self, texture: WebGlTexture, width: i32, height: i32) -> TextureId {
+    self.textures.push((texture, width, height));
+    TextureId(self.textures.len() - 1)
+  }
+
+  /// The `uv` of the pixel rectangle `x, y, width, height` of `texture`.
+  pub fn region(&self, texture: TextureId, x: f32, y: f32, width: f32, height: f32) -> [f32; 4] {
+    let (_, texture_width, texture_height) = self.textures[texture.0];
+    let (w, h) = (texture_width as f32, texture_height as f32);
+    [x / w, y / h, (x + width) / w, (y + height) / h]
+  }
+
+  pub fn len(&self) -> usize {
+    self.sprites.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.sprites.is_empty()
+  }
+
+  pub fn push(&mut self, sprite: Sprite) {
+    self.sprites.push(sprite);
+  }
+
+  /// Draw and forget every sprite pushed since the last flush and
+  /// return the number of draw calls used. The projection follows the
+  /// canvas drawing buffer size.
+  pub fn flush(&mut self, context: &Gl) -> Result<u32, JsValue> {
+    if self.sprites.is_empty() {
+      return Ok(0);
+    }
+    // Stable, so sprites of the same layer and texture keep their order.
+    self.sprites.sort_by_key(|sprite| (sprite.layer, sprite.texture));
+
+    let (width, height) = (context.drawing_buffer_width(), context.drawing_buffer_height());
+    context.use_program(Some(&self.program));
+    let projection = self.camera.projection(width as f32, height as f32);
+    let data: JsValue = JsValue::from_serde(&projection).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(self.projection_matrix.as_ref(), false, &data);
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+    context.uniform1i(self.sampler.as_ref(), 0);
+
+    context.disable(WebGlRenderingContext::DEPTH_TEST);
+    context.enable(WebGlRenderingContext::BLEND);
+    context.blend_func(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA);
+
+    let mut draw_calls = 0;
+    let sprites = std::mem::replace(&mut self.sprites, Vec::new());
+    for chunk in sprites.chunks(MAX_SPRITES) {
+      draw_calls += self.draw_chunk(context, chunk)?;
+    }
+    self.sprites = sprites;
+    self.sprites.clear();
+
+    context.disable(WebGlRenderingContext::BLEND);
+    context.disable_vertex_attrib_array(self.color);
+
+    Ok(draw_calls)
+  }
+
+  /// Upload `sprites`, already sorted, and draw each texture run.
+  fn draw_chunk(&mut self, context: &Gl, sprites: &[Sprite]) -> Result<u32, JsValue> {
+    self.vertices.clear();
+    for sprite in sprites {
+      let corners = sprite.corners();
+      let uv = sprite.uv;
+      let uvs = [[uv[0], uv[1]], [uv[2], uv[1]], [uv[2], uv[3]], [uv[0], uv[3]]];
+      for (corner, uv) in corners.iter().zip(uvs.iter()) {
+        self.vertices.extend_from_slice(corner);
+        self.vertices.extend_from_slice(uv);
+        self.vertices.extend_from_slice(&sprite.color);
+      }
+    }
+
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.vertex_buffer));
+    let view = f32_view(&self.vertices)?;
+    context.buffer_data_with_array_buffer_view(
+        WebGlRenderingContext::ARRAY_BUFFER,
+        &view,
+        WebGlRenderingContext::STREAM_DRAW,
+    );
+    for &(location, components, offset) in &[(0, 2, 0), (2, 2, 2), (self.color, 4, 4)] {
+      context.vertex_attrib_pointer_with_i32(
+          location, components, WebGlRenderingContext::FLOAT, false, VERTEX_STRIDE, offset * 4
+      );
+      context.enable_vertex_attrib_array(location);
+    }
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
+
+    let mut draw_calls = 0;
+    let mut start = 0;
+    while start < sprites.len() {
+      let texture = sprites[start].texture;
+      let end = start + sprites[start..].iter().take_while(|sprite| sprite.texture == texture).count();
+      context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.textures[texture.0].0));
+      context.draw_elements_with_i32(
+          WebGlRenderingContext::TRIANGLES,
+          ((end - start) * 6) as i32,
+          WebGlRenderingContext::UNSIGNED_SHORT,
+          (start * 6 * 2) as i32,
+      );
+      draw_calls += 1;
+      start = end;
+    }
+    Ok(draw_calls)
+  }
+
+  /// Deletes the built-in white texture, not the registered ones.
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.textures[0].0));
+    context.delete_program(Some(&self.program));
+    context.delete_buffer(Some(&self.vertex_buffer));
+    context.delete_buffer(Some(&self.index_buffer));
+  }
+}
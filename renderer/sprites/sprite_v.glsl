@@ -0,0 +1,15 @@
+// Corner in canvas pixels, already rotated around the pivot.
+attribute vec2 aVertexPosition;
+attribute vec2 aTextureCoord;
+attribute vec4 aColor;
+
+uniform mat4 uProjectionMatrix;
+
+varying highp vec2 vTextureCoord;
+varying lowp vec4 vColor;
+
+void main(void) {
+  gl_Position = uProjectionMatrix * vec4(aVertexPosition, 0.0, 1.0);
+  vTextureCoord = aTextureCoord;
+  vColor = aColor;
+}
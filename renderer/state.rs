@@ -0,0 +1,198 @@
+//! What `Gl` last told the context, so calls that change nothing can
+//! be skipped.
+//!
+//! Every slot starts unknown and becomes known with the first call
+//! that sets it, so a context someone else already used is never
+//! assumed to be at the defaults. State changed behind `Gl`'s back,
+//! through `webgl2()` or an extension, must be dropped with
+//! `Gl::forget_state`.
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlVertexArrayObject,
+};
+
+#[derive(Default)]
+pub struct GlState {
+  program: Option<Option<WebGlProgram>>,
+  array_buffer: Option<Option<WebGlBuffer>>,
+  /// Part of the vertex array object's state, like `attributes`.
+  element_buffer: Option<Option<WebGlBuffer>>,
+  vertex_array: Option<Option<WebGlVertexArrayObject>>,
+  /// Enabled or not, by location.
+  attributes: HashMap<u32, bool>,
+  active_texture: Option<u32>,
+  /// By `(unit, target)`.
+  textures: HashMap<(u32, u32), Option<WebGlTexture>>,
+  capabilities: HashMap<u32, bool>,
+  blend_equation: Option<u32>,
+  /// Source and destination colour, then alpha.
+  blend_func: Option<[u32; 4]>,
+  depth_func: Option<u32>,
+  depth_mask: Option<bool>,
+  cull_face: Option<u32>,
+  viewport: Option<[i32; 4]>,
+}
+
+/// Record `value` in `slot`, true if it is a change the context has
+/// to hear about.
+fn set<T: Copy + PartialEq>(slot: &mut Option<T>, value: T) -> bool {
+  if *slot == Some(value) {
+    return false;
+  }
+  *slot = Some(value);
+  true
+}
+
+/// The same JS object, or both none.
+fn same<T: AsRef<JsValue>>(a: Option<&T>, b: Option<&T>) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => a.as_ref() == b.as_ref(),
+    (None, None) => true,
+    _ => false,
+  }
+}
+
+/// `set` for bindings, which are compared by identity.
+fn bind<T: Clone + AsRef<JsValue>>(slot: &mut Option<Option<T>>, object: Option<&T>) -> bool {
+  if let Some(ref known) = *slot {
+    if same(known.as_ref(), object) {
+      return false;
+    }
+  }
+  *slot = Some(object.cloned());
+  true
+}
+
+impl GlState {
+  pub fn use_program(&mut self, program: Option<&WebGlProgram>) -> bool {
+    bind(&mut self.program, program)
+  }
+
+  /// Only `ARRAY_BUFFER` and `ELEMENT_ARRAY_BUFFER` are tracked, any
+  /// other target always goes through.
+  pub fn bind_buffer(&mut self, target: u32, buffer: Option<&WebGlBuffer>) -> bool {
+    match target {
+      WebGlRenderingContext::ARRAY_BUFFER => bind(&mut self.array_buffer, buffer),
+      WebGlRenderingContext::ELEMENT_ARRAY_BUFFER => bind(&mut self.element_buffer, buffer),
+      _ => true,
+    }
+  }
+
+  /// A different vertex array brings its own attributes and element
+  /// buffer, which start unknown again.
+  pub fn bind_vertex_array(&mut self, array: Option<&WebGlVertexArrayObject>) -> bool {
+    if !bind(&mut self.vertex_array, array) {
+      return false;
+    }
+    self.attributes.clear();
+    self.element_buffer = None;
+    true
+  }
+
+  pub fn set_attribute(&mut self, location: u32, enabled: bool) -> bool {
+    if self.attributes.get(&location) == Some(&enabled) {
+      return false;
+    }
+    self.attributes.insert(location, enabled);
+    true
+  }
+
+  pub fn active_texture(&mut self, unit: u32) -> bool {
+    set(&mut self.active_texture, unit)
+  }
+
+  /// Binds to the active unit, so goes through while that is unknown.
+  pub fn bind_texture(&mut self, target: u32, texture: Option<&WebGlTexture>) -> bool {
+    let unit = match self.active_texture {
+      Some(unit) => unit,
+      None => return true,
+    };
+    if let Some(known) = self.textures.get(&(unit, target)) {
+      if same(known.as_ref(), texture) {
+        return false;
+      }
+    }
+    self.textures.insert((unit, target), texture.cloned());
+    true
+  }
+
+  pub fn set_capability(&mut self, cap: u32, enabled: bool) -> bool {
+    if self.capabilities.get(&cap) == Some(&enabled) {
+      return false;
+    }
+    self.capabilities.insert(cap, enabled);
+    true
+  }
+
+  pub fn capability(&self, cap: u32) -> Option<bool> {
+    self.capabilities.get(&cap).cloned()
+  }
+
+  pub fn blend_equation(&mut self, mode: u32) -> bool {
+    set(&mut self.blend_equation, mode)
+  }
+
+  pub fn blend_func(&mut self, factors: [u32; 4]) -> bool {
+    set(&mut self.blend_func, factors)
+  }
+
+  pub fn depth_func(&mut self, func: u32) -> bool {
+    set(&mut self.depth_func, func)
+  }
+
+  pub fn known_depth_func(&self) -> Option<u32> {
+    self.depth_func
+  }
+
+  pub fn depth_mask(&mut self, flag: bool) -> bool {
+    set(&mut self.depth_mask, flag)
+  }
+
+  pub fn cull_face(&mut self, mode: u32) -> bool {
+    set(&mut self.cull_face, mode)
+  }
+
+  pub fn viewport(&mut self, rect: [i32; 4]) -> bool {
+    set(&mut self.viewport, rect)
+  }
+
+  /// Deleting a bound buffer unbinds it; an element buffer may still
+  /// be held by a vertex array that isn't bound, so that one is only
+  /// forgotten.
+  pub fn delete_buffer(&mut self, buffer: &WebGlBuffer) {
+    if let Some(ref mut known) = self.array_buffer {
+      if same(known.as_ref(), Some(buffer)) {
+        *known = None;
+      }
+    }
+    let element = self.element_buffer.as_ref().map_or(false, |known| same(known.as_ref(), Some(buffer)));
+    if element {
+      self.element_buffer = None;
+    }
+  }
+
+  /// Deleting a texture unbinds it from every unit.
+  pub fn delete_texture(&mut self, texture: &WebGlTexture) {
+    for known in self.textures.values_mut() {
+      if same(known.as_ref(), Some(texture)) {
+        *known = None;
+      }
+    }
+  }
+
+  /// Deleting the bound vertex array binds the default one.
+  pub fn delete_vertex_array(&mut self, array: &WebGlVertexArrayObject) {
+    let bound = self.vertex_array.as_ref().map_or(false, |known| same(known.as_ref(), Some(array)));
+    if bound {
+      self.vertex_array = None;
+      self.bind_vertex_array(None);
+    }
+  }
+}
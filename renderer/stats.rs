@@ -0,0 +1,52 @@
+//! Per-frame counters, published to JS through `renderer_frame_stats`.
+
+use std::cell::Cell;
+
+use serde_derive::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct FrameStats {
+  /// Objects with geometry that went through culling.
+  pub objects: u32,
+  /// Objects skipped because they were outside the camera frustum.
+  pub culled: u32,
+  pub draw_calls: u32,
+  pub triangles: u32,
+  /// State changes `Gl` skipped because the context already had them.
+  pub saved_calls: u32,
+}
+
+impl FrameStats {
+  pub fn record_draw(&mut self, triangles: u32) {
+    self.draw_calls += 1;
+    self.triangles += triangles;
+  }
+}
+
+thread_local! {
+  static LAST_FRAME: Cell<FrameStats> = Cell::new(FrameStats::default());
+  static SAVED_CALLS: Cell<u32> = Cell::new(0);
+}
+
+/// Count a call `Gl` didn't make, for the frame being drawn.
+pub fn record_saved_call() {
+  SAVED_CALLS.with(|saved| saved.set(saved.get() + 1));
+}
+
+/// Make `stats` the frame reported to JS; call once per frame after
+/// drawing. The calls saved since the last publish are added in.
+pub fn publish(mut stats: FrameStats) {
+  stats.saved_calls += SAVED_CALLS.with(|saved| saved.replace(0));
+  LAST_FRAME.with(|last| last.set(stats));
+}
+
+pub fn last_frame() -> FrameStats {
+  LAST_FRAME.with(|last| last.get())
+}
+
+/// Counters of the last finished frame.
+#[wasm_bindgen]
+pub fn renderer_frame_stats() -> JsValue {
+  JsValue::from_serde(&last_frame()).unwrap()
+}
@@ -0,0 +1,271 @@
+//! Per-vertex tangents for tangent space normal maps.
+//!
+//! Follows MikkTSpace, the convention glTF and most bakers use, so
+//! maps baked elsewhere come out the same way: each triangle's UV
+//! gradient is projected onto the tangent plane of each of its
+//! corners' normals, normalised, and summed weighted by the corner's
+//! angle. The result is `x, y, z, w` per vertex, where `w` is the
+//! handedness and the bitangent is `w * cross(normal, tangent)`.
+//!
+//! MikkTSpace would split a vertex shared by triangles with opposite
+//! UV winding, mirrored UVs for example. An index buffer can't grow
+//! new vertices here, so such a vertex keeps the handedness of the
+//! larger share of its angle; split mirrored seams when authoring.
+//!
+//! A triangle whose positions or UVs collapse to a line has no tangent
+//! direction and adds nothing, as in MikkTSpace; a vertex left with no
+//! contribution at all gets an arbitrary tangent perpendicular to its
+//! normal. Only malformed attributes and indices are errors.
+
+use renderer::geometry::Geometry;
+
+type Vec3 = [f32; 3];
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+  [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, s: f32) -> Vec3 {
+  [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// `a` with its component along unit `normal` removed, then
+/// normalised; `None` when nothing is left.
+fn project(a: Vec3, normal: Vec3) -> Option<Vec3> {
+  let projected = sub(a, scale(normal, dot(a, normal)));
+  let length = dot(projected, projected).sqrt();
+  if length > 1e-12 && length.is_finite() {
+    Some(scale(projected, 1.0 / length))
+  } else {
+    None
+  }
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+  [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Any unit vector perpendicular to unit `normal`, for vertices whose
+/// triangles all collapse to lines or points, in space or in UV.
+fn perpendicular(normal: Vec3) -> Vec3 {
+  let axis = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+  project(axis, normal).unwrap_or([1.0, 0.0, 0.0])
+}
+
+/// Tangents of an indexed triangle mesh with normals and UVs.
+pub fn generate(geometry: &Geometry) -> Result<Vec<f32>, String> {
+  let count = geometry.vertex_count();
+  if geometry.positions.len() % 3 != 0 {
+    return Err(format!("positions need 3 floats per vertex, got {}", geometry.positions.len()));
+  }
+  if geometry.indices.len() % 3 != 0 {
+    return Err(format!("indices need 3 per triangle, got {}", geometry.indices.len()));
+  }
+  if geometry.normals.len() != count * 3 {
+    return Err(format!("tangents need a normal per vertex, got {} floats for {} vertices", geometry.normals.len(), count));
+  }
+  if geometry.uvs.len() != count * 2 {
+    return Err(format!("tangents need UVs per vertex, got {} floats for {} vertices", geometry.uvs.len(), count));
+  }
+  if let Some(&index) = geometry.indices.iter().find(|&&index| index as usize >= count) {
+    return Err(format!("index {} out of range for {} vertices", index, count));
+  }
+
+  let normal = |i: usize| [geometry.normals[i * 3], geometry.normals[i * 3 + 1], geometry.normals[i * 3 + 2]];
+  let uv = |i: usize| [geometry.uvs[i * 2], geometry.uvs[i * 2 + 1]];
+
+  let mut sums = vec![[0.0f32; 3]; count];
+  // Corner angle with each UV winding, to settle the handedness.
+  let mut windings = vec![0.0f32; count];
+  for triangle in geometry.indices.chunks(3) {
+    let corners = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+    let p = [geometry.position(corners[0]), geometry.position(corners[1]), geometry.position(corners[2])];
+    let t = [uv(corners[0]), uv(corners[1]), uv(corners[2])];
+
+    let (e1, e2) = (sub(p[1], p[0]), sub(p[2], p[0]));
+    let (s1, t1) = (t[1][0] - t[0][0], t[1][1] - t[0][1]);
+    let (s2, t2) = (t[2][0] - t[0][0], t[2][1] - t[0][1]);
+    let area = s1 * t2 - s2 * t1;
+    // A triangle collapsed to a line or point, on the mesh or in UV,
+    // adds nothing.
+    let face = cross(e1, e2);
+    if dot(face, face) == 0.0 || area == 0.0 || !area.is_finite() {
+      continue;
+    }
+    // Direction of increasing u; only its direction matters, so the
+    // division by `area` is left to its sign.
+    let sign = if area > 0.0 { 1.0 } else { -1.0 };
+    let gradient = scale(sub(scale(e1, t2), scale(e2, t1)), sign);
+
+    for k in 0..3 {
+      let vertex = corners[k];
+      let n = normal(vertex);
+      let tangent = match project(gradient, n) {
+        Some(tangent) => tangent,
+        None => continue,
+      };
+      let to_next = project(sub(p[(k + 1) % 3], p[k]), n);
+      let to_previous = project(sub(p[(k + 2) % 3], p[k]), n);
+      let angle = match (to_next, to_previous) {
+        (Some(a), Some(b)) => dot(a, b).max(-1.0).min(1.0).acos(),
+        _ => continue,
+      };
+      sums[vertex] = [
+        sums[vertex][0] + tangent[0] * angle,
+        sums[vertex][1] + tangent[1] * angle,
+        sums[vertex][2] + tangent[2] * angle,
+      ];
+      windings[vertex] += sign * angle;
+    }
+  }
+
+  let mut tangents = Vec::with_capacity(count * 4);
+  for vertex in 0..count {
+    let n = normal(vertex);
+    let tangent = project(sums[vertex], n).unwrap_or_else(|| perpendicular(n));
+    tangents.extend_from_slice(&tangent);
+    tangents.push(if windings[vertex] < 0.0 { -1.0 } else { 1.0 });
+  }
+  Ok(tangents)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use renderer::geometry;
+
+  /// A unit quad in the `z = 0` plane facing `+z`, with `u` given per
+  /// corner and `v` growing along `+y`.
+  fn quad(u: [f32; 4]) -> Geometry {
+    Geometry {
+      positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+      normals: [0.0, 0.0, 1.0].iter().cycle().take(12).cloned().collect(),
+      uvs: vec![u[0], 0.0, u[1], 0.0, u[2], 1.0, u[3], 1.0],
+      indices: vec![0, 1, 2, 0, 2, 3],
+      ..Geometry::default()
+    }
+  }
+
+  fn assert_near(actual: &[f32], expected: &[f32]) {
+    assert!(actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", actual, expected);
+  }
+
+  #[test]
+  fn follows_u() {
+    let tangents = generate(&quad([0.0, 1.0, 1.0, 0.0])).unwrap();
+    for tangent in tangents.chunks(4) {
+      assert_near(tangent, &[1.0, 0.0, 0.0, 1.0]);
+    }
+  }
+
+  #[test]
+  fn mirrored_u_flips_handedness() {
+    let tangents = generate(&quad([1.0, 0.0, 0.0, 1.0])).unwrap();
+    for tangent in tangents.chunks(4) {
+      assert_near(tangent, &[-1.0, 0.0, 0.0, -1.0]);
+    }
+  }
+
+  #[test]
+  fn sphere_tangents_follow_u() {
+    let sphere = geometry::sphere(32, 24);
+    let tangents = generate(&sphere).unwrap();
+    assert_eq!(tangents.len(), sphere.vertex_count() * 4);
+    for (vertex, tangent) in tangents.chunks(4).enumerate() {
+      let t = [tangent[0], tangent[1], tangent[2]];
+      let n = [sphere.normals[vertex * 3], sphere.normals[vertex * 3 + 1], sphere.normals[vertex * 3 + 2]];
+      assert!((dot(t, t) - 1.0).abs() < 1e-4, "vertex {} has length {}", vertex, dot(t, t).sqrt());
+      assert!(dot(t, n).abs() < 1e-4, "vertex {} is off the tangent plane", vertex);
+      assert_eq!(tangent[3], 1.0);
+      // Away from the poles, `+u` is eastwards around the `y` axis.
+      let (x, z) = (n[0], n[2]);
+      let ring = (x * x + z * z).sqrt();
+      if ring > 0.01 {
+        let east = [z / ring, 0.0, -x / ring];
+        assert!(dot(t, east) > 0.99, "vertex {} points {:?}, expected {:?}", vertex, t, east);
+      }
+    }
+  }
+
+  #[test]
+  fn cube_tangents_are_unit_length() {
+    let tangents = generate(&geometry::cube()).unwrap();
+    for tangent in tangents.chunks(4) {
+      assert!((dot([tangent[0], tangent[1], tangent[2]], [tangent[0], tangent[1], tangent[2]]) - 1.0).abs() < 1e-4);
+    }
+  }
+
+  /// Every tangent is finite, unit length, on its normal's tangent plane
+  /// and has a handedness of exactly 1 or -1.
+  fn assert_orthonormal(geometry: &Geometry, tangents: &[f32]) {
+    assert_eq!(tangents.len(), geometry.vertex_count() * 4);
+    for (vertex, tangent) in tangents.chunks(4).enumerate() {
+      let t = [tangent[0], tangent[1], tangent[2]];
+      let n = [geometry.normals[vertex * 3], geometry.normals[vertex * 3 + 1], geometry.normals[vertex * 3 + 2]];
+      assert!(tangent.iter().all(|c| c.is_finite()), "vertex {} is {:?}", vertex, tangent);
+      assert!((dot(t, t) - 1.0).abs() < 1e-4, "vertex {} has length {}", vertex, dot(t, t).sqrt());
+      assert!(dot(t, n).abs() < 1e-4, "vertex {} is off the tangent plane", vertex);
+      assert!(tangent[3] == 1.0 || tangent[3] == -1.0);
+    }
+  }
+
+  #[test]
+  fn degenerate_uvs_fall_back_to_perpendicular() {
+    let unmapped = quad([0.0; 4]);
+    let mut flat = quad([0.0, 1.0, 1.0, 0.0]);
+    flat.uvs = vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 0.0, 1.0];
+    let mut nan = quad([0.0, 1.0, 1.0, 0.0]);
+    nan.uvs[4] = std::f32::NAN;
+    for geometry in &[unmapped, flat, nan] {
+      let tangents = generate(geometry).unwrap();
+      assert_orthonormal(geometry, &tangents);
+    }
+  }
+
+  #[test]
+  fn degenerate_uvs_leave_neighbours_alone() {
+    // A second quad beside the first whose UVs are all one point.
+    let mut mesh = quad([0.0, 1.0, 1.0, 0.0]);
+    mesh.positions.extend_from_slice(&[2.0, 0.0, 0.0, 3.0, 0.0, 0.0, 3.0, 1.0, 0.0, 2.0, 1.0, 0.0]);
+    mesh.normals.extend_from_slice(&mesh.normals.clone());
+    mesh.uvs.extend_from_slice(&[0.5; 8]);
+    mesh.indices.extend_from_slice(&[4, 5, 6, 4, 6, 7]);
+    let tangents = generate(&mesh).unwrap();
+    assert_orthonormal(&mesh, &tangents);
+    for tangent in tangents[..16].chunks(4) {
+      assert_near(tangent, &[1.0, 0.0, 0.0, 1.0]);
+    }
+  }
+
+  #[test]
+  fn skips_collapsed_triangles() {
+    let mut collapsed = quad([0.0, 1.0, 1.0, 0.0]);
+    collapsed.indices.extend_from_slice(&[0, 0, 1]);
+    let tangents = generate(&collapsed).unwrap();
+    assert_near(&tangents[..4], &[1.0, 0.0, 0.0, 1.0]);
+  }
+
+  #[test]
+  fn rejects_mismatched_attributes() {
+    let good = quad([0.0, 1.0, 1.0, 0.0]);
+    let mut normals = good.clone();
+    normals.normals.pop();
+    assert!(generate(&normals).is_err());
+    let mut uvs = good.clone();
+    uvs.uvs.truncate(6);
+    assert!(generate(&uvs).is_err());
+    let mut positions = good.clone();
+    positions.positions.push(0.0);
+    assert!(generate(&positions).is_err());
+    let mut indices = good.clone();
+    indices.indices.push(0);
+    assert!(generate(&indices).is_err());
+    let mut range = good.clone();
+    range.indices[5] = 4;
+    assert!(generate(&range).is_err());
+  }
+}
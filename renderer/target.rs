@@ -0,0 +1,264 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGl2RenderingContext,
+  WebGlFramebuffer,
+  WebGlRenderbuffer,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebglDrawBuffers,
+};
+
+use renderer::capabilities::capabilities;
+use renderer::gl::{Backend, Gl};
+use renderer::texture::set_sampling;
+
+/// An offscreen framebuffer with a colour texture and an optional
+/// depth renderbuffer.
+pub struct RenderTarget {
+  pub framebuffer: WebGlFramebuffer,
+  pub texture: WebGlTexture,
+  depth: Option<WebGlRenderbuffer>,
+  pub width: i32,
+  pub height: i32,
+  data_type: u32,
+}
+
+impl RenderTarget {
+  /// Create a target of `width` x `height` texels. `data_type` is the
+  /// texel type of the colour attachment, `UNSIGNED_BYTE` or `FLOAT`
+  /// when float textures can be rendered to.
+  pub fn new(
+    context: &Gl,
+    width: i32,
+    height: i32,
+    data_type: u32,
+    with_depth: bool,
+  ) -> Result<RenderTarget, JsValue> {
+    let framebuffer = context.create_framebuffer().ok_or("failed to create framebuffer")?;
+    let texture = context.create_texture().ok_or("failed to create texture")?;
+    let depth = if with_depth {
+      Some(context.create_renderbuffer().ok_or("failed to create renderbuffer")?)
+    } else {
+      None
+    };
+
+    let target = RenderTarget {
+      framebuffer,
+      texture,
+      depth,
+      width,
+      height,
+      data_type,
+    };
+    target.allocate(context)?;
+
+    Ok(target)
+  }
+
+  /// Render into this target from now on.
+  pub fn bind(&self, context: &Gl) {
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+    context.viewport(0, 0, self.width, self.height);
+  }
+
+  /// Go back to rendering into the canvas.
+  pub fn unbind(context: &Gl, width: i32, height: i32) {
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+    context.viewport(0, 0, width, height);
+  }
+
+  /// Reallocate the attachments if the size changed.
+  pub fn resize(
+    &mut self,
+    context: &Gl,
+    width: i32,
+    height: i32,
+  ) -> Result<(), JsValue> {
+    if self.width == width && self.height == height {
+      return Ok(());
+    }
+    self.width = width;
+    self.height = height;
+    self.allocate(context)
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_framebuffer(Some(&self.framebuffer));
+    context.delete_texture(Some(&self.texture));
+    if let Some(ref depth) = self.depth {
+      context.delete_renderbuffer(Some(depth));
+    }
+  }
+
+  fn allocate(&self, context: &Gl) -> Result<(), JsValue> {
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.texture));
+    // WebGL2 only renders to float textures with a sized format.
+    let internal_format = match (context.webgl2().is_some(), self.data_type) {
+      (true, WebGlRenderingContext::FLOAT) => WebGl2RenderingContext::RGBA32F,
+      _ => WebGlRenderingContext::RGBA,
+    };
+    context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        internal_format as i32,
+        self.width,
+        self.height,
+        0,
+        WebGlRenderingContext::RGBA,
+        self.data_type,
+        None,
+    )?;
+
+    // Targets are usually NPOT, so no mipmaps and no repeat.
+    set_sampling(context, WebGlRenderingContext::LINEAR);
+
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+    context.framebuffer_texture_2d(
+        WebGlRenderingContext::FRAMEBUFFER,
+        WebGlRenderingContext::COLOR_ATTACHMENT0,
+        WebGlRenderingContext::TEXTURE_2D,
+        Some(&self.texture),
+        0,
+    );
+
+    if let Some(ref depth) = self.depth {
+      context.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(depth));
+      context.renderbuffer_storage(
+          WebGlRenderingContext::RENDERBUFFER,
+          WebGlRenderingContext::DEPTH_COMPONENT16,
+          self.width,
+          self.height,
+      );
+      context.framebuffer_renderbuffer(
+          WebGlRenderingContext::FRAMEBUFFER,
+          WebGlRenderingContext::DEPTH_ATTACHMENT,
+          WebGlRenderingContext::RENDERBUFFER,
+          Some(depth),
+      );
+    }
+
+    let status = context.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+
+    if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+      return Err(format!("incomplete framebuffer: 0x{:x}", status).into());
+    }
+
+    Ok(())
+  }
+}
+
+/// A framebuffer with several colour attachments written in one pass
+/// through `gl_FragData[n]` (WebGL1) or `layout(location = n) out`
+/// (WebGL2), e.g. for a G-buffer.
+pub struct MultiRenderTarget {
+  pub framebuffer: WebGlFramebuffer,
+  pub textures: Vec<WebGlTexture>,
+  depth: WebGlRenderbuffer,
+  pub width: i32,
+  pub height: i32,
+}
+
+impl MultiRenderTarget {
+  /// Needs WebGL2 or `WEBGL_draw_buffers` on WebGL1.
+  pub fn new(
+    context: &Gl,
+    width: i32,
+    height: i32,
+    attachments: u32,
+  ) -> Result<MultiRenderTarget, JsValue> {
+    if !capabilities(context).features.draw_buffers {
+      return Err("multiple render targets need WebGL2 or WEBGL_draw_buffers".into());
+    }
+    let draw_buffers = match context.backend() {
+      Backend::WebGl2 => None,
+      Backend::WebGl1 => Some(context
+          .get_extension("WEBGL_draw_buffers")?
+          .ok_or("multiple render targets need WebGL2 or WEBGL_draw_buffers")?
+          .unchecked_into::<WebglDrawBuffers>()),
+    };
+
+    let framebuffer = context.create_framebuffer().ok_or("failed to create framebuffer")?;
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+
+    // COLOR_ATTACHMENTn_WEBGL share their values with WebGL2's
+    // COLOR_ATTACHMENTn, so one list serves both.
+    let buffers = js_sys::Array::new();
+    let mut textures = Vec::new();
+    for i in 0..attachments {
+      let texture = context.create_texture().ok_or("failed to create texture")?;
+      context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+      context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+          WebGlRenderingContext::TEXTURE_2D,
+          0,
+          WebGlRenderingContext::RGBA as i32,
+          width,
+          height,
+          0,
+          WebGlRenderingContext::RGBA,
+          WebGlRenderingContext::UNSIGNED_BYTE,
+          None,
+      )?;
+      set_sampling(context, WebGlRenderingContext::NEAREST);
+      let attachment = WebGlRenderingContext::COLOR_ATTACHMENT0 + i;
+      context.framebuffer_texture_2d(
+          WebGlRenderingContext::FRAMEBUFFER,
+          attachment,
+          WebGlRenderingContext::TEXTURE_2D,
+          Some(&texture),
+          0,
+      );
+      buffers.push(&JsValue::from(attachment));
+      textures.push(texture);
+    }
+
+    let depth = context.create_renderbuffer().ok_or("failed to create renderbuffer")?;
+    context.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(&depth));
+    context.renderbuffer_storage(
+        WebGlRenderingContext::RENDERBUFFER,
+        WebGlRenderingContext::DEPTH_COMPONENT16,
+        width,
+        height,
+    );
+    context.framebuffer_renderbuffer(
+        WebGlRenderingContext::FRAMEBUFFER,
+        WebGlRenderingContext::DEPTH_ATTACHMENT,
+        WebGlRenderingContext::RENDERBUFFER,
+        Some(&depth),
+    );
+
+    match (context.webgl2(), draw_buffers) {
+      (Some(gl), _) => gl.draw_buffers(&buffers),
+      (None, Some(ext)) => ext.draw_buffers_webgl(&buffers),
+      (None, None) => unreachable!(),
+    }
+
+    let status = context.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+    if status != WebGlRenderingContext::FRAMEBUFFER_COMPLETE {
+      return Err(format!("incomplete framebuffer: 0x{:x}", status).into());
+    }
+
+    Ok(MultiRenderTarget {
+      framebuffer,
+      textures,
+      depth,
+      width,
+      height,
+    })
+  }
+
+  pub fn bind(&self, context: &Gl) {
+    context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+    context.viewport(0, 0, self.width, self.height);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_framebuffer(Some(&self.framebuffer));
+    for texture in &self.textures {
+      context.delete_texture(Some(texture));
+    }
+    context.delete_renderbuffer(Some(&self.depth));
+  }
+}
@@ -0,0 +1,200 @@
+//! Cutting a heightfield into chunks at several levels of detail,
+//! independent of WebGL.
+//!
+//! Chunks are `chunk_size` quads square. Level `n` of a chunk keeps
+//! every `2^n`th sample, so a chunk next to a coarser one has border
+//! vertices the other lacks, and cracks open along the shared edge.
+//! Every chunk hangs a skirt down from its border to cover them, as
+//! deep as the worst error of any level of it or its neighbours.
+
+use renderer::geometry::Geometry;
+use renderer::terrain::heightfield::Heightfield;
+
+/// Quads per chunk side is limited by 16 bit indices.
+pub const MAX_CHUNK_SIZE: usize = 128;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainOptions {
+  /// Quads per chunk side, a power of two up to `MAX_CHUNK_SIZE`.
+  pub chunk_size: usize,
+  /// Levels of detail to build, at most `log2(chunk_size) + 1`.
+  pub lod_levels: usize,
+  /// World distance between samples.
+  pub spacing: f32,
+  /// Camera distance at which chunks drop to level 1; each further
+  /// level starts twice as far as the one before.
+  pub lod_distance: f32,
+  /// Added to the skirt depth the level errors call for.
+  pub skirt_margin: f32,
+}
+
+impl Default for TerrainOptions {
+  fn default() -> TerrainOptions {
+    TerrainOptions {
+      chunk_size: 32,
+      lod_levels: 4,
+      spacing: 1.0,
+      lod_distance: 40.0,
+      skirt_margin: 0.1,
+    }
+  }
+}
+
+impl TerrainOptions {
+  pub fn validate(&self) -> Result<(), String> {
+    if !self.chunk_size.is_power_of_two() || self.chunk_size < 2 || self.chunk_size > MAX_CHUNK_SIZE {
+      return Err(format!("chunk size must be a power of two from 2 to {}, got {}", MAX_CHUNK_SIZE, self.chunk_size));
+    }
+    if self.spacing.is_nan() || self.spacing <= 0.0 {
+      return Err(format!("sample spacing must be positive, got {}", self.spacing));
+    }
+    Ok(())
+  }
+
+  /// The levels actually built: `lod_levels`, but at least one and no
+  /// coarser than one quad per chunk.
+  pub fn levels(&self) -> usize {
+    let most = self.chunk_size.trailing_zeros() as usize + 1;
+    self.lod_levels.max(1).min(most)
+  }
+
+  /// The level for a chunk `distance` away from the camera.
+  pub fn select_lod(&self, distance: f32) -> usize {
+    let mut lod = 0;
+    let mut limit = self.lod_distance;
+    while lod + 1 < self.levels() && distance > limit {
+      lod += 1;
+      limit *= 2.0;
+    }
+    lod
+  }
+}
+
+/// Chunks across x and z. Chunks at the far edges may reach past the
+/// last sample; their vertices there are clamped onto it.
+pub fn chunk_counts(heightfield: &Heightfield, options: &TerrainOptions) -> (usize, usize) {
+  let size = options.chunk_size;
+  ((heightfield.width - 1 + size - 1) / size, (heightfield.depth - 1 + size - 1) / size)
+}
+
+/// Sample `k` of a chunk's row or column at `step` apart, clamped.
+fn sample(chunk: usize, k: usize, step: usize, options: &TerrainOptions, samples: usize) -> usize {
+  (chunk * options.chunk_size + k * step).min(samples - 1)
+}
+
+/// The largest height difference between level `lod` of a chunk and the
+/// full heightfield, interpolating across the triangles as they are
+/// drawn.
+pub fn lod_error(heightfield: &Heightfield, options: &TerrainOptions, cx: usize, cz: usize, lod: usize) -> f32 {
+  let step = 1 << lod;
+  let cells = options.chunk_size / step;
+  let (width, depth) = (heightfield.width, heightfield.depth);
+  let mut error: f32 = 0.0;
+  for j in 0..cells {
+    let (z0, z1) = (sample(cz, j, step, options, depth), sample(cz, j + 1, step, options, depth));
+    for i in 0..cells {
+      let (x0, x1) = (sample(cx, i, step, options, width), sample(cx, i + 1, step, options, width));
+      if x1 == x0 || z1 == z0 {
+        continue;
+      }
+      let a = heightfield.height(x0, z0);
+      let b = heightfield.height(x1, z0);
+      let c = heightfield.height(x0, z1);
+      let d = heightfield.height(x1, z1);
+      for z in z0..z1 + 1 {
+        for x in x0..x1 + 1 {
+          let u = (x - x0) as f32 / (x1 - x0) as f32;
+          let v = (z - z0) as f32 / (z1 - z0) as f32;
+          // Split along b-c like the indices of `chunk_geometry`.
+          let drawn = if u + v <= 1.0 {
+            a + u * (b - a) + v * (c - a)
+          } else {
+            d + (1.0 - u) * (c - d) + (1.0 - v) * (b - d)
+          };
+          error = error.max((heightfield.height(x, z) - drawn).abs());
+        }
+      }
+    }
+  }
+  error
+}
+
+/// How deep each chunk's skirt has to be, row by row: the worst error
+/// of any level of the chunk and the eight around it, plus the margin.
+pub fn skirt_depths(heightfield: &Heightfield, options: &TerrainOptions) -> Vec<f32> {
+  let (columns, rows) = chunk_counts(heightfield, options);
+  let errors: Vec<f32> = (0..rows * columns).map(|index| {
+    (0..options.levels())
+        .map(|lod| lod_error(heightfield, options, index % columns, index / columns, lod))
+        .fold(0.0, f32::max)
+  }).collect();
+  (0..rows * columns).map(|index| {
+    let (cx, cz) = (index % columns, index / columns);
+    let mut depth: f32 = 0.0;
+    for z in cz.saturating_sub(1)..(cz + 2).min(rows) {
+      for x in cx.saturating_sub(1)..(cx + 2).min(columns) {
+        depth = depth.max(errors[z * columns + x]);
+      }
+    }
+    depth + options.skirt_margin
+  }).collect()
+}
+
+/// Level `lod` of chunk `cx, cz` in world units with the heightfield
+/// starting at the origin, normals from the full heightfield so lighting
+/// doesn't change between levels, UVs from 0 to 1 across the whole
+/// terrain for the splat weights, and a skirt `skirt_depth` deep.
+pub fn chunk_geometry(
+  heightfield: &Heightfield,
+  options: &TerrainOptions,
+  cx: usize,
+  cz: usize,
+  lod: usize,
+  skirt_depth: f32,
+) -> Geometry {
+  let step = 1 << lod;
+  let n = options.chunk_size / step + 1;
+  let (width, depth) = (heightfield.width, heightfield.depth);
+  let mut geometry = Geometry::default();
+  let push = |geometry: &mut Geometry, i: usize, j: usize, drop: f32| {
+    let (x, z) = (sample(cx, i, step, options, width), sample(cz, j, step, options, depth));
+    geometry.positions.extend_from_slice(&[
+      x as f32 * options.spacing,
+      heightfield.height(x, z) - drop,
+      z as f32 * options.spacing,
+    ]);
+    geometry.normals.extend_from_slice(&heightfield.normal(x, z, options.spacing));
+    geometry.uvs.extend_from_slice(&[x as f32 / (width - 1) as f32, z as f32 / (depth - 1) as f32]);
+  };
+  for j in 0..n {
+    for i in 0..n {
+      push(&mut geometry, i, j, 0.0);
+    }
+  }
+  for j in 0..n - 1 {
+    for i in 0..n - 1 {
+      let a = (j * n + i) as u16;
+      let (b, c, d) = (a + 1, a + n as u16, a + n as u16 + 1);
+      geometry.indices.extend_from_slice(&[a, c, b, b, c, d]);
+    }
+  }
+
+  // The border once around, +x along the near edge first, which with
+  // the winding below makes the skirt face outwards.
+  let mut border = Vec::with_capacity(4 * (n - 1));
+  border.extend((0..n - 1).map(|i| (i, 0)));
+  border.extend((0..n - 1).map(|j| (n - 1, j)));
+  border.extend((1..n).rev().map(|i| (i, n - 1)));
+  border.extend((1..n).rev().map(|j| (0, j)));
+  let base = geometry.vertex_count();
+  for &(i, j) in &border {
+    push(&mut geometry, i, j, skirt_depth);
+  }
+  for k in 0..border.len() {
+    let next = (k + 1) % border.len();
+    let (a, b) = ((border[k].1 * n + border[k].0) as u16, (border[next].1 * n + border[next].0) as u16);
+    let (low_a, low_b) = ((base + k) as u16, (base + next) as u16);
+    geometry.indices.extend_from_slice(&[a, b, low_a, b, low_b, low_a]);
+  }
+  geometry
+}
@@ -0,0 +1,57 @@
+//! A regular grid of heights, from a Rust slice or a decoded image.
+
+use renderer::image::pipeline::Image;
+
+/// `width` x `depth` heights, row by row along x, rows along z.
+#[derive(Clone, Debug)]
+pub struct Heightfield {
+  pub width: usize,
+  pub depth: usize,
+  pub heights: Vec<f32>,
+}
+
+impl Heightfield {
+  pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> Result<Heightfield, String> {
+    if width < 2 || depth < 2 {
+      return Err(format!("a heightfield needs at least 2x2 samples, got {}x{}", width, depth));
+    }
+    if heights.len() != width * depth {
+      return Err(format!("expected {} heights for {}x{}, got {}", width * depth, width, depth, heights.len()));
+    }
+    Ok(Heightfield { width, depth, heights })
+  }
+
+  /// Heights from the brightness of `image`, black at 0 and white at
+  /// `scale`, with the top row of the image at `z = 0`. Images decode
+  /// to 8 bits per channel, so expect steps of `scale / 255`.
+  pub fn from_image(image: &Image, scale: f32) -> Result<Heightfield, String> {
+    let heights = image.pixels.chunks(4).map(|pixel| {
+      (pixel[0] as f32 + pixel[1] as f32 + pixel[2] as f32) / (3.0 * 255.0) * scale
+    }).collect();
+    Heightfield::new(image.width as usize, image.height as usize, heights)
+  }
+
+  /// The height at a sample, clamped to the edges.
+  pub fn height(&self, x: usize, z: usize) -> f32 {
+    self.heights[z.min(self.depth - 1) * self.width + x.min(self.width - 1)]
+  }
+
+  /// The unit normal at a sample for samples `spacing` apart, from the
+  /// central difference of its neighbours, one sided at the edges.
+  pub fn normal(&self, x: usize, z: usize, spacing: f32) -> [f32; 3] {
+    let (x, z) = (x.min(self.width - 1), z.min(self.depth - 1));
+    let (left, right) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+    let (near, far) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+    let slope_x = (self.height(right, z) - self.height(left, z)) / ((right - left) as f32 * spacing);
+    let slope_z = (self.height(x, far) - self.height(x, near)) / ((far - near) as f32 * spacing);
+    let length = (slope_x * slope_x + 1.0 + slope_z * slope_z).sqrt();
+    [-slope_x / length, 1.0 / length, -slope_z / length]
+  }
+
+  /// Lowest and highest height.
+  pub fn range(&self) -> (f32, f32) {
+    self.heights.iter().fold((std::f32::INFINITY, std::f32::NEG_INFINITY), |(low, high), &h| {
+      (low.min(h), high.max(h))
+    })
+  }
+}
@@ -0,0 +1,199 @@
+//! Heightmap terrain in chunks with distance based levels of detail
+//! and texture splatting.
+//!
+//! `heightfield` and `chunk` don't touch WebGL. `Terrain` uploads every
+//! level of every chunk up front, so picking a level is only choosing
+//! which mesh to draw; `TerrainProgram` draws the chunks in view with
+//! four layer textures blended by a weight map.
+
+pub mod heightfield;
+pub mod chunk;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use renderer::bounds::Bounds;
+use renderer::gl::Gl;
+use renderer::image::ImageTexture;
+use renderer::image::pipeline::{self, DecodeOptions, Image};
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program_variant, ShaderSource, ShaderVariants};
+use renderer::stats::FrameStats;
+use renderer::terrain::chunk::{chunk_counts, chunk_geometry, skirt_depths, TerrainOptions};
+use renderer::terrain::heightfield::Heightfield;
+use renderer::texture::create_rgba_texture;
+use renderer::uniforms::{Camera, SceneUniforms};
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("terrain_v.glsl"),
+    fragment: include_str!("terrain_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("terrain_v300.glsl"),
+    fragment: include_str!("terrain_f300.glsl"),
+  },
+};
+
+pub struct TerrainChunk {
+  /// World bounds, skirt included.
+  pub bounds: Bounds,
+  /// Finest first.
+  lods: Vec<Mesh>,
+}
+
+pub struct Terrain {
+  pub options: TerrainOptions,
+  /// Chunks across x and z.
+  pub columns: usize,
+  pub rows: usize,
+  /// Row by row along x.
+  chunks: Vec<TerrainChunk>,
+}
+
+impl Terrain {
+  pub fn new(context: &Gl, heightfield: &Heightfield, options: &TerrainOptions) -> Result<Terrain, JsValue> {
+    options.validate()?;
+    let (columns, rows) = chunk_counts(heightfield, options);
+    let depths = skirt_depths(heightfield, options);
+    let mut chunks = Vec::with_capacity(columns * rows);
+    for cz in 0..rows {
+      for cx in 0..columns {
+        let depth = depths[cz * columns + cx];
+        let mut lods = Vec::with_capacity(options.levels());
+        let mut bounds = None;
+        for lod in 0..options.levels() {
+          let geometry = chunk_geometry(heightfield, options, cx, cz, lod, depth);
+          // Every level spans the same border and heights, so the finest
+          // one's bounds hold for all.
+          bounds = bounds.or_else(|| Some(geometry.bounds()));
+          lods.push(Mesh::new(context, &geometry)?);
+        }
+        chunks.push(TerrainChunk { bounds: bounds.unwrap(), lods });
+      }
+    }
+    Ok(Terrain { options: *options, columns, rows, chunks })
+  }
+
+  pub fn chunks(&self) -> &[TerrainChunk] {
+    &self.chunks
+  }
+
+  /// The chunks `camera` sees and the level to draw each at, by their
+  /// distance from it, counting the rest as culled in `stats`.
+  pub fn visible(&self, camera: &Camera, stats: &mut FrameStats) -> Vec<(usize, usize)> {
+    let frustum = camera.frustum();
+    let mut visible = Vec::new();
+    for (index, chunk) in self.chunks.iter().enumerate() {
+      stats.objects += 1;
+      if !frustum.intersects(&chunk.bounds) {
+        stats.culled += 1;
+        continue;
+      }
+      let distance = chunk.bounds.aabb.distance_to(&camera.position);
+      visible.push((index, self.options.select_lod(distance)));
+    }
+    visible
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    for chunk in &self.chunks {
+      for mesh in &chunk.lods {
+        mesh.delete(context);
+      }
+    }
+  }
+}
+
+/// What the terrain is painted with.
+pub struct SplatMaterial {
+  /// Stretched once over the terrain: how much of each layer, in R, G,
+  /// B and A, normalized to sum to one where it's drawn.
+  pub weights: WebGlTexture,
+  pub layers: Vec<ImageTexture>,
+  /// Times the layers repeat across the terrain.
+  pub tiling: f32,
+}
+
+impl SplatMaterial {
+  /// Upload a weight map, any size, and four layer images, which are
+  /// mipmapped and, for WebGL1's sake, resized to powers of two so
+  /// they can repeat.
+  pub fn new(context: &Gl, weights: &Image, layers: &[Image; 4], tiling: f32) -> Result<SplatMaterial, JsValue> {
+    let weights = create_rgba_texture(
+      context, weights.width as i32, weights.height as i32, &weights.pixels, WebGlRenderingContext::LINEAR,
+    )?;
+    let options = DecodeOptions { power_of_two: true, ..DecodeOptions::default() };
+    let mut textures = Vec::with_capacity(layers.len());
+    for layer in layers.iter() {
+      let texture = ImageTexture::new(context, &pipeline::prepare(layer, &options))?;
+      for &wrap in &[WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::TEXTURE_WRAP_T] {
+        context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, wrap, WebGlRenderingContext::REPEAT as i32);
+      }
+      textures.push(texture);
+    }
+    Ok(SplatMaterial { weights, layers: textures, tiling })
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.weights));
+    for layer in &self.layers {
+      layer.delete(context);
+    }
+  }
+}
+
+pub struct TerrainProgram {
+  pub program: WebGlProgram,
+  weights: Option<WebGlUniformLocation>,
+  layers: Vec<Option<WebGlUniformLocation>>,
+  tiling: Option<WebGlUniformLocation>,
+}
+
+impl TerrainProgram {
+  pub fn new(context: &Gl, uniforms: &SceneUniforms) -> Result<TerrainProgram, JsValue> {
+    let program = build_program_variant(context, &SHADERS)?;
+    uniforms.attach(context, &program);
+    Ok(TerrainProgram {
+      weights: context.get_uniform_location(&program, "uWeights"),
+      layers: (0..4).map(|i| context.get_uniform_location(&program, &format!("uLayer{}", i))).collect(),
+      tiling: context.get_uniform_location(&program, "uTiling"),
+      program,
+    })
+  }
+
+  /// Draw the chunks of `terrain` that `camera` sees, each at the level
+  /// its distance calls for. `uniforms` must hold the same camera.
+  pub fn draw(
+    &self,
+    context: &Gl,
+    uniforms: &SceneUniforms,
+    terrain: &Terrain,
+    material: &SplatMaterial,
+    camera: &Camera,
+    stats: &mut FrameStats,
+  ) {
+    context.use_program(Some(&self.program));
+    uniforms.apply(context, &self.program);
+    let textures = Some(&material.weights).into_iter().chain(material.layers.iter().map(|layer| &layer.texture));
+    let locations = Some(&self.weights).into_iter().chain(self.layers.iter());
+    for (unit, (texture, location)) in textures.zip(locations).enumerate() {
+      context.active_texture(WebGlRenderingContext::TEXTURE0 + unit as u32);
+      context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+      context.uniform1i(location.as_ref(), unit as i32);
+    }
+    context.uniform1f(self.tiling.as_ref(), material.tiling);
+
+    for (index, lod) in terrain.visible(camera, stats) {
+      let mesh = &terrain.chunks[index].lods[lod];
+      mesh.draw(context);
+      stats.record_draw(mesh.triangle_count());
+    }
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+  }
+}
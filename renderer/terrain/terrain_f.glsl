@@ -0,0 +1,31 @@
+precision mediump float;
+
+uniform vec4 uLightDirection;
+uniform vec4 uLightColor;
+uniform vec4 uAmbientColor;
+
+// How much of each layer, in R, G, B and A, across the whole terrain.
+uniform sampler2D uWeights;
+uniform sampler2D uLayer0;
+uniform sampler2D uLayer1;
+uniform sampler2D uLayer2;
+uniform sampler2D uLayer3;
+uniform float uTiling;
+
+varying highp vec3 vNormal;
+varying highp vec2 vTextureCoord;
+
+void main(void) {
+  vec4 weights = texture2D(uWeights, vTextureCoord);
+  weights /= max(dot(weights, vec4(1.0)), 0.0001);
+  vec2 tiled = vTextureCoord * uTiling;
+  vec3 albedo =
+      texture2D(uLayer0, tiled).rgb * weights.r +
+      texture2D(uLayer1, tiled).rgb * weights.g +
+      texture2D(uLayer2, tiled).rgb * weights.b +
+      texture2D(uLayer3, tiled).rgb * weights.a;
+
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  gl_FragColor = vec4(albedo * (uAmbientColor.rgb + uLightColor.rgb * diffuse), 1.0);
+}
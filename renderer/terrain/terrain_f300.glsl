@@ -0,0 +1,36 @@
+#version 300 es
+precision mediump float;
+
+layout(std140) uniform Light {
+  vec4 uLightDirection;
+  vec4 uLightColor;
+  vec4 uAmbientColor;
+};
+
+// How much of each layer, in R, G, B and A, across the whole terrain.
+uniform sampler2D uWeights;
+uniform sampler2D uLayer0;
+uniform sampler2D uLayer1;
+uniform sampler2D uLayer2;
+uniform sampler2D uLayer3;
+uniform float uTiling;
+
+in highp vec3 vNormal;
+in highp vec2 vTextureCoord;
+
+out vec4 fragColor;
+
+void main(void) {
+  vec4 weights = texture(uWeights, vTextureCoord);
+  weights /= max(dot(weights, vec4(1.0)), 0.0001);
+  vec2 tiled = vTextureCoord * uTiling;
+  vec3 albedo =
+      texture(uLayer0, tiled).rgb * weights.r +
+      texture(uLayer1, tiled).rgb * weights.g +
+      texture(uLayer2, tiled).rgb * weights.b +
+      texture(uLayer3, tiled).rgb * weights.a;
+
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  fragColor = vec4(albedo * (uAmbientColor.rgb + uLightColor.rgb * diffuse), 1.0);
+}
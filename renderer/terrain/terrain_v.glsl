@@ -0,0 +1,16 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec2 aTextureCoord;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+
+varying highp vec3 vNormal;
+varying highp vec2 vTextureCoord;
+
+// Terrain vertices are already in world space.
+void main(void) {
+  vNormal = aVertexNormal;
+  vTextureCoord = aTextureCoord;
+  gl_Position = uProjectionMatrix * uViewMatrix * aVertexPosition;
+}
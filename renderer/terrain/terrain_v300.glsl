@@ -0,0 +1,21 @@
+#version 300 es
+
+layout(std140) uniform Camera {
+  mat4 uViewMatrix;
+  mat4 uProjectionMatrix;
+  vec4 uCameraPosition;
+};
+
+in vec4 aVertexPosition;
+in vec3 aVertexNormal;
+in vec2 aTextureCoord;
+
+out highp vec3 vNormal;
+out highp vec2 vTextureCoord;
+
+// Terrain vertices are already in world space.
+void main(void) {
+  vNormal = aVertexNormal;
+  vTextureCoord = aTextureCoord;
+  gl_Position = uProjectionMatrix * uViewMatrix * aVertexPosition;
+}
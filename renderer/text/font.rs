@@ -0,0 +1,321 @@
+//! Font atlas descriptors in the BMFont formats.
+//!
+//! Text (`.fnt`) descriptors are parsed here; JSON descriptors, as
+//! written by tools like `msdf-bmfont-xml`, deserialize into `BmFont`.
+
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+/// Where one character sits in the atlas and how to place it, in
+/// atlas pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Glyph {
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32,
+  pub x_offset: f32,
+  pub y_offset: f32,
+  pub x_advance: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Font {
+  /// The size the atlas was rendered at, in pixels.
+  pub size: f32,
+  pub line_height: f32,
+  /// Distance from the top of a line to the baseline.
+  pub base: f32,
+  pub atlas_width: f32,
+  pub atlas_height: f32,
+  pub glyphs: HashMap<char, Glyph>,
+  pub kerning: HashMap<(char, char), f32>,
+  /// Set for distance field atlases: how many atlas pixels the
+  /// distance spans on each side of the edge.
+  pub distance_range: Option<f32>,
+  /// A multi-channel distance field (MSDF), the distance is the median
+  /// of red, green and blue.
+  pub multichannel: bool,
+}
+
+impl Font {
+  pub fn glyph(&self, c: char) -> Option<&Glyph> {
+    self.glyphs.get(&c)
+  }
+
+  pub fn kerning(&self, first: char, second: char) -> f32 {
+    self.kerning.get(&(first, second)).cloned().unwrap_or(0.0)
+  }
+
+  pub fn is_sdf(&self) -> bool {
+    self.distance_range.is_some()
+  }
+
+  /// Parse a BMFont text descriptor. Only the first page is used;
+  /// characters on other pages are left out.
+  pub fn from_bmfont_text(source: &str) -> Result<Font, String> {
+    let mut font = Font::default();
+    for (number, line) in source.lines().enumerate() {
+      let mut tokens = tokenize(line).into_iter();
+      let tag = match tokens.next() {
+        Some((tag, None)) => tag,
+        _ => continue,
+      };
+      let mut values = HashMap::new();
+      for (key, value) in tokens {
+        values.insert(key, value.unwrap_or_default());
+      }
+      let number_of = |key: &str| -> Result<f32, String> {
+        match values.get(key) {
+          Some(value) => value
+              .parse::<f32>()
+              .map_err(|_| format!("line {}: bad value for {}: {}", number + 1, key, value)),
+          None => Ok(0.0),
+        }
+      };
+
+      match tag.as_str() {
+        "info" => font.size = number_of("size")?.abs(),
+        "common" => {
+          font.line_height = number_of("lineHeight")?;
+          font.base = number_of("base")?;
+          font.atlas_width = number_of("scaleW")?;
+          font.atlas_height = number_of("scaleH")?;
+        },
+        "char" => {
+          if number_of("page")? != 0.0 {
+            continue;
+          }
+          let id = number_of("id")? as u32;
+          let c = std::char::from_u32(id)
+              .ok_or_else(|| format!("line {}: bad character id {}", number + 1, id))?;
+          font.glyphs.insert(c, Glyph {
+            x: number_of("x")?,
+            y: number_of("y")?,
+            width: number_of("width")?,
+            height: number_of("height")?,
+            x_offset: number_of("xoffset")?,
+            y_offset: number_of("yoffset")?,
+            x_advance: number_of("xadvance")?,
+          });
+        },
+        "kerning" => {
+          let first = std::char::from_u32(number_of("first")? as u32);
+          let second = std::char::from_u32(number_of("second")? as u32);
+          if let (Some(first), Some(second)) = (first, second) {
+            font.kerning.insert((first, second), number_of("amount")?);
+          }
+        },
+        // Not part of the format, but how SDF tools commonly tag their
+        // text output.
+        "distanceField" => {
+          font.distance_range = Some(number_of("distanceRange")?);
+          font.multichannel = values.get("fieldType").map_or(false, |t| t == "msdf");
+        },
+        _ => (),
+      }
+    }
+    font.validate()?;
+    Ok(font)
+  }
+
+  fn validate(&mut self) -> Result<(), String> {
+    if self.atlas_width <= 0.0 || self.atlas_height <= 0.0 {
+      return Err("font has no atlas size (common scaleW/scaleH)".into());
+    }
+    if self.glyphs.is_empty() {
+      return Err("font has no characters".into());
+    }
+    if self.size == 0.0 {
+      self.size = self.line_height;
+    }
+    Ok(())
+  }
+}
+
+/// Split `key=value` pairs, keeping spaces inside quoted values. A bare
+/// word gives a `None` value.
+fn tokenize(line: &str) -> Vec<(String, Option<String>)> {
+  let mut tokens = Vec::new();
+  let mut chars = line.chars().peekable();
+  loop {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+      chars.next();
+    }
+    if chars.peek().is_none() {
+      break;
+    }
+    let mut key = String::new();
+    while let Some(&c) = chars.peek() {
+      if c.is_whitespace() || c == '=' {
+        break;
+      }
+      key.push(c);
+      chars.next();
+    }
+    if chars.peek() != Some(&'=') {
+      tokens.push((key, None));
+      continue;
+    }
+    chars.next();
+    let mut value = String::new();
+    if chars.peek() == Some(&'"') {
+      chars.next();
+      while let Some(c) = chars.next() {
+        if c == '"' {
+          break;
+        }
+        value.push(c);
+      }
+    } else {
+      while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+          break;
+        }
+        value.push(c);
+        chars.next();
+      }
+    }
+    tokens.push((key, Some(value)));
+  }
+  tokens
+}
+
+/// A BMFont JSON descriptor.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BmFont {
+  pub info: BmFontInfo,
+  pub common: BmFontCommon,
+  pub chars: Vec<BmFontChar>,
+  #[serde(default)]
+  pub kernings: Vec<BmFontKerning>,
+  #[serde(default, rename = "distanceField")]
+  pub distance_field: Option<BmFontDistanceField>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BmFontInfo {
+  pub size: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BmFontCommon {
+  pub line_height: f32,
+  pub base: f32,
+  pub scale_w: f32,
+  pub scale_h: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BmFontChar {
+  pub id: u32,
+  #[serde(default)]
+  pub page: u32,
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32,
+  pub xoffset: f32,
+  pub yoffset: f32,
+  pub xadvance: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BmFontKerning {
+  pub first: u32,
+  pub second: u32,
+  pub amount: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BmFontDistanceField {
+  pub field_type: String,
+  pub distance_range: f32,
+}
+
+impl Font {
+  /// Only the first page is used, as in `from_bmfont_text`.
+  pub fn from_bmfont(descriptor: &BmFont) -> Result<Font, String> {
+    let mut font = Font {
+      size: descriptor.info.size.abs(),
+      line_height: descriptor.common.line_height,
+      base: descriptor.common.base,
+      atlas_width: descriptor.common.scale_w,
+      atlas_height: descriptor.common.scale_h,
+      distance_range: descriptor.distance_field.as_ref().map(|field| field.distance_range),
+      multichannel: descriptor.distance_field.as_ref().map_or(false, |field| field.field_type == "msdf"),
+      ..Font::default()
+    };
+    for c in descriptor.chars.iter().filter(|c| c.page == 0) {
+      if let Some(id) = std::char::from_u32(c.id) {
+        font.glyphs.insert(id, Glyph {
+          x: c.x,
+          y: c.y,
+          width: c.width,
+          height: c.height,
+          x_offset: c.xoffset,
+          y_offset: c.yoffset,
+          x_advance: c.xadvance,
+        });
+      }
+    }
+    for k in &descriptor.kernings {
+      if let (Some(first), Some(second)) = (std::char::from_u32(k.first), std::char::from_u32(k.second)) {
+        font.kerning.insert((first, second), k.amount);
+      }
+    }
+    font.validate()?;
+    Ok(font)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DESCRIPTOR: &str = "info face=\"Test Sans\" size=-16
+common lineHeight=20 base=15 scaleW=64 scaleH=32 pages=2
+page id=0 file=\"test_0.png\"
+page id=1 file=\"test_1.png\"
+chars count=3
+char id=65 x=1 y=2 width=8 height=10 xoffset=0 yoffset=3 xadvance=9 page=0
+char id=66 x=10 y=2 width=8 height=10 xoffset=0 yoffset=3 xadvance=9 page=0
+char id=67 x=1 y=2 width=8 height=10 xoffset=0 yoffset=3 xadvance=9 page=1
+kerning first=65 second=66 amount=-1
+";
+
+  fn bmfont_char(id: u32, page: u32) -> BmFontChar {
+    BmFontChar { id, page, x: 1.0, y: 2.0, width: 8.0, height: 10.0, xoffset: 0.0, yoffset: 3.0, xadvance: 9.0 }
+  }
+
+  #[test]
+  fn parses_text_descriptors() {
+    let font = Font::from_bmfont_text(DESCRIPTOR).unwrap();
+    assert_eq!((font.size, font.line_height, font.base), (16.0, 20.0, 15.0));
+    assert_eq!((font.atlas_width, font.atlas_height), (64.0, 32.0));
+    assert_eq!(font.glyph('B').unwrap().x, 10.0);
+    assert_eq!(font.kerning('A', 'B'), -1.0);
+    assert_eq!(font.kerning('B', 'A'), 0.0);
+  }
+
+  #[test]
+  fn leaves_out_characters_on_other_pages() {
+    let font = Font::from_bmfont_text(DESCRIPTOR).unwrap();
+    assert!(font.glyph('A').is_some());
+    assert!(font.glyph('C').is_none());
+
+    let descriptor = BmFont {
+      info: BmFontInfo { size: 16.0 },
+      common: BmFontCommon { line_height: 20.0, base: 15.0, scale_w: 64.0, scale_h: 32.0 },
+      chars: vec![bmfont_char(65, 0), bmfont_char(67, 1)],
+      kernings: Vec::new(),
+      distance_field: None,
+    };
+    let font = Font::from_bmfont(&descriptor).unwrap();
+    assert!(font.glyph('A').is_some());
+    assert!(font.glyph('C').is_none());
+  }
+}
@@ -0,0 +1,174 @@
+//! Turning a string into positioned glyph quads: kerning, line breaks,
+//! word wrapping and alignment.
+
+use renderer::text::font::Font;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+  Left,
+  Center,
+  Right,
+}
+
+/// One glyph, in font pixels relative to the text origin with y going
+/// down, and its atlas rectangle in texture coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+  pub left: f32,
+  pub top: f32,
+  pub right: f32,
+  pub bottom: f32,
+  pub uv: [f32; 4],
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+  pub quads: Vec<GlyphQuad>,
+  /// Width of the widest line.
+  pub width: f32,
+  pub height: f32,
+  pub lines: usize,
+}
+
+/// Lay `text` out with `font`. Lines break at `\n` or `\r\n` and, when
+/// `max_width` is set, between words; a single word wider than that
+/// gets a line of its own. `align` places each line relative to the
+/// origin: starting at it, centered on it, or ending at it.
+pub fn layout(font: &Font, text: &str, max_width: Option<f32>, align: Align) -> Layout {
+  let mut lines = Vec::new();
+  for paragraph in text.split('\n') {
+    match max_width {
+      Some(max_width) => wrap(font, paragraph, max_width, &mut lines),
+      None => lines.push(paragraph.to_string()),
+    }
+  }
+
+  let mut result = Layout {
+    lines: lines.len(),
+    height: lines.len() as f32 * font.line_height,
+    ..Layout::default()
+  };
+  for (row, line) in lines.iter().enumerate() {
+    let width = line_width(font, line);
+    result.width = result.width.max(width);
+    let start = match align {
+      Align::Left => 0.0,
+      Align::Center => -width * 0.5,
+      Align::Right => -width,
+    };
+    emit(font, line, start, row as f32 * font.line_height, &mut result.quads);
+  }
+  result
+}
+
+/// The glyph used for `c`, falling back to `?` for characters missing
+/// from the atlas. Carriage returns from `\r\n` line ends draw nothing.
+fn resolve(font: &Font, c: char) -> Option<char> {
+  if c == '\r' {
+    None
+  } else if font.glyph(c).is_some() {
+    Some(c)
+  } else if font.glyph('?').is_some() {
+    Some('?')
+  } else {
+    None
+  }
+}
+
+/// Pen advance over `line`, kerning included.
+pub fn line_width(font: &Font, line: &str) -> f32 {
+  let mut width = 0.0;
+  let mut previous = None;
+  for c in line.chars().filter_map(|c| resolve(font, c)) {
+    if let Some(previous) = previous {
+      width += font.kerning(previous, c);
+    }
+    width += font.glyph(c).map_or(0.0, |glyph| glyph.x_advance);
+    previous = Some(c);
+  }
+  width
+}
+
+fn wrap(font: &Font, paragraph: &str, max_width: f32, lines: &mut Vec<String>) {
+  let mut line = String::new();
+  for word in paragraph.split(' ') {
+    if line.is_empty() {
+      line.push_str(word);
+      continue;
+    }
+    let candidate = format!("{} {}", line, word);
+    if line_width(font, &candidate) > max_width {
+      lines.push(line);
+      line = word.to_string();
+    } else {
+      line = candidate;
+    }
+  }
+  lines.push(line);
+}
+
+fn emit(font: &Font, line: &str, start: f32, top: f32, quads: &mut Vec<GlyphQuad>) {
+  let mut pen = start;
+  let mut previous = None;
+  for c in line.chars().filter_map(|c| resolve(font, c)) {
+    if let Some(previous) = previous {
+      pen += font.kerning(previous, c);
+    }
+    let glyph = match font.glyph(c) {
+      Some(glyph) => glyph,
+      None => continue,
+    };
+    if glyph.width > 0.0 && glyph.height > 0.0 {
+      let left = pen + glyph.x_offset;
+      let top = top + glyph.y_offset;
+      quads.push(GlyphQuad {
+        left,
+        top,
+        right: left + glyph.width,
+        bottom: top + glyph.height,
+        uv: [
+          glyph.x / font.atlas_width,
+          glyph.y / font.atlas_height,
+          (glyph.x + glyph.width) / font.atlas_width,
+          (glyph.y + glyph.height) / font.atlas_height,
+        ],
+      });
+    }
+    pen += glyph.x_advance;
+    previous = Some(c);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use renderer::text::font::Glyph;
+
+  /// `A` and `?`, 10 pixels wide each.
+  fn font() -> Font {
+    let mut font = Font { line_height: 20.0, atlas_width: 64.0, atlas_height: 64.0, ..Font::default() };
+    let glyph = Glyph { width: 8.0, height: 12.0, x_advance: 10.0, ..Glyph::default() };
+    font.glyphs.insert('A', glyph);
+    font.glyphs.insert('?', Glyph { x: 16.0, ..glyph });
+    font
+  }
+
+  #[test]
+  fn missing_characters_fall_back_to_question_marks() {
+    let text = layout(&font(), "AbA", None, Align::Left);
+    assert_eq!(text.quads.len(), 3);
+    assert_eq!(text.quads[1].uv[0], 16.0 / 64.0);
+    assert_eq!(text.width, 30.0);
+  }
+
+  #[test]
+  fn crlf_line_ends_draw_nothing() {
+    let font = font();
+    let unix = layout(&font, "AA\nA", None, Align::Left);
+    let windows = layout(&font, "AA\r\nA", None, Align::Left);
+    assert_eq!(windows.lines, 2);
+    assert_eq!(windows.width, 20.0);
+    assert_eq!(windows.quads, unix.quads);
+    assert_eq!(line_width(&font, "A\r"), 10.0);
+  }
+}
@@ -0,0 +1,371 @@
+//! Text from a font atlas: bitmap fonts, single channel and
+//! multi-channel distance fields.
+//!
+//! Strings are laid out on the CPU and collected into a `TextBatch`,
+//! which draws all of its glyphs with one call, either in canvas pixels
+//! or as camera facing billboards anchored in the world.
+
+pub mod font;
+pub mod layout;
+pub mod sdf;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  HtmlCanvasElement,
+  HtmlImageElement,
+  WebGlBuffer,
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlTexture,
+  WebGlUniformLocation,
+};
+
+use glm::{Mat4, Vec3};
+
+use renderer::{f32_view, u16_view, u8_view};
+use renderer::capabilities::capabilities;
+use renderer::gl::{Backend, Gl};
+use renderer::shader::{build_program_variant, ShaderSource, ShaderVariants};
+use renderer::text::font::{BmFont, Font};
+use renderer::text::layout::{layout, Align, Layout};
+use renderer::texture::set_sampling;
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("text_v.glsl"),
+    fragment: include_str!("text_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("text_v300.glsl"),
+    fragment: include_str!("text_f300.glsl"),
+  },
+};
+
+/// Floats per vertex: corner, texture coordinate, anchor, colour, scale.
+const VERTEX_FLOATS: usize = 2 + 2 + 3 + 4 + 1;
+const VERTEX_STRIDE: i32 = (VERTEX_FLOATS * 4) as i32;
+/// Four vertices per glyph and 16 bit indices.
+const MAX_GLYPHS: usize = 65536 / 4;
+
+/// Parse a BMFont JSON descriptor handed over from JS.
+pub fn font_from_json(descriptor: &JsValue) -> Result<Font, JsValue> {
+  let descriptor: BmFont = descriptor
+      .into_serde()
+      .map_err(|e| JsValue::from(format!("bad BMFont JSON: {}", e)))?;
+  Ok(Font::from_bmfont(&descriptor)?)
+}
+
+/// A font and its atlas texture.
+pub struct FontAtlas {
+  pub font: Font,
+  pub texture: WebGlTexture,
+}
+
+impl FontAtlas {
+  pub fn from_image(context: &Gl, font: Font, image: &HtmlImageElement) -> Result<FontAtlas, JsValue> {
+    let texture = begin_atlas(context)?;
+    context.tex_image_2d_with_image(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        WebGlRenderingContext::RGBA as i32,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        image,
+    )?;
+    Ok(FontAtlas { font, texture })
+  }
+
+  /// An atlas drawn at runtime into a 2D canvas.
+  pub fn from_canvas(context: &Gl, font: Font, canvas: &HtmlCanvasElement) -> Result<FontAtlas, JsValue> {
+    let texture = begin_atlas(context)?;
+    context.tex_image_2d_with_canvas(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        WebGlRenderingContext::RGBA as i32,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        canvas,
+    )?;
+    Ok(FontAtlas { font, texture })
+  }
+
+  /// An atlas from RGBA bytes, `font.atlas_width` by `font.atlas_height`.
+  pub fn from_rgba(context: &Gl, font: Font, pixels: &[u8]) -> Result<FontAtlas, JsValue> {
+    let (width, height) = (font.atlas_width as i32, font.atlas_height as i32);
+    if pixels.len() != (width * height * 4) as usize {
+      return Err(format!("expected {} bytes of RGBA, got {}", width * height * 4, pixels.len()).into());
+    }
+    let texture = begin_atlas(context)?;
+    let view = u8_view(pixels)?;
+    context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+        WebGlRenderingContext::TEXTURE_2D,
+        0,
+        WebGlRenderingContext::RGBA as i32,
+        width,
+        height,
+        0,
+        WebGlRenderingContext::RGBA,
+        WebGlRenderingContext::UNSIGNED_BYTE,
+        Some(&view),
+    )?;
+    Ok(FontAtlas { font, texture })
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_texture(Some(&self.texture));
+  }
+}
+
+fn begin_atlas(context: &Gl) -> Result<WebGlTexture, JsValue> {
+  let texture = context.create_texture().ok_or("failed to create texture")?;
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  // Atlases are rarely a power of two and are sampled without mipmaps,
+  // the distance field takes care of minification.
+  set_sampling(context, WebGlRenderingContext::LINEAR);
+  Ok(texture)
+}
+
+pub struct TextProgram {
+  pub program: WebGlProgram,
+  anchor: u32,
+  color: u32,
+  scale: u32,
+  view_matrix: Option<WebGlUniformLocation>,
+  projection_matrix: Option<WebGlUniformLocation>,
+  billboard: Option<WebGlUniformLocation>,
+  sampler: Option<WebGlUniformLocation>,
+  distance_field: Option<WebGlUniformLocation>,
+  smoothing: Option<WebGlUniformLocation>,
+}
+
+impl TextProgram {
+  pub fn new(context: &Gl) -> Result<TextProgram, JsValue> {
+    // WebGL1 needs the extension enabled before compiling for the
+    // shader to see GL_OES_standard_derivatives.
+    if context.backend() == Backend::WebGl1 && capabilities(context).features.standard_derivatives {
+      context.get_extension("OES_standard_derivatives")?;
+    }
+    let program = build_program_variant(context, &SHADERS)?;
+    let location = |name: &str| context.get_attrib_location(&program, name) as u32;
+    Ok(TextProgram {
+      anchor: location("aAnchor"),
+      color: location("aColor"),
+      scale: location("aScale"),
+      view_matrix: context.get_uniform_location(&program, "uViewMatrix"),
+      projection_matrix: context.get_uniform_location(&program, "uProjectionMatrix"),
+      billboard: context.get_uniform_location(&program, "uBillboard"),
+      sampler: context.get_uniform_location(&program, "uSampler"),
+      distance_field: context.get_uniform_location(&program, "uDistanceField"),
+      smoothing: context.get_uniform_location(&program, "uSmoothing"),
+      program,
+    })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextSpace {
+  /// Canvas pixels from the top-left corner, drawn over the scene.
+  Screen,
+  /// World positions, the text faces the camera and is depth tested.
+  Billboard,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+  /// Font size in output units: pixels on screen, world units for
+  /// billboards.
+  pub size: f32,
+  pub color: [f32; 4],
+  pub align: Align,
+  /// Wrap lines wider than this, in output units.
+  pub max_width: Option<f32>,
+}
+
+impl Default for TextStyle {
+  fn default() -> TextStyle {
+    TextStyle {
+      size: 16.0,
+      color: [1.0, 1.0, 1.0, 1.0],
+      align: Align::Left,
+      max_width: None,
+    }
+  }
+}
+
+/// Strings of one font collected into a single draw call.
+pub struct TextBatch {
+  pub space: TextSpace,
+  vertices: Vec<f32>,
+  indices: Vec<u16>,
+  vertex_buffer: WebGlBuffer,
+  index_buffer: WebGlBuffer,
+  /// The CPU copy changed since the last upload.
+  dirty: bool,
+}
+
+impl TextBatch {
+  pub fn new(context: &Gl, space: TextSpace) -> Result<TextBatch, JsValue> {
+    Ok(TextBatch {
+      space,
+      vertices: Vec::new(),
+      indices: Vec::new(),
+      vertex_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+      index_buffer: context.create_buffer().ok_or("failed to create buffer")?,
+      dirty: false,
+    })
+  }
+
+  pub fn glyph_count(&self) -> usize {
+    self.vertices.len() / (VERTEX_FLOATS * 4)
+  }
+
+  /// Drop every string, e.g. at the start of a frame for changing text.
+  pub fn clear(&mut self) {
+    self.vertices.clear();
+    self.indices.clear();
+    self.dirty = true;
+  }
+
+  /// Add `text` starting at `position` and return its layout, in font
+  /// pixels. For screen text `position` is in canvas pixels with `z` as
+  /// depth; the first line's top sits at `position`.
+  pub fn add(
+    &mut self,
+    font: &Font,
+    text: &str,
+    position: Vec3,
+    style: &TextStyle,
+  ) -> Result<Layout, JsValue> {
+    let scale = style.size / font.size;
+    let max_width = style.max_width.map(|width| width / scale);
+    let laid_out = layout(font, text, max_width, style.align);
+    if self.glyph_count() + laid_out.quads.len() > MAX_GLYPHS {
+      return Err(format!("a text batch holds at most {} glyphs", MAX_GLYPHS).into());
+    }
+
+    for quad in &laid_out.quads {
+      let base = self.glyph_count() as u16 * 4;
+      let corners = [
+        (quad.left, quad.top, quad.uv[0], quad.uv[1]),
+        (quad.right, quad.top, quad.uv[2], quad.uv[1]),
+        (quad.right, quad.bottom, quad.uv[2], quad.uv[3]),
+        (quad.left, quad.bottom, quad.uv[0], quad.uv[3]),
+      ];
+      for &(x, y, u, v) in &corners {
+        self.vertices.extend_from_slice(&[x, y, u, v, position.x, position.y, position.z]);
+        self.vertices.extend_from_slice(&style.color);
+        self.vertices.push(scale);
+      }
+      self.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    self.dirty = true;
+    Ok(laid_out)
+  }
+
+  /// Draw every glyph. Screen text ignores `view` and is best drawn
+  /// with `screen_projection`. Leaves blending off and depth writes on.
+  pub fn draw(
+    &mut self,
+    context: &Gl,
+    program: &TextProgram,
+    atlas: &FontAtlas,
+    view: &Mat4,
+    projection: &Mat4,
+  ) -> Result<(), JsValue> {
+    if self.indices.is_empty() {
+      return Ok(());
+    }
+
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.vertex_buffer));
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
+    if self.dirty {
+      let vertices = f32_view(&self.vertices)?;
+      context.buffer_data_with_array_buffer_view(
+          WebGlRenderingContext::ARRAY_BUFFER,
+          &vertices,
+          WebGlRenderingContext::DYNAMIC_DRAW,
+      );
+      let indices = u16_view(&self.indices)?;
+      context.buffer_data_with_array_buffer_view(
+          WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+          &indices,
+          WebGlRenderingContext::DYNAMIC_DRAW,
+      );
+      self.dirty = false;
+    }
+
+    context.use_program(Some(&program.program));
+    let view = match self.space {
+      TextSpace::Screen => Mat4::identity(),
+      TextSpace::Billboard => *view,
+    };
+    let data: JsValue = JsValue::from_serde(&view).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(program.view_matrix.as_ref(), false, &data);
+    let data: JsValue = JsValue::from_serde(projection).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(program.projection_matrix.as_ref(), false, &data);
+    context.uniform1f(program.billboard.as_ref(), if self.space == TextSpace::Billboard { 1.0 } else { 0.0 });
+
+    let font = &atlas.font;
+    let distance_field = match (font.distance_range, font.multichannel) {
+      (None, _) => 0.0,
+      (Some(_), false) => 1.0,
+      (Some(_), true) => 2.0,
+    };
+    context.uniform1f(program.distance_field.as_ref(), distance_field);
+    // Without derivatives assume glyphs are shown near their atlas size.
+    context.uniform1f(program.smoothing.as_ref(), 0.5 / font.distance_range.unwrap_or(1.0).max(1.0));
+    context.active_texture(WebGlRenderingContext::TEXTURE0);
+    context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&atlas.texture));
+    context.uniform1i(program.sampler.as_ref(), 0);
+
+    let attributes = [
+      (0, 2, 0),
+      (2, 2, 2),
+      (program.anchor, 3, 4),
+      (program.color, 4, 7),
+      (program.scale, 1, 11),
+    ];
+    for &(location, components, offset) in &attributes {
+      context.vertex_attrib_pointer_with_i32(
+          location, components, WebGlRenderingContext::FLOAT, false, VERTEX_STRIDE, offset * 4
+      );
+      context.enable_vertex_attrib_array(location);
+    }
+
+    context.enable(WebGlRenderingContext::BLEND);
+    context.blend_func(WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA);
+    match self.space {
+      TextSpace::Screen => context.disable(WebGlRenderingContext::DEPTH_TEST),
+      TextSpace::Billboard => {
+        context.enable(WebGlRenderingContext::DEPTH_TEST);
+        context.depth_mask(false);
+      },
+    }
+
+    context.draw_elements_with_i32(
+        WebGlRenderingContext::TRIANGLES,
+        self.indices.len() as i32,
+        WebGlRenderingContext::UNSIGNED_SHORT,
+        0,
+    );
+
+    context.disable(WebGlRenderingContext::BLEND);
+    context.depth_mask(true);
+    // Leave only the shared locations enabled for the next program.
+    for &(location, _, _) in &attributes[2..] {
+      context.disable_vertex_attrib_array(location);
+    }
+
+    Ok(())
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    context.delete_buffer(Some(&self.vertex_buffer));
+    context.delete_buffer(Some(&self.index_buffer));
+  }
+}
+
+/// A pixel projection for screen text: origin top-left, y down.
+pub fn screen_projection(width: f32, height: f32) -> Mat4 {
+  glm::ortho(0.0, width, height, 0.0, -1.0, 1.0)
+}
@@ -0,0 +1,41 @@
+//! Building a signed distance field atlas from a plain coverage mask,
+//! for fonts rendered at runtime instead of loaded from an SDF tool.
+
+/// Distance field of the `width` x `height` mask `alpha`, one byte per
+/// texel. Texels further than `spread` from an edge saturate; the edge
+/// itself maps to 128, inside is above. The matching font uses
+/// `distance_range = 2 * spread`.
+///
+/// Brute force within `spread`, fine for atlases of a few hundred
+/// texels generated once.
+pub fn distance_field(alpha: &[u8], width: usize, height: usize, spread: usize) -> Vec<u8> {
+  let inside = |x: usize, y: usize| alpha[y * width + x] >= 128;
+  let radius = spread as isize;
+  let mut field = vec![0u8; width * height];
+  for y in 0..height {
+    for x in 0..width {
+      let here = inside(x, y);
+      let mut nearest = (spread * spread) as f32 * 2.0;
+      for dy in -radius..=radius {
+        let sy = y as isize + dy;
+        if sy < 0 || sy >= height as isize {
+          continue;
+        }
+        for dx in -radius..=radius {
+          let sx = x as isize + dx;
+          if sx < 0 || sx >= width as isize {
+            continue;
+          }
+          if inside(sx as usize, sy as usize) != here {
+            nearest = nearest.min((dx * dx + dy * dy) as f32);
+          }
+        }
+      }
+      // Edges lie between texels, half a texel from either side.
+      let distance = (nearest.sqrt() - 0.5).min(spread as f32);
+      let signed = if here { distance } else { -distance };
+      field[y * width + x] = (128.0 + signed / spread as f32 * 127.0).max(0.0).min(255.0) as u8;
+    }
+  }
+  field
+}
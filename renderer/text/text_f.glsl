@@ -0,0 +1,34 @@
+#ifdef GL_OES_standard_derivatives
+#extension GL_OES_standard_derivatives : enable
+#endif
+
+precision mediump float;
+
+uniform sampler2D uSampler;
+// 0 plain coverage, 1 distance in alpha, 2 multi-channel distance.
+uniform float uDistanceField;
+// Half width of the anti-aliased edge when derivatives are missing.
+uniform float uSmoothing;
+
+varying highp vec2 vTextureCoord;
+varying lowp vec4 vColor;
+
+float median(float r, float g, float b) {
+  return max(min(r, g), min(max(r, g), b));
+}
+
+void main(void) {
+  vec4 texel = texture2D(uSampler, vTextureCoord);
+  float alpha = texel.a;
+  if (uDistanceField > 0.5) {
+    float distance = uDistanceField > 1.5 ? median(texel.r, texel.g, texel.b) : texel.a;
+#ifdef GL_OES_standard_derivatives
+    // About one screen pixel of distance, whatever the scale.
+    float width = 0.7 * length(vec2(dFdx(distance), dFdy(distance)));
+#else
+    float width = uSmoothing;
+#endif
+    alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+  }
+  gl_FragColor = vec4(vColor.rgb, vColor.a * alpha);
+}
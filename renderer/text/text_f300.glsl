@@ -0,0 +1,30 @@
+#version 300 es
+
+precision mediump float;
+
+uniform sampler2D uSampler;
+// 0 plain coverage, 1 distance in alpha, 2 multi-channel distance.
+uniform float uDistanceField;
+// Unused, derivatives are always there in GLSL ES 3.00.
+uniform float uSmoothing;
+
+in highp vec2 vTextureCoord;
+in lowp vec4 vColor;
+
+out vec4 fragColor;
+
+float median(float r, float g, float b) {
+  return max(min(r, g), min(max(r, g), b));
+}
+
+void main(void) {
+  vec4 texel = texture(uSampler, vTextureCoord);
+  float alpha = texel.a;
+  if (uDistanceField > 0.5) {
+    float distance = uDistanceField > 1.5 ? median(texel.r, texel.g, texel.b) : texel.a;
+    // About one screen pixel of distance, whatever the scale.
+    float width = 0.7 * length(vec2(dFdx(distance), dFdy(distance)));
+    alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+  }
+  fragColor = vec4(vColor.rgb, vColor.a * alpha);
+}
@@ -0,0 +1,31 @@
+#version 300 es
+
+// Glyph corner in font pixels, y down.
+in vec2 aVertexPosition;
+in vec2 aTextureCoord;
+// Where the string starts: canvas pixels for screen text, a world
+// position for billboards.
+in vec3 aAnchor;
+in vec4 aColor;
+// Font pixels to output units.
+in float aScale;
+
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+uniform float uBillboard;
+
+out highp vec2 vTextureCoord;
+out lowp vec4 vColor;
+
+void main(void) {
+  vec2 offset = aVertexPosition * aScale;
+  if (uBillboard > 0.5) {
+    // Offset in view space so the text always faces the camera.
+    vec4 anchor = uViewMatrix * vec4(aAnchor, 1.0);
+    gl_Position = uProjectionMatrix * (anchor + vec4(offset.x, -offset.y, 0.0, 0.0));
+  } else {
+    gl_Position = uProjectionMatrix * vec4(aAnchor.xy + offset, aAnchor.z, 1.0);
+  }
+  vTextureCoord = aTextureCoord;
+  vColor = aColor;
+}
@@ -0,0 +1,123 @@
+//! Texture creation helpers.
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGl2RenderingContext,
+  WebGlRenderingContext,
+  WebGlTexture,
+};
+
+use renderer::capabilities::capabilities;
+use renderer::gl::{Backend, Gl};
+use renderer::{u32_view, u8_view};
+
+/// Set nearest or linear filtering and edge clamping on the texture
+/// bound to `TEXTURE_2D`.
+pub fn set_sampling(context: &Gl, filter: u32) {
+  for &(name, value) in &[
+    (WebGlRenderingContext::TEXTURE_MIN_FILTER, filter),
+    (WebGlRenderingContext::TEXTURE_MAG_FILTER, filter),
+    (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+    (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+  ] {
+    context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, name, value as i32);
+  }
+}
+
+/// Linear filtering for the texture bound to `TEXTURE_2D` once `levels`
+/// mip levels of a `width` x `height` image are uploaded: between mip
+/// levels too when it has them, and repeating.
+///
+/// WebGL1 only samples the mips of power of two textures whose chain
+/// goes down to 1x1; a shorter chain is clamped on WebGL2 and ignored
+/// on WebGL1.
+pub fn set_mip_sampling(context: &Gl, width: u32, height: u32, levels: usize) {
+  let complete = levels as u32 == 32 - width.max(height).max(1).leading_zeros();
+  let webgl2 = context.backend() == Backend::WebGl2;
+  // WebGL1 can neither mipmap nor repeat other sizes.
+  let power_of_two = width.is_power_of_two() && height.is_power_of_two();
+  let min_filter = if levels > 1 && (webgl2 || (complete && power_of_two)) {
+    if !complete {
+      context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MAX_LEVEL, levels as i32 - 1);
+    }
+    WebGlRenderingContext::LINEAR_MIPMAP_LINEAR
+  } else {
+    WebGlRenderingContext::LINEAR
+  };
+  let wrap = if power_of_two || webgl2 { WebGlRenderingContext::REPEAT } else { WebGlRenderingContext::CLAMP_TO_EDGE };
+  for &(name, value) in &[
+    (WebGlRenderingContext::TEXTURE_MIN_FILTER, min_filter),
+    (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::LINEAR),
+    (WebGlRenderingContext::TEXTURE_WRAP_S, wrap),
+    (WebGlRenderingContext::TEXTURE_WRAP_T, wrap),
+  ] {
+    context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, name, value as i32);
+  }
+}
+
+/// An `RGBA` / `UNSIGNED_BYTE` texture from `width * height * 4` bytes,
+/// sampled with `filter` and clamped.
+pub fn create_rgba_texture(
+  context: &Gl,
+  width: i32,
+  height: i32,
+  pixels: &[u8],
+  filter: u32,
+) -> Result<WebGlTexture, JsValue> {
+  if pixels.len() != (width * height * 4) as usize {
+    return Err(format!("expected {} bytes of RGBA, got {}", width * height * 4, pixels.len()).into());
+  }
+  let texture = context.create_texture().ok_or("failed to create texture")?;
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  let view = u8_view(pixels)?;
+  context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+      WebGlRenderingContext::TEXTURE_2D,
+      0,
+      WebGlRenderingContext::RGBA as i32,
+      width,
+      height,
+      0,
+      WebGlRenderingContext::RGBA,
+      WebGlRenderingContext::UNSIGNED_BYTE,
+      Some(&view),
+  )?;
+  set_sampling(context, filter);
+  Ok(texture)
+}
+
+/// A single channel `R32UI` texture, read in GLSL ES 3.00 through a
+/// `usampler2D` with `texelFetch`. WebGL1 has no integer textures.
+pub fn create_integer_texture(
+  context: &Gl,
+  width: i32,
+  height: i32,
+  data: &[u32],
+) -> Result<WebGlTexture, JsValue> {
+  if !capabilities(context).features.integer_textures {
+    return Err("integer textures need WebGL2".into());
+  }
+  if data.len() != (width * height) as usize {
+    return Err(format!("expected {} texels, got {}", width * height, data.len()).into());
+  }
+
+  let texture = context.create_texture().ok_or("failed to create texture")?;
+  context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  // Rows of R32UI are always 4-byte aligned, but be explicit.
+  context.pixel_storei(WebGlRenderingContext::UNPACK_ALIGNMENT, 4);
+  let pixels = u32_view(data)?;
+  context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+      WebGlRenderingContext::TEXTURE_2D,
+      0,
+      WebGl2RenderingContext::R32UI as i32,
+      width,
+      height,
+      0,
+      WebGl2RenderingContext::RED_INTEGER,
+      WebGlRenderingContext::UNSIGNED_INT,
+      Some(&pixels),
+  )?;
+  // Integer textures can't be filtered.
+  set_sampling(context, WebGlRenderingContext::NEAREST);
+
+  Ok(texture)
+}
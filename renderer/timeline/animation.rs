@@ -0,0 +1,163 @@
+//! Animation definitions: keyframe tracks and markers, loadable from
+//! JSON.
+//!
+//! ```json
+//! {
+//!   "name": "hover",
+//!   "mode": "pingPong",
+//!   "rate": 1.5,
+//!   "tracks": [
+//!     { "target": { "translation": { "node": "cube" } },
+//!       "keyframes": [
+//!         { "time": 0, "value": [0, 0, 0], "easing": "bounce" },
+//!         { "time": 1, "value": [0, 1, 0] } ] },
+//!     { "target": { "parameter": { "material": "cube", "name": "color" } },
+//!       "keyframes": [
+//!         { "time": 0, "value": [1, 0, 0, 1], "easing": { "cubicBezier": [0.4, 0, 0.2, 1] } },
+//!         { "time": 1, "value": [0, 0, 1, 1] } ] }
+//!   ],
+//!   "markers": [{ "time": 1, "name": "top" }]
+//! }
+//! ```
+
+use serde_derive::Deserialize;
+
+use renderer::timeline::easing::Easing;
+
+/// What a track animates.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Target {
+  /// `x, y, z` added on top of the node's own transform.
+  Translation { node: String },
+  /// Euler angles in radians, applied X then Y then Z.
+  Rotation { node: String },
+  /// `x, y, z` multiplied into the node's own transform.
+  Scale { node: String },
+  /// Any number of floats, read back from `MaterialParameters`.
+  Parameter { material: String, name: String },
+}
+
+impl Target {
+  /// Values per keyframe the target needs, `None` for any.
+  fn components(&self) -> Option<usize> {
+    match *self {
+      Target::Parameter { .. } => None,
+      _ => Some(3),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Keyframe {
+  /// Seconds from the start of the animation.
+  pub time: f32,
+  pub value: Vec<f32>,
+  /// How the value moves from this key to the next.
+  #[serde(default)]
+  pub easing: Easing,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Track {
+  pub target: Target,
+  pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+  /// The value at `time`, holding the first and last keys outside
+  /// their range.
+  pub fn sample(&self, time: f32, out: &mut Vec<f32>) {
+    out.clear();
+    let keys = &self.keyframes;
+    let next = keys.iter().position(|key| key.time > time).unwrap_or(keys.len());
+    if next == 0 || next == keys.len() {
+      let key = if next == 0 { &keys[0] } else { &keys[keys.len() - 1] };
+      out.extend_from_slice(&key.value);
+      return;
+    }
+    let (from, to) = (&keys[next - 1], &keys[next]);
+    let span = to.time - from.time;
+    let t = from.easing.apply(if span > 0.0 { (time - from.time) / span } else { 1.0 });
+    out.extend(from.value.iter().zip(to.value.iter()).map(|(a, b)| a + (b - a) * t));
+  }
+}
+
+/// A named point in time that fires an event when playback passes it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Marker {
+  pub time: f32,
+  pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaybackMode {
+  /// Stop at the end.
+  Once,
+  /// Jump back to the start.
+  Loop,
+  /// Turn around at either end.
+  PingPong,
+}
+
+impl Default for PlaybackMode {
+  fn default() -> PlaybackMode {
+    PlaybackMode::Once
+  }
+}
+
+fn default_rate() -> f32 {
+  1.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Animation {
+  pub name: String,
+  /// Seconds; the last keyframe or marker when left out.
+  #[serde(default)]
+  pub duration: Option<f32>,
+  #[serde(default)]
+  pub mode: PlaybackMode,
+  /// Playback speed, negative plays backwards.
+  #[serde(default = "default_rate")]
+  pub rate: f32,
+  pub tracks: Vec<Track>,
+  #[serde(default)]
+  pub markers: Vec<Marker>,
+}
+
+impl Animation {
+  /// Check the keys of every track and work out the duration.
+  pub fn validate(mut self) -> Result<Animation, String> {
+    let mut end: f32 = 0.0;
+    for (i, track) in self.tracks.iter().enumerate() {
+      let keys = &track.keyframes;
+      if keys.is_empty() {
+        return Err(format!("{}: track {} has no keyframes", self.name, i));
+      }
+      let components = track.target.components().unwrap_or(keys[0].value.len());
+      for pair in keys.windows(2) {
+        if pair[1].time < pair[0].time {
+          return Err(format!("{}: track {} keyframes are out of order", self.name, i));
+        }
+      }
+      if let Some(key) = keys.iter().find(|key| key.value.len() != components) {
+        return Err(format!(
+            "{}: track {} key at {}s has {} values, expected {}",
+            self.name, i, key.time, key.value.len(), components,
+        ));
+      }
+      end = end.max(keys[keys.len() - 1].time);
+    }
+    for marker in &self.markers {
+      end = end.max(marker.time);
+    }
+    self.duration = Some(self.duration.unwrap_or(end));
+    Ok(self)
+  }
+
+  pub fn duration(&self) -> f32 {
+    self.duration.unwrap_or(0.0)
+  }
+}
@@ -0,0 +1,104 @@
+//! Easing curves, mapping linear progress in `[0, 1]` to eased
+//! progress.
+
+use serde_derive::Deserialize;
+
+/// In JSON: `"linear"`, `"elastic"`, `"bounce"` or
+/// `{ "cubicBezier": [x1, y1, x2, y2] }`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Easing {
+  Linear,
+  /// CSS style, from `(0, 0)` to `(1, 1)` with the two control points
+  /// given; `x1` and `x2` must be in `[0, 1]`.
+  CubicBezier([f32; 4]),
+  /// Overshoots and springs back into place.
+  Elastic,
+  /// Bounces off the end value like a dropped ball.
+  Bounce,
+}
+
+impl Default for Easing {
+  fn default() -> Easing {
+    Easing::Linear
+  }
+}
+
+impl Easing {
+  pub fn apply(&self, t: f32) -> f32 {
+    let t = t.max(0.0).min(1.0);
+    match *self {
+      Easing::Linear => t,
+      Easing::CubicBezier([x1, y1, x2, y2]) => cubic_bezier(x1, y1, x2, y2, t),
+      Easing::Elastic => elastic(t),
+      Easing::Bounce => bounce(t),
+    }
+  }
+}
+
+/// One coordinate of the curve with control values `a` and `b` at `s`.
+fn bezier(a: f32, b: f32, s: f32) -> f32 {
+  let u = 1.0 - s;
+  3.0 * u * u * s * a + 3.0 * u * s * s * b + s * s * s
+}
+
+fn bezier_slope(a: f32, b: f32, s: f32) -> f32 {
+  let u = 1.0 - s;
+  3.0 * u * u * a + 6.0 * u * s * (b - a) + 3.0 * s * s * (1.0 - b)
+}
+
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+  // Find the curve parameter whose x is `x`: Newton first, bisection
+  // if the slope is too flat for it to converge.
+  let mut s = x;
+  for _ in 0..8 {
+    let error = bezier(x1, x2, s) - x;
+    if error.abs() < 1e-6 {
+      return bezier(y1, y2, s);
+    }
+    let slope = bezier_slope(x1, x2, s);
+    if slope.abs() < 1e-6 {
+      break;
+    }
+    s -= error / slope;
+  }
+  let (mut low, mut high) = (0.0, 1.0);
+  s = x;
+  for _ in 0..32 {
+    let value = bezier(x1, x2, s);
+    if (value - x).abs() < 1e-6 {
+      break;
+    }
+    if value < x {
+      low = s;
+    } else {
+      high = s;
+    }
+    s = (low + high) * 0.5;
+  }
+  bezier(y1, y2, s)
+}
+
+fn elastic(t: f32) -> f32 {
+  if t <= 0.0 || t >= 1.0 {
+    return t;
+  }
+  let period = 2.0 * std::f32::consts::PI / 3.0;
+  2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * period).sin() + 1.0
+}
+
+fn bounce(t: f32) -> f32 {
+  let (n, d) = (7.5625, 2.75);
+  if t < 1.0 / d {
+    n * t * t
+  } else if t < 2.0 / d {
+    let t = t - 1.5 / d;
+    n * t * t + 0.75
+  } else if t < 2.5 / d {
+    let t = t - 2.25 / d;
+    n * t * t + 0.9375
+  } else {
+    let t = t - 2.625 / d;
+    n * t * t + 0.984375
+  }
+}
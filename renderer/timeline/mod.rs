@@ -0,0 +1,282 @@
+//! Keyframe animation of node transforms and material parameters.
+//!
+//! An `Animation` is the authored data, usually JSON; a `Timeline`
+//! plays one back, applying its tracks to a `Scene` and a
+//! `MaterialParameters` store and reporting the markers it passes.
+//! JS can define animations with `timeline_define` and listen for
+//! markers with `timeline_set_callback`.
+
+pub mod animation;
+pub mod easing;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use serde_derive::Serialize;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+
+use glm::Mat4;
+
+use renderer::scene::{NodeId, Scene};
+use renderer::timeline::animation::{Animation, PlaybackMode, Target};
+
+/// Named float values per material, for shaders to read each frame.
+#[derive(Clone, Debug, Default)]
+pub struct MaterialParameters {
+  values: BTreeMap<(String, String), Vec<f32>>,
+}
+
+impl MaterialParameters {
+  pub fn new() -> MaterialParameters {
+    MaterialParameters::default()
+  }
+
+  pub fn get(&self, material: &str, name: &str) -> Option<&[f32]> {
+    self.values.get(&(material.to_string(), name.to_string())).map(|value| &value[..])
+  }
+
+  pub fn set(&mut self, material: &str, name: &str, value: &[f32]) {
+    let entry = self.values.entry((material.to_string(), name.to_string())).or_insert_with(Vec::new);
+    entry.clear();
+    entry.extend_from_slice(value);
+  }
+}
+
+/// The translation, rotation and scale tracks acting on one node.
+#[derive(Clone, Copy, Debug)]
+struct NodeOffset {
+  translation: [f32; 3],
+  rotation: [f32; 3],
+  scale: [f32; 3],
+}
+
+impl Default for NodeOffset {
+  fn default() -> NodeOffset {
+    NodeOffset { translation: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] }
+  }
+}
+
+impl NodeOffset {
+  fn matrix(&self) -> Mat4 {
+    let [tx, ty, tz] = self.translation;
+    let [rx, ry, rz] = self.rotation;
+    let mut matrix = glm::translate(&Mat4::identity(), &glm::vec3(tx, ty, tz));
+    matrix = glm::rotate(&matrix, rz, &glm::vec3(0.0, 0.0, 1.0));
+    matrix = glm::rotate(&matrix, ry, &glm::vec3(0.0, 1.0, 0.0));
+    matrix = glm::rotate(&matrix, rx, &glm::vec3(1.0, 0.0, 0.0));
+    let [sx, sy, sz] = self.scale;
+    glm::scale(&matrix, &glm::vec3(sx, sy, sz))
+  }
+}
+
+/// Plays an `Animation`.
+pub struct Timeline {
+  pub animation: Animation,
+  /// Seconds into the animation.
+  pub time: f32,
+  /// Multiplies the animation's own rate; negative plays backwards.
+  pub rate: f32,
+  pub playing: bool,
+  /// Flipped by ping-pong playback at either end.
+  direction: f32,
+  /// Markers exactly at the start time fire on the first update.
+  at_start: bool,
+  /// Each animated node's transform before the timeline touched it.
+  bases: HashMap<NodeId, Mat4>,
+  scratch: Vec<f32>,
+}
+
+impl Timeline {
+  pub fn new(animation: Animation) -> Result<Timeline, String> {
+    let animation = animation.validate()?;
+    let time = if animation.rate < 0.0 { animation.duration() } else { 0.0 };
+    Ok(Timeline {
+      animation,
+      time,
+      rate: 1.0,
+      playing: true,
+      direction: 1.0,
+      at_start: true,
+      bases: HashMap::new(),
+      scratch: Vec::new(),
+    })
+  }
+
+  /// Back to the beginning and playing, in whichever direction the
+  /// rate points.
+  pub fn restart(&mut self) {
+    self.direction = 1.0;
+    self.time = if self.velocity() < 0.0 { self.animation.duration() } else { 0.0 };
+    self.playing = true;
+    self.at_start = true;
+  }
+
+  fn velocity(&self) -> f32 {
+    self.animation.rate * self.rate * self.direction
+  }
+
+  /// Move the playhead by `dt` seconds of wall time and return the
+  /// names of the markers passed, in the order they were passed.
+  pub fn update(&mut self, dt: f32) -> Vec<String> {
+    let mut fired = Vec::new();
+    let duration = self.animation.duration();
+    if !self.playing {
+      return fired;
+    }
+    if self.at_start {
+      self.at_start = false;
+      self.fire_at(self.time, &mut fired);
+    }
+    if duration <= 0.0 {
+      self.playing = false;
+      return fired;
+    }
+
+    let mut step = dt * self.velocity();
+    // Each pass runs to the next end of the animation at most; long
+    // steps with a looping mode may take several.
+    while step != 0.0 {
+      let forward = step > 0.0;
+      let target = self.time + step;
+      let hit_end = if forward { target >= duration } else { target <= 0.0 };
+      let end = if !hit_end { target } else if forward { duration } else { 0.0 };
+      self.fire_between(self.time, end, &mut fired);
+      step -= end - self.time;
+      self.time = end;
+      if !hit_end {
+        break;
+      }
+      match self.animation.mode {
+        PlaybackMode::Once => {
+          self.playing = false;
+          break;
+        },
+        PlaybackMode::Loop => {
+          self.time = if forward { 0.0 } else { duration };
+          self.fire_at(self.time, &mut fired);
+        },
+        PlaybackMode::PingPong => {
+          self.direction = -self.direction;
+          step = -step;
+        },
+      }
+    }
+    fired
+  }
+
+  /// Markers in `(from, to]` going forwards, `[to, from)` backwards.
+  fn fire_between(&self, from: f32, to: f32, fired: &mut Vec<String>) {
+    let mut passed: Vec<_> = self.animation.markers.iter()
+        .filter(|marker| if to >= from {
+          marker.time > from && marker.time <= to
+        } else {
+          marker.time < from && marker.time >= to
+        })
+        .collect();
+    passed.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+    if to < from {
+      passed.reverse();
+    }
+    fired.extend(passed.iter().map(|marker| marker.name.clone()));
+  }
+
+  fn fire_at(&self, time: f32, fired: &mut Vec<String>) {
+    fired.extend(
+      self.animation.markers.iter()
+          .filter(|marker| marker.time == time)
+          .map(|marker| marker.name.clone())
+    );
+  }
+
+  /// Write every track's value at the current time. Transform tracks
+  /// act on top of the transform each node had the first time this
+  /// timeline was applied; nodes missing from `scene` are skipped.
+  pub fn apply(&mut self, scene: &mut Scene, parameters: &mut MaterialParameters) {
+    let mut offsets: BTreeMap<NodeId, NodeOffset> = BTreeMap::new();
+    for track in &self.animation.tracks {
+      track.sample(self.time, &mut self.scratch);
+      let value = &self.scratch;
+      let (node, offset) = match track.target {
+        Target::Parameter { ref material, ref name } => {
+          parameters.set(material, name, value);
+          continue;
+        },
+        Target::Translation { ref node } | Target::Rotation { ref node } | Target::Scale { ref node } => {
+          match scene.find(node) {
+            Some(id) => (id, offsets.entry(id).or_insert_with(NodeOffset::default)),
+            None => continue,
+          }
+        },
+      };
+      let vector = [value[0], value[1], value[2]];
+      match track.target {
+        Target::Translation { .. } => offset.translation = vector,
+        Target::Rotation { .. } => offset.rotation = vector,
+        Target::Scale { .. } => offset.scale = vector,
+        Target::Parameter { .. } => unreachable!(),
+      }
+      self.bases.entry(node).or_insert(scene.node(node).local);
+    }
+    for (node, offset) in offsets {
+      scene.set_local(node, self.bases[&node] * offset.matrix());
+    }
+  }
+}
+
+/// Parse an animation from a JS object in the format described in
+/// `animation`.
+pub fn animation_from_json(definition: &JsValue) -> Result<Animation, JsValue> {
+  let animation: Animation = definition
+      .into_serde()
+      .map_err(|e| JsValue::from(format!("bad animation JSON: {}", e)))?;
+  Ok(animation.validate()?)
+}
+
+thread_local! {
+  static DEFINED: RefCell<HashMap<String, Animation>> = RefCell::new(HashMap::new());
+  static MARKER_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+}
+
+/// Define or replace an animation by name, so demos that look it up
+/// with `defined` play it instead of their built-in one.
+#[wasm_bindgen]
+pub fn timeline_define(definition: JsValue) -> Result<(), JsValue> {
+  let animation = animation_from_json(&definition)?;
+  DEFINED.with(|defined| defined.borrow_mut().insert(animation.name.clone(), animation));
+  Ok(())
+}
+
+/// The animation defined from JS under `name`, if any.
+pub fn defined(name: &str) -> Option<Animation> {
+  DEFINED.with(|defined| defined.borrow().get(name).cloned())
+}
+
+/// Register a JS function called with `{ animation, marker }` each time
+/// playback passes a marker. Pass `null` to remove it.
+#[wasm_bindgen]
+pub fn timeline_set_callback(callback: JsValue) {
+  MARKER_CALLBACK.with(|stored| {
+    *stored.borrow_mut() = callback.dyn_into::<js_sys::Function>().ok();
+  });
+}
+
+#[derive(Serialize)]
+struct MarkerEvent<'a> {
+  animation: &'a str,
+  marker: &'a str,
+}
+
+/// Hand the markers returned by `Timeline::update` to the JS callback,
+/// if one is registered.
+pub fn notify(timeline: &Timeline, markers: &[String]) -> Result<(), JsValue> {
+  MARKER_CALLBACK.with(|stored| {
+    if let Some(ref callback) = *stored.borrow() {
+      for marker in markers {
+        let event = MarkerEvent { animation: &timeline.animation.name, marker };
+        callback.call1(&JsValue::NULL, &JsValue::from_serde(&event).unwrap())?;
+      }
+    }
+    Ok(())
+  })
+}
@@ -0,0 +1,177 @@
+//! Per-frame camera and light uniforms.
+//!
+//! On WebGL2 they live in two uniform buffer objects, `Camera` and
+//! `Light`, bound once to fixed binding points and shared by every
+//! program. On WebGL1 the same members are plain uniforms and get
+//! uploaded to each program before it draws.
+//!
+//! GLSL ES 3.00 declaration, std140:
+//!
+//! ```glsl
+//! layout(std140) uniform Camera {
+//!   mat4 uViewMatrix;
+//!   mat4 uProjectionMatrix;
+//!   vec4 uCameraPosition;
+//! };
+//! layout(std140) uniform Light {
+//!   vec4 uLightDirection;
+//!   vec4 uLightColor;
+//!   vec4 uAmbientColor;
+//! };
+//! ```
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGl2RenderingContext,
+  WebGlBuffer,
+  WebGlProgram,
+};
+
+use glm::{Mat4, Vec3};
+
+use renderer::bounds::Frustum;
+use renderer::f32_view;
+use renderer::gl::Gl;
+
+pub const CAMERA_BLOCK_BINDING: u32 = 0;
+pub const LIGHT_BLOCK_BINDING: u32 = 1;
+
+const CAMERA_FLOATS: usize = 16 + 16 + 4;
+const LIGHT_FLOATS: usize = 4 + 4 + 4;
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+  pub view: Mat4,
+  pub projection: Mat4,
+  pub position: Vec3,
+}
+
+#[derive(Clone, Debug)]
+pub struct Light {
+  /// Direction the light travels in, world space.
+  pub direction: Vec3,
+  pub color: Vec3,
+  pub ambient: Vec3,
+}
+
+impl Camera {
+  pub fn view_projection(&self) -> Mat4 {
+    self.projection * self.view
+  }
+
+  pub fn frustum(&self) -> Frustum {
+    Frustum::from_matrix(&self.view_projection())
+  }
+}
+
+impl Default for Light {
+  fn default() -> Light {
+    Light {
+      direction: glm::normalize(&glm::vec3(-0.85, -0.8, -0.75)),
+      color: glm::vec3(1.0, 1.0, 1.0),
+      ambient: glm::vec3(0.3, 0.3, 0.3),
+    }
+  }
+}
+
+pub struct SceneUniforms {
+  camera: [f32; CAMERA_FLOATS],
+  light: [f32; LIGHT_FLOATS],
+  /// `(camera, light)` uniform buffers, WebGL2 only.
+  buffers: Option<(WebGlBuffer, WebGlBuffer)>,
+}
+
+impl SceneUniforms {
+  pub fn new(context: &Gl) -> Result<SceneUniforms, JsValue> {
+    let buffers = match context.webgl2() {
+      Some(gl) => {
+        let camera = uniform_buffer(gl, CAMERA_BLOCK_BINDING, CAMERA_FLOATS)?;
+        let light = uniform_buffer(gl, LIGHT_BLOCK_BINDING, LIGHT_FLOATS)?;
+        Some((camera, light))
+      },
+      None => None,
+    };
+
+    Ok(SceneUniforms {
+      camera: [0.0; CAMERA_FLOATS],
+      light: [0.0; LIGHT_FLOATS],
+      buffers,
+    })
+  }
+
+  /// Update the CPU copy, and on WebGL2 the uniform buffers as well.
+  pub fn update(&mut self, context: &Gl, camera: &Camera, light: &Light) -> Result<(), JsValue> {
+    self.camera[0..16].copy_from_slice(camera.view.as_slice());
+    self.camera[16..32].copy_from_slice(camera.projection.as_slice());
+    self.camera[32..35].copy_from_slice(camera.position.as_slice());
+    self.camera[35] = 1.0;
+
+    self.light[0..3].copy_from_slice(light.direction.as_slice());
+    self.light[4..7].copy_from_slice(light.color.as_slice());
+    self.light[8..11].copy_from_slice(light.ambient.as_slice());
+
+    if let (Some(gl), Some((ref camera_buffer, ref light_buffer))) = (context.webgl2(), self.buffers.as_ref()) {
+      for &(buffer, data) in &[(camera_buffer, &self.camera[..]), (light_buffer, &self.light[..])] {
+        gl.bind_buffer(WebGl2RenderingContext::UNIFORM_BUFFER, Some(buffer));
+        let array = f32_view(data)?;
+        gl.buffer_sub_data_with_i32_and_array_buffer_view(
+            WebGl2RenderingContext::UNIFORM_BUFFER,
+            0,
+            &array,
+        );
+      }
+      gl.bind_buffer(WebGl2RenderingContext::UNIFORM_BUFFER, None);
+    }
+    Ok(())
+  }
+
+  /// Hook the blocks up to a freshly linked program. Only needed once
+  /// per program on WebGL2.
+  pub fn attach(&self, context: &Gl, program: &WebGlProgram) {
+    if let Some(gl) = context.webgl2() {
+      for &(name, binding) in &[("Camera", CAMERA_BLOCK_BINDING), ("Light", LIGHT_BLOCK_BINDING)] {
+        let index = gl.get_uniform_block_index(program, name);
+        if index != WebGl2RenderingContext::INVALID_INDEX {
+          gl.uniform_block_binding(program, index, binding);
+        }
+      }
+    }
+  }
+
+  /// Make the current values visible to `program`, which must be in
+  /// use. A no-op on WebGL2 where the blocks are already bound.
+  pub fn apply(&self, context: &Gl, program: &WebGlProgram) {
+    if self.buffers.is_some() {
+      return;
+    }
+    let mat4 = |name: &str, data: &[f32]| {
+      let data: JsValue = JsValue::from_serde(&data).unwrap();
+      context.uniform_matrix4fv_with_f32_sequence(
+          context.get_uniform_location(program, name).as_ref(), false, &data
+      );
+    };
+    mat4("uViewMatrix", &self.camera[0..16]);
+    mat4("uProjectionMatrix", &self.camera[16..32]);
+
+    let vec4 = |name: &str, v: &[f32]| {
+      context.uniform4f(context.get_uniform_location(program, name).as_ref(), v[0], v[1], v[2], v[3]);
+    };
+    vec4("uCameraPosition", &self.camera[32..36]);
+    vec4("uLightDirection", &self.light[0..4]);
+    vec4("uLightColor", &self.light[4..8]);
+    vec4("uAmbientColor", &self.light[8..12]);
+  }
+}
+
+fn uniform_buffer(gl: &WebGl2RenderingContext, binding: u32, floats: usize) -> Result<WebGlBuffer, JsValue> {
+  let buffer = gl.create_buffer().ok_or("failed to create buffer")?;
+  gl.bind_buffer(WebGl2RenderingContext::UNIFORM_BUFFER, Some(&buffer));
+  gl.buffer_data_with_i32(
+      WebGl2RenderingContext::UNIFORM_BUFFER,
+      (floats * 4) as i32,
+      WebGl2RenderingContext::DYNAMIC_DRAW,
+  );
+  gl.bind_buffer_base(WebGl2RenderingContext::UNIFORM_BUFFER, binding, Some(&buffer));
+  gl.bind_buffer(WebGl2RenderingContext::UNIFORM_BUFFER, None);
+  Ok(buffer)
+}
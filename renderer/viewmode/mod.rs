@@ -0,0 +1,269 @@
+//! Debug view modes that can be switched while running: lit solid,
+//! wireframe, wireframe over solid, normals as colour, a UV checker and
+//! linear depth.
+//!
+//! Every shading is its own program, built once from one shader pair
+//! with a `#define`, and the wireframe comes from the edge index buffer
+//! every `Mesh` keeps, so switching only changes which program and
+//! index buffer the next draw uses. Hidden edges are removed with a
+//! depth-only pass of the triangles pushed back by a polygon offset.
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{
+  WebGlProgram,
+  WebGlRenderingContext,
+  WebGlUniformLocation,
+};
+
+use glm::Mat4;
+
+use renderer::gl::Gl;
+use renderer::mesh::Mesh;
+use renderer::shader::{build_program, with_defines, ShaderSource, ShaderVariants};
+use renderer::uniforms::SceneUniforms;
+
+pub static SHADERS: ShaderVariants = ShaderVariants {
+  es100: ShaderSource {
+    vertex: include_str!("viewmode_v.glsl"),
+    fragment: include_str!("viewmode_f.glsl"),
+  },
+  es300: ShaderSource {
+    vertex: include_str!("viewmode_v300.glsl"),
+    fragment: include_str!("viewmode_f300.glsl"),
+  },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewMode {
+  /// Diffuse lit, the mesh colour.
+  Solid,
+  /// Triangle edges only.
+  Wireframe,
+  /// `Solid` with the edges drawn over it.
+  Overlay,
+  /// World space normals mapped from -1..1 to 0..1 as RGB.
+  Normals,
+  /// A checker in texture space, to check UV layout and stretching.
+  UvChecker,
+  /// Distance from the camera across `depth_range`, near white.
+  Depth,
+}
+
+impl ViewMode {
+  pub const ALL: [ViewMode; 6] = [
+    ViewMode::Solid,
+    ViewMode::Wireframe,
+    ViewMode::Overlay,
+    ViewMode::Normals,
+    ViewMode::UvChecker,
+    ViewMode::Depth,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      ViewMode::Solid => "solid",
+      ViewMode::Wireframe => "wireframe",
+      ViewMode::Overlay => "overlay",
+      ViewMode::Normals => "normals",
+      ViewMode::UvChecker => "uvChecker",
+      ViewMode::Depth => "depth",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<ViewMode> {
+    ViewMode::ALL.iter().cloned().find(|mode| mode.name() == name)
+  }
+
+  /// The mode after this one in `ALL`, wrapping around.
+  pub fn next(self) -> ViewMode {
+    let index = ViewMode::ALL.iter().position(|&mode| mode == self).unwrap();
+    ViewMode::ALL[(index + 1) % ViewMode::ALL.len()]
+  }
+}
+
+/// The fragment shader paths, in the order of `ALL`, which is how
+/// `ModeRenderer::programs` holds them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shade {
+  Lit,
+  Flat,
+  Normals,
+  Checker,
+  Depth,
+}
+
+impl Shade {
+  const ALL: [Shade; 5] = [Shade::Lit, Shade::Flat, Shade::Normals, Shade::Checker, Shade::Depth];
+
+  fn define(self) -> Option<&'static str> {
+    match self {
+      Shade::Lit => None,
+      Shade::Flat => Some("SHADE_FLAT"),
+      Shade::Normals => Some("SHADE_NORMALS"),
+      Shade::Checker => Some("SHADE_CHECKER"),
+      Shade::Depth => Some("SHADE_DEPTH"),
+    }
+  }
+}
+
+struct ShadeProgram {
+  program: WebGlProgram,
+  model_matrix: Option<WebGlUniformLocation>,
+  color: Option<WebGlUniformLocation>,
+  depth_range: Option<WebGlUniformLocation>,
+}
+
+/// Draws meshes the way `mode` says. Camera and light come from
+/// `SceneUniforms`, like `LitProgram`, which `Solid` matches.
+pub struct ModeRenderer {
+  pub mode: ViewMode,
+  /// Colour of the wireframe edges.
+  pub wire_color: [f32; 4],
+  /// Hide the edges behind other triangles in `Wireframe`, the way
+  /// `Overlay` always does. That covers the mesh's own triangles and
+  /// those of meshes drawn before it, so draw front to back for the
+  /// cleanest result.
+  pub hide_hidden_edges: bool,
+  /// View distances drawn white and black in `Depth`.
+  pub depth_range: [f32; 2],
+  programs: Vec<ShadeProgram>,
+}
+
+impl ModeRenderer {
+  pub fn new(context: &Gl, uniforms: &SceneUniforms) -> Result<ModeRenderer, JsValue> {
+    let source = SHADERS.for_backend(context.backend());
+    let mut programs = Vec::with_capacity(Shade::ALL.len());
+    for shade in &Shade::ALL {
+      let defines: Vec<(&str, String)> = shade.define().into_iter().map(|name| (name, "1".to_string())).collect();
+      let program = build_program(context, source.vertex, &with_defines(source.fragment, &defines))?;
+      uniforms.attach(context, &program);
+      programs.push(ShadeProgram {
+        model_matrix: context.get_uniform_location(&program, "uModelMatrix"),
+        color: context.get_uniform_location(&program, "uColor"),
+        depth_range: context.get_uniform_location(&program, "uDepthRange"),
+        program,
+      });
+    }
+    Ok(ModeRenderer {
+      mode: ViewMode::Solid,
+      wire_color: [0.9, 0.9, 0.9, 1.0],
+      hide_hidden_edges: true,
+      depth_range: [0.1, 50.0],
+      programs,
+    })
+  }
+
+  /// Start a frame of drawing: hand the camera and light to every
+  /// program, since one mode may use several.
+  pub fn begin(&self, context: &Gl, uniforms: &SceneUniforms) {
+    for shade in &self.programs {
+      context.use_program(Some(&shade.program));
+      uniforms.apply(context, &shade.program);
+      context.uniform2f(shade.depth_range.as_ref(), self.depth_range[0], self.depth_range[1]);
+    }
+  }
+
+  /// Draw `mesh` in the current mode; `begin` must have been called.
+  /// `color` is what `Solid` and `Overlay` shade.
+  pub fn draw(&self, context: &Gl, mesh: &Mesh, model: &Mat4, color: [f32; 4]) {
+    match self.mode {
+      ViewMode::Solid => {
+        self.use_shade(context, Shade::Lit, model, color);
+        mesh.draw(context);
+      },
+      ViewMode::Wireframe => {
+        if self.hide_hidden_edges {
+          context.color_mask(false, false, false, false);
+          self.draw_pushed_back(context, Shade::Flat, mesh, model, color);
+          context.color_mask(true, true, true, true);
+        }
+        self.use_shade(context, Shade::Flat, model, self.wire_color);
+        mesh.draw_edges(context);
+      },
+      ViewMode::Overlay => {
+        self.draw_pushed_back(context, Shade::Lit, mesh, model, color);
+        self.use_shade(context, Shade::Flat, model, self.wire_color);
+        mesh.draw_edges(context);
+      },
+      ViewMode::Normals => {
+        self.use_shade(context, Shade::Normals, model, color);
+        mesh.draw(context);
+      },
+      ViewMode::UvChecker => {
+        self.use_shade(context, Shade::Checker, model, color);
+        mesh.draw(context);
+      },
+      ViewMode::Depth => {
+        self.use_shade(context, Shade::Depth, model, color);
+        mesh.draw(context);
+      },
+    }
+  }
+
+  /// The triangles a little further away than they are, so the edges
+  /// drawn on them afterwards win the depth test.
+  fn draw_pushed_back(&self, context: &Gl, shade: Shade, mesh: &Mesh, model: &Mat4, color: [f32; 4]) {
+    context.enable(WebGlRenderingContext::POLYGON_OFFSET_FILL);
+    context.polygon_offset(1.0, 1.0);
+    self.use_shade(context, shade, model, color);
+    mesh.draw(context);
+    context.disable(WebGlRenderingContext::POLYGON_OFFSET_FILL);
+  }
+
+  fn use_shade(&self, context: &Gl, shade: Shade, model: &Mat4, color: [f32; 4]) {
+    let shade = &self.programs[shade as usize];
+    context.use_program(Some(&shade.program));
+    let data: JsValue = JsValue::from_serde(model).unwrap().into();
+    context.uniform_matrix4fv_with_f32_sequence(shade.model_matrix.as_ref(), false, &data);
+    context.uniform4f(shade.color.as_ref(), color[0], color[1], color[2], color[3]);
+  }
+
+  pub fn delete(&self, context: &Gl) {
+    for shade in &self.programs {
+      context.delete_program(Some(&shade.program));
+    }
+  }
+}
+
+/* JS controls */
+
+thread_local! {
+  static CURRENT: Cell<ViewMode> = Cell::new(ViewMode::Solid);
+}
+
+/// The mode picked from JS with `view_mode_set`, or by a demo.
+pub fn current() -> ViewMode {
+  CURRENT.with(|current| current.get())
+}
+
+pub fn set_current(mode: ViewMode) {
+  CURRENT.with(|current| current.set(mode));
+}
+
+/// Switch the view mode by name, one of `view_modes()`. Returns whether
+/// the name was known.
+#[wasm_bindgen]
+pub fn view_mode_set(name: &str) -> bool {
+  match ViewMode::from_name(name) {
+    Some(mode) => {
+      set_current(mode);
+      true
+    },
+    None => false,
+  }
+}
+
+/// The name of the current view mode.
+#[wasm_bindgen]
+pub fn view_mode() -> String {
+  current().name().to_string()
+}
+
+/// The names `view_mode_set` accepts.
+#[wasm_bindgen]
+pub fn view_modes() -> JsValue {
+  let names: Vec<&str> = ViewMode::ALL.iter().map(|mode| mode.name()).collect();
+  JsValue::from_serde(&names).unwrap()
+}
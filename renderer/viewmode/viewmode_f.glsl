@@ -0,0 +1,32 @@
+precision mediump float;
+
+uniform vec4 uColor;
+uniform vec4 uLightDirection;
+uniform vec4 uLightColor;
+uniform vec4 uAmbientColor;
+
+varying highp vec3 vNormal;
+varying highp vec2 vTextureCoord;
+varying highp float vDepth;
+
+void main(void) {
+#if defined(SHADE_NORMALS)
+  gl_FragColor = vec4(normalize(vNormal) * 0.5 + 0.5, 1.0);
+#elif defined(SHADE_CHECKER)
+  // Eight squares across the texture, tinted red along u and green
+  // along v so stretching and flipped UVs show.
+  vec2 cell = floor(vTextureCoord * 8.0);
+  float check = mod(cell.x + cell.y, 2.0);
+  vec3 tint = vec3(0.4 + 0.6 * fract(vTextureCoord.x), 0.4 + 0.6 * fract(vTextureCoord.y), 0.5);
+  gl_FragColor = vec4(tint * mix(0.35, 1.0, check), 1.0);
+#elif defined(SHADE_DEPTH)
+  gl_FragColor = vec4(vec3(1.0 - clamp(vDepth, 0.0, 1.0)), 1.0);
+#elif defined(SHADE_FLAT)
+  gl_FragColor = uColor;
+#else
+  vec3 normal = normalize(vNormal);
+  float diffuse = max(dot(normal, -uLightDirection.xyz), 0.0);
+  vec3 color = uColor.rgb * (uAmbientColor.rgb + uLightColor.rgb * diffuse);
+  gl_FragColor = vec4(color, uColor.a);
+#endif
+}
@@ -0,0 +1,21 @@
+attribute vec4 aVertexPosition;
+attribute vec3 aVertexNormal;
+attribute vec2 aTextureCoord;
+
+uniform mat4 uModelMatrix;
+uniform mat4 uViewMatrix;
+uniform mat4 uProjectionMatrix;
+// Near and far view distance mapped to white and black.
+uniform vec2 uDepthRange;
+
+varying highp vec3 vNormal;
+varying highp vec2 vTextureCoord;
+varying highp float vDepth;
+
+void main(void) {
+  vec4 view = uViewMatrix * uModelMatrix * aVertexPosition;
+  vNormal = (uModelMatrix * vec4(aVertexNormal, 0.0)).xyz;
+  vTextureCoord = aTextureCoord;
+  vDepth = (-view.z - uDepthRange.x) / (uDepthRange.y - uDepthRange.x);
+  gl_Position = uProjectionMatrix * view;
+}
@@ -0,0 +1,27 @@
+#version 300 es
+
+layout(std140) uniform Camera {
+  mat4 uViewMatrix;
+  mat4 uProjectionMatrix;
+  vec4 uCameraPosition;
+};
+
+in vec4 aVertexPosition;
+in vec3 aVertexNormal;
+in vec2 aTextureCoord;
+
+uniform mat4 uModelMatrix;
+// Near and far view distance mapped to white and black.
+uniform vec2 uDepthRange;
+
+out highp vec3 vNormal;
+out highp vec2 vTextureCoord;
+out highp float vDepth;
+
+void main(void) {
+  vec4 view = uViewMatrix * uModelMatrix * aVertexPosition;
+  vNormal = mat3(uModelMatrix) * aVertexNormal;
+  vTextureCoord = aTextureCoord;
+  vDepth = (-view.z - uDepthRange.x) / (uDepthRange.y - uDepthRange.x);
+  gl_Position = uProjectionMatrix * view;
+}
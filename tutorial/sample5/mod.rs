@@ -445,12 +445,6 @@ pub fn draw_scene(
       unf_model_view_matrix, false, &data
   );
 
-  // context.draw_elements_with_i32(
-  //     WebGlRenderingContext::LINES,
-  //     (36) as i32,
-  //     WebGlRenderingContext::UNSIGNED_SHORT,
-  //     0,
-  // );
   context.draw_arrays(
       WebGlRenderingContext::TRIANGLES,
       0,